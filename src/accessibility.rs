@@ -0,0 +1,360 @@
+// semantic labels and an accessibility tree for screen readers
+use std::collections::HashSet;
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::scenes::Scene;
+use super::shapes::{Point, Rectangle};
+use super::text::RichText;
+use super::user_data::Hit;
+use super::Drawable;
+
+/// What a drawable is, for assistive technology.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Role {
+    /// Text to be read, e.g. instructions.
+    #[default]
+    Text,
+    Heading,
+    Paragraph,
+    Image,
+    Button,
+    CheckBox,
+    Slider,
+    TextInput,
+    Link,
+    /// Groups other accessible drawables, e.g. the options of a questionnaire item.
+    Group,
+}
+
+/// The semantic description of a drawable.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Semantics {
+    pub role: Role,
+    /// The name announced by screen readers (e.g. the text of a label or a button).
+    pub label: String,
+    /// Additional information, announced after the label.
+    pub description: Option<String>,
+    /// A stable id, so that assistive technology can follow the node across frames. Nodes
+    /// without an id are numbered in the order they are drawn.
+    ///
+    /// Ids must be unique within a frame: a node whose id is already used (or is [`u64::MAX`],
+    /// which is reserved for the window) is numbered like a node without an id.
+    pub id: Option<u64>,
+}
+
+/// The id reserved for the window node that holds the tree.
+const WINDOW_ID: u64 = u64::MAX;
+
+impl Semantics {
+    pub fn new(role: Role, label: impl Into<String>) -> Self {
+        Self {
+            role,
+            label: label.into(),
+            description: None,
+            id: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+}
+
+/// A node of an [`AccessTree`].
+#[derive(Debug, Clone)]
+pub struct AccessNode {
+    /// The id of the node, unique within the frame.
+    pub id: u64,
+    pub semantics: Semantics,
+    /// The bounds of the node in device pixels, if its drawable has bounds.
+    pub bounds: Option<Rectangle>,
+    /// The indices of the child nodes.
+    pub children: Vec<usize>,
+    /// The index of the parent node, `None` for top-level nodes.
+    pub parent: Option<usize>,
+}
+
+/// The accessible drawables of a frame, in the order they were drawn and nested like they were
+/// drawn. It is rebuilt every frame by the [`Accessible`] drawables.
+#[derive(Debug, Clone, Default)]
+pub struct AccessTree {
+    nodes: Vec<AccessNode>,
+    /// The nodes whose drawables are being drawn.
+    open: Vec<usize>,
+    /// The ids of the nodes.
+    ids: HashSet<u64>,
+}
+
+impl AccessTree {
+    /// All nodes, parents before their children.
+    pub fn nodes(&self) -> &[AccessNode] {
+        &self.nodes
+    }
+
+    /// The top-level nodes.
+    pub fn roots(&self) -> impl Iterator<Item = &AccessNode> {
+        self.nodes.iter().filter(|node| node.parent.is_none())
+    }
+
+    /// The children of a node.
+    pub fn children<'a>(&'a self, node: &'a AccessNode) -> impl Iterator<Item = &'a AccessNode> {
+        node.children.iter().map(|&index| &self.nodes[index])
+    }
+
+    /// The node with an id.
+    pub fn get(&self, id: u64) -> Option<&AccessNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// The topmost node whose bounds contain a position (in device pixels), e.g. to announce
+    /// what is under the pointer.
+    pub fn node_at(&self, position: Point) -> Option<&AccessNode> {
+        self.nodes.iter().rev().find(|node| {
+            node.bounds.as_ref().is_some_and(|bounds| {
+                (bounds.a.x..=bounds.b.x).contains(&position.x) && (bounds.a.y..=bounds.b.y).contains(&position.y)
+            })
+        })
+    }
+
+    /// The tree as indented text (one node per line), e.g. for logging or to check the reading
+    /// order.
+    pub fn to_text(&self) -> String {
+        fn write(tree: &AccessTree, node: &AccessNode, depth: usize, text: &mut String) {
+            text.push_str(&"  ".repeat(depth));
+            text.push_str(&format!("{:?}: {}", node.semantics.role, node.semantics.label));
+            if let Some(description) = &node.semantics.description {
+                text.push_str(&format!(" ({description})"));
+            }
+            text.push('\n');
+            for child in tree.children(node) {
+                write(tree, child, depth + 1, text);
+            }
+        }
+        let mut text = String::new();
+        for root in self.roots() {
+            write(self, root, 0, &mut text);
+        }
+        text
+    }
+
+    /// Remove all nodes.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.open.clear();
+        self.ids.clear();
+    }
+
+    /// Add the nodes of a child scene below the innermost open node, with their bounds moved by
+    /// the transform the child scene is appended with.
+    pub(crate) fn append(&mut self, other: &AccessTree, transform: Affine) {
+        let offset = self.nodes.len();
+        let parent = self.open.last().copied();
+        for node in &other.nodes {
+            let index = self.nodes.len();
+            if let (None, Some(parent)) = (node.parent, parent) {
+                self.nodes[parent].children.push(index);
+            }
+            let id = self.unique_id(node.semantics.id);
+            self.nodes.push(AccessNode {
+                id,
+                semantics: node.semantics.clone(),
+                bounds: node.bounds.clone().map(|bounds| device_bounds(bounds, transform)),
+                children: node.children.iter().map(|child| child + offset).collect(),
+                parent: node.parent.map(|parent| parent + offset).or(parent),
+            });
+        }
+    }
+
+    /// The chosen id of a new node if it is free, otherwise an automatic id.
+    fn unique_id(&mut self, chosen: Option<u64>) -> u64 {
+        let id = match chosen {
+            Some(id) if id != WINDOW_ID && !self.ids.contains(&id) => id,
+            // automatic ids count from the top so that they don't collide with small chosen ids
+            _ => {
+                let mut id = WINDOW_ID - 1 - self.nodes.len() as u64;
+                while self.ids.contains(&id) {
+                    id -= 1;
+                }
+                id
+            }
+        };
+        self.ids.insert(id);
+        id
+    }
+
+    /// Add a node as a child of the innermost open node and open it.
+    fn open(&mut self, semantics: &Semantics, bounds: Option<Rectangle>) {
+        let index = self.nodes.len();
+        let parent = self.open.last().copied();
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(index);
+        }
+        let id = self.unique_id(semantics.id);
+        self.nodes.push(AccessNode {
+            id,
+            semantics: semantics.clone(),
+            bounds,
+            children: Vec::new(),
+            parent,
+        });
+        self.open.push(index);
+    }
+
+    fn close(&mut self) {
+        self.open.pop();
+    }
+}
+
+/// Attaches [`Semantics`] to a drawable. When drawn, it adds a node to the scene's
+/// [`AccessTree`] (see [`Scene::accessibility`]); accessible drawables drawn by the inner
+/// drawable become its children.
+#[derive(Debug, Clone)]
+pub struct Accessible<D> {
+    pub drawable: D,
+    pub semantics: Semantics,
+    /// The bounds of the node in the coordinates the drawable is drawn in, for drawables without
+    /// bounds of their own (e.g. text).
+    pub bounds: Option<Rectangle>,
+}
+
+impl<D> Accessible<D> {
+    pub fn new(drawable: D, role: Role, label: impl Into<String>) -> Self {
+        Self::with_semantics(drawable, Semantics::new(role, label))
+    }
+
+    pub fn with_semantics(drawable: D, semantics: Semantics) -> Self {
+        Self {
+            drawable,
+            semantics,
+            bounds: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.semantics.description = Some(description.into());
+        self
+    }
+
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.semantics.id = Some(id);
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: Rectangle) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+}
+
+impl<T> Accessible<RichText<T>> {
+    /// Text labelled with its own content.
+    pub fn text(text: RichText<T>) -> Self {
+        let label = text.spans.iter().map(|span| span.text.as_str()).collect::<String>();
+        Self::new(text, Role::Text, label)
+    }
+}
+
+impl<Backend: RenderBackend, D: Drawable<Backend>> Drawable<Backend> for Accessible<D> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        let bounds = self
+            .bounds
+            .clone()
+            .or_else(|| self.drawable.bounds())
+            .map(|bounds| device_bounds(bounds, scene.view_transform()));
+        scene.accessibility.open(&self.semantics, bounds);
+        self.drawable.draw(scene);
+        scene.accessibility.close();
+    }
+
+    fn hit_test(&self, point: Point) -> Option<Hit<'_>> {
+        self.drawable.hit_test(point)
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        self.bounds.clone().or_else(|| self.drawable.bounds())
+    }
+}
+
+/// The axis-aligned bounds of a rectangle after a transform.
+fn device_bounds(bounds: Rectangle, transform: Affine) -> Rectangle {
+    let rect = kurbo::Rect::new(bounds.a.x, bounds.a.y, bounds.b.x, bounds.b.y);
+    let rect = kurbo::Affine::from(transform).transform_rect_bbox(rect);
+    Rectangle {
+        a: Point { x: rect.x0, y: rect.y0 },
+        b: Point { x: rect.x1, y: rect.y1 },
+    }
+}
+
+#[cfg(feature = "accesskit")]
+mod accesskit_tree {
+    use accesskit::{Node, NodeBuilder, NodeId, Tree, TreeUpdate};
+
+    use super::*;
+
+    /// The id of the window node that holds the tree.
+    const ROOT_ID: NodeId = NodeId(WINDOW_ID);
+
+    impl Role {
+        fn to_accesskit(self) -> accesskit::Role {
+            match self {
+                Role::Text => accesskit::Role::Label,
+                Role::Heading => accesskit::Role::Heading,
+                Role::Paragraph => accesskit::Role::Paragraph,
+                Role::Image => accesskit::Role::Image,
+                Role::Button => accesskit::Role::Button,
+                Role::CheckBox => accesskit::Role::CheckBox,
+                Role::Slider => accesskit::Role::Slider,
+                Role::TextInput => accesskit::Role::TextInput,
+                Role::Link => accesskit::Role::Link,
+                Role::Group => accesskit::Role::Group,
+            }
+        }
+    }
+
+    impl AccessTree {
+        /// The tree as a full AccessKit update, below a window node named `window_label`. Focus
+        /// stays on the window. Pass it to an AccessKit adapter (e.g. `accesskit_winit`) after
+        /// each frame.
+        pub fn to_accesskit(&self, window_label: &str) -> TreeUpdate {
+            let mut root = NodeBuilder::new(accesskit::Role::Window);
+            root.set_name(window_label);
+            root.set_children(self.roots().map(|node| NodeId(node.id)).collect::<Vec<_>>());
+
+            let mut nodes = vec![(ROOT_ID, root.build())];
+            nodes.extend(
+                self.nodes
+                    .iter()
+                    .map(|node| (NodeId(node.id), self.accesskit_node(node))),
+            );
+            TreeUpdate {
+                nodes,
+                tree: Some(Tree::new(ROOT_ID)),
+                focus: ROOT_ID,
+            }
+        }
+
+        fn accesskit_node(&self, node: &AccessNode) -> Node {
+            let semantics = &node.semantics;
+            let mut builder = NodeBuilder::new(semantics.role.to_accesskit());
+            builder.set_name(semantics.label.as_str());
+            if let Some(description) = &semantics.description {
+                builder.set_description(description.as_str());
+            }
+            if let Some(bounds) = &node.bounds {
+                builder.set_bounds(accesskit::Rect::new(bounds.a.x, bounds.a.y, bounds.b.x, bounds.b.y));
+            }
+            builder.set_children(self.children(node).map(|child| NodeId(child.id)).collect::<Vec<_>>());
+            if matches!(semantics.role, Role::Button | Role::CheckBox | Role::Link) {
+                builder.add_action(accesskit::Action::Default);
+            }
+            builder.build()
+        }
+    }
+}
@@ -1,7 +1,19 @@
-use shapes::Point;
+use shapes::{Point, Rectangle};
 
 use super::shapes;
 
+/// How content is scaled into a target region by [`Affine::fit`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fit {
+    /// Scale uniformly so that the content fits entirely into the target.
+    #[default]
+    Contain,
+    /// Scale uniformly so that the content covers the whole target (and may extend beyond it).
+    Cover,
+    /// Scale each axis separately so that the content fills the target exactly.
+    Stretch,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Affine(pub [f64; 6]);
 
@@ -43,6 +55,56 @@ impl Affine {
         let (s, c) = theta.sin_cos();
         Affine([c, s, -s, c, x - x * c + y * s, y - x * s - y * c])
     }
+
+    /// Apply the transform to a point.
+    #[inline]
+    pub fn transform_point(&self, point: Point) -> Point {
+        let [a, b, c, d, e, f] = self.0;
+        Point {
+            x: a * point.x + c * point.y + e,
+            y: b * point.x + d * point.y + f,
+        }
+    }
+
+    /// The determinant of the linear part of the transform.
+    #[inline]
+    pub fn determinant(&self) -> f64 {
+        self.0[0] * self.0[3] - self.0[1] * self.0[2]
+    }
+
+    /// The transform that scales the rectangle `content` according to `fit` and centers it in
+    /// `target`. Empty dimensions of the content are not scaled.
+    pub fn fit(content: Rectangle, target: Rectangle, fit: Fit) -> Affine {
+        let size = |rect: &Rectangle| ((rect.b.x - rect.a.x).abs(), (rect.b.y - rect.a.y).abs());
+        let center = |rect: &Rectangle| ((rect.a.x + rect.b.x) / 2.0, (rect.a.y + rect.b.y) / 2.0);
+        let ((width, height), (target_width, target_height)) = (size(&content), size(&target));
+        let scale_x = (width > 0.0).then_some(target_width / width);
+        let scale_y = (height > 0.0).then_some(target_height / height);
+
+        let (scale_x, scale_y) = match (fit, scale_x, scale_y) {
+            (Fit::Stretch, x, y) => (x.unwrap_or(1.0), y.unwrap_or(1.0)),
+            (Fit::Contain, Some(x), Some(y)) => (x.min(y), x.min(y)),
+            (Fit::Cover, Some(x), Some(y)) => (x.max(y), x.max(y)),
+            (_, Some(scale), None) | (_, None, Some(scale)) => (scale, scale),
+            (_, None, None) => (1.0, 1.0),
+        };
+        let ((x, y), (target_x, target_y)) = (center(&content), center(&target));
+        Affine::translate(-x, -y) * Affine::scale_xy(scale_x, scale_y) * Affine::translate(target_x, target_y)
+    }
+
+    /// The inverse transform (non-finite if the transform is not invertible).
+    pub fn inverse(&self) -> Affine {
+        let [a, b, c, d, e, f] = self.0;
+        let inv_det = 1.0 / self.determinant();
+        Affine([
+            d * inv_det,
+            -b * inv_det,
+            -c * inv_det,
+            a * inv_det,
+            (c * f - d * e) * inv_det,
+            (b * e - a * f) * inv_det,
+        ])
+    }
 }
 
 impl std::ops::Mul for Affine {
@@ -59,6 +121,12 @@ impl std::ops::Mul for Affine {
     }
 }
 
+impl From<Affine> for kurbo::Affine {
+    fn from(affine: Affine) -> Self {
+        kurbo::Affine::new(affine.0)
+    }
+}
+
 // [f64; 6] 3x3 array into Affine
 impl Into<Affine> for [f64; 6] {
     fn into(self) -> Affine {
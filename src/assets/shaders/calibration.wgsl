@@ -0,0 +1,53 @@
+// applies per-channel calibration lookup tables to the final frame
+
+struct Params {
+    // the number of entries per channel
+    size: u32,
+    // 1 if the frame stores linear values that have to be encoded first
+    linear_input: u32,
+    // 1 if the target encodes its values in hardware, so they have to be decoded
+    srgb_target: u32,
+    _padding: u32,
+};
+
+@group(0) @binding(0) var frame: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: Params;
+// the tables of the red, green and blue channel, one after the other
+@group(0) @binding(2) var<storage, read> lut: array<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) ix: u32) -> @builtin(position) vec4<f32> {
+    // a triangle that covers the whole target
+    let vertex = vec2<f32>(f32((ix << 1u) & 2u), f32(ix & 2u));
+    return vec4<f32>(vertex * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn encode(value: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(value, vec3<f32>(1.0 / 2.4)) - 0.055, value * 12.92, value <= vec3<f32>(0.0031308));
+}
+
+fn decode(value: vec3<f32>) -> vec3<f32> {
+    return select(pow((value + 0.055) / 1.055, vec3<f32>(2.4)), value / 12.92, value <= vec3<f32>(0.04045));
+}
+
+fn sample(channel: u32, value: f32) -> f32 {
+    let position = clamp(value, 0.0, 1.0) * f32(params.size - 1u);
+    let index = min(u32(position), params.size - 2u);
+    let t = position - f32(index);
+    let offset = channel * params.size + index;
+    return mix(lut[offset], lut[offset + 1u], t);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(frame, vec2<i32>(position.xy), 0);
+    var rgb = color.rgb;
+    if (params.linear_input == 1u) {
+        rgb = encode(rgb);
+    }
+    rgb = vec3<f32>(sample(0u, rgb.r), sample(1u, rgb.g), sample(2u, rgb.b));
+    if (params.srgb_target == 1u) {
+        rgb = decode(rgb);
+    }
+    return vec4<f32>(rgb, color.a);
+}
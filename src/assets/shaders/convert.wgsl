@@ -0,0 +1,37 @@
+// converts the colors of an image on the GPU like the color management of the scene
+
+struct Params {
+    // the rows of the matrix applied to linear colors
+    rows: array<vec4<f32>, 3>,
+    // whether the result is encoded with the sRGB transfer function
+    encode: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var input: texture_2d<f32>;
+@group(0) @binding(2) var output: texture_storage_2d<rgba8unorm, write>;
+
+fn decode(value: vec3<f32>) -> vec3<f32> {
+    return select(pow((value + 0.055) / 1.055, vec3<f32>(2.4)), value / 12.92, value <= vec3<f32>(0.04045));
+}
+
+fn encode(value: vec3<f32>) -> vec3<f32> {
+    // colors converted to a wider gamut are never negative, but rounding errors can be
+    let clamped = max(value, vec3<f32>(0.0));
+    return select(1.055 * pow(clamped, vec3<f32>(1.0 / 2.4)) - 0.055, clamped * 12.92, clamped <= vec3<f32>(0.0031308));
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let dimensions = textureDimensions(output);
+    if (gid.x >= dimensions.x || gid.y >= dimensions.y) {
+        return;
+    }
+    let color = textureLoad(input, vec2<i32>(gid.xy), 0);
+    let linear = decode(color.rgb);
+    var converted = vec3<f32>(dot(params.rows[0].xyz, linear), dot(params.rows[1].xyz, linear), dot(params.rows[2].xyz, linear));
+    if (params.encode != 0u) {
+        converted = encode(converted);
+    }
+    textureStore(output, vec2<i32>(gid.xy), vec4<f32>(converted, color.a));
+}
@@ -0,0 +1,77 @@
+// layer effects: separable gaussian blur, drop shadows and luminance masks
+
+struct Params {
+    // the premultiplied color of a shadow
+    color: vec4<f32>,
+    // moves the input, in pixels
+    offset: vec2<i32>,
+    // the direction of the blur pass, (1, 0) or (0, 1)
+    direction: vec2<i32>,
+    sigma: f32,
+    radius: i32,
+    mode: u32,
+    _padding: u32,
+};
+
+const MODE_BLUR: u32 = 0u;
+const MODE_SHADOW: u32 = 1u;
+const MODE_COMPOSITE: u32 = 2u;
+const MODE_LUMINANCE_MASK: u32 = 3u;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var input: texture_2d<f32>;
+// the layer drawn over the shadow when compositing
+@group(0) @binding(2) var base: texture_2d<f32>;
+@group(0) @binding(3) var output: texture_storage_2d<rgba8unorm, write>;
+
+// the textures have straight alpha, but blurring and compositing need premultiplied colors
+fn load_premultiplied(texture: texture_2d<f32>, position: vec2<i32>) -> vec4<f32> {
+    let dimensions = vec2<i32>(textureDimensions(texture));
+    if (any(position < vec2<i32>(0)) || any(position >= dimensions)) {
+        return vec4<f32>(0.0);
+    }
+    let color = textureLoad(texture, position, 0);
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+fn store_premultiplied(position: vec2<i32>, color: vec4<f32>) {
+    let rgb = select(vec3<f32>(0.0), color.rgb / color.a, color.a > 0.0);
+    textureStore(output, position, vec4<f32>(rgb, color.a));
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let dimensions = textureDimensions(output);
+    if (gid.x >= dimensions.x || gid.y >= dimensions.y) {
+        return;
+    }
+    let position = vec2<i32>(gid.xy);
+
+    if (params.mode == MODE_COMPOSITE) {
+        let shadow = load_premultiplied(input, position);
+        let layer = load_premultiplied(base, position);
+        store_premultiplied(position, layer + shadow * (1.0 - layer.a));
+        return;
+    }
+
+    if (params.mode == MODE_LUMINANCE_MASK) {
+        // premultiplied, so the luminance is already scaled by alpha
+        let color = load_premultiplied(input, position);
+        let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        store_premultiplied(position, vec4<f32>(luminance));
+        return;
+    }
+
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = -params.radius; i <= params.radius; i++) {
+        let weight = select(1.0, exp(-f32(i * i) / (2.0 * params.sigma * params.sigma)), params.sigma > 0.0);
+        var color = load_premultiplied(input, position - params.offset + params.direction * i);
+        if (params.mode == MODE_SHADOW) {
+            color = params.color * color.a;
+        }
+        sum += weight * color;
+        total += weight;
+    }
+    store_premultiplied(position, sum / total);
+}
@@ -0,0 +1,18 @@
+// encodes linear colors with the sRGB transfer function for the output
+
+@group(0) @binding(0) var input: texture_2d<f32>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba8unorm, write>;
+
+fn encode(value: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(value, vec3<f32>(1.0 / 2.4)) - 0.055, value * 12.92, value <= vec3<f32>(0.0031308));
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let dimensions = textureDimensions(output);
+    if (gid.x >= dimensions.x || gid.y >= dimensions.y) {
+        return;
+    }
+    let color = textureLoad(input, vec2<i32>(gid.xy), 0);
+    textureStore(output, vec2<i32>(gid.xy), vec4<f32>(encode(color.rgb), color.a));
+}
@@ -0,0 +1,42 @@
+// generates sine gratings and plaids from analytic parameters
+
+struct Wave {
+    spatial_frequency: f32,
+    orientation: f32,
+    phase: f32,
+    contrast: f32,
+};
+
+struct Params {
+    mean: vec4<f32>,
+    // the size of the grating in local units
+    size: vec2<f32>,
+    count: u32,
+    _padding: u32,
+    waves: array<Wave, 4>,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var output: texture_storage_2d<rgba8unorm, write>;
+
+const TAU: f32 = 6.283185307179586;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let dimensions = textureDimensions(output);
+    if (gid.x >= dimensions.x || gid.y >= dimensions.y) {
+        return;
+    }
+
+    // sample at the center of the texel
+    let position = (vec2<f32>(gid.xy) + 0.5) / vec2<f32>(dimensions) * params.size;
+    var modulation = 0.0;
+    for (var i = 0u; i < params.count; i++) {
+        let wave = params.waves[i];
+        let direction = vec2<f32>(cos(wave.orientation), sin(wave.orientation));
+        modulation += wave.contrast * sin(TAU * wave.spatial_frequency * dot(position, direction) + wave.phase);
+    }
+
+    let color = clamp(params.mean.rgb * (1.0 + modulation), vec3<f32>(0.0), vec3<f32>(1.0));
+    textureStore(output, vec2<i32>(gid.xy), vec4<f32>(color, params.mean.a));
+}
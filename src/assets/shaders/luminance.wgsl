@@ -0,0 +1,68 @@
+// luminance histogram and sum of a rendered frame
+
+struct Params {
+    // 1 if the texture stores sRGB-encoded values that have to be linearized
+    encoded: u32,
+    // 1 if the texture stores blue in the first channel
+    bgra: u32,
+};
+
+struct Stats {
+    histogram: array<atomic<u32>, 256>,
+    // the sum of the luminances in 16 bit fixed point, split into two words
+    sum_low: atomic<u32>,
+    sum_high: atomic<u32>,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var input: texture_2d<f32>;
+@group(0) @binding(2) var<storage, read_write> stats: Stats;
+
+var<workgroup> local_histogram: array<atomic<u32>, 256>;
+var<workgroup> local_sum: atomic<u32>;
+
+fn linearize(value: vec3<f32>) -> vec3<f32> {
+    return select(pow((value + 0.055) / 1.055, vec3<f32>(2.4)), value / 12.92, value <= vec3<f32>(0.04045));
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>, @builtin(local_invocation_index) index: u32) {
+    // each of the 64 invocations clears and flushes 4 bins
+    for (var i = 0u; i < 4u; i++) {
+        atomicStore(&local_histogram[index * 4u + i], 0u);
+    }
+    if (index == 0u) {
+        atomicStore(&local_sum, 0u);
+    }
+    workgroupBarrier();
+
+    let dimensions = textureDimensions(input);
+    if (gid.x < dimensions.x && gid.y < dimensions.y) {
+        var color = textureLoad(input, vec2<i32>(gid.xy), 0).rgb;
+        if (params.bgra == 1u) {
+            color = color.bgr;
+        }
+        if (params.encoded == 1u) {
+            color = linearize(color);
+        }
+        let luminance = clamp(dot(color, vec3<f32>(0.2126, 0.7152, 0.0722)), 0.0, 1.0);
+        atomicAdd(&local_histogram[min(u32(luminance * 256.0), 255u)], 1u);
+        atomicAdd(&local_sum, u32(round(luminance * 65535.0)));
+    }
+    workgroupBarrier();
+
+    for (var i = 0u; i < 4u; i++) {
+        let count = atomicLoad(&local_histogram[index * 4u + i]);
+        if (count > 0u) {
+            atomicAdd(&stats.histogram[index * 4u + i], count);
+        }
+    }
+    if (index == 0u) {
+        let sum = atomicLoad(&local_sum);
+        let previous = atomicAdd(&stats.sum_low, sum);
+        // carry into the high word when the low word wraps around
+        if (previous + sum < previous) {
+            atomicAdd(&stats.sum_high, 1u);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+// many small images packed into one texture
+use super::affine::Affine;
+use super::brushes::{Brush, Extend, Image};
+use super::geoms::Geom;
+use super::shapes::{Point, Rectangle};
+use super::styles::ImageFitMode;
+
+/// A single image holding many sprites (a sprite sheet or texture atlas), so that they share one
+/// texture instead of each uploading their own.
+///
+/// Sprites are sampled with bilinear filtering, which blends in the neighboring pixels along
+/// their edges when they are scaled or placed between pixels. Leave transparent space between
+/// sprites (like [`Atlas::pack`] does) to avoid bleeding. Sprites drawn at less than half their
+/// size are sampled from the mip levels of the atlas, which blend neighboring sprites over
+/// larger distances; disable them with [`Image::with_mipmaps`] if that shows.
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    image: Image,
+}
+
+/// The space [`Atlas::pack`] leaves between sprites.
+const PACK_PADDING: u32 = 1;
+
+impl Atlas {
+    pub fn new(image: Image) -> Self {
+        Self { image }
+    }
+
+    /// Pack images into a new atlas of the given width, returning the atlas and a sprite for each
+    /// image (in order). Returns `None` if an image is wider than the atlas.
+    ///
+    /// The images are placed in rows, tallest first, with a pixel of transparent space between
+    /// them.
+    pub fn pack(images: &[Image], width: u32) -> Option<(Self, Vec<Sprite>)> {
+        let mut order = (0..images.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| std::cmp::Reverse(images[i].height));
+
+        // fill rows from left to right, starting a new row when the next image doesn't fit
+        let mut positions = vec![(0, 0); images.len()];
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for &i in &order {
+            let image = &images[i];
+            if image.width > width {
+                return None;
+            }
+            if x + image.width > width {
+                (x, y, row_height) = (0, y + row_height + PACK_PADDING, 0);
+            }
+            positions[i] = (x, y);
+            x += image.width + PACK_PADDING;
+            row_height = row_height.max(image.height);
+        }
+        let height = y + row_height;
+
+        let mut pixels = image::RgbaImage::new(width, height.max(1));
+        for (image, &(x, y)) in images.iter().zip(&positions) {
+            let data = image.to_rgba8();
+            for row in 0..image.height {
+                let source = (row * image.width * 4) as usize;
+                let target = (((y + row) * width + x) * 4) as usize;
+                let len = (image.width * 4) as usize;
+                let buffer: &mut [u8] = &mut pixels;
+                buffer[target..target + len].copy_from_slice(&data[source..source + len]);
+            }
+        }
+
+        let atlas = Self::new(Image::new(&image::DynamicImage::ImageRgba8(pixels)));
+        let sprites = images
+            .iter()
+            .zip(positions)
+            .map(|(image, (x, y))| atlas.sprite(x, y, image.width, image.height))
+            .collect();
+        Some((atlas, sprites))
+    }
+
+    /// The image holding the sprites.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// The sprite in a rectangle of the atlas, in pixels from its top left corner.
+    pub fn sprite(&self, x: u32, y: u32, width: u32, height: u32) -> Sprite {
+        Sprite {
+            image: self.image.clone(),
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The sprites of a sheet of `columns` x `rows` equally sized cells, row by row.
+    pub fn grid(&self, columns: u32, rows: u32) -> Vec<Sprite> {
+        let (width, height) = (self.image.width / columns.max(1), self.image.height / rows.max(1));
+        (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| self.sprite(column * width, row * height, width, height))
+            .collect()
+    }
+}
+
+/// A rectangle of an [`Atlas`]. Sprites share the image of their atlas, so they are cheap to
+/// clone and create.
+#[derive(Debug, Clone)]
+pub struct Sprite {
+    image: Image,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Sprite {
+    /// A brush of the whole atlas, to be used with [`Sprite::brush_transform`] on a shape that
+    /// only covers the sprite (like [`Sprite::rect`]).
+    pub fn brush(&self) -> Brush {
+        Brush::Image {
+            image: self.image.clone(),
+            x: 0.0,
+            y: 0.0,
+            fit_mode: ImageFitMode::Original,
+            edge_mode: Extend::Pad,
+            edge_mode_y: None,
+            mapping: Default::default(),
+        }
+    }
+
+    /// The brush transform that moves the sprite to the origin.
+    pub fn brush_transform(&self) -> Affine {
+        Affine::translate(-(self.x as f64), -(self.y as f64))
+    }
+
+    /// The rectangle the sprite covers after [`Sprite::brush_transform`], from the origin to its
+    /// size.
+    pub fn rect(&self) -> Rectangle {
+        Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: self.width as f64,
+                y: self.height as f64,
+            },
+        }
+    }
+
+    /// A geom that draws the sprite at its size from the origin; position and scale it with
+    /// [`Geom::with_transform`].
+    pub fn geom(&self) -> Geom<Rectangle> {
+        let mut geom = Geom::fill(self.rect(), self.brush());
+        geom.brush_transform = Some(self.brush_transform());
+        geom
+    }
+}
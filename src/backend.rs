@@ -0,0 +1,142 @@
+use super::affine::Affine;
+use super::brushes::{Brush, Image};
+use super::colors::{ColorManagement, RGBA};
+use super::quality::QualitySettings;
+use super::shapes::{RoundedRectangle, Shape};
+use super::styles::{CompositeMode, FillStyle, LayerEffect, MixMode, StrokeOptions, Style};
+use super::text::{Glyph, TextRendering};
+
+/// The primitive operations a rendering backend has to provide.
+///
+/// Generic drawables (geoms, layers, ...) are implemented on top of this trait, so a new backend
+/// only needs to implement these primitives instead of every drawable.
+pub trait RenderBackend {
+    /// The backend-specific font type.
+    type Font;
+
+    /// Discard all content and start a new scene.
+    fn begin_scene(&mut self);
+
+    /// Create an empty backend of the same kind with an identity global transform, used to
+    /// record content that is appended to this backend later.
+    fn create_child(&self) -> Self;
+
+    /// The transform from scene coordinates to device pixels.
+    fn global_transform(&self) -> Affine;
+
+    /// Replace the transform from scene coordinates to device pixels.
+    fn set_global_transform(&mut self, transform: Affine);
+
+    /// Apply quality settings to content drawn from now on.
+    ///
+    /// Backends without quality settings ignore them.
+    fn set_quality(&mut self, quality: &QualitySettings) {
+        let _ = quality;
+    }
+
+    /// Apply a color management to content drawn from now on.
+    ///
+    /// Backends without color management draw all colors as sRGB.
+    fn set_color_management(&mut self, color_management: &ColorManagement) {
+        let _ = color_management;
+    }
+
+    /// Fill or stroke a shape with a brush.
+    fn draw_shape(
+        &mut self,
+        shape: &impl Shape,
+        style: &Style,
+        brush: &Brush,
+        transform: Affine,
+        brush_transform: Option<Affine>,
+    );
+
+    /// Draw a run of already positioned glyphs.
+    ///
+    /// `variations` are `(axis tag, value)` pairs for variable fonts, e.g. `("wght", 700.0)`. If
+    /// `stroke` is given, the outlines of the glyphs are stroked instead of filled.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_glyphs(
+        &mut self,
+        font: &Self::Font,
+        font_size: f32,
+        variations: &[(&str, f32)],
+        glyphs: &[Glyph],
+        brush: &Brush,
+        transform: Affine,
+        glyph_transform: Option<Affine>,
+        rendering: TextRendering,
+        stroke: Option<&StrokeOptions>,
+    );
+
+    /// Push a layer that is clipped to a shape and composited with the given modes when popped.
+    fn push_layer(
+        &mut self,
+        mix_mode: MixMode,
+        composite_mode: CompositeMode,
+        clip: &impl Shape,
+        clip_transform: Affine,
+        alpha: f32,
+    );
+
+    /// Fill a rounded rectangle with a color, blurred by a Gaussian with a standard deviation of
+    /// `std_dev` (in the coordinates of the rectangle), e.g. for shadows.
+    ///
+    /// Backends that can't blur fill the rectangle without blurring it.
+    fn draw_blurred_rounded_rect(&mut self, rect: &RoundedRectangle, color: RGBA, std_dev: f64, transform: Affine) {
+        let _ = std_dev;
+        self.draw_shape(
+            rect,
+            &Style::Fill(FillStyle::NonZero),
+            &Brush::Solid(color),
+            transform,
+            None,
+        );
+    }
+
+    /// Pop the most recently pushed layer.
+    fn pop_layer(&mut self);
+
+    /// Draw an image at its natural size.
+    fn draw_image(&mut self, image: &Image, transform: Affine);
+
+    /// Append the content of another backend (in its device space), transformed by `transform`.
+    fn append(&mut self, other: &Self, transform: Affine);
+
+    /// Append the content of another backend with effects applied to it as a whole. `width` and
+    /// `height` are the size of the scene in device pixels.
+    ///
+    /// Backends that don't support effects append the content unchanged.
+    fn append_with_effects(
+        &mut self,
+        other: &Self,
+        transform: Affine,
+        effects: &[LayerEffect],
+        width: u32,
+        height: u32,
+    ) {
+        let _ = (effects, width, height);
+        self.append(other, transform);
+    }
+
+    /// Append the content of another backend as a mask: its luminance (times its alpha)
+    /// becomes its alpha, e.g. to be composited with [`CompositeMode::DestinationIn`].
+    /// `width` and `height` are the size of the scene in device pixels.
+    ///
+    /// Backends that can't convert luminance append the content unchanged, so that it masks
+    /// by its alpha.
+    fn append_luminance_mask(&mut self, mask: &Self, transform: Affine, width: u32, height: u32) {
+        let _ = (width, height);
+        self.append(mask, transform);
+    }
+
+    /// Append the content of another backend over the previous frame of the same trail layer
+    /// (identified by `id`), faded to an opacity of `decay`. `width` and `height` are the size
+    /// of the scene in device pixels.
+    ///
+    /// Backends that don't keep frames append the content without a trail.
+    fn append_with_trail(&mut self, other: &Self, transform: Affine, id: u64, decay: f32, width: u32, height: u32) {
+        let _ = (id, decay, width, height);
+        self.append(other, transform);
+    }
+}
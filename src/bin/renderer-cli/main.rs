@@ -0,0 +1,196 @@
+// renders scene files to images and videos without a window
+mod scene_file;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use renderer::export::{save_png, ColorProfile};
+use renderer::styles::Antialiasing;
+use renderer::vello_backend::VelloRenderer;
+use renderer::VelloScene;
+use scene_file::{PreparedScene, SceneFile};
+
+const USAGE: &str = "\
+Render a scene file to PNG or MP4 without a window.
+
+usage: renderer-cli <scene.json|scene.ron|image.svg> -o <output.png|output.mp4> [options]
+
+options:
+  -o, --output <path>   the output file; PNG sequences are numbered if more than one frame is
+                        rendered, MP4 files are encoded with ffmpeg (which has to be installed)
+  -w, --width <pixels>  the width (defaults to the width of the scene)
+  -h, --height <pixels> the height (defaults to the height of the scene)
+  -n, --frames <count>  the number of frames to render (default 1)
+  -r, --fps <rate>      the frame rate that animated elements advance at (default 60)
+      --help            show this message";
+
+/// The size of scenes that don't specify one.
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+struct Options {
+    input: PathBuf,
+    output: PathBuf,
+    width: Option<u32>,
+    height: Option<u32>,
+    frames: u32,
+    fps: f64,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>> {
+    let (mut input, mut output) = (None, None);
+    let (mut width, mut height, mut frames, mut fps) = (None, None, 1, 60.0);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--help" => return Ok(None),
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "-w" | "--width" => width = Some(value()?.parse().context("invalid width")?),
+            "-h" | "--height" => height = Some(value()?.parse().context("invalid height")?),
+            "-n" | "--frames" => frames = value()?.parse().context("invalid frame count")?,
+            "-r" | "--fps" => fps = value()?.parse().context("invalid frame rate")?,
+            flag if flag.starts_with('-') => bail!("unknown option {flag}"),
+            _ if input.is_none() => input = Some(PathBuf::from(&arg)),
+            _ => bail!("unexpected argument {arg}"),
+        }
+    }
+    if frames == 0 || fps <= 0.0 {
+        bail!("the frame count and frame rate must be positive");
+    }
+    Ok(Some(Options {
+        input: input.context("missing scene file")?,
+        output: output.context("missing output file (-o)")?,
+        width,
+        height,
+        frames,
+        fps,
+    }))
+}
+
+fn load_scene(path: &Path) -> Result<PreparedScene> {
+    if path.extension().is_some_and(|extension| extension == "svg") {
+        PreparedScene::from_svg(path)
+    } else {
+        let directory = path.parent().unwrap_or(Path::new("."));
+        SceneFile::load(path)?.prepare(directory)
+    }
+}
+
+/// Where the rendered frames go.
+enum Sink {
+    Png { path: PathBuf, numbered: bool },
+    Ffmpeg(std::process::Child),
+}
+
+impl Sink {
+    fn new(path: &Path, frames: u32, fps: f64, (width, height): (u32, u32)) -> Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("png") => Ok(Sink::Png {
+                path: path.to_owned(),
+                numbered: frames > 1,
+            }),
+            Some("mp4") => {
+                let child = Command::new("ffmpeg")
+                    .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+                    .args(["-s", &format!("{width}x{height}"), "-r", &fps.to_string(), "-i", "-"])
+                    // yuv420p (which most players need) only supports even sizes
+                    .args([
+                        "-vf",
+                        "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+                        "-c:v",
+                        "libx264",
+                        "-pix_fmt",
+                        "yuv420p",
+                    ])
+                    .arg(path)
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .context("failed to start ffmpeg, which is needed for MP4 output")?;
+                Ok(Sink::Ffmpeg(child))
+            }
+            _ => bail!("unsupported output file {} (expected .png or .mp4)", path.display()),
+        }
+    }
+
+    fn write(&mut self, index: u32, image: &renderer::image::RgbaImage) -> Result<()> {
+        match self {
+            Sink::Png { path, numbered: false } => save_png(image, &*path, &ColorProfile::Srgb)?,
+            Sink::Png { path, numbered: true } => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let numbered = path.with_file_name(format!("{stem}_{index:05}.png"));
+                save_png(image, numbered, &ColorProfile::Srgb)?;
+            }
+            Sink::Ffmpeg(child) => {
+                let stdin = child.stdin.as_mut().context("ffmpeg closed its input")?;
+                stdin.write_all(image.as_raw()).context("failed to write to ffmpeg")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Sink::Ffmpeg(mut child) = self {
+            drop(child.stdin.take());
+            if !child.wait()?.success() {
+                bail!("ffmpeg failed to encode the video");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run(options: Options) -> Result<()> {
+    let mut content = load_scene(&options.input)?;
+    let width = options.width.or(content.width).unwrap_or(DEFAULT_SIZE.0);
+    let height = options.height.or(content.height).unwrap_or(DEFAULT_SIZE.1);
+
+    let (device, queue) = pollster::block_on(async {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&Default::default())
+            .await
+            .context("no GPU adapter available")?;
+        adapter
+            .request_device(&Default::default(), None)
+            .await
+            .context("failed to create a GPU device")
+    })?;
+    let mut renderer = VelloRenderer::new(
+        &device,
+        wgpu::TextureFormat::Rgba8Unorm,
+        width,
+        height,
+        Antialiasing::Area,
+    )?;
+
+    let mut scene = VelloScene::new(content.background, width, height);
+    let mut sink = Sink::new(&options.output, options.frames, options.fps, (width, height))?;
+    for index in 0..options.frames {
+        let time = index as f64 / options.fps;
+        scene.begin_frame(Duration::from_secs_f64(time));
+        content.draw(&mut scene, time)?;
+        let image = scene.render_to_image(&mut renderer, &device, &queue)?;
+        sink.write(index, &image)?;
+    }
+    sink.finish()
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {error:#}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Err(error) = run(options) {
+        eprintln!("error: {error:#}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,379 @@
+// the scene description format of the command line tool
+use std::f64::consts::TAU;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use renderer::affine::Affine;
+use renderer::colors::RGBA;
+use renderer::fonts::FontRegistry;
+use renderer::geoms::Geom;
+use renderer::gratings::{Gabor, Grating, GratingWave};
+use renderer::prerenderd_scene::PrerenderedScene;
+use renderer::shapes::{Circle, Fillable, Line, Point, Polygon, Rectangle, RoundedRectangle};
+use renderer::styles::{FillStyle, StrokeOptions, Style};
+use renderer::text::{FontStyle, RichText, TextSpan};
+use renderer::vello_backend::VelloFont;
+use renderer::{Drawable, VelloScene};
+use serde::Deserialize;
+
+/// A scene read from a JSON or RON file.
+///
+/// ```json
+/// {
+///   "width": 800, "height": 600, "background": [0.5, 0.5, 0.5],
+///   "elements": [
+///     { "type": "grating", "x": 100, "y": 100, "width": 200, "height": 200,
+///       "spatial_frequency": 0.05, "orientation": 45, "temporal_frequency": 2 },
+///     { "type": "circle", "x": 400, "y": 300, "radius": 5, "fill": "#ff0000" }
+///   ]
+/// }
+/// ```
+///
+/// RON files have the same structure, with the elements written as maps.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneFile {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(default = "black")]
+    pub background: Color,
+    #[serde(default)]
+    pub elements: Vec<Element>,
+}
+
+/// A color as `[r, g, b]` or `[r, g, b, a]` (0 to 1) or as a hex string (`#rgb`, `#rrggbb` or
+/// `#rrggbbaa`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Color {
+    Rgb([f32; 3]),
+    Rgba([f32; 4]),
+    Hex(String),
+}
+
+fn black() -> Color {
+    Color::Rgb([0.0, 0.0, 0.0])
+}
+
+fn gray() -> Color {
+    Color::Rgb([0.5, 0.5, 0.5])
+}
+
+fn white() -> Color {
+    Color::Rgb([1.0, 1.0, 1.0])
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+impl Color {
+    pub fn to_rgba(&self) -> Result<RGBA> {
+        Ok(match self {
+            Color::Rgb([r, g, b]) => RGBA::new(*r, *g, *b, 1.0),
+            Color::Rgba([r, g, b, a]) => RGBA::new(*r, *g, *b, *a),
+            Color::Hex(hex) => RGBA::from_hex(hex).with_context(|| format!("invalid color {hex:?}"))?,
+        })
+    }
+}
+
+/// How a shape is painted. Shapes without fill and stroke are filled white.
+#[derive(Debug, Deserialize)]
+pub struct Paint {
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    #[serde(default = "one")]
+    pub stroke_width: f64,
+}
+
+/// An element of a scene. Coordinates are in pixels, angles in degrees and frequencies in
+/// cycles per pixel (spatial) or per second (temporal).
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Element {
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        #[serde(default)]
+        corner_radius: f64,
+        #[serde(flatten)]
+        paint: Paint,
+    },
+    Circle {
+        x: f64,
+        y: f64,
+        radius: f64,
+        #[serde(flatten)]
+        paint: Paint,
+    },
+    Polygon {
+        points: Vec<[f64; 2]>,
+        #[serde(flatten)]
+        paint: Paint,
+    },
+    Line {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        #[serde(default = "white")]
+        color: Color,
+        #[serde(default = "one")]
+        width: f64,
+    },
+    /// Text with its baseline starting at `(x, y)`, in a system font.
+    Text {
+        text: String,
+        x: f64,
+        y: f64,
+        size: f32,
+        #[serde(default = "white")]
+        color: Color,
+        #[serde(default = "sans_serif")]
+        font: String,
+    },
+    /// A sine grating filling a rectangle, drifting at `temporal_frequency`.
+    Grating {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        #[serde(flatten)]
+        wave: Wave,
+    },
+    /// A Gabor patch centered on `(x, y)`, drifting at `temporal_frequency`.
+    Gabor {
+        x: f64,
+        y: f64,
+        sigma: f64,
+        #[serde(flatten)]
+        wave: Wave,
+    },
+    /// An SVG file (relative to the scene file), with its top left corner at `(x, y)`.
+    Svg {
+        path: String,
+        #[serde(default)]
+        x: f64,
+        #[serde(default)]
+        y: f64,
+        #[serde(default = "one")]
+        scale: f64,
+    },
+}
+
+fn sans_serif() -> String {
+    "sans-serif".into()
+}
+
+/// The wave of a grating or Gabor patch.
+#[derive(Debug, Deserialize)]
+pub struct Wave {
+    pub spatial_frequency: f64,
+    #[serde(default)]
+    pub orientation: f64,
+    #[serde(default)]
+    pub phase: f64,
+    #[serde(default = "one")]
+    pub contrast: f64,
+    #[serde(default)]
+    pub temporal_frequency: f64,
+    #[serde(default = "gray")]
+    pub mean: Color,
+}
+
+impl Wave {
+    /// The wave at a time (in seconds).
+    fn at(&self, time: f64) -> GratingWave {
+        GratingWave::new(self.spatial_frequency, self.orientation.to_radians())
+            .with_contrast(self.contrast)
+            .with_phase(self.phase.to_radians() + TAU * self.temporal_frequency * time)
+    }
+}
+
+impl SceneFile {
+    /// Read a scene from a JSON or RON file, by its extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_str(&source).with_context(|| format!("invalid scene {}", path.display())),
+            Some("ron") => ron::from_str(&source).with_context(|| format!("invalid scene {}", path.display())),
+            _ => bail!(
+                "unsupported scene file {} (expected .json, .ron or .svg)",
+                path.display()
+            ),
+        }
+    }
+
+    /// Load the resources (fonts and SVG files) of the elements, relative to `directory`.
+    pub fn prepare(self, directory: &Path) -> Result<PreparedScene> {
+        let background = self.background.to_rgba()?;
+        let mut fonts = None;
+        let elements = self
+            .elements
+            .into_iter()
+            .map(|element| {
+                Ok(match element {
+                    Element::Text {
+                        text,
+                        x,
+                        y,
+                        size,
+                        color,
+                        font,
+                    } => {
+                        let fonts = fonts.get_or_insert_with(|| Arc::new(FontRegistry::with_system_fonts()));
+                        let data = fonts
+                            .query(&font, 400, FontStyle::Normal)
+                            .with_context(|| format!("font {font:?} not found"))?;
+                        let font = VelloFont::from_data(&data)?.with_fallback(fonts.clone());
+                        let span = TextSpan::new(text, font, size, color.to_rgba()?);
+                        Prepared::Text(RichText::new(vec![span]).with_transform(Affine::translate(x, y)))
+                    }
+                    Element::Svg { path, x, y, scale } => {
+                        let path = directory.join(path);
+                        let source = std::fs::read_to_string(&path)
+                            .with_context(|| format!("failed to read {}", path.display()))?;
+                        let transform = Affine::scale(scale) * Affine::translate(x, y);
+                        Prepared::Svg(PrerenderedScene::from_svg_string(&source, transform)?)
+                    }
+                    element => Prepared::Element(element),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(PreparedScene {
+            width: self.width,
+            height: self.height,
+            background,
+            elements,
+        })
+    }
+}
+
+/// A scene with its resources loaded, ready to be drawn.
+pub struct PreparedScene {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub background: RGBA,
+    elements: Vec<Prepared>,
+}
+
+enum Prepared {
+    Element(Element),
+    Text(RichText<VelloFont>),
+    Svg(PrerenderedScene),
+}
+
+impl PreparedScene {
+    /// A scene that shows a single SVG document at its natural size.
+    pub fn from_svg(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let svg = PrerenderedScene::from_svg_string(&source, Affine::identity())?;
+        Ok(Self {
+            width: Some(svg.width.ceil() as u32),
+            height: Some(svg.height.ceil() as u32),
+            background: RGBA::TRANSPARENT,
+            elements: vec![Prepared::Svg(svg)],
+        })
+    }
+
+    /// Draw the scene at a time (in seconds).
+    pub fn draw(&mut self, scene: &mut VelloScene, time: f64) -> Result<()> {
+        for element in &mut self.elements {
+            match element {
+                Prepared::Element(element) => draw_element(element, scene, time)?,
+                Prepared::Text(text) => text.draw(scene),
+                Prepared::Svg(svg) => (&*svg).draw(scene),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn point(x: f64, y: f64) -> Point {
+    Point { x, y }
+}
+
+fn draw_shape(shape: impl Fillable, paint: &Paint, scene: &mut VelloScene) -> Result<()> {
+    if let Some(fill) = &paint.fill {
+        Geom::fill(shape.clone(), fill.to_rgba()?).draw(scene);
+    }
+    if let Some(stroke) = &paint.stroke {
+        Geom::stroke(shape.clone(), paint.stroke_width, stroke.to_rgba()?).draw(scene);
+    }
+    if paint.fill.is_none() && paint.stroke.is_none() {
+        Geom::new(shape, Style::Fill(FillStyle::NonZero), RGBA::WHITE).draw(scene);
+    }
+    Ok(())
+}
+
+fn draw_element(element: &Element, scene: &mut VelloScene, time: f64) -> Result<()> {
+    match element {
+        Element::Rect {
+            x,
+            y,
+            width,
+            height,
+            corner_radius,
+            paint,
+        } => {
+            let (a, b) = (point(*x, *y), point(x + width, y + height));
+            if *corner_radius > 0.0 {
+                let rect = RoundedRectangle {
+                    a,
+                    b,
+                    radius: *corner_radius,
+                };
+                draw_shape(rect, paint, scene)?;
+            } else {
+                draw_shape(Rectangle { a, b }, paint, scene)?;
+            }
+        }
+        Element::Circle { x, y, radius, paint } => {
+            let circle = Circle {
+                center: point(*x, *y),
+                radius: *radius,
+            };
+            draw_shape(circle, paint, scene)?;
+        }
+        Element::Polygon { points, paint } => {
+            let points = points.iter().map(|&[x, y]| point(x, y)).collect();
+            draw_shape(Polygon { points }, paint, scene)?;
+        }
+        Element::Line {
+            x1,
+            y1,
+            x2,
+            y2,
+            color,
+            width,
+        } => {
+            let line = Line {
+                start: point(*x1, *y1),
+                end: point(*x2, *y2),
+            };
+            Geom::new(line, Style::Stroke(StrokeOptions::new(*width)), color.to_rgba()?).draw(scene);
+        }
+        Element::Grating {
+            x,
+            y,
+            width,
+            height,
+            wave,
+        } => {
+            Grating::new(wave.at(time), wave.mean.to_rgba()?, *width, *height)
+                .with_transform(Affine::translate(*x, *y))
+                .draw(scene);
+        }
+        Element::Gabor { x, y, sigma, wave } => {
+            Gabor::new(wave.at(time), *sigma, wave.mean.to_rgba()?)
+                .with_transform(Affine::translate(*x, *y))
+                .draw(scene);
+        }
+        // loaded by `SceneFile::prepare`
+        Element::Text { .. } | Element::Svg { .. } => {}
+    }
+    Ok(())
+}
@@ -1,6 +1,12 @@
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use image::GenericImageView;
+
+use crate::error::{RendererError, Result};
 use crate::styles::{ImageFitMode};
-use super::{colors::RGBA, shapes::Point};
+use super::{affine::Affine, colors::RGBA, shapes::Point};
 
 #[derive(Debug, Clone)]
 pub enum Brush {
@@ -14,27 +20,486 @@ pub enum Brush {
         x: f64,
         y: f64,
         fit_mode: ImageFitMode,
+        /// How the image is extended beyond its edges (horizontally, and vertically unless
+        /// `edge_mode_y` is set).
         edge_mode: Extend,
+        /// A separate vertical edge mode, e.g. to repeat an image horizontally but pad it vertically.
+        edge_mode_y: Option<Extend>,
+        /// Per-pixel color mapping applied to the image.
+        mapping: ImageMapping,
     },
 }
 
+impl Brush {
+    /// A copy of the brush with a function applied to its colors (the color of a solid brush or
+    /// the stops of a gradient). Image brushes are returned unchanged.
+    pub fn map_colors(&self, f: impl Fn(RGBA) -> RGBA) -> Brush {
+        match self {
+            Brush::Solid(color) => Brush::Solid(f(*color)),
+            Brush::Gradient(gradient) => {
+                let mut gradient = gradient.clone();
+                for stop in &mut gradient.stops {
+                    stop.color = f(stop.color);
+                }
+                Brush::Gradient(gradient)
+            }
+            Brush::Image { .. } => self.clone(),
+        }
+    }
+}
+
+impl From<RGBA> for Brush {
+    fn from(color: RGBA) -> Self {
+        Brush::Solid(color)
+    }
+}
+
+impl From<Gradient> for Brush {
+    fn from(gradient: Gradient) -> Self {
+        Brush::Gradient(gradient)
+    }
+}
+
+/// A source for an output channel of a [`ImageMapping::swizzle`].
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Channel {
+    R,
+    G,
+    B,
+    A,
+    /// Constant 0.
+    Zero,
+    /// Constant 1.
+    One,
+}
+
+/// Makes pixels of a specific color transparent.
+#[derive(Debug, Clone, Copy)]
+pub struct ColorKey {
+    /// The color to remove (alpha is ignored).
+    pub color: RGBA,
+    /// Maximum per-channel difference (0-1) for a pixel to match.
+    pub tolerance: f32,
+}
+
+/// Per-pixel color operations applied to an image brush, in the order: color key (on the
+/// original colors), swizzle, colormap.
+#[derive(Debug, Clone, Default)]
+pub struct ImageMapping {
+    /// Pixels matching the key become fully transparent.
+    pub color_key: Option<ColorKey>,
+    /// The source channel of each output channel (r, g, b, a).
+    pub swizzle: Option<[Channel; 4]>,
+    /// Map a single channel of the image through a gradient (e.g. to colorize grayscale images).
+    pub colormap: Option<(Channel, Gradient)>,
+    /// Multiply every pixel with a color (e.g. to color alpha-only images).
+    pub tint: Option<RGBA>,
+}
+
+impl ImageMapping {
+    /// Whether the mapping leaves every pixel unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.color_key.is_none() && self.swizzle.is_none() && self.colormap.is_none() && self.tint.is_none()
+    }
+
+    /// Apply the mapping to RGBA8 pixel data.
+    pub fn apply(&self, data: &[u8]) -> Vec<u8> {
+        let colormap = self
+            .colormap
+            .as_ref()
+            .map(|(channel, gradient)| (*channel, gradient.to_lut(256).data));
+        let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as i32;
+        let tint = self.tint.map(|c| [c.r, c.g, c.b, c.a].map(|v| v.clamp(0.0, 1.0)));
+        let key = self.color_key.map(|key| {
+            (
+                [to_u8(key.color.r), to_u8(key.color.g), to_u8(key.color.b)],
+                to_u8(key.tolerance),
+            )
+        });
+
+        let pick = |pixel: &[u8], channel: Channel| match channel {
+            Channel::R => pixel[0],
+            Channel::G => pixel[1],
+            Channel::B => pixel[2],
+            Channel::A => pixel[3],
+            Channel::Zero => 0,
+            Channel::One => 255,
+        };
+
+        data.chunks_exact(4)
+            .flat_map(|pixel| {
+                let keyed = key.is_some_and(|(color, tolerance)| {
+                    (0..3).all(|i| (pixel[i] as i32 - color[i]).abs() <= tolerance)
+                });
+
+                let mut out = match self.swizzle {
+                    Some(swizzle) => swizzle.map(|channel| pick(pixel, channel)),
+                    None => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                };
+                if let Some((channel, lut)) = &colormap {
+                    let i = pick(pixel, *channel) as usize * 4;
+                    out = [lut[i], lut[i + 1], lut[i + 2], lut[i + 3]];
+                }
+                if let Some(tint) = tint {
+                    out = [0, 1, 2, 3].map(|i| (out[i] as f32 * tint[i]).round() as u8);
+                }
+                if keyed {
+                    out = [0, 0, 0, 0];
+                }
+                out
+            })
+            .collect()
+    }
+}
+
+impl Hash for ImageMapping {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // which operations are set, so that e.g. a color key and a tint of the same color differ
+        [
+            self.color_key.is_some(),
+            self.swizzle.is_some(),
+            self.colormap.is_some(),
+            self.tint.is_some(),
+        ]
+        .hash(state);
+        if let Some(key) = &self.color_key {
+            let c = key.color;
+            [c.r, c.g, c.b, key.tolerance].map(f32::to_bits).hash(state);
+        }
+        self.swizzle.hash(state);
+        if let Some((channel, gradient)) = &self.colormap {
+            channel.hash(state);
+            gradient.hash_ramp(state);
+        }
+        if let Some(c) = &self.tint {
+            [c.r, c.g, c.b, c.a].map(f32::to_bits).hash(state);
+        }
+    }
+}
+
+/// The pixel format of an [`Image`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageFormat {
+    /// Four bytes per pixel (red, green, blue, alpha).
+    #[default]
+    Rgba8,
+    /// One byte per pixel (alpha only), e.g. for masks and icons that are tinted when drawn.
+    /// Drawn as white with the given alpha.
+    Alpha8,
+}
+
+/// How an image is sampled between its pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ImageFilter {
+    /// Blend the nearest four pixels (bilinear filtering).
+    #[default]
+    Linear,
+    /// Use the nearest pixel, so that enlarged images keep hard pixel edges (e.g. for pixel art
+    /// or noise stimuli). The GPU backend approximates it by sampling a copy of the image that
+    /// is enlarged to about the size it is drawn at, which leaves edges soft by up to a pixel.
+    Nearest,
+}
+
 #[derive(Debug, Clone)]
 pub struct Image {
     /// Data of the image.
     pub data: Arc<Vec<u8>>,
     /// Gpu buffer (if available).
+    #[cfg(feature = "vello-backend")]
     pub gpu_texture: Option<Arc<wgpu::Texture>>,
+    /// The data the GPU texture is registered under. Its id stays the same across frames, so
+    /// the renderer keeps the texture registered until it is invalidated.
+    #[cfg(feature = "vello-backend")]
+    pub gpu_blob: Option<vello::peniko::Blob<u8>>,
     /// The width of the image.
     pub width: u32,
     /// The height of the image.
     pub height: u32,
+    /// The format of `data`.
+    pub format: ImageFormat,
+    /// How the image is sampled.
+    pub filter: ImageFilter,
+    /// Sample smaller, prefiltered copies of the image (mip levels) when it is drawn at less
+    /// than half its size, so that shrunk photographs don't alias. The levels are created the
+    /// first time they are needed.
+    pub mipmaps: bool,
+    /// The mip level of a GPU texture this image samples (see [`Image::mip_level`]).
+    #[cfg(feature = "vello-backend")]
+    pub gpu_mip_level: u32,
+    /// The blobs the mip levels of the GPU texture are registered under, from level 1.
+    #[cfg(feature = "vello-backend")]
+    pub gpu_mip_blobs: Vec<vello::peniko::Blob<u8>>,
+    /// The mip levels from level 1 and the data they were made from, shared by clones of the
+    /// image. Holding the data means that changing it (e.g. with `Arc::make_mut`) gives it a
+    /// new address, so the levels are made again.
+    mip_chain: Arc<Mutex<Option<MipChain>>>,
+    /// The data of an alpha-only image expanded to RGBA8 and the data it was made from, shared
+    /// by clones like the mip levels.
+    rgba8: Arc<Mutex<Option<ExpandedData>>>,
+}
+
+/// Mip levels from level 1 and the data they were made from.
+type MipChain = (Arc<Vec<u8>>, Arc<Vec<Image>>);
+
+/// Pixel data expanded to RGBA8 and the data it was made from.
+type ExpandedData = (Arc<Vec<u8>>, Arc<Vec<u8>>);
+
+impl Image {
+    /// Create a new texture from an image::DynamicImage.
+    pub fn new(image: &image::DynamicImage) -> Self {
+        let data = Arc::new(image.clone().into_rgba8().into_vec());
+
+        Self {
+            #[cfg(feature = "vello-backend")]
+            gpu_texture: None,
+            #[cfg(feature = "vello-backend")]
+            gpu_blob: None,
+            #[cfg(feature = "vello-backend")]
+            gpu_mip_level: 0,
+            #[cfg(feature = "vello-backend")]
+            gpu_mip_blobs: Vec::new(),
+            data,
+            width: image.width(),
+            height: image.height(),
+            format: ImageFormat::Rgba8,
+            filter: ImageFilter::Linear,
+            mipmaps: true,
+            mip_chain: Default::default(),
+            rgba8: Default::default(),
+        }
+    }
+
+    /// Create an alpha-only image from one byte per pixel. The data is kept with one byte per
+    /// pixel and only expanded when a backend needs RGBA pixels (e.g. to upload it to the GPU).
+    ///
+    /// Fails if the length of the data doesn't match the size.
+    pub fn new_alpha(data: Vec<u8>, width: u32, height: u32) -> Result<Self> {
+        let pixels = width as u64 * height as u64;
+        if data.len() as u64 != pixels {
+            return Err(RendererError::InvalidArgument(format!(
+                "an alpha image of {width}x{height} pixels needs {pixels} bytes, not {}",
+                data.len()
+            )));
+        }
+        Ok(Self::alpha(data, width, height))
+    }
+
+    /// Create an alpha-only image from data with one byte per pixel.
+    fn alpha(data: Vec<u8>, width: u32, height: u32) -> Self {
+        Self {
+            #[cfg(feature = "vello-backend")]
+            gpu_texture: None,
+            #[cfg(feature = "vello-backend")]
+            gpu_blob: None,
+            #[cfg(feature = "vello-backend")]
+            gpu_mip_level: 0,
+            #[cfg(feature = "vello-backend")]
+            gpu_mip_blobs: Vec::new(),
+            data: Arc::new(data),
+            width,
+            height,
+            format: ImageFormat::Alpha8,
+            filter: ImageFilter::Linear,
+            mipmaps: true,
+            mip_chain: Default::default(),
+            rgba8: Default::default(),
+        }
+    }
+
+    /// Create an alpha-only image from the luminance of an image (e.g. a grayscale mask).
+    pub fn alpha_from_luminance(image: &image::DynamicImage) -> Self {
+        let luma = image.to_luma8();
+        Self::alpha(luma.into_raw(), image.width(), image.height())
+    }
+
+    /// The pixels as RGBA8, expanding alpha-only images to white.
+    pub fn to_rgba8(&self) -> Cow<'_, [u8]> {
+        match self.format {
+            ImageFormat::Rgba8 => Cow::Borrowed(&self.data),
+            ImageFormat::Alpha8 => Cow::Owned(self.data.iter().flat_map(|a| [255, 255, 255, *a]).collect()),
+        }
+    }
+
+    /// The pixels as RGBA8 like [`Image::to_rgba8`], but shared: alpha-only images are expanded
+    /// once and the result is kept until the data changes.
+    pub fn rgba8_data(&self) -> Arc<Vec<u8>> {
+        if self.format == ImageFormat::Rgba8 {
+            return self.data.clone();
+        }
+        let mut cached = self.rgba8.lock().expect("image data lock poisoned");
+        match &*cached {
+            Some((data, rgba8)) if Arc::ptr_eq(data, &self.data) => rgba8.clone(),
+            _ => {
+                let rgba8 = Arc::new(self.to_rgba8().into_owned());
+                *cached = Some((self.data.clone(), rgba8.clone()));
+                rgba8
+            }
+        }
+    }
+
+    /// Set how the image is sampled.
+    pub fn with_filter(mut self, filter: ImageFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Enable or disable mipmapping (enabled by default).
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// The number of mip levels, including the image itself, down to a single pixel.
+    pub fn mip_level_count(&self) -> u32 {
+        32 - self.width.max(self.height).max(1).leading_zeros()
+    }
+
+    /// A mip level of the image: level 0 is the image itself, and every further level has half
+    /// the size of the previous one (rounded down, at least a pixel). Levels are averaged from
+    /// the image with the colors weighted by their alpha, so that transparent pixels don't
+    /// darken the edges. Levels past the last one return the last level.
+    pub fn mip_level(&self, level: u32) -> Image {
+        if level == 0 {
+            return self.clone();
+        }
+        let chain = {
+            let mut cached = self.mip_chain.lock().expect("mip chain lock poisoned");
+            match &*cached {
+                Some((data, chain)) if Arc::ptr_eq(data, &self.data) => chain.clone(),
+                _ => {
+                    let mut chain = Vec::new();
+                    let mut previous = self;
+                    for _ in 1..self.mip_level_count() {
+                        chain.push(previous.downsample());
+                        previous = chain.last().expect("just pushed");
+                    }
+                    let chain = Arc::new(chain);
+                    *cached = Some((self.data.clone(), chain.clone()));
+                    chain
+                }
+            }
+        };
+        let Some(mut mip) = chain.get(level as usize - 1).or(chain.last()).cloned() else {
+            return self.clone();
+        };
+        #[cfg(feature = "vello-backend")]
+        if let Some(blob) = self.gpu_mip_blobs.get(level as usize - 1) {
+            mip.gpu_texture = self.gpu_texture.clone();
+            mip.gpu_blob = Some(blob.clone());
+            mip.gpu_mip_level = level;
+        }
+        mip.filter = self.filter;
+        mip
+    }
+
+    /// The mip level to sample when the image is drawn with `transform` (from image pixels to
+    /// device pixels), together with the transform from the pixels of the level to the pixels
+    /// of the image. Returns `None` if the image itself is sampled, i.e. if mipmapping is
+    /// disabled or the image is shrunk to no less than half its size along both axes.
+    pub fn mip_level_for(&self, transform: Affine) -> Option<(Image, Affine)> {
+        if !self.mipmaps {
+            return None;
+        }
+        let [a, b, c, d, _, _] = transform.0;
+        // choose the level by the axis that is shrunk the most, so that neither aliases
+        let scale = a.hypot(b).min(c.hypot(d));
+        if scale <= 0.0 || !scale.is_finite() {
+            return None;
+        }
+        let level = ((1.0 / scale).log2().floor().max(0.0) as u32).min(self.mip_level_count() - 1);
+        if level == 0 {
+            return None;
+        }
+        let mip = self.mip_level(level);
+        let to_image = Affine::scale_xy(self.width as f64 / mip.width as f64, self.height as f64 / mip.height as f64);
+        Some((mip, to_image))
+    }
+
+    /// The image at half its size, averaging the pixels each new pixel covers.
+    fn downsample(&self) -> Image {
+        let (width, height) = (self.width, self.height);
+        let (new_width, new_height) = ((width / 2).max(1), (height / 2).max(1));
+        let channels = match self.format {
+            ImageFormat::Rgba8 => 4,
+            ImageFormat::Alpha8 => 1,
+        };
+        // the range of source pixels covered by a pixel of the smaller image
+        let span = |i: u32, size: u32, new_size: u32| {
+            let start = i * size / new_size;
+            start..((i + 1) * size / new_size).max(start + 1)
+        };
+
+        let mut data = Vec::with_capacity((new_width * new_height * channels) as usize);
+        for y in 0..new_height {
+            for x in 0..new_width {
+                let mut sum = [0u32; 4];
+                let mut count = 0;
+                for sy in span(y, height, new_height) {
+                    for sx in span(x, width, new_width) {
+                        let i = ((sy * width + sx) * channels) as usize;
+                        let pixel = &self.data[i..i + channels as usize];
+                        if channels == 4 {
+                            let alpha = pixel[3] as u32;
+                            for channel in 0..3 {
+                                sum[channel] += pixel[channel] as u32 * alpha;
+                            }
+                            sum[3] += alpha;
+                        } else {
+                            sum[0] += pixel[0] as u32;
+                        }
+                        count += 1;
+                    }
+                }
+                if channels == 4 {
+                    let alpha = sum[3];
+                    let color = |channel: usize| (sum[channel] + alpha / 2).checked_div(alpha).unwrap_or(0);
+                    data.extend([color(0), color(1), color(2), (alpha + count / 2) / count].map(|v| v as u8));
+                } else {
+                    data.push(((sum[0] + count / 2) / count) as u8);
+                }
+            }
+        }
+
+        self.with_pixels(data, new_width, new_height)
+    }
+
+    /// A new image in the format and with the filter of this one (and without mipmaps).
+    pub(crate) fn with_pixels(&self, data: Vec<u8>, width: u32, height: u32) -> Image {
+        Image {
+            data: Arc::new(data),
+            #[cfg(feature = "vello-backend")]
+            gpu_texture: None,
+            #[cfg(feature = "vello-backend")]
+            gpu_blob: None,
+            #[cfg(feature = "vello-backend")]
+            gpu_mip_level: 0,
+            #[cfg(feature = "vello-backend")]
+            gpu_mip_blobs: Vec::new(),
+            width,
+            height,
+            format: self.format,
+            filter: self.filter,
+            mipmaps: false,
+            mip_chain: Default::default(),
+            rgba8: Default::default(),
+        }
+    }
 }
 
+/// The maximum number of stops of gradients created by [`Gradient::from_fn_auto`].
+const MAX_FUNCTION_STOPS: usize = 1024;
+
 #[derive(Debug, Clone)]
 pub struct Gradient {
     pub extend: Extend,
     pub kind: GradientKind,
     pub stops: Vec<ColorStop>,
+    /// Contrast of the stop colors relative to their mean color (1 = unchanged, 0 = uniform mean).
+    pub contrast: f32,
+    /// Cyclic shift of the stop offsets, in units of the gradient length (0 = unchanged).
+    pub phase: f32,
+    /// Blend blue noise of one 8-bit step into filled gradients to hide banding in smooth ramps.
+    pub dither: bool,
 }
 
 impl Gradient {
@@ -51,11 +516,205 @@ impl Gradient {
             extend,
             kind,
             stops,
+            contrast: 1.0,
+            phase: 0.0,
+            dither: false,
+        }
+    }
+
+    /// Create a gradient with `n` (at least 2) equidistant stops whose colors are sampled from a
+    /// function of the offset in [0, 1], e.g. for sine or Gaussian luminance profiles.
+    pub fn from_fn(extend: Extend, kind: GradientKind, n: usize, f: impl Fn(f32) -> RGBA) -> Self {
+        let n = n.max(2);
+        let colors = (0..n).map(|i| f(i as f32 / (n - 1) as f32)).collect::<Vec<_>>();
+        Self::new_equidistant(extend, kind, &colors)
+    }
+
+    /// Create a gradient from a function like [`Gradient::from_fn`], with about one stop per
+    /// device pixel when drawn with `transform` (the transform from gradient space to device
+    /// pixels, e.g. the geom transform followed by [`Scene::view_transform`]).
+    ///
+    /// [`Scene::view_transform`]: crate::scenes::Scene::view_transform
+    pub fn from_fn_auto(extend: Extend, kind: GradientKind, transform: Affine, f: impl Fn(f32) -> RGBA) -> Self {
+        let scale = transform.determinant().abs().sqrt();
+        let distance = |a: Point, b: Point| (b.x - a.x).hypot(b.y - a.y);
+        let length = match kind {
+            GradientKind::Linear { start, end } => distance(start, end) * scale,
+            GradientKind::Radial {
+                start_center,
+                start_radius,
+                end_center,
+                end_radius,
+            } => (distance(start_center, end_center) + (end_radius - start_radius).abs() as f64) * scale,
+            // the radius of a sweep isn't known, so use one stop per degree
+            GradientKind::Sweep { .. } => 360.0,
+        };
+        let n = (length.ceil() as usize).clamp(2, MAX_FUNCTION_STOPS);
+        Self::from_fn(extend, kind, n, f)
+    }
+
+    /// Set the contrast of the gradient.
+    pub fn with_contrast(mut self, contrast: f32) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Set the phase of the gradient.
+    pub fn with_phase(mut self, phase: f32) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Enable or disable dithering of the gradient.
+    pub fn with_dither(mut self, dither: bool) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Hash the color ramp (stops, contrast, phase and extend mode) of the gradient.
+    pub fn hash_ramp<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(&self.extend).hash(state);
+        [self.contrast, self.phase].map(f32::to_bits).hash(state);
+        for stop in &self.stops {
+            let c = stop.color;
+            [stop.offset, c.r, c.g, c.b, c.a].map(f32::to_bits).hash(state);
+        }
+    }
+
+    /// The stops with contrast and phase applied.
+    ///
+    /// Contrast scales the color (not alpha) of every stop around the mean color of the ramp over
+    /// [0, 1], weighted by the offsets of the stops. Phase shifts the ramp cyclically within
+    /// [0, 1]; stops are added at 0 and 1 so the ramp stays continuous where it wraps around.
+    pub fn resolved_stops(&self) -> Vec<ColorStop> {
+        if self.stops.is_empty() || (self.contrast == 1.0 && self.phase == 0.0) {
+            return self.stops.clone();
+        }
+
+        let mut stops = self.stops.clone();
+        stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        if self.contrast != 1.0 {
+            let mean = mean_color(&stops);
+            for stop in &mut stops {
+                let c = &mut stop.color;
+                c.r = mean.r + (c.r - mean.r) * self.contrast;
+                c.g = mean.g + (c.g - mean.g) * self.contrast;
+                c.b = mean.b + (c.b - mean.b) * self.contrast;
+            }
+        }
+
+        let phase = self.phase.rem_euclid(1.0);
+        if phase != 0.0 {
+            // the color at the wrap point, which ends up at offsets 0 and 1
+            let wrap = sample_stops(&stops, 1.0 - phase);
+            // pin the ends of the ramp, so that padding before the first and after the last stop
+            // survives the rotation as a hard step where offset 1 meets offset 0
+            if stops[0].offset > 0.0 {
+                stops.insert(0, ColorStop { offset: 0.0, color: stops[0].color });
+            }
+            if stops[stops.len() - 1].offset < 1.0 {
+                stops.push(ColorStop { offset: 1.0, color: stops[stops.len() - 1].color });
+            }
+            // rotate the ramp: the stops that wrap around past 1 come first, so that they end
+            // up before the stops they now share an offset with
+            let (wrapped, unwrapped): (Vec<_>, Vec<_>) =
+                stops.into_iter().partition(|stop| stop.offset + phase >= 1.0);
+            stops = std::iter::once(ColorStop { offset: 0.0, color: wrap })
+                .chain(wrapped.into_iter().map(|stop| ColorStop { offset: stop.offset + phase - 1.0, ..stop }))
+                .chain(unwrapped.into_iter().map(|stop| ColorStop { offset: stop.offset + phase, ..stop }))
+                .chain(std::iter::once(ColorStop { offset: 1.0, color: wrap }))
+                .collect();
+        }
+        stops
+    }
+
+    /// Evaluate the color of the gradient at the normalized offset `t`.
+    pub fn color_at(&self, t: f32) -> RGBA {
+        sample_stops(&self.resolved_stops(), t)
+    }
+
+    /// Sample the color ramp into a `resolution` x 1 image (a 1D lookup texture).
+    ///
+    /// Pixel `i` holds the color at offset `(i + 0.5) / resolution`, so the image maps the
+    /// gradient's [0, 1] range onto `[0, resolution]` in image space.
+    pub fn to_lut(&self, resolution: u32) -> Image {
+        let stops = self.resolved_stops();
+        let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let data = (0..resolution)
+            .flat_map(|i| {
+                let color = sample_stops(&stops, (i as f32 + 0.5) / resolution as f32);
+                [to_u8(color.r), to_u8(color.g), to_u8(color.b), to_u8(color.a)]
+            })
+            .collect::<Vec<_>>();
+
+        Image {
+            data: Arc::new(data),
+            #[cfg(feature = "vello-backend")]
+            gpu_texture: None,
+            #[cfg(feature = "vello-backend")]
+            gpu_blob: None,
+            #[cfg(feature = "vello-backend")]
+            gpu_mip_level: 0,
+            #[cfg(feature = "vello-backend")]
+            gpu_mip_blobs: Vec::new(),
+            width: resolution,
+            height: 1,
+            format: ImageFormat::Rgba8,
+            filter: ImageFilter::Linear,
+            // lookup textures are sampled at their offsets, not shrunk
+            mipmaps: false,
+            mip_chain: Default::default(),
+            rgba8: Default::default(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// Linearly interpolate the color of sorted stops at offset `t`.
+/// The mean color of the ramp over [0, 1], i.e. the integral of the piecewise linear ramp
+/// (padded with the first and last stop) divided by its length. `stops` must be sorted.
+fn mean_color(stops: &[ColorStop]) -> RGBA {
+    let (first, last) = (&stops[0], &stops[stops.len() - 1]);
+    let start = first.offset.clamp(0.0, 1.0);
+    let end = last.offset.clamp(0.0, 1.0);
+    let mut sum = [
+        first.color.r * start + last.color.r * (1.0 - end),
+        first.color.g * start + last.color.g * (1.0 - end),
+        first.color.b * start + last.color.b * (1.0 - end),
+    ];
+    for pair in stops.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        let width = b.offset.clamp(0.0, 1.0) - a.offset.clamp(0.0, 1.0);
+        sum[0] += (a.color.r + b.color.r) / 2.0 * width;
+        sum[1] += (a.color.g + b.color.g) / 2.0 * width;
+        sum[2] += (a.color.b + b.color.b) / 2.0 * width;
+    }
+    RGBA::new(sum[0], sum[1], sum[2], 1.0)
+}
+
+fn sample_stops(stops: &[ColorStop], t: f32) -> RGBA {
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return RGBA::TRANSPARENT,
+    };
+    if t <= first.offset {
+        return first.color;
+    }
+    if t >= last.offset {
+        return last.color;
+    }
+
+    let i = stops.partition_point(|stop| stop.offset <= t);
+    let (a, b) = (&stops[i - 1], &stops[i]);
+    let f = if b.offset > a.offset { (t - a.offset) / (b.offset - a.offset) } else { 0.0 };
+    RGBA::new(
+        a.color.r + (b.color.r - a.color.r) * f,
+        a.color.g + (b.color.g - a.color.g) * f,
+        a.color.b + (b.color.b - a.color.b) * f,
+        a.color.a + (b.color.a - a.color.a) * f,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Extend {
     /// Extends the image by repeating the edge color of the brush.
     Pad,
@@ -0,0 +1,138 @@
+// display calibration applied to the final frame
+use image::RgbaImage;
+
+use super::error::{RendererError, Result};
+
+/// Per-channel lookup tables that map the values of a rendered frame to the values sent to the
+/// display, e.g. to linearize a monitor measured with a photometer.
+///
+/// Each table maps equally spaced input values from 0 to 1 to output values and is interpolated
+/// linearly between its entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayCalibration {
+    pub r: Vec<f32>,
+    pub g: Vec<f32>,
+    pub b: Vec<f32>,
+}
+
+impl DisplayCalibration {
+    /// Create a calibration from lookup tables with at least two finite entries each.
+    pub fn from_luts(r: Vec<f32>, g: Vec<f32>, b: Vec<f32>) -> Result<Self> {
+        for lut in [&r, &g, &b] {
+            if lut.len() < 2 {
+                return Err(invalid("calibration lookup tables need at least two entries"));
+            }
+            if !lut.iter().all(|value| value.is_finite()) {
+                return Err(invalid("calibration lookup tables need finite entries"));
+            }
+        }
+        Ok(Self { r, g, b })
+    }
+
+    /// Correct a display with the given (positive) gamma per channel, so that the emitted light
+    /// is proportional to the rendered values.
+    pub fn gamma(r: f32, g: f32, b: f32) -> Result<Self> {
+        if ![r, g, b].iter().all(|gamma| gamma.is_finite() && *gamma > 0.0) {
+            return Err(invalid("display gammas need to be positive"));
+        }
+        let lut = |gamma: f32| {
+            (0..CALIBRATION_LUT_SIZE)
+                .map(|i| (i as f32 / (CALIBRATION_LUT_SIZE - 1) as f32).powf(1.0 / gamma))
+                .collect()
+        };
+        Self::from_luts(lut(r), lut(g), lut(b))
+    }
+
+    /// Linearize a display from the luminance it emits at equally spaced input values from 0 to 1
+    /// (per channel, e.g. measured with a photometer), so that the emitted light is proportional
+    /// to the rendered values. The measurements are normalized, may not decrease with the input
+    /// and have to increase overall.
+    pub fn from_measurements(r: &[f32], g: &[f32], b: &[f32]) -> Result<Self> {
+        Self::from_luts(invert(r)?, invert(g)?, invert(b)?)
+    }
+
+    /// The output values for an input color (components from 0 to 1).
+    pub fn apply(&self, [r, g, b]: [f32; 3]) -> [f32; 3] {
+        [sample(&self.r, r), sample(&self.g, g), sample(&self.b, b)]
+    }
+
+    /// Apply the calibration to an image on the CPU, e.g. to a frame that is shown by other means.
+    pub fn apply_to_image(&self, image: &mut RgbaImage) {
+        for pixel in image.pixels_mut() {
+            let [r, g, b, _] = pixel.0;
+            let color = self.apply([r, g, b].map(|value| value as f32 / 255.0));
+            for (value, calibrated) in pixel.0.iter_mut().zip(color) {
+                *value = (calibrated.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+    }
+
+    /// The tables resampled to [`CALIBRATION_LUT_SIZE`] entries each, one after the other.
+    #[cfg(feature = "vello-backend")]
+    pub(crate) fn resampled(&self) -> Vec<f32> {
+        [&self.r, &self.g, &self.b]
+            .into_iter()
+            .flat_map(|lut| {
+                (0..CALIBRATION_LUT_SIZE).map(|i| sample(lut, i as f32 / (CALIBRATION_LUT_SIZE - 1) as f32))
+            })
+            .collect()
+    }
+}
+
+/// The number of entries per channel of the lookup tables used when rendering.
+pub(crate) const CALIBRATION_LUT_SIZE: usize = 1024;
+
+/// Sample a lookup table with linear interpolation.
+fn sample(lut: &[f32], value: f32) -> f32 {
+    let position = value.clamp(0.0, 1.0) * (lut.len() - 1) as f32;
+    let index = (position as usize).min(lut.len() - 2);
+    let t = position - index as f32;
+    lut[index] + (lut[index + 1] - lut[index]) * t
+}
+
+/// An invalid argument error.
+fn invalid(message: &str) -> RendererError {
+    RendererError::InvalidArgument(message.to_string())
+}
+
+/// The lookup table that maps a normalized luminance to the input value producing it.
+fn invert(measurements: &[f32]) -> Result<Vec<f32>> {
+    if measurements.len() < 2 {
+        return Err(invalid("calibration needs at least two measurements"));
+    }
+    if !measurements.iter().all(|value| value.is_finite()) {
+        return Err(invalid("calibration measurements need to be finite"));
+    }
+    if measurements.windows(2).any(|pair| pair[1] < pair[0]) {
+        return Err(invalid("calibration measurements may not decrease with the input"));
+    }
+    // the measurements don't decrease, so the first and last are the extremes
+    let (min, max) = (measurements[0], measurements[measurements.len() - 1]);
+    if max <= min {
+        return Err(invalid("calibration measurements need to increase with the input"));
+    }
+    let normalized = measurements
+        .iter()
+        .map(|value| (value - min) / (max - min))
+        .collect::<Vec<_>>();
+    let step = 1.0 / (measurements.len() - 1) as f32;
+    let lut = (0..CALIBRATION_LUT_SIZE)
+        .map(|i| {
+            let target = i as f32 / (CALIBRATION_LUT_SIZE - 1) as f32;
+            // the first measurement at or above the target, interpolated with the one before it
+            let above = normalized
+                .iter()
+                .position(|&value| value >= target)
+                .unwrap_or(normalized.len() - 1)
+                .max(1);
+            let (low, high) = (normalized[above - 1], normalized[above]);
+            let t = if high > low {
+                ((target - low) / (high - low)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            (above - 1) as f32 * step + t * step
+        })
+        .collect();
+    Ok(lut)
+}
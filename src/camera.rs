@@ -0,0 +1,57 @@
+use super::affine::Affine;
+use super::shapes::Point;
+
+/// A view onto the scene (pan, zoom and rotation), applied on top of the global transform.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    /// The point in scene coordinates that appears at the center of the view.
+    pub position: Point,
+    /// Zoom factor, 1.0 shows the scene at its original scale.
+    pub zoom: f64,
+    /// Rotation of the camera in radians (the content appears rotated the opposite way).
+    pub rotation: f64,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: Point { x: 0.0, y: 0.0 },
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl Camera {
+    /// Create a camera looking at the given point.
+    pub fn new(position: Point, zoom: f64, rotation: f64) -> Self {
+        Self {
+            position,
+            zoom,
+            rotation,
+        }
+    }
+
+    /// Move the camera by the given offset in scene coordinates.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.position.x += dx;
+        self.position.y += dy;
+    }
+
+    /// Multiply the zoom by `factor`, keeping the scene point at `anchor` fixed on screen.
+    pub fn zoom_at(&mut self, factor: f64, anchor: Point) {
+        self.zoom *= factor;
+        self.position.x = anchor.x - (anchor.x - self.position.x) / factor;
+        self.position.y = anchor.y - (anchor.y - self.position.y) / factor;
+    }
+
+    /// The transform from scene coordinates to (centered) view coordinates.
+    pub fn transform(&self) -> Affine {
+        Affine::translate(-self.position.x, -self.position.y) * Affine::rotate(-self.rotation) * Affine::scale(self.zoom)
+    }
+
+    /// The transform from (centered) view coordinates to scene coordinates.
+    pub fn inverse_transform(&self) -> Affine {
+        Affine::scale(1.0 / self.zoom) * Affine::rotate(self.rotation) * Affine::translate(self.position.x, self.position.y)
+    }
+}
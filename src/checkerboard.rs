@@ -0,0 +1,270 @@
+// checkerboard stimuli
+use std::f64::consts::TAU;
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::colors::RGBA;
+use super::geoms::Geom;
+use super::scenes::Scene;
+use super::shapes::{Annulus, Path, Point, Rectangle, PATH_TOLERANCE};
+use super::Drawable;
+
+/// A checkerboard of square checks, drawn as paths so that it stays crisp under any transform.
+///
+/// The board fills the rectangle from the origin of `transform` to `(width, height)`. The check
+/// whose corner is at `phase` has the first color.
+#[derive(Debug, Clone)]
+pub struct Checkerboard {
+    /// The side length of a check, in scene units.
+    pub check_size: f64,
+    /// The colors of the checks.
+    pub colors: [RGBA; 2],
+    /// The offset of the pattern, in scene units.
+    pub phase: Point,
+    pub width: f64,
+    pub height: f64,
+    pub transform: Affine,
+    /// Swap the colors of the checks (see [`Checkerboard::reverse`]).
+    pub reversed: bool,
+    /// Whether the board is drawn.
+    pub visible: bool,
+}
+
+impl Checkerboard {
+    pub fn new(check_size: f64, colors: [RGBA; 2], width: f64, height: f64) -> Self {
+        Self {
+            check_size,
+            colors,
+            phase: Point { x: 0.0, y: 0.0 },
+            width,
+            height,
+            transform: Affine::identity(),
+            reversed: false,
+            visible: true,
+        }
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Set the offset of the pattern.
+    pub fn with_phase(mut self, phase: Point) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Swap the colors of the checks, e.g. every few frames for a contrast-reversing
+    /// (counterphase) stimulus.
+    pub fn reverse(&mut self) {
+        self.reversed = !self.reversed;
+    }
+
+    fn rect(&self) -> Rectangle {
+        Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: self.width,
+                y: self.height,
+            },
+        }
+    }
+
+    /// The checks of the second color, clipped to the board.
+    fn checks(&self) -> Path {
+        let mut path = kurbo::BezPath::new();
+        let size = self.check_size;
+        let cell = |value: f64, phase: f64| ((value - phase) / size).floor() as i64;
+        let (columns, rows) = (
+            cell(0.0, self.phase.x)..=cell(self.width, self.phase.x),
+            cell(0.0, self.phase.y)..=cell(self.height, self.phase.y),
+        );
+        for row in rows {
+            let y0 = (self.phase.y + row as f64 * size).max(0.0);
+            let y1 = (self.phase.y + (row + 1) as f64 * size).min(self.height);
+            for column in columns.clone().filter(|column| (column + row).rem_euclid(2) == 1) {
+                let x0 = (self.phase.x + column as f64 * size).max(0.0);
+                let x1 = (self.phase.x + (column + 1) as f64 * size).min(self.width);
+                if x1 > x0 && y1 > y0 {
+                    path.extend(kurbo::Shape::path_elements(&kurbo::Rect::new(x0, y0, x1, y1), 0.0));
+                }
+            }
+        }
+        path.into()
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for Checkerboard {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible || self.check_size <= 0.0 {
+            return;
+        }
+        let [first, second] = if self.reversed {
+            [self.colors[1], self.colors[0]]
+        } else {
+            self.colors
+        };
+        Geom::fill(self.rect(), first)
+            .with_transform(self.transform)
+            .draw(scene);
+        Geom::fill(self.checks(), second)
+            .with_transform(self.transform)
+            .draw(scene);
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        Some(
+            Geom::fill(self.rect(), self.colors[0])
+                .with_transform(self.transform)
+                .bounding_box(),
+        )
+    }
+}
+
+/// A polar checkerboard of rings and wedges (a dartboard), e.g. for retinotopic mapping, drawn
+/// as paths so that it stays crisp under any transform.
+///
+/// The board is centered on the origin of `transform`. The check of the innermost ring that
+/// starts at `rotation` has the first color.
+#[derive(Debug, Clone)]
+pub struct RadialCheckerboard {
+    /// The number of rings.
+    pub rings: u32,
+    /// The number of wedges.
+    pub wedges: u32,
+    /// The radius of the board.
+    pub radius: f64,
+    /// The radius of the hole in the center (e.g. around a fixation point).
+    pub inner_radius: f64,
+    /// Space the rings logarithmically instead of evenly, so that they grow with eccentricity
+    /// like the cortical magnification (needs an inner radius above 0).
+    pub logarithmic: bool,
+    /// The angle the first wedge starts at, in radians, e.g. to rotate the board.
+    pub rotation: f64,
+    /// The colors of the checks.
+    pub colors: [RGBA; 2],
+    pub transform: Affine,
+    /// Swap the colors of the checks (see [`RadialCheckerboard::reverse`]).
+    pub reversed: bool,
+    /// Whether the board is drawn.
+    pub visible: bool,
+}
+
+impl RadialCheckerboard {
+    pub fn new(rings: u32, wedges: u32, radius: f64, colors: [RGBA; 2]) -> Self {
+        Self {
+            rings,
+            wedges,
+            radius,
+            inner_radius: 0.0,
+            logarithmic: false,
+            rotation: 0.0,
+            colors,
+            transform: Affine::identity(),
+            reversed: false,
+            visible: true,
+        }
+    }
+
+    /// Set the radius of the hole in the center.
+    pub fn with_inner_radius(mut self, inner_radius: f64) -> Self {
+        self.inner_radius = inner_radius;
+        self
+    }
+
+    /// Space the rings logarithmically.
+    pub fn with_logarithmic(mut self, logarithmic: bool) -> Self {
+        self.logarithmic = logarithmic;
+        self
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Swap the colors of the checks, e.g. every few frames for a contrast-reversing
+    /// (counterphase) stimulus.
+    pub fn reverse(&mut self) {
+        self.reversed = !self.reversed;
+    }
+
+    /// The radius of the inner edge of a ring (`rings` gives the outer radius of the board).
+    fn ring_radius(&self, ring: u32) -> f64 {
+        let t = ring as f64 / self.rings as f64;
+        if self.logarithmic && self.inner_radius > 0.0 {
+            self.inner_radius * (self.radius / self.inner_radius).powf(t)
+        } else {
+            self.inner_radius + (self.radius - self.inner_radius) * t
+        }
+    }
+
+    fn annulus(&self) -> Annulus {
+        Annulus {
+            center: Point { x: 0.0, y: 0.0 },
+            inner_radius: self.inner_radius,
+            outer_radius: self.radius,
+        }
+    }
+
+    /// The checks of the second color.
+    fn checks(&self) -> Path {
+        let mut path = kurbo::BezPath::new();
+        let sweep = TAU / self.wedges as f64;
+        let arc = |radius: f64, start: f64, sweep: f64| kurbo::Arc {
+            center: kurbo::Point::ORIGIN,
+            radii: kurbo::Vec2::new(radius, radius),
+            start_angle: start,
+            sweep_angle: sweep,
+            x_rotation: 0.0,
+        };
+        for ring in 0..self.rings {
+            let (inner, outer) = (self.ring_radius(ring), self.ring_radius(ring + 1));
+            for wedge in (0..self.wedges).filter(|wedge| (wedge + ring) % 2 == 1) {
+                let start = self.rotation + wedge as f64 * sweep;
+                // along the outer edge, then back along the inner one
+                path.extend(kurbo::Shape::path_elements(&arc(outer, start, sweep), PATH_TOLERANCE));
+                if inner > 0.0 {
+                    let inner_arc = arc(inner, start + sweep, -sweep);
+                    let end = kurbo::Point::new(inner * (start + sweep).cos(), inner * (start + sweep).sin());
+                    path.line_to(end);
+                    path.extend(kurbo::Shape::path_elements(&inner_arc, PATH_TOLERANCE).skip(1));
+                } else {
+                    path.line_to(kurbo::Point::ORIGIN);
+                }
+                path.close_path();
+            }
+        }
+        path.into()
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for RadialCheckerboard {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible || self.rings == 0 || self.wedges == 0 {
+            return;
+        }
+        let [first, second] = if self.reversed {
+            [self.colors[1], self.colors[0]]
+        } else {
+            self.colors
+        };
+        Geom::fill(self.annulus(), first)
+            .with_transform(self.transform)
+            .draw(scene);
+        Geom::fill(self.checks(), second)
+            .with_transform(self.transform)
+            .draw(scene);
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        Some(
+            Geom::fill(self.annulus(), self.colors[0])
+                .with_transform(self.transform)
+                .bounding_box(),
+        )
+    }
+}
@@ -1,5 +1,8 @@
 #[derive(Debug, Clone, Copy)]
 /// A color with red, green, blue, and alpha components.
+///
+/// The components are sRGB-encoded; use [`RGBA::from_space`] for colors specified in other
+/// color spaces.
 pub struct RGBA {
     pub r: f32,
     pub g: f32,
@@ -12,6 +15,121 @@ impl RGBA {
         Self { r, g, b, a }
     }
 
+    /// Create an opaque color.
+    pub fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::new(r, g, b, 1.0)
+    }
+
+    /// Create a color from components in the given color space. Colors outside of the sRGB gamut
+    /// (e.g. saturated Display P3 colors) get components outside of 0 to 1, which are kept until
+    /// they are converted to the output color space.
+    pub fn from_space(space: ColorSpace, r: f32, g: f32, b: f32, a: f32) -> Self {
+        let [r, g, b] = match space {
+            ColorSpace::Srgb => return Self::new(r, g, b, a),
+            ColorSpace::LinearSrgb => [r, g, b],
+            ColorSpace::DisplayP3 => mul(&P3_TO_SRGB, [r, g, b].map(srgb_to_linear)),
+        };
+        let [r, g, b] = [r, g, b].map(linear_to_srgb);
+        Self::new(r, g, b, a)
+    }
+
+    /// Create a color from linear sRGB components, e.g. to specify colors by relative luminance.
+    pub fn linear(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self::from_space(ColorSpace::LinearSrgb, r, g, b, a)
+    }
+
+    /// Create a color from Display P3 components.
+    pub fn display_p3(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self::from_space(ColorSpace::DisplayP3, r, g, b, a)
+    }
+
+    /// The red, green, blue and alpha components of the color in the given color space.
+    pub fn to_space(&self, space: ColorSpace) -> [f32; 4] {
+        let linear = [self.r, self.g, self.b].map(srgb_to_linear);
+        let [r, g, b] = match space {
+            ColorSpace::Srgb => [self.r, self.g, self.b],
+            ColorSpace::LinearSrgb => linear,
+            ColorSpace::DisplayP3 => mul(&SRGB_TO_P3, linear).map(linear_to_srgb),
+        };
+        [r, g, b, self.a]
+    }
+
+    /// The relative luminance of the color (0 for black, 1 for white).
+    pub fn luminance(&self) -> f32 {
+        let [r, g, b, _] = self.to_space(ColorSpace::LinearSrgb);
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// Parse a hex color of the form `#RGB`, `#RRGGBB` or `#RRGGBBAA` (the `#` is optional).
+    /// Returns `None` if the string is not a valid hex color.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize, len: usize| {
+            let value = u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).ok()?;
+            // a single digit is repeated, e.g. `f` is `ff`
+            Some(if len == 1 { value * 17 } else { value })
+        };
+        let (len, count) = match hex.len() {
+            3 => (1, 3),
+            6 => (2, 3),
+            8 => (2, 4),
+            _ => return None,
+        };
+        let alpha = if count == 4 { channel(3, len)? } else { 255 };
+        Some(Self::new(
+            channel(0, len)? as f32 / 255.0,
+            channel(1, len)? as f32 / 255.0,
+            channel(2, len)? as f32 / 255.0,
+            alpha as f32 / 255.0,
+        ))
+    }
+
+    /// Create an opaque color from hue (in degrees), saturation and value (0 to 1).
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let chroma = value * saturation;
+        Self::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    /// Create an opaque color from hue (in degrees), saturation and lightness (0 to 1).
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Self::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    fn from_hue_chroma(hue: f32, chroma: f32, min: f32) -> Self {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        Self::rgb(r + min, g + min, b + min)
+    }
+
+    /// Interpolate linearly (per component) between this color (`t = 0`) and `other` (`t = 1`).
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    /// The same color with a different alpha.
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.a = alpha;
+        self
+    }
+
     pub const WHITE: Self = Self {
         r: 1.0,
         g: 1.0,
@@ -82,3 +200,476 @@ impl RGBA {
         a: 1.0,
     };
 }
+
+/// A color space that colors can be specified in or rendered to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// sRGB primaries with the sRGB transfer function, the color space of most displays.
+    #[default]
+    Srgb,
+    /// sRGB primaries without a transfer function, i.e. proportional to the emitted light.
+    LinearSrgb,
+    /// The wider gamut of many recent displays, with the sRGB transfer function.
+    DisplayP3,
+}
+
+/// How colors are converted and blended when rendering.
+///
+/// By default, colors are drawn as sRGB and blended (including antialiasing and gradient
+/// interpolation) on the encoded values, which makes the luminance of blends, edges and
+/// gradients depend on the transfer function. With linear blending, colors are converted to
+/// linear light before drawing and the result is encoded for the output afterwards.
+///
+/// Linear values are stored with 8 bits per channel while rendering, because the GPU rasterizer
+/// only writes `Rgba8Unorm` textures, so dark shades have fewer distinct levels than with the
+/// default pipeline.
+///
+/// Surfaces have to use an sRGB format (e.g. `Bgra8UnormSrgb`) for linear blending, so that the
+/// output is encoded in hardware. Images on the GPU are converted on the GPU when they are first
+/// drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ColorManagement {
+    /// The color space of the output, i.e. of the display the frames are shown on. With
+    /// [`ColorSpace::LinearSrgb`], linear values are written, e.g. to an sRGB surface format
+    /// that encodes them in hardware.
+    pub output: ColorSpace,
+    /// Blend in linear light instead of on encoded values.
+    pub linear_blending: bool,
+}
+
+impl ColorManagement {
+    /// Create a color management for the given output color space, without linear blending.
+    pub fn new(output: ColorSpace) -> Self {
+        Self {
+            output,
+            linear_blending: false,
+        }
+    }
+
+    /// Set whether colors are blended in linear light.
+    pub fn with_linear_blending(mut self, linear_blending: bool) -> Self {
+        self.linear_blending = linear_blending;
+        self
+    }
+
+    /// Whether colors are drawn unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.output == ColorSpace::Srgb && !self.linear_blending
+    }
+
+    /// Whether the rendered values are linear and have to be encoded for the output.
+    pub fn needs_output_encoding(&self) -> bool {
+        self.linear_blending && self.output != ColorSpace::LinearSrgb
+    }
+
+    /// The values that are drawn for a color.
+    pub fn convert(&self, color: RGBA) -> RGBA {
+        if self.is_identity() {
+            return color;
+        }
+        let [r, g, b, a] = color.to_space(self.output);
+        if self.needs_output_encoding() {
+            let [r, g, b] = [r, g, b].map(srgb_to_linear);
+            return RGBA::new(r, g, b, a);
+        }
+        RGBA::new(r, g, b, a)
+    }
+
+    /// The conversion as a matrix applied to linear sRGB colors, and whether the result is encoded
+    /// with the sRGB transfer function afterwards, e.g. to convert colors in a shader.
+    pub fn linear_transform(&self) -> ([[f32; 3]; 3], bool) {
+        let matrix = match self.output {
+            ColorSpace::DisplayP3 => SRGB_TO_P3,
+            _ => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        };
+        (matrix, !self.linear_blending && self.output != ColorSpace::LinearSrgb)
+    }
+
+    /// Convert the colors of straight-alpha RGBA8 pixels in place.
+    pub fn convert_pixels(&self, data: &mut [u8]) {
+        if self.is_identity() {
+            return;
+        }
+        for pixel in data.chunks_exact_mut(4) {
+            let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|value| value as f32 / 255.0);
+            let color = self.convert(RGBA::new(r, g, b, 1.0));
+            for (value, converted) in pixel.iter_mut().zip([color.r, color.g, color.b]) {
+                *value = (converted.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+    }
+}
+
+/// Linear Display P3 to linear sRGB (both with a D65 white point).
+const P3_TO_SRGB: [[f32; 3]; 3] = [
+    [1.224_940_2, -0.224_940_4, 0.0],
+    [-0.042_056_955, 1.042_057_1, 0.0],
+    [-0.019_637_555, -0.078_636_05, 1.098_273_6],
+];
+
+/// Linear sRGB to linear Display P3 (both with a D65 white point).
+const SRGB_TO_P3: [[f32; 3]; 3] = [
+    [0.822_462_1, 0.177_538_02, 0.0],
+    [0.033_194_2, 0.966_805_8, 0.0],
+    [0.017_082_632, 0.072_397_44, 0.910_519_9],
+];
+
+fn mul(matrix: &[[f32; 3]; 3], [r, g, b]: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * r + row[1] * g + row[2] * b)
+}
+
+/// Decode an sRGB-encoded component to linear light. Negative values (outside of the gamut) are
+/// mirrored.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    let magnitude = value.abs();
+    let linear = if magnitude <= 0.04045 {
+        magnitude / 12.92
+    } else {
+        ((magnitude + 0.055) / 1.055).powf(2.4)
+    };
+    linear.copysign(value)
+}
+
+/// Encode a linear component with the sRGB transfer function. Negative values (outside of the
+/// gamut) are mirrored.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let magnitude = value.abs();
+    let encoded = if magnitude <= 0.003_130_8 {
+        magnitude * 12.92
+    } else {
+        1.055 * magnitude.powf(1.0 / 2.4) - 0.055
+    };
+    encoded.copysign(value)
+}
+
+/// A color in CIE 1931 XYZ, scaled so that the white of the monitor has a luminance `y` of 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XYZ {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// A color as CIE 1931 chromaticity (`x`, `y`) and luminance (relative to the monitor white).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XyY {
+    pub x: f64,
+    pub y: f64,
+    pub luminance: f64,
+}
+
+/// A color in CIELAB, relative to a D65 white.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LAB {
+    /// Lightness, from 0 (black) to 100 (white).
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// A color in CIE LCh, the polar form of CIELAB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LCh {
+    /// Lightness, from 0 (black) to 100 (white).
+    pub l: f64,
+    /// Chroma, the distance from the gray axis.
+    pub c: f64,
+    /// Hue angle, in degrees.
+    pub h: f64,
+}
+
+/// A color in the DKL (Derrington-Krauskopf-Lennie) space around a background color, with
+/// Smith-Pokorny cone fundamentals.
+///
+/// The axes are cone contrasts relative to the background: `luminance` scales all cone
+/// excitations, `l_minus_m` is the difference between the L- and M-cone contrasts at constant
+/// luminance, and `s` is the S-cone contrast at constant L- and M-cone excitations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DKL {
+    pub luminance: f64,
+    pub l_minus_m: f64,
+    pub s: f64,
+    /// The background (adaptation) color the contrasts are relative to.
+    pub background: XYZ,
+}
+
+/// The D65 white point in XYZ (with a luminance of 1).
+const D65: XYZ = XYZ {
+    x: 0.950_47,
+    y: 1.0,
+    z: 1.088_83,
+};
+
+/// XYZ to Smith-Pokorny cone excitations (scaled so that L + M is the luminance).
+const XYZ_TO_LMS: [[f64; 3]; 3] = [
+    [0.155_14, 0.543_12, -0.032_86],
+    [-0.155_14, 0.456_84, 0.032_86],
+    [0.0, 0.0, 0.008_01],
+];
+
+impl XYZ {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    /// The cone excitations (L, M, S).
+    pub fn to_lms(&self) -> [f64; 3] {
+        mul64(&XYZ_TO_LMS, [self.x, self.y, self.z])
+    }
+
+    /// The color with the given cone excitations (L, M, S).
+    pub fn from_lms(lms: [f64; 3]) -> Self {
+        let [x, y, z] = mul64(&invert64(&XYZ_TO_LMS), lms);
+        Self { x, y, z }
+    }
+}
+
+impl From<XyY> for XYZ {
+    fn from(color: XyY) -> Self {
+        if color.y == 0.0 {
+            return XYZ::new(0.0, 0.0, 0.0);
+        }
+        let scale = color.luminance / color.y;
+        XYZ::new(color.x * scale, color.luminance, (1.0 - color.x - color.y) * scale)
+    }
+}
+
+impl From<XYZ> for XyY {
+    fn from(color: XYZ) -> Self {
+        let sum = color.x + color.y + color.z;
+        if sum == 0.0 {
+            // black has no chromaticity, so use the white point
+            let white = XyY::from(D65);
+            return XyY {
+                luminance: 0.0,
+                ..white
+            };
+        }
+        XyY {
+            x: color.x / sum,
+            y: color.y / sum,
+            luminance: color.y,
+        }
+    }
+}
+
+impl From<LAB> for XYZ {
+    fn from(color: LAB) -> Self {
+        let f_inv = |t: f64| {
+            if t > 6.0 / 29.0 {
+                t.powi(3)
+            } else {
+                3.0 * (6.0_f64 / 29.0).powi(2) * (t - 4.0 / 29.0)
+            }
+        };
+        let fy = (color.l + 16.0) / 116.0;
+        XYZ::new(
+            D65.x * f_inv(fy + color.a / 500.0),
+            D65.y * f_inv(fy),
+            D65.z * f_inv(fy - color.b / 200.0),
+        )
+    }
+}
+
+impl From<XYZ> for LAB {
+    fn from(color: XYZ) -> Self {
+        let f = |t: f64| {
+            if t > (6.0_f64 / 29.0).powi(3) {
+                t.cbrt()
+            } else {
+                t / (3.0 * (6.0_f64 / 29.0).powi(2)) + 4.0 / 29.0
+            }
+        };
+        let (fx, fy, fz) = (f(color.x / D65.x), f(color.y / D65.y), f(color.z / D65.z));
+        LAB {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+}
+
+impl From<LCh> for LAB {
+    fn from(color: LCh) -> Self {
+        let (sin, cos) = color.h.to_radians().sin_cos();
+        LAB {
+            l: color.l,
+            a: color.c * cos,
+            b: color.c * sin,
+        }
+    }
+}
+
+impl From<LAB> for LCh {
+    fn from(color: LAB) -> Self {
+        LCh {
+            l: color.l,
+            c: color.a.hypot(color.b),
+            h: color.b.atan2(color.a).to_degrees().rem_euclid(360.0),
+        }
+    }
+}
+
+impl From<LCh> for XYZ {
+    fn from(color: LCh) -> Self {
+        LAB::from(color).into()
+    }
+}
+
+impl From<DKL> for XYZ {
+    fn from(color: DKL) -> Self {
+        let [l, m, s] = color.background.to_lms();
+        // L - M contrast at constant L + M
+        let opponent = color.l_minus_m * l * m / (l + m);
+        XYZ::from_lms([
+            l * (1.0 + color.luminance) + opponent,
+            m * (1.0 + color.luminance) - opponent,
+            s * (1.0 + color.luminance + color.s),
+        ])
+    }
+}
+
+/// The transfer function of a monitor, from device values to emitted light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transfer {
+    /// The sRGB transfer function.
+    Srgb,
+    /// A power function with the given exponent.
+    Gamma(f64),
+    /// Light proportional to the device values, e.g. for a display linearized with a
+    /// [`DisplayCalibration`](crate::calibration::DisplayCalibration).
+    Linear,
+}
+
+impl Transfer {
+    fn encode(&self, value: f64) -> f64 {
+        match self {
+            Transfer::Srgb => linear_to_srgb(value as f32) as f64,
+            Transfer::Gamma(gamma) => value.max(0.0).powf(1.0 / gamma),
+            Transfer::Linear => value,
+        }
+    }
+
+    fn decode(&self, value: f64) -> f64 {
+        match self {
+            Transfer::Srgb => srgb_to_linear(value as f32) as f64,
+            Transfer::Gamma(gamma) => value.max(0.0).powf(*gamma),
+            Transfer::Linear => value,
+        }
+    }
+}
+
+/// The colorimetry of a monitor: the chromaticities of its primaries and white point and its
+/// transfer function, e.g. measured with a colorimeter.
+///
+/// Colors converted with a profile have the device values of the monitor as components, so draw
+/// them without a [`ColorManagement`] (which would treat them as sRGB and convert them again).
+/// The default profile is an ideal sRGB monitor, for which the components are plain sRGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorProfile {
+    /// The chromaticities (x, y) of the red, green and blue primaries.
+    pub primaries: [(f64, f64); 3],
+    /// The chromaticity (x, y) of the white point (all primaries at full intensity).
+    pub white: (f64, f64),
+    pub transfer: Transfer,
+}
+
+impl MonitorProfile {
+    /// An ideal sRGB monitor.
+    pub fn srgb() -> Self {
+        Self {
+            primaries: [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)],
+            white: (0.3127, 0.3290),
+            transfer: Transfer::Srgb,
+        }
+    }
+
+    /// An ideal Display P3 monitor.
+    pub fn display_p3() -> Self {
+        Self {
+            primaries: [(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)],
+            ..Self::srgb()
+        }
+    }
+
+    /// The matrix from linear device values to XYZ.
+    fn rgb_to_xyz(&self) -> [[f64; 3]; 3] {
+        let xyz = |(x, y): (f64, f64)| [x / y, 1.0, (1.0 - x - y) / y];
+        let [r, g, b] = self.primaries.map(xyz);
+        let primaries = [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]];
+        // scale the primaries so that they add up to the white point
+        let scale = mul64(&invert64(&primaries), xyz(self.white));
+        primaries.map(|row| [row[0] * scale[0], row[1] * scale[1], row[2] * scale[2]])
+    }
+
+    /// The device values of a color (out of gamut colors get components outside of 0 to 1).
+    pub fn to_rgba(&self, color: impl Into<XYZ>, alpha: f32) -> RGBA {
+        let color = color.into();
+        let linear = mul64(&invert64(&self.rgb_to_xyz()), [color.x, color.y, color.z]);
+        let [r, g, b] = linear.map(|value| self.transfer.encode(value) as f32);
+        RGBA::new(r, g, b, alpha)
+    }
+
+    /// The XYZ coordinates of device values.
+    pub fn to_xyz(&self, color: RGBA) -> XYZ {
+        let linear = [color.r, color.g, color.b].map(|value| self.transfer.decode(value as f64));
+        let [x, y, z] = mul64(&self.rgb_to_xyz(), linear);
+        XYZ::new(x, y, z)
+    }
+}
+
+impl Default for MonitorProfile {
+    fn default() -> Self {
+        Self::srgb()
+    }
+}
+
+impl From<XYZ> for RGBA {
+    fn from(color: XYZ) -> Self {
+        MonitorProfile::default().to_rgba(color, 1.0)
+    }
+}
+
+impl From<XyY> for RGBA {
+    fn from(color: XyY) -> Self {
+        MonitorProfile::default().to_rgba(color, 1.0)
+    }
+}
+
+impl From<LAB> for RGBA {
+    fn from(color: LAB) -> Self {
+        MonitorProfile::default().to_rgba(color, 1.0)
+    }
+}
+
+impl From<LCh> for RGBA {
+    fn from(color: LCh) -> Self {
+        MonitorProfile::default().to_rgba(color, 1.0)
+    }
+}
+
+impl From<DKL> for RGBA {
+    fn from(color: DKL) -> Self {
+        MonitorProfile::default().to_rgba(color, 1.0)
+    }
+}
+
+impl From<RGBA> for XYZ {
+    fn from(color: RGBA) -> Self {
+        MonitorProfile::default().to_xyz(color)
+    }
+}
+
+fn mul64(matrix: &[[f64; 3]; 3], [x, y, z]: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * x + row[1] * y + row[2] * z)
+}
+
+fn invert64(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let [[a, b, c], [d, e, f], [g, h, i]] = *m;
+    let det = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+    [
+        [(e * i - f * h) / det, (c * h - b * i) / det, (b * f - c * e) / det],
+        [(f * g - d * i) / det, (a * i - c * g) / det, (c * d - a * f) / det],
+        [(d * h - e * g) / det, (b * g - a * h) / det, (a * e - b * d) / det],
+    ]
+}
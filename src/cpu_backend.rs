@@ -0,0 +1,620 @@
+// software rendering backend based on tiny-skia
+use std::sync::Arc;
+
+use tiny_skia::{
+    BlendMode, Color, FillRule, FilterQuality, GradientStop, IntSize, LineCap, LineJoin, Mask, Paint, Pixmap,
+    PixmapPaint, PremultipliedColorU8, Shader, SpreadMode, Stroke, StrokeDash, Transform,
+};
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::brushes::{Brush, Extend, Gradient, GradientKind, Image, ImageFilter};
+use super::colors::RGBA;
+use super::error::{RendererError, Result};
+use super::scenes::Scene;
+use super::shapes::{Point, Rectangle, Shape};
+use super::snapshot::SnapshotBackend;
+use super::styles::{Cap, CompositeMode, FillStyle, Join, MixMode, StrokeOptions, Style};
+use super::text::{Glyph, TextRendering};
+
+/// A drawing operation recorded by the [`CpuBackend`].
+#[derive(Debug, Clone)]
+enum Command {
+    Shape {
+        path: kurbo::BezPath,
+        style: Style,
+        brush: Box<Brush>,
+        transform: Affine,
+        brush_transform: Option<Affine>,
+    },
+    PushLayer {
+        blend_mode: BlendMode,
+        clip: kurbo::BezPath,
+        clip_transform: Affine,
+        alpha: f32,
+        /// Whether the luminance of the layer is converted to alpha when it is popped.
+        luminance_mask: bool,
+    },
+    PopLayer,
+    Image {
+        image: Image,
+        transform: Affine,
+    },
+}
+
+/// A software rendering backend that rasterizes scenes on the CPU with tiny-skia, e.g. to render
+/// in CI, on headless servers or on machines without a compatible GPU.
+///
+/// Drawing operations are recorded and rasterized by [`CpuBackend::render`]. Compared to the
+/// GPU backend, there are a few limitations:
+/// - text can't be drawn (there is no font type for this backend),
+/// - sweep gradients are drawn with the color of their first stop,
+/// - radial gradients ignore the radius of the start circle,
+/// - strokes use the start cap for both ends,
+/// - image brushes use the horizontal edge mode for both axes,
+/// - gradient dithering and quality settings are ignored.
+#[derive(Debug, Clone)]
+pub struct CpuBackend {
+    commands: Vec<Command>,
+    global_transform: Affine,
+    width: u32,
+    height: u32,
+}
+
+/// Fonts are not supported by the [`CpuBackend`], so no value of this type can exist.
+#[derive(Debug, Clone, Copy)]
+pub enum CpuFont {}
+
+impl CpuBackend {
+    /// Create a new backend with the origin at the center of a `width` x `height` pixel canvas.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            commands: Vec::new(),
+            global_transform: Affine::translate(width as f64 / 2.0, height as f64 / 2.0),
+            width,
+            height,
+        }
+    }
+
+    /// Rasterize the recorded content onto a canvas filled with the background color. Fails if
+    /// the canvas is empty or too large to allocate.
+    pub fn render(&self, background: RGBA) -> Result<Pixmap> {
+        let mut pixmap = new_pixmap(self.width, self.height)?;
+        pixmap.fill(to_color(background));
+
+        // layers that have been pushed, with the mask and paint used to composite them on pop
+        // and whether they are luminance masks
+        let mut layers: Vec<(Pixmap, Option<Mask>, PixmapPaint, bool)> = Vec::new();
+
+        for command in &self.commands {
+            let target = layers.last_mut().map(|(layer, ..)| layer).unwrap_or(&mut pixmap);
+            match command {
+                Command::Shape {
+                    path,
+                    style,
+                    brush,
+                    transform,
+                    brush_transform,
+                } => draw_path(target, path, style, brush, *transform, *brush_transform),
+                Command::Image { image, transform } => {
+                    let (image, transform) = match image.mip_level_for(*transform) {
+                        Some((mip, to_image)) => (mip, to_image * *transform),
+                        None => (image.clone(), *transform),
+                    };
+                    if let Some(pixmap) = to_pixmap(&image.rgba8_data(), image.width, image.height) {
+                        let paint = PixmapPaint {
+                            quality: to_filter_quality(image.filter),
+                            ..Default::default()
+                        };
+                        target.draw_pixmap(0, 0, pixmap.as_ref(), &paint, to_transform(transform), None);
+                    }
+                }
+                Command::PushLayer {
+                    blend_mode,
+                    clip,
+                    clip_transform,
+                    alpha,
+                    luminance_mask,
+                } => {
+                    let mut mask = Mask::new(target.width(), target.height()).ok_or_else(|| too_large(target))?;
+                    if let Some(clip) = to_path(clip) {
+                        mask.fill_path(&clip, FillRule::Winding, true, to_transform(*clip_transform));
+                    }
+                    let paint = PixmapPaint {
+                        opacity: *alpha,
+                        blend_mode: *blend_mode,
+                        quality: FilterQuality::Nearest,
+                    };
+                    let layer = new_pixmap(target.width(), target.height())?;
+                    layers.push((layer, Some(mask), paint, *luminance_mask));
+                }
+                Command::PopLayer => {
+                    if let Some((mut layer, mask, paint, luminance_mask)) = layers.pop() {
+                        if luminance_mask {
+                            luminance_to_alpha(&mut layer);
+                        }
+                        let target = layers.last_mut().map(|(layer, ..)| layer).unwrap_or(&mut pixmap);
+                        target.draw_pixmap(0, 0, layer.as_ref(), &paint, Transform::identity(), mask.as_ref());
+                    }
+                }
+            }
+        }
+
+        // composite layers that were never popped
+        while let Some((mut layer, mask, paint, luminance_mask)) = layers.pop() {
+            if luminance_mask {
+                luminance_to_alpha(&mut layer);
+            }
+            let target = layers.last_mut().map(|(layer, ..)| layer).unwrap_or(&mut pixmap);
+            target.draw_pixmap(0, 0, layer.as_ref(), &paint, Transform::identity(), mask.as_ref());
+        }
+        Ok(pixmap)
+    }
+}
+
+/// A transparent canvas, or an error if it is empty or too large to allocate.
+fn new_pixmap(width: u32, height: u32) -> Result<Pixmap> {
+    Pixmap::new(width, height)
+        .ok_or_else(|| RendererError::InvalidArgument(format!("can't allocate a {width}x{height} canvas")))
+}
+
+/// The error for a layer mask of the size of a canvas that can't be allocated.
+fn too_large(canvas: &Pixmap) -> RendererError {
+    RendererError::InvalidArgument(format!("can't allocate a {}x{} mask", canvas.width(), canvas.height()))
+}
+
+impl Scene<CpuBackend> {
+    /// Create a new scene that is rendered on the CPU.
+    pub fn new(background_color: RGBA, width: u32, height: u32) -> Self {
+        Self::with_backend(background_color, width, height, CpuBackend::new(width, height))
+    }
+
+    /// Rasterize the scene into an image. Fails if the scene is empty or too large to allocate.
+    pub fn render_to_image(&self) -> Result<image::RgbaImage> {
+        let pixmap = match self.content_with_clear_brush() {
+            Some(backend) => backend.render(self.background_color)?,
+            None => self.backend.render(self.background_color)?,
+        };
+        let data = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        Ok(image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), data).expect("invalid pixmap size"))
+    }
+}
+
+impl RenderBackend for CpuBackend {
+    type Font = CpuFont;
+
+    fn begin_scene(&mut self) {
+        self.commands.clear();
+    }
+
+    fn create_child(&self) -> Self {
+        Self {
+            commands: Vec::new(),
+            global_transform: Affine::identity(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    fn global_transform(&self) -> Affine {
+        self.global_transform
+    }
+
+    fn set_global_transform(&mut self, transform: Affine) {
+        self.global_transform = transform;
+    }
+
+    fn draw_shape(
+        &mut self,
+        shape: &impl Shape,
+        style: &Style,
+        brush: &Brush,
+        transform: Affine,
+        brush_transform: Option<Affine>,
+    ) {
+        self.commands.push(Command::Shape {
+            path: shape.to_path(),
+            style: style.clone(),
+            brush: Box::new(brush.clone()),
+            transform,
+            brush_transform,
+        });
+    }
+
+    fn draw_glyphs(
+        &mut self,
+        font: &Self::Font,
+        _font_size: f32,
+        _variations: &[(&str, f32)],
+        _glyphs: &[Glyph],
+        _brush: &Brush,
+        _transform: Affine,
+        _glyph_transform: Option<Affine>,
+        _rendering: TextRendering,
+        _stroke: Option<&StrokeOptions>,
+    ) {
+        match *font {}
+    }
+
+    fn push_layer(
+        &mut self,
+        mix_mode: MixMode,
+        composite_mode: CompositeMode,
+        clip: &impl Shape,
+        clip_transform: Affine,
+        alpha: f32,
+    ) {
+        self.commands.push(Command::PushLayer {
+            blend_mode: to_blend_mode(mix_mode, composite_mode),
+            clip: clip.to_path(),
+            clip_transform,
+            alpha,
+            luminance_mask: false,
+        });
+    }
+
+    fn pop_layer(&mut self) {
+        self.commands.push(Command::PopLayer);
+    }
+
+    fn draw_image(&mut self, image: &Image, transform: Affine) {
+        self.commands.push(Command::Image {
+            image: image.clone(),
+            transform,
+        });
+    }
+
+    fn append(&mut self, other: &Self, transform: Affine) {
+        self.commands.extend(other.commands.iter().map(|command| match command.clone() {
+            Command::Shape {
+                path,
+                style,
+                brush,
+                transform: t,
+                brush_transform,
+            } => Command::Shape {
+                path,
+                style,
+                brush,
+                transform: t * transform,
+                brush_transform,
+            },
+            Command::PushLayer {
+                blend_mode,
+                clip,
+                clip_transform,
+                alpha,
+                luminance_mask,
+            } => Command::PushLayer {
+                blend_mode,
+                clip,
+                clip_transform: clip_transform * transform,
+                alpha,
+                luminance_mask,
+            },
+            Command::PopLayer => Command::PopLayer,
+            Command::Image { image, transform: t } => Command::Image {
+                image,
+                transform: t * transform,
+            },
+        }));
+    }
+
+    fn append_luminance_mask(&mut self, mask: &Self, transform: Affine, width: u32, height: u32) {
+        let bounds = Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: width as f64,
+                y: height as f64,
+            },
+        };
+        self.commands.push(Command::PushLayer {
+            blend_mode: BlendMode::SourceOver,
+            clip: bounds.to_path(),
+            clip_transform: Affine::identity(),
+            alpha: 1.0,
+            luminance_mask: true,
+        });
+        self.append(mask, transform);
+        self.commands.push(Command::PopLayer);
+    }
+}
+
+impl SnapshotBackend for CpuBackend {
+    fn snapshot(&self) -> Vec<u8> {
+        let mut snapshot = Vec::new();
+        for command in &self.commands {
+            match command {
+                Command::Shape {
+                    path,
+                    style,
+                    brush,
+                    transform,
+                    brush_transform,
+                } => {
+                    let header = format!("shape {path:?} {style:?} {transform:?} {brush_transform:?} ");
+                    snapshot.extend_from_slice(header.as_bytes());
+                    match &**brush {
+                        Brush::Image {
+                            image,
+                            x,
+                            y,
+                            fit_mode,
+                            edge_mode,
+                            edge_mode_y,
+                            mapping,
+                        } => {
+                            let brush =
+                                format!("image {x:?} {y:?} {fit_mode:?} {edge_mode:?} {edge_mode_y:?} {mapping:?} ");
+                            snapshot.extend_from_slice(brush.as_bytes());
+                            write_image(&mut snapshot, image);
+                        }
+                        brush => snapshot.extend_from_slice(format!("{brush:?}").as_bytes()),
+                    }
+                }
+                Command::PushLayer {
+                    blend_mode,
+                    clip,
+                    clip_transform,
+                    alpha,
+                    luminance_mask,
+                } => {
+                    let layer = format!("layer {blend_mode:?} {clip:?} {clip_transform:?} {alpha:?} {luminance_mask}");
+                    snapshot.extend_from_slice(layer.as_bytes());
+                }
+                Command::PopLayer => snapshot.extend_from_slice(b"pop"),
+                Command::Image { image, transform } => {
+                    snapshot.extend_from_slice(format!("image {transform:?} ").as_bytes());
+                    write_image(&mut snapshot, image);
+                }
+            }
+            snapshot.push(b'\n');
+        }
+        snapshot
+    }
+}
+
+/// Write the size, format, sampling and pixels of an image (but not its GPU texture) to a
+/// snapshot.
+fn write_image(snapshot: &mut Vec<u8>, image: &Image) {
+    let header = format!(
+        "{}x{} {:?} {:?} {} ",
+        image.width, image.height, image.format, image.filter, image.mipmaps
+    );
+    snapshot.extend_from_slice(header.as_bytes());
+    snapshot.extend_from_slice(&image.data);
+}
+
+fn draw_path(
+    pixmap: &mut Pixmap,
+    path: &kurbo::BezPath,
+    style: &Style,
+    brush: &Brush,
+    transform: Affine,
+    brush_transform: Option<Affine>,
+) {
+    let Some(path) = to_path(path) else {
+        return;
+    };
+    let mut brush_transform = brush_transform.unwrap_or(Affine::identity());
+
+    // shrunk images are sampled from a mip level
+    let mip;
+    let brush = match brush {
+        Brush::Image { image, .. } => match image.mip_level_for(brush_transform * transform) {
+            Some((level, to_image)) => {
+                let mut mipmapped = brush.clone();
+                if let Brush::Image { image, .. } = &mut mipmapped {
+                    *image = level;
+                }
+                brush_transform = to_image * brush_transform;
+                mip = mipmapped;
+                &mip
+            }
+            None => brush,
+        },
+        _ => brush,
+    };
+    let brush_transform = to_transform(brush_transform);
+
+    // image brushes need to keep the pattern pixmap alive while drawing
+    let pattern;
+    let shader = match brush {
+        Brush::Solid(color) => Shader::SolidColor(to_color(*color)),
+        Brush::Gradient(gradient) => to_shader(gradient, brush_transform),
+        Brush::Image {
+            image,
+            edge_mode,
+            mapping,
+            ..
+        } => {
+            let data = image.rgba8_data();
+            let data = if mapping.is_identity() { data } else { Arc::new(mapping.apply(&data)) };
+            pattern = match to_pixmap(&data, image.width, image.height) {
+                Some(pattern) => pattern,
+                None => return,
+            };
+            tiny_skia::Pattern::new(
+                pattern.as_ref(),
+                to_spread_mode(*edge_mode),
+                to_filter_quality(image.filter),
+                1.0,
+                brush_transform,
+            )
+        }
+    };
+    let paint = Paint {
+        shader,
+        anti_alias: true,
+        ..Default::default()
+    };
+
+    match style {
+        Style::Fill(fill) => {
+            let rule = match fill {
+                FillStyle::NonZero => FillRule::Winding,
+                FillStyle::EvenOdd => FillRule::EvenOdd,
+            };
+            pixmap.fill_path(&path, &paint, rule, to_transform(transform), None);
+        }
+        Style::Stroke(options) => {
+            let stroke = Stroke {
+                width: options.width as f32,
+                miter_limit: options.miter_limit as f32,
+                line_cap: match options.start_cap {
+                    Cap::Butt => LineCap::Butt,
+                    Cap::Square => LineCap::Square,
+                    Cap::Round => LineCap::Round,
+                },
+                line_join: match options.join {
+                    Join::Bevel => LineJoin::Bevel,
+                    Join::Miter => LineJoin::Miter,
+                    Join::Round => LineJoin::Round,
+                },
+                dash: StrokeDash::new(
+                    options.dash_pattern.iter().map(|d| *d as f32).collect(),
+                    options.dash_offset as f32,
+                ),
+            };
+            pixmap.stroke_path(&path, &paint, &stroke, to_transform(transform), None);
+        }
+    }
+}
+
+fn to_shader(gradient: &Gradient, transform: Transform) -> Shader<'static> {
+    let stops = gradient.resolved_stops();
+    let first = stops.first().map(|stop| to_color(stop.color)).unwrap_or(Color::TRANSPARENT);
+    let skia_stops = stops
+        .iter()
+        .map(|stop| GradientStop::new(stop.offset, to_color(stop.color)))
+        .collect::<Vec<_>>();
+    let mode = to_spread_mode(gradient.extend);
+    let point = |p: super::shapes::Point| tiny_skia::Point::from_xy(p.x as f32, p.y as f32);
+
+    // degenerate gradients can't be created and are drawn with their first color
+    let shader = match gradient.kind {
+        GradientKind::Linear { start, end } => {
+            tiny_skia::LinearGradient::new(point(start), point(end), skia_stops, mode, transform)
+        }
+        GradientKind::Radial {
+            start_center,
+            end_center,
+            end_radius,
+            ..
+        } => tiny_skia::RadialGradient::new(
+            point(start_center),
+            point(end_center),
+            end_radius,
+            skia_stops,
+            mode,
+            transform,
+        ),
+        GradientKind::Sweep { .. } => None,
+    };
+    shader.unwrap_or(Shader::SolidColor(first))
+}
+
+fn to_path(path: &kurbo::BezPath) -> Option<tiny_skia::Path> {
+    let mut builder = tiny_skia::PathBuilder::new();
+    for element in path.elements() {
+        match *element {
+            kurbo::PathEl::MoveTo(p) => builder.move_to(p.x as f32, p.y as f32),
+            kurbo::PathEl::LineTo(p) => builder.line_to(p.x as f32, p.y as f32),
+            kurbo::PathEl::QuadTo(c, p) => builder.quad_to(c.x as f32, c.y as f32, p.x as f32, p.y as f32),
+            kurbo::PathEl::CurveTo(c1, c2, p) => builder.cubic_to(
+                c1.x as f32,
+                c1.y as f32,
+                c2.x as f32,
+                c2.y as f32,
+                p.x as f32,
+                p.y as f32,
+            ),
+            kurbo::PathEl::ClosePath => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+/// Replace the pixels of a layer with white, with their luminance (times their alpha) as alpha.
+fn luminance_to_alpha(pixmap: &mut Pixmap) {
+    for pixel in pixmap.pixels_mut() {
+        // the colors are premultiplied, so the luminance is already scaled by alpha
+        let luminance = 0.2126 * pixel.red() as f32 + 0.7152 * pixel.green() as f32 + 0.0722 * pixel.blue() as f32;
+        let luminance = luminance.round().min(255.0) as u8;
+        *pixel = PremultipliedColorU8::from_rgba(luminance, luminance, luminance, luminance)
+            .unwrap_or(PremultipliedColorU8::TRANSPARENT);
+    }
+}
+
+/// Create a pixmap from RGBA8 data with straight alpha.
+fn to_pixmap(data: &[u8], width: u32, height: u32) -> Option<Pixmap> {
+    let premultiply = |c: u8, a: u8| ((c as u16 * a as u16 + 127) / 255) as u8;
+    let data = data
+        .chunks_exact(4)
+        .flat_map(|p| [premultiply(p[0], p[3]), premultiply(p[1], p[3]), premultiply(p[2], p[3]), p[3]])
+        .collect();
+    Pixmap::from_vec(data, IntSize::from_wh(width, height)?)
+}
+
+fn to_transform(affine: Affine) -> Transform {
+    let [a, b, c, d, e, f] = affine.0.map(|v| v as f32);
+    Transform::from_row(a, b, c, d, e, f)
+}
+
+fn to_color(color: RGBA) -> Color {
+    let clamp = |v: f32| v.clamp(0.0, 1.0);
+    Color::from_rgba(clamp(color.r), clamp(color.g), clamp(color.b), clamp(color.a)).unwrap_or(Color::TRANSPARENT)
+}
+
+fn to_spread_mode(extend: Extend) -> SpreadMode {
+    match extend {
+        Extend::Pad => SpreadMode::Pad,
+        Extend::Repeat => SpreadMode::Repeat,
+        Extend::Reflect => SpreadMode::Reflect,
+    }
+}
+
+fn to_filter_quality(filter: ImageFilter) -> FilterQuality {
+    match filter {
+        ImageFilter::Linear => FilterQuality::Bilinear,
+        ImageFilter::Nearest => FilterQuality::Nearest,
+    }
+}
+
+fn to_blend_mode(mix_mode: MixMode, composite_mode: CompositeMode) -> BlendMode {
+    match (mix_mode, composite_mode) {
+        (MixMode::Multiply, _) => BlendMode::Multiply,
+        (MixMode::Screen, _) => BlendMode::Screen,
+        (MixMode::Overlay, _) => BlendMode::Overlay,
+        (MixMode::Darken, _) => BlendMode::Darken,
+        (MixMode::Lighten, _) => BlendMode::Lighten,
+        (MixMode::ColorDodge, _) => BlendMode::ColorDodge,
+        (MixMode::ColorBurn, _) => BlendMode::ColorBurn,
+        (MixMode::HardLight, _) => BlendMode::HardLight,
+        (MixMode::SoftLight, _) => BlendMode::SoftLight,
+        (MixMode::Difference, _) => BlendMode::Difference,
+        (MixMode::Exclusion, _) => BlendMode::Exclusion,
+        (MixMode::Hue, _) => BlendMode::Hue,
+        (MixMode::Saturation, _) => BlendMode::Saturation,
+        (MixMode::Color, _) => BlendMode::Color,
+        (MixMode::Luminosity, _) => BlendMode::Luminosity,
+        (_, CompositeMode::SourceOver) => BlendMode::SourceOver,
+        (_, CompositeMode::DestinationOver) => BlendMode::DestinationOver,
+        (_, CompositeMode::SourceIn) => BlendMode::SourceIn,
+        (_, CompositeMode::DestinationIn) => BlendMode::DestinationIn,
+        (_, CompositeMode::SourceOut) => BlendMode::SourceOut,
+        (_, CompositeMode::DestinationOut) => BlendMode::DestinationOut,
+        (_, CompositeMode::SourceAtop) => BlendMode::SourceAtop,
+        (_, CompositeMode::DestinationAtop) => BlendMode::DestinationAtop,
+        (_, CompositeMode::Lighter) => BlendMode::Plus,
+        (_, CompositeMode::Copy) => BlendMode::Source,
+        (_, CompositeMode::Xor) => BlendMode::Xor,
+    }
+}
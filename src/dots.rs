@@ -0,0 +1,268 @@
+// random-dot kinematograms
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::colors::RGBA;
+use super::geoms::Geom;
+use super::random::Random;
+use super::scenes::Scene;
+use super::shapes::{Path, Point, Rectangle, PATH_TOLERANCE};
+use super::Drawable;
+
+/// The area the dots of a [`DotField`] move in, centered on the origin of its transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aperture {
+    Circle { radius: f64 },
+    Rectangle { width: f64, height: f64 },
+}
+
+impl Aperture {
+    fn contains(&self, point: Point) -> bool {
+        match *self {
+            Aperture::Circle { radius } => point.x.hypot(point.y) <= radius,
+            Aperture::Rectangle { width, height } => point.x.abs() <= width / 2.0 && point.y.abs() <= height / 2.0,
+        }
+    }
+
+    /// A uniformly distributed point inside the aperture.
+    fn sample(&self, random: &mut Random) -> Point {
+        match *self {
+            Aperture::Circle { radius } => {
+                let (distance, angle) = (radius * random.next_f64().sqrt(), random.range(0.0, TAU));
+                Point {
+                    x: distance * angle.cos(),
+                    y: distance * angle.sin(),
+                }
+            }
+            Aperture::Rectangle { width, height } => Point {
+                x: random.range(-width / 2.0, width / 2.0),
+                y: random.range(-height / 2.0, height / 2.0),
+            },
+        }
+    }
+
+    fn half_extent(&self) -> (f64, f64) {
+        match *self {
+            Aperture::Circle { radius } => (radius, radius),
+            Aperture::Rectangle { width, height } => (width / 2.0, height / 2.0),
+        }
+    }
+}
+
+/// A dot of a [`DotField`].
+#[derive(Debug, Clone, Copy)]
+pub struct Dot {
+    /// The position, relative to the center of the aperture.
+    pub position: Point,
+    /// The time since the dot was (re)placed, in seconds.
+    pub age: f64,
+    /// Whether the dot moves in the direction of the field (signal) or its own (noise).
+    pub coherent: bool,
+    /// The direction of a noise dot, in radians.
+    pub direction: f64,
+    /// The speed of the dot, in scene units per second.
+    pub speed: f64,
+}
+
+/// A random-dot kinematogram: dots in an aperture of which a fraction (the coherence) moves in a
+/// common direction while the others move in random directions, drawn as a single batched path.
+///
+/// The noise dots keep their random direction (and every dot its speed) for their lifetime. Dots
+/// that leave the aperture or exceed their lifetime are placed at a random position. The number of coherent dots is
+/// exact (rounded), so the coherence doesn't fluctuate between trials.
+#[derive(Debug, Clone)]
+pub struct DotField {
+    pub dots: Vec<Dot>,
+    pub aperture: Aperture,
+    /// The radius of the dots, in scene units.
+    pub dot_radius: f64,
+    pub color: RGBA,
+    /// The direction of the coherent dots, in radians (0 moves to the right).
+    pub direction: f64,
+    /// The lowest and highest speed of the dots, in scene units per second. Every dot gets a
+    /// speed from this range at random when it is placed.
+    pub speed: (f64, f64),
+    /// The lifetime of the dots, in seconds (infinite by default).
+    pub lifetime: f64,
+    pub transform: Affine,
+    /// Whether the field is drawn.
+    pub visible: bool,
+    coherence: f64,
+    random: Random,
+}
+
+impl DotField {
+    /// Create a field of randomly placed dots, seeded from the system time (see
+    /// [`DotField::with_seed`]).
+    pub fn new(count: usize, aperture: Aperture, dot_radius: f64, color: RGBA) -> Self {
+        let mut field = Self {
+            dots: Vec::new(),
+            aperture,
+            dot_radius,
+            color,
+            direction: 0.0,
+            speed: (0.0, 0.0),
+            lifetime: f64::INFINITY,
+            transform: Affine::identity(),
+            visible: true,
+            coherence: 0.0,
+            random: Random::from_time(),
+        };
+        field.reset(count);
+        field
+    }
+
+    /// Re-place the dots with a seeded random generator, so that the sequence is reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.random = Random::new(seed);
+        self.reset(self.dots.len());
+        self
+    }
+
+    /// Set the fraction of coherent dots.
+    pub fn with_coherence(mut self, coherence: f64) -> Self {
+        self.set_coherence(coherence);
+        self
+    }
+
+    /// Set the direction of the coherent dots.
+    pub fn with_direction(mut self, direction: f64) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the speed of all dots.
+    pub fn with_speed(self, speed: f64) -> Self {
+        self.with_speed_range(speed, speed)
+    }
+
+    /// Give every dot a speed between `min` and `max` at random.
+    pub fn with_speed_range(mut self, min: f64, max: f64) -> Self {
+        self.speed = (min, max);
+        for dot in &mut self.dots {
+            dot.speed = self.random.range(min, max);
+        }
+        self
+    }
+
+    /// Set the lifetime of the dots. Their ages are spread at random, so that about the same
+    /// number of dots is replaced every frame.
+    pub fn with_lifetime(mut self, lifetime: f64) -> Self {
+        self.lifetime = lifetime;
+        for dot in &mut self.dots {
+            dot.age = random_age(&mut self.random, lifetime);
+        }
+        self
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// The fraction of coherent dots.
+    pub fn coherence(&self) -> f64 {
+        self.coherence
+    }
+
+    /// Set the fraction (0 to 1) of coherent dots, choosing them at random.
+    pub fn set_coherence(&mut self, coherence: f64) {
+        self.coherence = coherence.clamp(0.0, 1.0);
+        let count = (self.coherence * self.dots.len() as f64).round() as usize;
+        // a partial shuffle picks the coherent dots
+        let mut order = (0..self.dots.len()).collect::<Vec<_>>();
+        for i in 0..count {
+            let j = i + self.random.index(order.len() - i);
+            order.swap(i, j);
+        }
+        for (rank, &index) in order.iter().enumerate() {
+            self.dots[index].coherent = rank < count;
+        }
+    }
+
+    /// Replace all dots with `count` new ones at random positions, with ages spread over the
+    /// lifetime.
+    pub fn reset(&mut self, count: usize) {
+        let (aperture, (min_speed, max_speed), random) = (self.aperture, self.speed, &mut self.random);
+        self.dots = (0..count)
+            .map(|_| Dot {
+                position: aperture.sample(random),
+                age: random_age(random, self.lifetime),
+                coherent: false,
+                direction: random.range(0.0, TAU),
+                speed: random.range(min_speed, max_speed),
+            })
+            .collect();
+        self.set_coherence(self.coherence);
+    }
+
+    /// Move the dots by the time since the last frame, e.g. [`FrameInfo::delta`].
+    ///
+    /// [`FrameInfo::delta`]: crate::scenes::FrameInfo::delta
+    pub fn advance(&mut self, dt: Duration) {
+        let dt = dt.as_secs_f64();
+        let (min_speed, max_speed) = self.speed;
+        for dot in &mut self.dots {
+            dot.age += dt;
+            let direction = if dot.coherent { self.direction } else { dot.direction };
+            dot.position.x += dot.speed * dt * direction.cos();
+            dot.position.y += dot.speed * dt * direction.sin();
+
+            if dot.age >= self.lifetime || !self.aperture.contains(dot.position) {
+                dot.position = self.aperture.sample(&mut self.random);
+                dot.age = 0.0;
+                dot.direction = self.random.range(0.0, TAU);
+                dot.speed = self.random.range(min_speed, max_speed);
+            }
+        }
+    }
+
+    /// The dots as one path of circles.
+    fn path(&self) -> Path {
+        let mut path = kurbo::BezPath::new();
+        for dot in &self.dots {
+            let circle = kurbo::Circle::new((dot.position.x, dot.position.y), self.dot_radius);
+            path.extend(kurbo::Shape::path_elements(&circle, PATH_TOLERANCE));
+        }
+        path.into()
+    }
+}
+
+/// A random age for a dot (0 if the lifetime is infinite).
+fn random_age(random: &mut Random, lifetime: f64) -> f64 {
+    if lifetime.is_finite() {
+        random.range(0.0, lifetime)
+    } else {
+        0.0
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for DotField {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible || self.dots.is_empty() {
+            return;
+        }
+        Geom::fill(self.path(), self.color)
+            .with_transform(self.transform)
+            .draw(scene);
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        let (x, y) = self.aperture.half_extent();
+        let (x, y) = (x + self.dot_radius, y + self.dot_radius);
+        let bounds = kurbo::Affine::from(self.transform).transform_rect_bbox(kurbo::Rect::new(-x, -y, x, y));
+        Some(Rectangle {
+            a: Point {
+                x: bounds.x0,
+                y: bounds.y0,
+            },
+            b: Point {
+                x: bounds.x1,
+                y: bounds.y1,
+            },
+        })
+    }
+}
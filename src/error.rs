@@ -0,0 +1,34 @@
+use std::fmt;
+
+/// Errors returned by the fallible parts of the rendering API.
+#[derive(Debug)]
+pub enum RendererError {
+    /// The GPU renderer could not be created or failed to render, e.g. after the device was lost.
+    Gpu(String),
+    /// Reading rendered data back from the GPU failed.
+    Readback(String),
+    /// An SVG document could not be parsed.
+    Svg(String),
+    /// Font data could not be read.
+    Font,
+    /// An argument is out of range or doesn't make sense, e.g. filling a shape that can only be
+    /// stroked.
+    InvalidArgument(String),
+}
+
+/// A result with a [`RendererError`].
+pub type Result<T> = std::result::Result<T, RendererError>;
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererError::Gpu(message) => write!(f, "GPU rendering failed: {message}"),
+            RendererError::Readback(message) => write!(f, "reading back rendered data failed: {message}"),
+            RendererError::Svg(message) => write!(f, "invalid SVG: {message}"),
+            RendererError::Font => f.write_str("invalid or unsupported font data"),
+            RendererError::InvalidArgument(message) => write!(f, "invalid argument: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
@@ -0,0 +1,121 @@
+// exporting rendered images with color information
+use std::fs;
+use std::io;
+
+use image::codecs::png::PngEncoder;
+use image::{ColorType, RgbaImage};
+
+/// The PNG file signature.
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// The color information embedded in exported images.
+///
+/// The renderer produces sRGB-encoded pixels, so exports are tagged as sRGB by default. Embed
+/// the ICC profile of a calibrated display instead if the stimuli were prepared for it, so that
+/// color-managed viewers show the exported image the same way as the live render.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ColorProfile {
+    /// No color information; most viewers assume sRGB.
+    None,
+    /// Tag the image as sRGB (with gamma and chromaticities for viewers that ignore the tag).
+    #[default]
+    Srgb,
+    /// Embed an ICC profile. The name must be 1-79 Latin-1 characters.
+    Icc { name: String, data: Vec<u8> },
+}
+
+/// Encode an image as PNG with the given color profile.
+pub fn encode_png(image: &RgbaImage, profile: &ColorProfile) -> io::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .encode(image.as_raw(), image.width(), image.height(), ColorType::Rgba8)
+        .map_err(io::Error::other)?;
+
+    let mut chunks = Vec::new();
+    match profile {
+        ColorProfile::None => {}
+        ColorProfile::Srgb => {
+            // rendering intent: perceptual
+            write_chunk(&mut chunks, b"sRGB", &[0]);
+            write_chunk(&mut chunks, b"gAMA", &45455u32.to_be_bytes());
+            // white point and red, green and blue primaries, times 100000
+            let chromaticities: [u32; 8] = [31270, 32900, 64000, 33000, 30000, 60000, 15000, 6000];
+            let data = chromaticities.iter().flat_map(|value| value.to_be_bytes()).collect::<Vec<_>>();
+            write_chunk(&mut chunks, b"cHRM", &data);
+        }
+        ColorProfile::Icc { name, data } => {
+            if name.is_empty() || name.chars().count() > 79 || name.chars().any(|c| c == '\0' || c as u32 > 0xff) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "ICC profile names must be 1-79 Latin-1 characters",
+                ));
+            }
+            let mut chunk = name.chars().map(|c| c as u8).collect::<Vec<_>>();
+            // null separator and compression method (deflate)
+            chunk.extend_from_slice(&[0, 0]);
+            chunk.extend_from_slice(&zlib_stored(data));
+            write_chunk(&mut chunks, b"iCCP", &chunk);
+        }
+    }
+
+    // color chunks have to come before the image data, so insert them right after the header
+    let header_end = PNG_SIGNATURE.len() + 4 + 4 + 13 + 4;
+    debug_assert_eq!(&png[..PNG_SIGNATURE.len()], &PNG_SIGNATURE);
+    debug_assert_eq!(&png[12..16], b"IHDR");
+    png.splice(header_end..header_end, chunks);
+    Ok(png)
+}
+
+/// Save an image as PNG with the given color profile, overwriting any existing file.
+pub fn save_png(image: &RgbaImage, path: impl AsRef<std::path::Path>, profile: &ColorProfile) -> io::Result<()> {
+    fs::write(path, encode_png(image, profile)?)
+}
+
+/// Append a PNG chunk.
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// The CRC-32 used by PNG chunks.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Wrap data in a zlib stream of uncompressed blocks. ICC profiles are small, so this avoids
+/// pulling in a compressor.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    // Adler-32 checksum
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
@@ -0,0 +1,394 @@
+// font discovery and fallback
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::locale::Locale;
+use super::text::FontStyle;
+
+/// The data of a single font face: the font file and the index of the face in it.
+///
+/// Cloning is cheap, the file data is shared. Turn it into a font of a backend with e.g.
+/// `VelloFont::from_data`.
+#[derive(Clone)]
+pub struct FontData {
+    pub data: Arc<Vec<u8>>,
+    /// The index of the face in a font collection (0 for single fonts).
+    pub index: u32,
+    /// The characters the face has glyphs for, read when they are first needed.
+    coverage: Arc<OnceLock<Coverage>>,
+}
+
+impl FontData {
+    /// The face at `index` of a font file (or collection).
+    pub fn new(data: Arc<Vec<u8>>, index: u32) -> Self {
+        Self {
+            data,
+            index,
+            coverage: Arc::default(),
+        }
+    }
+
+    /// Whether the face has a glyph for a character.
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.coverage
+            .get_or_init(|| Coverage::of(&self.data, self.index))
+            .contains(c)
+    }
+}
+
+/// The characters a face has glyphs for, as sorted ranges of code points (inclusive).
+#[derive(Debug, Clone, Default)]
+struct Coverage(Vec<(u32, u32)>);
+
+impl Coverage {
+    /// Read the character map of a face (empty if the face can't be read).
+    fn of(data: &[u8], index: u32) -> Self {
+        let Some(face) = rustybuzz::Face::from_slice(data, index) else {
+            return Self::default();
+        };
+        let mut code_points = Vec::new();
+        if let Some(cmap) = face.tables().cmap {
+            for subtable in cmap.subtables.into_iter().filter(|subtable| subtable.is_unicode()) {
+                subtable.codepoints(|c| code_points.push(c));
+            }
+        }
+        code_points.sort_unstable();
+        code_points.dedup();
+
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for c in code_points {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == c => *end = c,
+                _ => ranges.push((c, c)),
+            }
+        }
+        Self(ranges)
+    }
+
+    fn contains(&self, c: char) -> bool {
+        let c = c as u32;
+        let index = self.0.partition_point(|(_, end)| *end < c);
+        self.0.get(index).is_some_and(|(start, _)| *start <= c)
+    }
+}
+
+impl std::fmt::Debug for FontData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontData")
+            .field("bytes", &self.data.len())
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// A collection of fonts that can be looked up by family name, built from the fonts installed on
+/// the system and fonts registered from memory.
+///
+/// The registry also keeps a list of fallback families that are used for characters the
+/// requested font has no glyphs for (see [`FontRegistry::runs`]), and families preferred for
+/// text of a locale, e.g. so that Chinese characters in Japanese instructions are drawn with
+/// Japanese glyph forms (see [`FontRegistry::runs_for_locale`]). Text uses the fallbacks of a
+/// registry once its font is given one, e.g. with `VelloFont::with_fallback`.
+#[derive(Debug, Default)]
+pub struct FontRegistry {
+    database: fontdb::Database,
+    /// The family names searched for missing glyphs, in order.
+    pub fallbacks: Vec<String>,
+    /// The family names preferred for text of a locale, keyed by a locale tag (e.g. `ja`,
+    /// `zh-Hant` or `de-CH`). Used for missing glyphs before `fallbacks` and to choose the font
+    /// of a locale (see [`FontRegistry::locale_font`]).
+    pub locale_families: HashMap<String, Vec<String>>,
+    /// The faces that were already read.
+    loaded: Mutex<HashMap<fontdb::ID, FontData>>,
+    /// The characters each face has glyphs for, for faces that were searched for a fallback.
+    coverages: Mutex<HashMap<fontdb::ID, Coverage>>,
+    /// The fallback found for a character, keyed by the character, the families searched
+    /// first, the weight and the style.
+    fallback_cache: Mutex<HashMap<FallbackKey, Option<FontData>>>,
+}
+
+type FallbackKey = (char, Vec<String>, u16, FontStyle);
+
+impl FontRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with the fonts installed on the system.
+    pub fn with_system_fonts() -> Self {
+        let mut registry = Self::new();
+        registry.load_system_fonts();
+        registry
+    }
+
+    /// Add the fonts installed on the system.
+    pub fn load_system_fonts(&mut self) {
+        self.database.load_system_fonts();
+    }
+
+    /// Register the faces of a font file (or collection) from memory. Returns the number of
+    /// faces that were found.
+    pub fn register(&mut self, data: Vec<u8>) -> usize {
+        // the new faces may have glyphs that weren't found before
+        self.fallback_cache.lock().expect("font cache lock poisoned").clear();
+        self.database.load_font_source(fontdb::Source::Binary(Arc::new(data))).len()
+    }
+
+    /// Set the family names searched for missing glyphs, in order.
+    pub fn with_fallbacks(mut self, families: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.fallbacks = families.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the family names preferred for text of a locale (see
+    /// [`FontRegistry::locale_families`]), in order.
+    pub fn with_locale_families(
+        mut self,
+        tag: impl Into<String>,
+        families: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.locale_families
+            .insert(tag.into(), families.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Prefer common system fonts (Noto and the defaults of Windows and macOS) for Chinese,
+    /// Japanese, Korean, Arabic, Hebrew, Devanagari and Thai text. Locales that already have
+    /// families are kept.
+    pub fn with_default_locale_families(mut self) -> Self {
+        let defaults: [(&str, &[&str]); 9] = [
+            ("ja", &["Noto Sans CJK JP", "Noto Sans JP", "Hiragino Sans", "Yu Gothic", "Meiryo"]),
+            ("zh-Hans", &["Noto Sans CJK SC", "Noto Sans SC", "PingFang SC", "Microsoft YaHei"]),
+            ("zh-Hant", &["Noto Sans CJK TC", "Noto Sans TC", "PingFang TC", "Microsoft JhengHei"]),
+            ("ko", &["Noto Sans CJK KR", "Noto Sans KR", "Apple SD Gothic Neo", "Malgun Gothic"]),
+            ("ar", &["Noto Sans Arabic", "Geeza Pro", "Segoe UI"]),
+            ("fa", &["Noto Sans Arabic", "Geeza Pro", "Segoe UI"]),
+            ("he", &["Noto Sans Hebrew", "Arial Hebrew", "Segoe UI"]),
+            ("hi", &["Noto Sans Devanagari", "Kohinoor Devanagari", "Nirmala UI"]),
+            ("th", &["Noto Sans Thai", "Thonburi", "Leelawadee UI"]),
+        ];
+        for (tag, families) in defaults {
+            self.locale_families
+                .entry(tag.to_owned())
+                .or_insert_with(|| families.iter().map(|family| family.to_string()).collect());
+        }
+        self
+    }
+
+    /// The families preferred for a locale, the most specific tag first: e.g. for `zh-TW` the
+    /// families of `zh-Hant-TW`, `zh-TW`, `zh-Hant` and `zh`.
+    pub fn families_for_locale(&self, locale: &Locale) -> Vec<&str> {
+        let language = locale.language.as_str();
+        let script = locale.likely_script();
+        let region = locale.region.as_deref();
+        let tags = [
+            script.zip(region).map(|(script, region)| format!("{language}-{script}-{region}")),
+            region.map(|region| format!("{language}-{region}")),
+            script.map(|script| format!("{language}-{script}")),
+            Some(language.to_owned()),
+        ];
+        let mut families = Vec::new();
+        for tag in tags.into_iter().flatten() {
+            for family in self.locale_families.get(&tag).into_iter().flatten() {
+                if !families.contains(&family.as_str()) {
+                    families.push(family.as_str());
+                }
+            }
+        }
+        families
+    }
+
+    /// The best installed face for text of a locale: the first of its
+    /// [families](FontRegistry::families_for_locale) that is registered.
+    pub fn locale_font(&self, locale: &Locale, weight: u16, style: FontStyle) -> Option<FontData> {
+        self.families_for_locale(locale)
+            .into_iter()
+            .find_map(|family| self.query(family, weight, style.clone()))
+    }
+
+    /// The number of registered faces.
+    pub fn len(&self) -> usize {
+        self.database.len()
+    }
+
+    /// Whether no faces are registered.
+    pub fn is_empty(&self) -> bool {
+        self.database.is_empty()
+    }
+
+    /// The names of all registered families, sorted and without duplicates.
+    pub fn families(&self) -> Vec<String> {
+        let mut families = self
+            .database
+            .faces()
+            .flat_map(|face| face.families.iter().map(|(family, _)| family.clone()))
+            .collect::<Vec<_>>();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    /// Find the face of a family that best matches a weight (100-900) and style.
+    ///
+    /// The generic names `serif`, `sans-serif`, `monospace`, `cursive` and `fantasy` select the
+    /// system's default font of that kind.
+    pub fn query(&self, family: &str, weight: u16, style: FontStyle) -> Option<FontData> {
+        let family = match family {
+            "serif" => fontdb::Family::Serif,
+            "sans-serif" => fontdb::Family::SansSerif,
+            "monospace" => fontdb::Family::Monospace,
+            "cursive" => fontdb::Family::Cursive,
+            "fantasy" => fontdb::Family::Fantasy,
+            name => fontdb::Family::Name(name),
+        };
+        let style = match style {
+            FontStyle::Normal => fontdb::Style::Normal,
+            FontStyle::Italic => fontdb::Style::Italic,
+            FontStyle::Oblique => fontdb::Style::Oblique,
+        };
+        let id = self.database.query(&fontdb::Query {
+            families: &[family],
+            weight: fontdb::Weight(weight),
+            stretch: fontdb::Stretch::Normal,
+            style,
+        })?;
+        self.load(id)
+    }
+
+    /// The first face with a glyph for a character: a face of the fallback families, or any
+    /// registered face if none of them has one.
+    pub fn fallback(&self, c: char, weight: u16, style: FontStyle) -> Option<FontData> {
+        self.fallback_in(&[], c, weight, style)
+    }
+
+    /// Like [`FontRegistry::fallback`], but searching the families of a locale first.
+    pub fn fallback_for_locale(&self, c: char, locale: &Locale, weight: u16, style: FontStyle) -> Option<FontData> {
+        self.fallback_in(&self.families_for_locale(locale), c, weight, style)
+    }
+
+    /// The first face with a glyph for a character in `preferred`, the fallback families or
+    /// any registered face (cached per character).
+    fn fallback_in(&self, preferred: &[&str], c: char, weight: u16, style: FontStyle) -> Option<FontData> {
+        let key = (c, preferred.iter().map(|family| family.to_string()).collect(), weight, style.clone());
+        if let Some(font) = self.fallback_cache.lock().expect("font cache lock poisoned").get(&key) {
+            return font.clone();
+        }
+        let font = self.find_fallback(preferred, c, weight, style);
+        self.fallback_cache
+            .lock()
+            .expect("font cache lock poisoned")
+            .insert(key, font.clone());
+        font
+    }
+
+    fn find_fallback(&self, preferred: &[&str], c: char, weight: u16, style: FontStyle) -> Option<FontData> {
+        let preferred = preferred
+            .iter()
+            .copied()
+            .chain(self.fallbacks.iter().map(String::as_str))
+            .filter_map(|family| self.query(family, weight, style.clone()))
+            .find(|font| font.has_glyph(c));
+        preferred.or_else(|| {
+            // only keep the face that is used, not every face that was checked
+            let mut coverages = self.coverages.lock().expect("font cache lock poisoned");
+            let face = self.database.faces().find(|face| {
+                coverages
+                    .entry(face.id)
+                    .or_insert_with(|| {
+                        self.database
+                            .with_face_data(face.id, Coverage::of)
+                            .unwrap_or_default()
+                    })
+                    .contains(c)
+            })?;
+            let id = face.id;
+            drop(coverages);
+            self.load(id)
+        })
+    }
+
+    /// Split text into runs that can each be drawn with a single font, using `primary` where it
+    /// has glyphs and a [fallback](FontRegistry::fallback) elsewhere.
+    ///
+    /// Characters no registered font has a glyph for stay with the primary font (and are drawn as
+    /// its missing glyph). Whitespace always stays in the current run.
+    pub fn runs<'a>(
+        &self,
+        primary: &FontData,
+        text: &'a str,
+        weight: u16,
+        style: FontStyle,
+    ) -> Vec<(&'a str, FontData)> {
+        self.runs_with(primary, text, &[], weight, style)
+    }
+
+    /// Like [`FontRegistry::runs`], but searching the families of a locale first for missing
+    /// glyphs.
+    pub fn runs_for_locale<'a>(
+        &self,
+        primary: &FontData,
+        text: &'a str,
+        locale: &Locale,
+        weight: u16,
+        style: FontStyle,
+    ) -> Vec<(&'a str, FontData)> {
+        self.runs_with(primary, text, &self.families_for_locale(locale), weight, style)
+    }
+
+    fn runs_with<'a>(
+        &self,
+        primary: &FontData,
+        text: &'a str,
+        preferred: &[&str],
+        weight: u16,
+        style: FontStyle,
+    ) -> Vec<(&'a str, FontData)> {
+        let mut runs: Vec<(usize, FontData)> = Vec::new();
+        for (i, c) in text.char_indices() {
+            let current = runs.last().map(|(_, font)| font);
+            if c.is_whitespace() && current.is_some() {
+                continue;
+            }
+            let font = match current {
+                _ if primary.has_glyph(c) => primary.clone(),
+                Some(current) if current.has_glyph(c) => continue,
+                _ => self
+                    .fallback_in(preferred, c, weight, style.clone())
+                    .unwrap_or_else(|| primary.clone()),
+            };
+            if current.is_some_and(|current| same_face(current, &font)) {
+                continue;
+            }
+            runs.push((i, font));
+        }
+
+        let ends = runs.iter().skip(1).map(|(start, _)| *start).chain([text.len()]).collect::<Vec<_>>();
+        runs.into_iter()
+            .zip(ends)
+            .map(|((start, font), end)| (&text[start..end], font))
+            .collect()
+    }
+
+    /// Read the data of a face, sharing it with earlier lookups of the same face.
+    fn load(&self, id: fontdb::ID) -> Option<FontData> {
+        let mut loaded = self.loaded.lock().expect("font cache lock poisoned");
+        if let Some(font) = loaded.get(&id) {
+            return Some(font.clone());
+        }
+        let font = self
+            .database
+            .with_face_data(id, |data, index| FontData::new(Arc::new(data.to_vec()), index))?;
+        // share the character map if it was already read to search for a fallback
+        if let Some(coverage) = self.coverages.lock().expect("font cache lock poisoned").get(&id) {
+            let _ = font.coverage.set(coverage.clone());
+        }
+        loaded.insert(id, font.clone());
+        Some(font)
+    }
+}
+
+/// Whether two fonts are the same face of the same file.
+pub(crate) fn same_face(a: &FontData, b: &FontData) -> bool {
+    Arc::ptr_eq(&a.data, &b.data) && a.index == b.index
+}
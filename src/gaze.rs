@@ -0,0 +1,196 @@
+// overlays for eye tracking
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::backend::RenderBackend;
+use super::brushes::Brush;
+use super::colors::RGBA;
+use super::scenes::Scene;
+use super::shapes::{Circle, Line, Point};
+use super::styles::{Cap, FillStyle, StrokeOptions, Style};
+use super::Drawable;
+
+/// A gaze position reported by an eye tracker.
+#[derive(Debug, Clone, Copy)]
+pub struct GazeSample {
+    /// The time of the sample, on any clock that is shared by all samples.
+    pub time: Duration,
+    /// The gaze position in scene coordinates (convert tracker positions given in device pixels
+    /// with [`Scene::screen_to_world`]).
+    pub position: Point,
+}
+
+/// Shows the current gaze position and a fading trail of the recent samples, e.g. on the
+/// experimenter's screen while monitoring a participant.
+///
+/// Sizes are given in device pixels, so the overlay looks the same at every zoom level.
+#[derive(Debug, Clone)]
+pub struct GazeOverlay {
+    /// The recent samples, oldest first.
+    pub samples: VecDeque<GazeSample>,
+    /// How long samples stay in the trail.
+    pub trail_duration: Duration,
+    pub color: RGBA,
+    /// The radius of the dot at the current gaze position, in device pixels.
+    pub point_radius: f64,
+    /// The width of the trail, in device pixels (0 to hide the trail).
+    pub trail_width: f64,
+    /// Whether the overlay is drawn.
+    pub visible: bool,
+}
+
+impl GazeOverlay {
+    /// Create an overlay with a trail of the last half second.
+    pub fn new(color: RGBA) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            trail_duration: Duration::from_millis(500),
+            color,
+            point_radius: 8.0,
+            trail_width: 2.0,
+            visible: true,
+        }
+    }
+
+    /// Set how long samples stay in the trail.
+    pub fn with_trail(mut self, duration: Duration) -> Self {
+        self.trail_duration = duration;
+        self
+    }
+
+    /// Set the radius of the gaze dot and the width of the trail, in device pixels.
+    pub fn with_sizes(mut self, point_radius: f64, trail_width: f64) -> Self {
+        self.point_radius = point_radius;
+        self.trail_width = trail_width;
+        self
+    }
+
+    /// Add a sample and drop the samples that are older than the trail.
+    pub fn push(&mut self, time: Duration, x: f64, y: f64) {
+        self.samples.push_back(GazeSample {
+            time,
+            position: Point { x, y },
+        });
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| time.saturating_sub(sample.time) > self.trail_duration)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The most recent sample.
+    pub fn latest(&self) -> Option<&GazeSample> {
+        self.samples.back()
+    }
+
+    /// Remove all samples.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for GazeOverlay {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+        let Some(latest) = self.latest().copied() else {
+            return;
+        };
+        let transform = scene.view_transform();
+
+        if self.trail_width > 0.0 {
+            let style = Style::Stroke(StrokeOptions::new(scene.pixels_to_units(self.trail_width)).caps(Cap::Round));
+            let trail = self.trail_duration.as_secs_f64();
+            for (from, to) in self.samples.iter().zip(self.samples.iter().skip(1)) {
+                // older segments fade out
+                let age = latest.time.saturating_sub(to.time).as_secs_f64();
+                let fade = if trail > 0.0 { (1.0 - age / trail).clamp(0.0, 1.0) } else { 1.0 };
+                let color = RGBA {
+                    a: self.color.a * fade as f32,
+                    ..self.color
+                };
+                let line = Line {
+                    start: from.position,
+                    end: to.position,
+                };
+                scene
+                    .backend
+                    .draw_shape(&line, &style, &Brush::Solid(color), transform, None);
+            }
+        }
+
+        let dot = Circle {
+            center: latest.position,
+            radius: scene.pixels_to_units(self.point_radius),
+        };
+        scene.backend.draw_shape(
+            &dot,
+            &Style::Fill(FillStyle::NonZero),
+            &Brush::Solid(self.color),
+            transform,
+            None,
+        );
+    }
+}
+
+/// A calibration target: a disk with a dot in its center, e.g. for eye tracker calibration and
+/// validation sequences.
+#[derive(Debug, Clone)]
+pub struct CalibrationTarget {
+    /// The center of the target in scene coordinates.
+    pub center: Point,
+    /// The radius of the outer disk, in scene units.
+    pub radius: f64,
+    /// The radius of the center dot, in scene units.
+    pub dot_radius: f64,
+    pub color: RGBA,
+    pub dot_color: RGBA,
+    /// A scale applied to the outer disk, e.g. to let it shrink towards the dot to draw the
+    /// participant's attention.
+    pub scale: f64,
+    /// Whether the target is drawn.
+    pub visible: bool,
+}
+
+impl CalibrationTarget {
+    /// Create a target with a dot a quarter of its size.
+    pub fn new(center: Point, radius: f64, color: RGBA, dot_color: RGBA) -> Self {
+        Self {
+            center,
+            radius,
+            dot_radius: radius / 4.0,
+            color,
+            dot_color,
+            scale: 1.0,
+            visible: true,
+        }
+    }
+
+    /// Set the scale of the outer disk.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for CalibrationTarget {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+        let transform = scene.view_transform();
+        let fill = Style::Fill(FillStyle::NonZero);
+        for (radius, color) in [(self.radius * self.scale, self.color), (self.dot_radius, self.dot_color)] {
+            let circle = Circle {
+                center: self.center,
+                radius,
+            };
+            scene
+                .backend
+                .draw_shape(&circle, &fill, &Brush::Solid(color), transform, None);
+        }
+    }
+}
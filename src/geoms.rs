@@ -1,8 +1,17 @@
-use super::affine::Affine;
-use super::brushes::{Brush, Image};
+use std::any::Any;
+
+use super::affine::{Affine, Fit};
+use super::backend::RenderBackend;
+use super::brushes::{Brush, Gradient, Image};
+use super::colors::RGBA;
+use super::error::{RendererError, Result};
 pub use super::scenes::Scene;
-use super::shapes::{Point, Rectangle, Shape};
-use super::styles::{FillStyle, ImageFitMode, Style};
+use super::shapes::{Fillable, Path, PathElement, Point, Polyline, Rectangle, RoundedRectangle, Shape};
+use super::styles::{Cap, CompositeMode, FillStyle, ImageFitMode, Join, MixMode, StrokeOptions, Style};
+use super::user_data::{Hit, UserData};
+use super::Drawable;
+use kurbo::Shape as _;
+use kurbo::{ParamCurve, ParamCurveArclen};
 
 // A geometric object that can be rendered, consisting of a shape and a brush.
 #[derive(Debug, Clone)]
@@ -12,6 +21,35 @@ pub struct Geom<S: Shape> {
     pub brush: Brush,
     pub transform: Affine,
     pub brush_transform: Option<Affine>,
+    /// The coordinate space `brush_transform` maps the brush into.
+    pub brush_space: BrushSpace,
+    /// Align axis-aligned edges to the pixel grid (see [`Scene::pixel_snapping`]).
+    pub pixel_snap: bool,
+    /// Whether the geom is drawn.
+    pub visible: bool,
+    /// The opacity of the geom, multiplied with the alpha of its brush.
+    pub opacity: f32,
+    /// The part of the shape's length that is stroked (see [`Geom::stroke_trim`]).
+    pub stroke_trim: Option<(f64, f64)>,
+    /// Application data returned when the geom is hit-tested.
+    pub user_data: Option<UserData>,
+    /// The last trimmed path, so that a trim that doesn't change isn't recomputed every frame.
+    trimmed: TrimCache,
+}
+
+/// The coordinate space a brush is positioned in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BrushSpace {
+    /// The brush moves with the shape (the brush transform is followed by the geom transform).
+    #[default]
+    Object,
+    /// The brush is fixed to the screen (the brush transform maps to device pixels), so it stays
+    /// in place when the shape moves, e.g. for a grating seen through a moving aperture.
+    Screen,
+    /// A gradient brush is sampled by arc length along the stroked path, from offset 0 at the
+    /// start to 1 at the end, e.g. for trajectories or progress strokes. Fills and other brushes
+    /// are drawn as with [`BrushSpace::Object`].
+    PathLength,
 }
 
 pub trait GeomTrait {
@@ -39,41 +77,858 @@ pub trait GeomTrait {
         };
 
 
-        let org_width = image.width as f64;
-        let org_height = image.height as f64;
+        let image_rect = Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: image.width as f64,
+                y: image.height as f64,
+            },
+        };
+        let corner = Affine::translate(shape.a.x, shape.a.y);
+        let placement = match fit_mode {
+            ImageFitMode::Original => corner,
+            ImageFitMode::Fill => Affine::fit(image_rect.clone(), shape.clone(), Fit::Stretch),
+            ImageFitMode::Exact {
+                width: new_width,
+                height: new_height,
+            } => Affine::scale_xy(new_width / image_rect.b.x, new_height / image_rect.b.y) * corner,
+            ImageFitMode::Contain => Affine::fit(image_rect.clone(), shape.clone(), Fit::Contain),
+            ImageFitMode::Cover => Affine::fit(image_rect.clone(), shape.clone(), Fit::Cover),
+        };
+        // the image offset moves the image within the rectangle
+        let brush_transform = placement * Affine::translate(image_x, image_y);
 
-        let brush = Brush::Image{
+        // without tiling, the rectangle is clipped to the image so that the edge pixels aren't
+        // smeared over the rest of it (e.g. the bars left by `Contain`)
+        let shape = if edge_mode == crate::brushes::Extend::Pad {
+            let placed = kurbo::Affine::from(brush_transform)
+                .transform_rect_bbox(kurbo::Rect::new(0.0, 0.0, image_rect.b.x, image_rect.b.y));
+            let clipped = kurbo::Rect::new(shape.a.x, shape.a.y, shape.b.x, shape.b.y).intersect(placed);
+            Rectangle {
+                a: Point {
+                    x: clipped.x0,
+                    y: clipped.y0,
+                },
+                b: Point {
+                    x: clipped.x1.max(clipped.x0),
+                    y: clipped.y1.max(clipped.y0),
+                },
+            }
+        } else {
+            shape
+        };
+
+        let brush = Brush::Image {
             image,
             x: image_x,
             y: image_y,
             fit_mode,
             edge_mode,
+            edge_mode_y: None,
+            mapping: Default::default(),
         };
 
-        let brush_transform = match fit_mode {
-            ImageFitMode::Original => None,
-            ImageFitMode::Fill => Some(Affine::scale_xy(width / org_width, height / org_height)
-                * Affine::translate(x - width / 2.0, y - height / 2.0)),
-            ImageFitMode::Exact { width: new_width, height: new_height } => {
-                Some(Affine::scale_xy(new_width / org_width, new_height / org_height))
-            }
-        };
-
-        // Center the brush.
-        // let brush_transform =
-        //     brush_transform.map(|t| t * Affine::translate(x - width / 2.0, y - height / 2.0));
-
-        // Apple image_x and image_y.
-        let brush_transform = brush_transform.map(|t| t * Affine::translate(image_x, image_y));
-
         Geom {
             style: Style::Fill(FillStyle::NonZero),
             shape,
             brush,
             transform,
-            brush_transform,
+            brush_transform: Some(brush_transform),
+            brush_space: BrushSpace::Object,
+            pixel_snap: false,
+            visible: true,
+            opacity: 1.0,
+            stroke_trim: None,
+            user_data: None,
+            trimmed: TrimCache::default(),
         }
     }
 }
 
 impl GeomTrait for Geom<Rectangle> {}
+
+impl Geom<Rectangle> {
+    /// Set how the corners of the stroke are drawn: sharp ([`Join::Miter`]), rounded
+    /// ([`Join::Round`]) or cut off ([`Join::Bevel`]). Fills are not affected.
+    pub fn with_corner_join(mut self, join: Join) -> Self {
+        if let Style::Stroke(options) = &mut self.style {
+            options.join = join;
+        }
+        self
+    }
+}
+
+impl Geom<RoundedRectangle> {
+    /// Set how the corners of the stroke are drawn if the corner radius is 0, like for
+    /// rectangles. Corners with a radius are always rounded. Fills are not affected.
+    pub fn with_corner_join(mut self, join: Join) -> Self {
+        if let Style::Stroke(options) = &mut self.style {
+            options.join = join;
+        }
+        self
+    }
+}
+
+impl<S: Shape> Geom<S> {
+    /// Only stroke the part of the shape between the normalized arc lengths `start` and `end`
+    /// (0 is the start and 1 the end of the path), e.g. to reveal a line drawing over time.
+    /// Nothing is stroked if `start` is not before `end`. Fills are not trimmed.
+    pub fn stroke_trim(&mut self, start: f64, end: f64) {
+        self.stroke_trim = Some((start, end));
+    }
+}
+
+impl<S: Fillable> Geom<S> {
+    /// Create a geom that fills a shape (with the non-zero rule). Stroke-only shapes (e.g.
+    /// [`Line`](super::shapes::Line)) can't be filled.
+    pub fn fill(shape: S, brush: impl Into<Brush>) -> Self {
+        Self::new(shape, Style::Fill(FillStyle::NonZero), brush)
+    }
+}
+
+impl<S: Shape> Geom<S> {
+    /// Create a geom with an identity transform, e.g. `Geom::new(shape, style, RGBA::RED)`.
+    pub fn new(shape: S, style: Style, brush: impl Into<Brush>) -> Self {
+        Self {
+            style,
+            shape,
+            brush: brush.into(),
+            transform: Affine::identity(),
+            brush_transform: None,
+            brush_space: BrushSpace::Object,
+            pixel_snap: false,
+            visible: true,
+            opacity: 1.0,
+            stroke_trim: None,
+            user_data: None,
+            trimmed: TrimCache::default(),
+        }
+    }
+
+    /// Create a geom that fills a shape, or an error if the shape can only be stroked. Unlike
+    /// [`Geom::fill`], this works for any [`Shape`], e.g. in code that is generic over shapes.
+    pub fn try_fill(shape: S, brush: impl Into<Brush>) -> Result<Self> {
+        if !shape.is_fillable() {
+            return Err(RendererError::InvalidArgument(format!(
+                "{} can only be stroked, use Style::Stroke to draw it",
+                std::any::type_name::<S>()
+            )));
+        }
+        Ok(Self::new(shape, Style::Fill(FillStyle::NonZero), brush))
+    }
+
+    /// Create a geom that strokes the outline of a shape.
+    pub fn stroke(shape: S, width: f64, brush: impl Into<Brush>) -> Self {
+        Self::new(shape, Style::Stroke(StrokeOptions::new(width)), brush)
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Attach application data, e.g. the id of the entity the geom shows.
+    pub fn with_user_data(mut self, data: impl Any + Send + Sync) -> Self {
+        self.user_data = Some(UserData::new(data));
+        self
+    }
+
+    /// The axis-aligned bounding box of the geom in the coordinates it is drawn in, including its
+    /// stroke (estimated generously for miter joins and square caps).
+    pub fn bounding_box(&self) -> Rectangle {
+        let bounds = self.transformed_bounds(self.transform);
+        Rectangle {
+            a: Point {
+                x: bounds.x0,
+                y: bounds.y0,
+            },
+            b: Point {
+                x: bounds.x1,
+                y: bounds.y1,
+            },
+        }
+    }
+
+    /// The bounding box of the geom (with its stroke) after `transform`.
+    fn transformed_bounds(&self, transform: Affine) -> kurbo::Rect {
+        let bounds = self.shape.bounding_box();
+        let mut bounds = kurbo::Rect::new(bounds.a.x, bounds.a.y, bounds.b.x, bounds.b.y);
+        if let Style::Stroke(options) = &self.style {
+            // miter joins can reach `miter_limit` half widths out, square caps sqrt(2)
+            let extent = options.width / 2.0 * options.miter_limit.max(std::f64::consts::SQRT_2);
+            bounds = bounds.inflate(extent, extent);
+        }
+        kurbo::Affine::from(transform).transform_rect_bbox(bounds)
+    }
+
+    /// Whether the geom lies entirely outside of the viewport of the scene, extended by `margin`
+    /// device pixels.
+    fn is_culled<Backend: RenderBackend>(&self, scene: &Scene<Backend>, margin: f64) -> bool {
+        let bounds = self.transformed_bounds(self.transform * scene.device_transform());
+        let viewport = kurbo::Rect::new(0.0, 0.0, scene.width as f64, scene.height as f64).inflate(margin, margin);
+        bounds.x1 < viewport.x0 || bounds.x0 > viewport.x1 || bounds.y1 < viewport.y0 || bounds.y0 > viewport.y1
+    }
+
+    /// Whether a point (in the coordinates the geom is drawn in) is inside the filled shape or
+    /// on the stroke, ignoring the brush.
+    pub fn contains(&self, point: Point) -> bool {
+        if !self.visible || self.transform.determinant() == 0.0 {
+            return false;
+        }
+        let local = self.transform.inverse().transform_point(point);
+        let local = kurbo::Point::new(local.x, local.y);
+        match &self.style {
+            Style::Fill(_) => self.shape.to_path().contains(local),
+            Style::Stroke(options) => {
+                let path = match self.stroke_trim {
+                    Some((start, end)) => {
+                        let path = self.shape.to_path();
+                        self.trimmed
+                            .get(&path, start, end)
+                            .unwrap_or_else(|| trim_path(&path, start, end))
+                    }
+                    None => self.shape.to_path(),
+                };
+                let outline = kurbo::stroke(
+                    path,
+                    &kurbo::Stroke::from(options.clone()),
+                    &kurbo::StrokeOpts::default(),
+                    HIT_TEST_TOLERANCE,
+                );
+                outline.contains(local)
+            }
+        }
+    }
+
+    /// Set the opacity.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Draw the geom without its opacity.
+    fn draw_opaque<Backend: RenderBackend>(&mut self, scene: &mut Scene<Backend>) {
+        let transform = self.transform * scene.view_transform();
+        let brush_transform = match self.brush_space {
+            BrushSpace::Object | BrushSpace::PathLength => self.brush_transform,
+            // undo the shape transform so the brush ends up in device space
+            BrushSpace::Screen => Some(self.brush_transform.unwrap_or(Affine::identity()) * transform.inverse()),
+        };
+
+        let trimmed = match (self.stroke_trim, &self.style) {
+            (Some((start, end)), Style::Stroke(_)) => {
+                Some(Path::from(self.trimmed.trim(self.shape.to_path(), start, end)))
+            }
+            _ => None,
+        };
+
+        if self.brush_space == BrushSpace::PathLength {
+            if let (Style::Stroke(options), Brush::Gradient(gradient)) = (&self.style, &self.brush) {
+                let path = trimmed.as_ref().map_or_else(|| self.shape.to_path(), Shape::to_path);
+                stroke_along_path(scene, &path, options, gradient, transform);
+                return;
+            }
+        }
+
+        if let Some(path) = trimmed {
+            scene
+                .backend
+                .draw_shape(&path, &self.style, &self.brush, transform, brush_transform);
+            return;
+        }
+
+        if self.pixel_snap || scene.pixel_snapping {
+            let stroke_width = match &self.style {
+                Style::Stroke(options) => Some(options.width),
+                Style::Fill(_) => None,
+            };
+            if let Some(rect) = self.shape.as_rect() {
+                if let Some((rect, stroke_width)) = snap_rect(&rect, transform, stroke_width) {
+                    self.draw_snapped(scene, &rect, stroke_width, transform, brush_transform);
+                    return;
+                }
+            } else if let Some((path, stroke_width)) = snap_path(&self.shape.to_path(), transform, stroke_width) {
+                self.draw_snapped(scene, &path, stroke_width, transform, brush_transform);
+                return;
+            }
+        }
+
+        scene
+            .backend
+            .draw_shape(&self.shape, &self.style, &self.brush, transform, brush_transform);
+    }
+
+    /// Draw a shape that was snapped to the pixel grid (in device space) with the style and brush
+    /// of the geom.
+    fn draw_snapped<Backend: RenderBackend>(
+        &self,
+        scene: &mut Scene<Backend>,
+        shape: &impl Shape,
+        stroke_width: Option<f64>,
+        transform: Affine,
+        brush_transform: Option<Affine>,
+    ) {
+        let mut style = self.style.clone();
+        if let (Style::Stroke(options), Some(width)) = (&mut style, stroke_width) {
+            options.width = width;
+        }
+        // the snapped shape is in device space, so the brush needs to follow
+        let brush_transform = brush_transform.unwrap_or(Affine::identity()) * transform;
+        scene
+            .backend
+            .draw_shape(shape, &style, &self.brush, Affine::identity(), Some(brush_transform));
+    }
+}
+
+impl<S: Shape, Backend: RenderBackend> Drawable<Backend> for Geom<S> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible || self.opacity <= 0.0 {
+            return;
+        }
+        if let Some(margin) = scene.cull_margin.filter(|_| !scene.recording_cache()) {
+            if self.is_culled(scene, margin) {
+                return;
+            }
+        }
+        if self.opacity >= 1.0 {
+            self.draw_opaque(scene);
+            return;
+        }
+
+        match &self.brush {
+            Brush::Solid(color) => {
+                let faded = Brush::Solid(RGBA {
+                    a: color.a * self.opacity,
+                    ..*color
+                });
+                let brush = std::mem::replace(&mut self.brush, faded);
+                self.draw_opaque(scene);
+                self.brush = brush;
+            }
+            _ => {
+                // other brushes would have to be rebuilt, so the geom is faded as a layer
+                let bounds = Rectangle {
+                    a: Point { x: 0.0, y: 0.0 },
+                    b: Point {
+                        x: scene.width as f64,
+                        y: scene.height as f64,
+                    },
+                };
+                scene.backend.push_layer(
+                    MixMode::Normal,
+                    CompositeMode::SourceOver,
+                    &bounds,
+                    Affine::identity(),
+                    self.opacity,
+                );
+                self.draw_opaque(scene);
+                scene.backend.pop_layer();
+            }
+        }
+    }
+
+    fn hit_test(&self, point: Point) -> Option<Hit<'_>> {
+        self.contains(point).then_some(Hit {
+            user_data: self.user_data.as_ref(),
+        })
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        Some(self.bounding_box())
+    }
+}
+
+/// A path with the part of it that was cut out by [`trim_path`].
+#[derive(Debug, Clone, Default)]
+struct TrimCache(Option<(kurbo::BezPath, f64, f64, kurbo::BezPath)>);
+
+impl TrimCache {
+    /// The cached part of a path, if the path and the trim are the same as last time.
+    fn get(&self, path: &kurbo::BezPath, start: f64, end: f64) -> Option<kurbo::BezPath> {
+        let (cached_path, cached_start, cached_end, trimmed) = self.0.as_ref()?;
+        (*cached_start == start && *cached_end == end && cached_path == path).then(|| trimmed.clone())
+    }
+
+    /// Cut out a part of a path (see [`trim_path`]), reusing the last result if nothing changed.
+    fn trim(&mut self, path: kurbo::BezPath, start: f64, end: f64) -> kurbo::BezPath {
+        if let Some(trimmed) = self.get(&path, start, end) {
+            return trimmed;
+        }
+        let trimmed = trim_path(&path, start, end);
+        self.0 = Some((path, start, end, trimmed.clone()));
+        trimmed
+    }
+}
+
+/// Cut out the part of a path between the normalized arc lengths `start` and `end`. The path is
+/// empty if `start` is not before `end`.
+pub(crate) fn trim_path(path: &kurbo::BezPath, start: f64, end: f64) -> kurbo::BezPath {
+    const ACCURACY: f64 = 1e-3;
+    let (start, end) = (start.clamp(0.0, 1.0), end.clamp(0.0, 1.0));
+    if start >= end {
+        return kurbo::BezPath::new();
+    }
+    if start == 0.0 && end == 1.0 {
+        return path.clone();
+    }
+
+    let segments: Vec<_> = path.segments().map(|segment| (segment, segment.arclen(ACCURACY))).collect();
+    let total_length: f64 = segments.iter().map(|(_, length)| length).sum();
+    let (start, end) = (start * total_length, end * total_length);
+
+    let mut trimmed = kurbo::BezPath::new();
+    let mut position = 0.0;
+    for (segment, length) in segments {
+        let segment_start = position;
+        position += length;
+        if length <= 0.0 || position <= start || segment_start >= end {
+            continue;
+        }
+        let t0 = if start > segment_start { segment.inv_arclen(start - segment_start, ACCURACY) } else { 0.0 };
+        let t1 = if end < position { segment.inv_arclen(end - segment_start, ACCURACY) } else { 1.0 };
+        let part = segment.subsegment(t0..t1);
+        // start a new subpath unless the part continues the previous one
+        if trimmed.elements().last().and_then(kurbo::PathEl::end_point) != Some(part.start()) {
+            trimmed.move_to(part.start());
+        }
+        trimmed.push(part.as_path_el());
+    }
+    trimmed
+}
+
+/// The number of pieces a stroke is split into when its gradient is sampled along the path.
+const PATH_LENGTH_PIECES: usize = 256;
+
+/// The accuracy of stroke outlines used for hit-testing, in local units.
+const HIT_TEST_TOLERANCE: f64 = 0.01;
+
+/// Stroke a path with a gradient that is sampled by arc length.
+///
+/// The flattened path is split into pieces of equal length that are each drawn with the solid
+/// color at their center. Pieces in the middle of a subpath have butt caps, and dashes continue
+/// across pieces.
+fn stroke_along_path<Backend: RenderBackend>(
+    scene: &mut Scene<Backend>,
+    path: &kurbo::BezPath,
+    options: &StrokeOptions,
+    gradient: &Gradient,
+    transform: Affine,
+) {
+    // flatten with a tolerance of a tenth of a device pixel
+    let scale = transform.determinant().abs().sqrt();
+    let tolerance = if scale > 0.0 { 0.1 / scale } else { 0.1 };
+    let mut subpaths: Vec<(Vec<kurbo::Point>, bool)> = Vec::new();
+    kurbo::flatten(path, tolerance, |element| match element {
+        kurbo::PathEl::MoveTo(p) => subpaths.push((vec![p], false)),
+        kurbo::PathEl::LineTo(p) => {
+            if let Some((points, _)) = subpaths.last_mut() {
+                points.push(p);
+            }
+        }
+        kurbo::PathEl::ClosePath => {
+            if let Some((points, closed)) = subpaths.last_mut() {
+                points.push(points[0]);
+                *closed = true;
+            }
+        }
+        _ => {}
+    });
+
+    let length = |points: &[kurbo::Point]| points.windows(2).map(|w| w[0].distance(w[1])).sum::<f64>();
+    let total_length: f64 = subpaths.iter().map(|(points, _)| length(points)).sum();
+    if total_length <= 0.0 {
+        return;
+    }
+    let piece_length = total_length / PATH_LENGTH_PIECES as f64;
+
+    let mut draw_piece = |points: Vec<kurbo::Point>, start: f64, offset: f64, first: bool, last: bool, closed: bool| {
+        let end = start + length(&points);
+        let mut options = options.clone();
+        if !first || closed {
+            options.start_cap = Cap::Butt;
+        }
+        if !last || closed {
+            options.end_cap = Cap::Butt;
+        }
+        options.dash_offset += offset;
+        let color = gradient.color_at(((start + end) / 2.0 / total_length) as f32);
+        let shape = Polyline {
+            points: points.into_iter().map(Point::from).collect(),
+        };
+        scene
+            .backend
+            .draw_shape(&shape, &Style::Stroke(options), &Brush::Solid(color), transform, None);
+    };
+
+    // arc length at the start of the current subpath and at the start of the current piece
+    let mut subpath_start = 0.0;
+    for (points, closed) in subpaths {
+        let mut piece = vec![points[0]];
+        let mut piece_start = subpath_start;
+        let mut position = subpath_start;
+        let mut first = true;
+        for segment in points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let segment_length = a.distance(b);
+            let segment_start = position;
+            // split the segment wherever it crosses the end of the current piece
+            while segment_start + segment_length > piece_start + piece_length + 1e-9 {
+                let split = a.lerp(b, (piece_start + piece_length - segment_start) / segment_length);
+                piece.push(split);
+                let offset = piece_start - subpath_start;
+                draw_piece(std::mem::replace(&mut piece, vec![split]), piece_start, offset, first, false, closed);
+                piece_start += piece_length;
+                first = false;
+            }
+            piece.push(b);
+            position += segment_length;
+        }
+        if piece.len() > 1 {
+            let offset = piece_start - subpath_start;
+            draw_piece(piece, piece_start, offset, first, true, closed);
+        }
+        subpath_start = position;
+    }
+}
+
+/// Snap an axis-aligned rectangle to the device pixel grid.
+///
+/// Returns the rectangle in device space and the stroke width in device pixels, or `None` if
+/// the transform contains a rotation or skew. Strokes with an odd pixel width are centered on
+/// pixel centers so they cover whole pixels.
+fn snap_rect(rect: &Rectangle, transform: Affine, stroke_width: Option<f64>) -> Option<(Rectangle, Option<f64>)> {
+    let [a, b, c, d, e, f] = transform.0;
+    if b != 0.0 || c != 0.0 {
+        return None;
+    }
+
+    let stroke_width = stroke_width.map(|w| (w * (a.abs() + d.abs()) / 2.0).round().max(1.0));
+    let offset = match stroke_width {
+        Some(w) if w as i64 % 2 == 1 => 0.5,
+        _ => 0.0,
+    };
+    let snap = |v: f64| (v - offset).round() + offset;
+
+    let (x0, x1) = (a * rect.a.x + e, a * rect.b.x + e);
+    let (y0, y1) = (d * rect.a.y + f, d * rect.b.y + f);
+
+    Some((
+        Rectangle {
+            a: Point {
+                x: snap(x0.min(x1)),
+                y: snap(y0.min(y1)),
+            },
+            b: Point {
+                x: snap(x0.max(x1)),
+                y: snap(y0.max(y1)),
+            },
+        },
+        stroke_width,
+    ))
+}
+
+/// Snap the horizontal and vertical line segments of a path to the device pixel grid, like
+/// [`snap_rect`] does for rectangles. Curves and diagonal lines are only moved where they meet a
+/// snapped segment.
+///
+/// Returns the path in device space and the stroke width in device pixels, or `None` if the
+/// transform contains a rotation or skew or the path has no horizontal or vertical segments.
+fn snap_path(path: &kurbo::BezPath, transform: Affine, stroke_width: Option<f64>) -> Option<(Path, Option<f64>)> {
+    let [a, b, c, d, _, _] = transform.0;
+    if b != 0.0 || c != 0.0 {
+        return None;
+    }
+
+    let stroke_width = stroke_width.map(|w| (w * (a.abs() + d.abs()) / 2.0).round().max(1.0));
+    let offset = match stroke_width {
+        Some(w) if w as i64 % 2 == 1 => 0.5,
+        _ => 0.0,
+    };
+    let snap = |v: f64| (v - offset).round() + offset;
+
+    let mut path = Path::from(kurbo::Affine::from(transform) * path.clone());
+    let ends: Vec<Option<Point>> = path.elements.iter_mut().map(|element| end_point(element).copied()).collect();
+
+    // for every element, whether the x and y coordinate of its end point are snapped
+    let mut snap_x = vec![false; path.elements.len()];
+    let mut snap_y = vec![false; path.elements.len()];
+    // the element whose end point is the current point, and the start of the subpath
+    let (mut current, mut start): (Option<usize>, usize) = (None, 0);
+    for (i, element) in path.elements.iter().enumerate() {
+        let segment = match element {
+            PathElement::MoveTo(_) => {
+                start = i;
+                None
+            }
+            PathElement::LineTo(_) => current.map(|from| (from, i)),
+            PathElement::Close => current.map(|from| (from, start)),
+            PathElement::QuadTo(..) | PathElement::CurveTo(..) => None,
+        };
+        if let Some((from, to)) = segment {
+            let (p, q) = (ends[from]?, ends[to]?);
+            if p.y == q.y {
+                snap_y[from] = true;
+                snap_y[to] = true;
+            }
+            if p.x == q.x {
+                snap_x[from] = true;
+                snap_x[to] = true;
+            }
+        }
+        current = Some(if matches!(element, PathElement::Close) { start } else { i });
+    }
+    if !snap_x.iter().chain(&snap_y).any(|&snapped| snapped) {
+        return None;
+    }
+
+    for (i, element) in path.elements.iter_mut().enumerate() {
+        let Some(p) = end_point(element) else {
+            continue;
+        };
+        if snap_x[i] {
+            p.x = snap(p.x);
+        }
+        if snap_y[i] {
+            p.y = snap(p.y);
+        }
+    }
+    Some((path, stroke_width))
+}
+
+/// The point a path element ends at.
+fn end_point(element: &mut PathElement) -> Option<&mut Point> {
+    match element {
+        PathElement::MoveTo(p) | PathElement::LineTo(p) => Some(p),
+        PathElement::QuadTo(_, p) | PathElement::CurveTo(_, _, p) => Some(p),
+        PathElement::Close => None,
+    }
+}
+
+/// A brush that repeats infinitely in scene coordinates, e.g. a textured background.
+///
+/// The brush always covers the whole scene, no matter where the camera is, and moves with the
+/// camera like any other object in the scene.
+#[derive(Debug, Clone)]
+pub struct TiledBackground {
+    pub brush: Brush,
+    /// Transform from brush coordinates to scene coordinates (e.g. to scale or offset the tiles).
+    pub tile_transform: Affine,
+}
+
+impl TiledBackground {
+    /// Create a tiled background from a brush. Image brushes should use [`Extend::Repeat`](crate::brushes::Extend).
+    pub fn new(brush: Brush) -> Self {
+        Self {
+            brush,
+            tile_transform: Affine::identity(),
+        }
+    }
+
+    /// Create a background that repeats an image at its natural size, with its top left corner at
+    /// the origin of the scene.
+    pub fn image(image: Image) -> Self {
+        Self::new(Brush::Image {
+            image,
+            x: 0.0,
+            y: 0.0,
+            fit_mode: ImageFitMode::Original,
+            edge_mode: crate::brushes::Extend::Repeat,
+            edge_mode_y: None,
+            mapping: Default::default(),
+        })
+    }
+
+    /// Set the transform from brush coordinates to scene coordinates.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.tile_transform = transform;
+        self
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for TiledBackground {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        // fill the whole device area and anchor the brush in scene space
+        let rect = Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: scene.width as f64,
+                y: scene.height as f64,
+            },
+        };
+        let brush_transform = self.tile_transform * scene.view_transform();
+        scene.backend.draw_shape(
+            &rect,
+            &Style::Fill(FillStyle::NonZero),
+            &self.brush,
+            Affine::identity(),
+            Some(brush_transform),
+        );
+    }
+}
+
+/// A soft shadow of a rounded rectangle, e.g. below a card or a dialog.
+///
+/// The blur is computed analytically, so it is much cheaper than a [`LayerEffect`] drop shadow
+/// (backends that can't blur draw a sharp shadow).
+///
+/// [`LayerEffect`]: crate::styles::LayerEffect
+#[derive(Debug, Clone)]
+pub struct Shadow {
+    /// The rectangle that casts the shadow.
+    pub rect: RoundedRectangle,
+    /// The offset of the shadow from the rectangle, in scene units.
+    pub offset: Point,
+    /// The standard deviation of the blur, in the units of the rectangle.
+    pub softness: f64,
+    pub color: RGBA,
+    pub transform: Affine,
+    /// Whether the shadow is drawn.
+    pub visible: bool,
+}
+
+impl Shadow {
+    /// Create a shadow directly below a rectangle.
+    pub fn new(rect: RoundedRectangle, softness: f64, color: RGBA) -> Self {
+        Self {
+            rect,
+            offset: Point { x: 0.0, y: 0.0 },
+            softness,
+            color,
+            transform: Affine::identity(),
+            visible: true,
+        }
+    }
+
+    /// Set the offset of the shadow.
+    pub fn with_offset(mut self, x: f64, y: f64) -> Self {
+        self.offset = Point { x, y };
+        self
+    }
+
+    /// Set the transform of the rectangle.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for Shadow {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+        // the offset is applied after the transform, so the shadow falls the same way for rotated
+        // rectangles
+        let transform = self.transform * Affine::translate(self.offset.x, self.offset.y) * scene.view_transform();
+        scene
+            .backend
+            .draw_blurred_rounded_rect(&self.rect, self.color, self.softness, transform);
+    }
+}
+
+/// An animated two-color dashed outline ("marching ants"), e.g. to show a selection in an editor.
+///
+/// The dashes move along the outline with the frame clock (see [`Scene::begin_frame`]). Widths
+/// and lengths are in device pixels, so the outline looks the same at every zoom level.
+#[derive(Debug, Clone)]
+pub struct MarchingAnts<S: Shape> {
+    pub shape: S,
+    /// The colors of the dashes and of the gaps between them.
+    pub colors: [RGBA; 2],
+    /// The width of the outline, in device pixels.
+    pub width: f64,
+    /// The length of a dash (and of a gap), in device pixels.
+    pub dash_length: f64,
+    /// The speed of the dashes along the outline, in device pixels per second.
+    pub speed: f64,
+    pub transform: Affine,
+    /// Whether the outline is drawn.
+    pub visible: bool,
+}
+
+impl<S: Shape> MarchingAnts<S> {
+    /// Create a black and white outline of one pixel with dashes of four pixels that move by 16
+    /// pixels per second.
+    pub fn new(shape: S) -> Self {
+        Self {
+            shape,
+            colors: [RGBA::BLACK, RGBA::WHITE],
+            width: 1.0,
+            dash_length: 4.0,
+            speed: 16.0,
+            transform: Affine::identity(),
+            visible: true,
+        }
+    }
+
+    /// Set the colors of the dashes and of the gaps.
+    pub fn with_colors(mut self, dash: RGBA, gap: RGBA) -> Self {
+        self.colors = [dash, gap];
+        self
+    }
+
+    /// Set the width of the outline, in device pixels.
+    pub fn with_width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the length of the dashes, in device pixels.
+    pub fn with_dash_length(mut self, dash_length: f64) -> Self {
+        self.dash_length = dash_length;
+        self
+    }
+
+    /// Set the speed of the dashes, in device pixels per second.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+}
+
+impl<S: Shape, Backend: RenderBackend> Drawable<Backend> for MarchingAnts<S> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        let scale = (self.transform * scene.view_transform()).determinant().abs().sqrt();
+        if !self.visible || scale == 0.0 || self.dash_length <= 0.0 {
+            return;
+        }
+        // device pixels in the coordinates of the shape
+        let unit = 1.0 / scale;
+        let period = 2.0 * self.dash_length;
+        let offset = (self.speed * scene.frame.time.as_secs_f64()).rem_euclid(period);
+
+        let gaps = StrokeOptions::new(self.width * unit);
+        let dashes = gaps
+            .clone()
+            .dash(&[self.dash_length * unit, self.dash_length * unit], -offset * unit);
+        for (options, color) in [(gaps, self.colors[1]), (dashes, self.colors[0])] {
+            Geom::new(self.shape.clone(), Style::Stroke(options), color)
+                .with_transform(self.transform)
+                .draw(scene);
+        }
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        // the width is in device pixels, so only the outline itself is known
+        let bounds = self.shape.bounding_box();
+        let bounds = kurbo::Rect::new(bounds.a.x, bounds.a.y, bounds.b.x, bounds.b.y);
+        let bounds = kurbo::Affine::from(self.transform).transform_rect_bbox(bounds);
+        Some(Rectangle {
+            a: Point {
+                x: bounds.x0,
+                y: bounds.y0,
+            },
+            b: Point {
+                x: bounds.x1,
+                y: bounds.y1,
+            },
+        })
+    }
+}
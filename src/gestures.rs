@@ -0,0 +1,198 @@
+// touch gestures
+use std::time::{Duration, Instant};
+
+use super::backend::RenderBackend;
+use super::input::{InputEvent, TouchPhase};
+use super::scenes::Scene;
+use super::shapes::Point;
+
+/// A gesture recognized from touch events by a [`GestureRecognizer`]. Positions and distances
+/// are in device pixels.
+#[derive(Debug, Clone, Copy)]
+pub enum Gesture {
+    /// A finger touched and lifted without moving.
+    Tap { position: Point },
+    /// A single finger moved by `delta` since the previous drag event.
+    Drag { position: Point, delta: Point },
+    /// The finger of a drag lifted.
+    DragEnded { position: Point },
+    /// Two fingers moved: their midpoint moved by `translation` and their distance changed by
+    /// the factor `scale` since the previous pinch event.
+    Pinch {
+        center: Point,
+        translation: Point,
+        scale: f64,
+    },
+}
+
+/// A touch point that is down.
+#[derive(Debug, Clone, Copy)]
+struct Touch {
+    id: u64,
+    position: Point,
+}
+
+/// Recognizes taps, drags and two-finger pinches from the touch events of [`InputEvent`]s, e.g.
+/// for experiments run on tablets. Other events are ignored.
+///
+/// Drags start once a finger moves further than `tap_slop`. When a second finger touches, the
+/// gesture becomes a pinch until a finger lifts; the remaining finger then continues as a drag.
+/// Pass the gestures to [`Scene::apply_gesture`] to pan and zoom the camera with them.
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    /// How far a finger can move (in device pixels) and still tap.
+    pub tap_slop: f64,
+    /// How long a finger can be down and still tap.
+    pub tap_timeout: Duration,
+    touches: Vec<Touch>,
+    /// The start of the touch that may become a tap.
+    tap: Option<(Point, Instant)>,
+    dragging: bool,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        Self {
+            tap_slop: 10.0,
+            tap_timeout: Duration::from_millis(300),
+            touches: Vec::new(),
+            tap: None,
+            dragging: false,
+        }
+    }
+
+    /// The number of fingers that are down.
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    /// Feed an input event, returning the gesture it completes or continues (if any).
+    pub fn handle(&mut self, event: &InputEvent) -> Option<Gesture> {
+        let InputEvent::Touch { id, phase, position } = *event else {
+            return None;
+        };
+        match phase {
+            TouchPhase::Started => {
+                self.touches.retain(|touch| touch.id != id);
+                self.touches.push(Touch { id, position });
+                // a second finger turns any tap or drag into a pinch
+                self.tap = (self.touches.len() == 1).then(|| (position, Instant::now()));
+                self.dragging = false;
+                None
+            }
+            TouchPhase::Moved => {
+                let pinch = self.pinch();
+                let index = self.touches.iter().position(|touch| touch.id == id)?;
+                let previous = std::mem::replace(&mut self.touches[index].position, position);
+
+                if index >= 2 {
+                    // only the first two fingers pinch
+                    return None;
+                }
+                if let (Some((center, distance)), Some((new_center, new_distance))) = (pinch, self.pinch()) {
+                    return Some(Gesture::Pinch {
+                        center: new_center,
+                        translation: Point {
+                            x: new_center.x - center.x,
+                            y: new_center.y - center.y,
+                        },
+                        scale: if distance > 0.0 { new_distance / distance } else { 1.0 },
+                    });
+                }
+                if self.touches.len() != 1 {
+                    return None;
+                }
+                if let Some((start, _)) = self.tap {
+                    if (position.x - start.x).hypot(position.y - start.y) <= self.tap_slop {
+                        return None;
+                    }
+                    // the drag starts where the finger touched, so that no movement is lost
+                    self.tap = None;
+                    self.dragging = true;
+                    return Some(drag(position, start));
+                }
+                self.dragging = true;
+                Some(drag(position, previous))
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let count = self.touches.len();
+                self.touches.retain(|touch| touch.id != id);
+                if self.touches.len() == count {
+                    return None;
+                }
+                if self.touches.len() == 1 {
+                    // the remaining finger of a pinch continues as a drag
+                    self.dragging = true;
+                    return None;
+                }
+                let tap = self.tap.take();
+                let dragging = std::mem::replace(&mut self.dragging, false);
+                match tap {
+                    Some((_, start)) if phase == TouchPhase::Ended && start.elapsed() <= self.tap_timeout => {
+                        Some(Gesture::Tap { position })
+                    }
+                    _ if dragging && self.touches.is_empty() => Some(Gesture::DragEnded { position }),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// The midpoint and distance of the first two fingers, if there are two.
+    fn pinch(&self) -> Option<(Point, f64)> {
+        let [a, b, ..] = self.touches.as_slice() else {
+            return None;
+        };
+        let (a, b) = (a.position, b.position);
+        let center = Point {
+            x: (a.x + b.x) / 2.0,
+            y: (a.y + b.y) / 2.0,
+        };
+        Some((center, (b.x - a.x).hypot(b.y - a.y)))
+    }
+}
+
+fn drag(position: Point, previous: Point) -> Gesture {
+    Gesture::Drag {
+        position,
+        delta: Point {
+            x: position.x - previous.x,
+            y: position.y - previous.y,
+        },
+    }
+}
+
+impl<Backend: RenderBackend> Scene<Backend> {
+    /// Pan and zoom the camera with a gesture, so that the content follows the fingers: drags
+    /// and the movement of pinches pan, and pinches zoom around their center. Taps are ignored.
+    pub fn apply_gesture(&mut self, gesture: &Gesture) {
+        let (position, translation, scale) = match *gesture {
+            Gesture::Drag { position, delta } => (position, delta, 1.0),
+            Gesture::Pinch {
+                center,
+                translation,
+                scale,
+            } => (center, translation, scale),
+            Gesture::Tap { .. } | Gesture::DragEnded { .. } => return,
+        };
+
+        // move the camera against the movement of the fingers, in scene units
+        let previous = Point {
+            x: position.x - translation.x,
+            y: position.y - translation.y,
+        };
+        let (from, to) = (self.screen_to_world(previous), self.screen_to_world(position));
+        self.camera.pan(from.x - to.x, from.y - to.y);
+
+        if scale > 0.0 && scale != 1.0 {
+            let anchor = self.screen_to_world(position);
+            self.camera.zoom_at(scale, anchor);
+        }
+    }
+}
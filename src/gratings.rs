@@ -0,0 +1,500 @@
+// sine gratings, plaids and Gabor patches
+use std::f64::consts::TAU;
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::brushes::{Extend, Gradient, GradientKind};
+use super::colors::RGBA;
+use super::geoms::Geom;
+use super::scenes::Scene;
+use super::shapes::{Circle, Point, Rectangle};
+use super::styles::{CompositeMode, MixMode};
+use super::Drawable;
+
+/// The maximum number of waves of a [`GpuGrating`].
+pub const MAX_GRATING_WAVES: usize = 4;
+
+/// A sine wave that modulates the color of a grating.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GratingWave {
+    /// The spatial frequency, in cycles per scene unit.
+    pub spatial_frequency: f64,
+    /// The direction in which the wave varies, in radians (0 gives vertical stripes).
+    pub orientation: f64,
+    /// The phase, in radians.
+    pub phase: f64,
+    /// The Michelson contrast of the wave (0 to 1).
+    pub contrast: f64,
+}
+
+impl GratingWave {
+    /// Create a full-contrast wave with zero phase.
+    pub fn new(spatial_frequency: f64, orientation: f64) -> Self {
+        Self {
+            spatial_frequency,
+            orientation,
+            phase: 0.0,
+            contrast: 1.0,
+        }
+    }
+
+    /// Set the phase.
+    pub fn with_phase(mut self, phase: f64) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Set the contrast.
+    pub fn with_contrast(mut self, contrast: f64) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// The modulation of the wave at a point (in local coordinates), between `-contrast` and
+    /// `contrast`.
+    pub fn value_at(&self, point: Point) -> f64 {
+        let (sin, cos) = self.orientation.sin_cos();
+        let position = point.x * cos + point.y * sin;
+        self.contrast * (TAU * self.spatial_frequency * position + self.phase).sin()
+    }
+}
+
+/// A drifting sine grating or plaid that is generated directly into a texture by a compute pass
+/// every frame, instead of being encoded as a gradient. Only supported by the vello backend.
+///
+/// The grating fills the rectangle from the origin of `transform` to `(width, height)`. Its
+/// color is `mean * (1 + sum of the waves)`, clamped to the displayable range.
+#[derive(Debug, Clone)]
+pub struct GpuGrating {
+    /// The waves that are summed, at most [`MAX_GRATING_WAVES`] (two for a plaid).
+    pub waves: Vec<GratingWave>,
+    /// The mean color.
+    pub mean: RGBA,
+    pub width: f64,
+    pub height: f64,
+    pub transform: Affine,
+    /// Whether the grating is drawn.
+    pub visible: bool,
+}
+
+impl GpuGrating {
+    /// Create a grating of a single wave.
+    pub fn new(wave: GratingWave, mean: RGBA, width: f64, height: f64) -> Self {
+        Self {
+            waves: vec![wave],
+            mean,
+            width,
+            height,
+            transform: Affine::identity(),
+            visible: true,
+        }
+    }
+
+    /// Create a plaid of two waves.
+    pub fn plaid(first: GratingWave, second: GratingWave, mean: RGBA, width: f64, height: f64) -> Self {
+        Self {
+            waves: vec![first, second],
+            ..Self::new(first, mean, width, height)
+        }
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Advance the phase of all waves, e.g. by `TAU * temporal_frequency * frame_duration` to
+    /// let the grating drift.
+    pub fn advance_phase(&mut self, delta: f64) {
+        for wave in &mut self.waves {
+            wave.phase = (wave.phase + delta) % TAU;
+        }
+    }
+}
+
+/// The number of gradient stops per period of a [`Grating`].
+const GRATING_STOPS: usize = 128;
+
+/// The radius of a [`Gabor`] patch in standard deviations of its envelope, beyond which the
+/// envelope is below an 8-bit step.
+const GABOR_EXTENT: f64 = 4.0;
+
+/// The gradient of one period of a wave at zero phase, `mean * (1 + sin)` at the contrast of the
+/// wave. The phase is applied with [`phase_offset`], so that the gradient (and its color ramp)
+/// stays the same while the wave drifts.
+fn wave_gradient(wave: &GratingWave, mean: RGBA) -> Gradient {
+    let (sin, cos) = wave.orientation.sin_cos();
+    let period = 1.0 / wave.spatial_frequency;
+    let kind = GradientKind::Linear {
+        start: Point { x: 0.0, y: 0.0 },
+        end: Point {
+            x: cos * period,
+            y: sin * period,
+        },
+    };
+    Gradient::from_fn(Extend::Repeat, kind, GRATING_STOPS, |t| {
+        let value = 1.0 + (TAU as f32 * t).sin();
+        RGBA::new(mean.r * value, mean.g * value, mean.b * value, mean.a)
+    })
+    .with_contrast(wave.contrast as f32)
+}
+
+/// The brush transform that moves a [`wave_gradient`] to the phase of the wave.
+fn phase_offset(wave: &GratingWave) -> Affine {
+    let (sin, cos) = wave.orientation.sin_cos();
+    // the wave at a point is the zero-phase wave `phase / TAU` periods further along its direction
+    let distance = wave.phase / TAU / wave.spatial_frequency;
+    Affine::translate(-cos * distance, -sin * distance)
+}
+
+/// The gradient of a wave, kept while the wave (apart from its phase) and the mean color stay the
+/// same.
+#[derive(Debug, Clone, Default)]
+struct GradientCache(Option<(GratingWave, [f32; 4], Gradient)>);
+
+impl GradientCache {
+    fn get(&mut self, wave: &GratingWave, mean: RGBA) -> Gradient {
+        let wave = wave.with_phase(0.0);
+        let key = [mean.r, mean.g, mean.b, mean.a];
+        match &self.0 {
+            Some((cached_wave, cached_mean, gradient)) if *cached_wave == wave && *cached_mean == key => {
+                gradient.clone()
+            }
+            _ => {
+                let gradient = wave_gradient(&wave, mean);
+                self.0 = Some((wave, key, gradient.clone()));
+                gradient
+            }
+        }
+    }
+}
+
+/// A sine grating drawn with a repeating gradient, e.g. for drifting grating stimuli. Works with
+/// every backend (see [`GpuGrating`] for plaids generated on the GPU).
+///
+/// The grating fills the rectangle from the origin of `transform` to `(width, height)`. Its
+/// color is `mean * (1 + wave)`, clamped to the displayable range.
+#[derive(Debug, Clone)]
+pub struct Grating {
+    pub wave: GratingWave,
+    /// The mean color.
+    pub mean: RGBA,
+    pub width: f64,
+    pub height: f64,
+    pub transform: Affine,
+    /// Whether the grating is drawn.
+    pub visible: bool,
+    gradient: GradientCache,
+}
+
+impl Grating {
+    pub fn new(wave: GratingWave, mean: RGBA, width: f64, height: f64) -> Self {
+        Self {
+            wave,
+            mean,
+            width,
+            height,
+            transform: Affine::identity(),
+            visible: true,
+            gradient: GradientCache::default(),
+        }
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Set the phase of the wave, in radians. The phase only moves the gradient, which is kept
+    /// between frames, so this is cheap enough to animate every frame.
+    pub fn set_phase(&mut self, phase: f64) {
+        self.wave.phase = phase % TAU;
+    }
+
+    /// Advance the phase, e.g. by `TAU * temporal_frequency * frame_duration` to let the grating
+    /// drift.
+    pub fn advance_phase(&mut self, delta: f64) {
+        self.set_phase(self.wave.phase + delta);
+    }
+
+    fn rect(&self) -> Rectangle {
+        Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: self.width,
+                y: self.height,
+            },
+        }
+    }
+
+    fn geom(&mut self) -> Geom<Rectangle> {
+        self.geom_with(self.wave.contrast, self.mean)
+    }
+
+    /// The grating with another contrast and mean color.
+    fn geom_with(&mut self, contrast: f64, mean: RGBA) -> Geom<Rectangle> {
+        let wave = self.wave.with_contrast(contrast);
+        let mut geom = Geom::fill(self.rect(), self.gradient.get(&wave, mean)).with_transform(self.transform);
+        geom.brush_transform = Some(phase_offset(&wave));
+        geom
+    }
+
+    /// The extents of the grating in the coordinates it is drawn in.
+    fn bounding_box(&self) -> Rectangle {
+        Geom::fill(self.rect(), self.mean)
+            .with_transform(self.transform)
+            .bounding_box()
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for Grating {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if self.visible {
+            self.geom().draw(scene);
+        }
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        Some(self.bounding_box())
+    }
+}
+
+/// How the two gratings of a [`Plaid`] are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaidBlend {
+    /// The modulations add up, `mean * (1 + c1 * s1 + c2 * s2)`, like the plaids of
+    /// [`GpuGrating`]. Drawn with additive (`Lighter`) compositing.
+    #[default]
+    Additive,
+    /// The modulations multiply, `mean * (1 + c1 * s1) * (1 + c2 * s2)`. Drawn with the
+    /// `Multiply` mix mode.
+    Multiplicative,
+}
+
+/// A plaid of two superimposed [`Grating`]s, composited with layers so that it works with every
+/// backend.
+///
+/// The plaid fills the rectangle of the first grating, with the mean color of the first grating;
+/// the second grating is clipped to it. The contrast of each grating is scaled by its weight,
+/// giving the contrasts `c1` and `c2` of [`PlaidBlend`]. The result is exact as long as it stays
+/// in the displayable range: `c1 + c2 <= 1` for additive plaids and
+/// `mean * (1 + c1) * (1 + c2) <= 1` for multiplicative ones.
+#[derive(Debug, Clone)]
+pub struct Plaid {
+    pub first: Grating,
+    pub second: Grating,
+    /// The weights the contrasts of the gratings are multiplied with.
+    pub weights: [f64; 2],
+    pub blend: PlaidBlend,
+    /// A transform applied after the transforms of the gratings.
+    pub transform: Affine,
+    /// Whether the plaid is drawn.
+    pub visible: bool,
+}
+
+impl Plaid {
+    /// Create an additive plaid with both weights at 0.5, so that two full-contrast gratings
+    /// stay in range.
+    pub fn new(first: Grating, second: Grating) -> Self {
+        Self {
+            first,
+            second,
+            weights: [0.5, 0.5],
+            blend: PlaidBlend::Additive,
+            transform: Affine::identity(),
+            visible: true,
+        }
+    }
+
+    /// Set the contrast weights of the gratings.
+    pub fn with_weights(mut self, first: f64, second: f64) -> Self {
+        self.weights = [first, second];
+        self
+    }
+
+    /// Set how the gratings are combined.
+    pub fn with_blend(mut self, blend: PlaidBlend) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Advance the phases of both gratings, e.g. by `TAU * temporal_frequency * frame_duration`.
+    pub fn advance_phase(&mut self, delta: f64) {
+        self.first.advance_phase(delta);
+        self.second.advance_phase(delta);
+    }
+
+    /// The effective contrasts of the gratings.
+    fn contrasts(&self) -> (f64, f64) {
+        (
+            self.weights[0] * self.first.wave.contrast,
+            self.weights[1] * self.second.wave.contrast,
+        )
+    }
+}
+
+/// Scale the color channels (but not the alpha) of a color.
+fn scale_color(color: RGBA, factor: f64) -> RGBA {
+    let factor = factor as f32;
+    RGBA::new(color.r * factor, color.g * factor, color.b * factor, color.a)
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for Plaid {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+        let (c1, c2) = self.contrasts();
+        let mean = self.first.mean;
+
+        // the first grating is drawn opaque, the second into a layer clipped to the first that is
+        // composited onto it
+        let (first, second, mix_mode, composite_mode) = match self.blend {
+            PlaidBlend::Additive => {
+                // split the mean between the gratings so that neither goes below zero: both get
+                // the summed contrast around their share of the mean
+                let total = c1 + c2;
+                let share = if total > 0.0 { c1 / total } else { 1.0 };
+                (
+                    self.first.geom_with(total, scale_color(mean, share)),
+                    self.second.geom_with(total, scale_color(mean, 1.0 - share)),
+                    MixMode::Normal,
+                    CompositeMode::Lighter,
+                )
+            }
+            PlaidBlend::Multiplicative => {
+                // the second grating peaks at white around a gray of 1 / (1 + c2), which the mean
+                // of the first compensates for
+                let gray = 1.0 / (1.0 + c2);
+                (
+                    self.first.geom_with(c1, scale_color(mean, 1.0 + c2)),
+                    self.second
+                        .geom_with(c2, RGBA::new(gray as f32, gray as f32, gray as f32, 1.0)),
+                    MixMode::Multiply,
+                    CompositeMode::SourceOver,
+                )
+            }
+        };
+
+        let clip_transform = self.first.transform * self.transform * scene.view_transform();
+        first.with_transform(self.first.transform * self.transform).draw(scene);
+        scene
+            .backend
+            .push_layer(mix_mode, composite_mode, &self.first.rect(), clip_transform, 1.0);
+        second
+            .with_transform(self.second.transform * self.transform)
+            .draw(scene);
+        scene.backend.pop_layer();
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        Some(
+            Geom::fill(self.first.rect(), self.first.mean)
+                .with_transform(self.first.transform * self.transform)
+                .bounding_box(),
+        )
+    }
+}
+
+/// A Gabor patch: a sine grating under a Gaussian envelope that fades it into the mean color,
+/// drawn with gradients.
+///
+/// The patch is centered on the origin of `transform`, which is also where the phase of the wave
+/// is measured, and covers a circle of four standard deviations (outside of which the envelope
+/// is negligible).
+#[derive(Debug, Clone)]
+pub struct Gabor {
+    pub wave: GratingWave,
+    /// The standard deviation of the envelope, in scene units.
+    pub sigma: f64,
+    /// The mean color, which should match the background.
+    pub mean: RGBA,
+    pub transform: Affine,
+    /// Whether the patch is drawn.
+    pub visible: bool,
+    gradient: GradientCache,
+}
+
+impl Gabor {
+    pub fn new(wave: GratingWave, sigma: f64, mean: RGBA) -> Self {
+        Self {
+            wave,
+            sigma,
+            mean,
+            transform: Affine::identity(),
+            visible: true,
+            gradient: GradientCache::default(),
+        }
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Set the phase of the wave, in radians. The phase only moves the gradient, which is kept
+    /// between frames, so this is cheap enough to animate every frame.
+    pub fn set_phase(&mut self, phase: f64) {
+        self.wave.phase = phase % TAU;
+    }
+
+    /// Advance the phase, e.g. by `TAU * temporal_frequency * frame_duration` to let the patch
+    /// drift.
+    pub fn advance_phase(&mut self, delta: f64) {
+        self.set_phase(self.wave.phase + delta);
+    }
+
+    fn circle(&self) -> Circle {
+        Circle {
+            center: Point { x: 0.0, y: 0.0 },
+            radius: GABOR_EXTENT * self.sigma,
+        }
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for Gabor {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+        let mut grating =
+            Geom::fill(self.circle(), self.gradient.get(&self.wave, self.mean)).with_transform(self.transform);
+        grating.brush_transform = Some(phase_offset(&self.wave));
+        grating.draw(scene);
+
+        // blending the mean color over the grating with the inverted envelope as alpha gives
+        // `mean + envelope * (grating - mean)`
+        let radius = GABOR_EXTENT * self.sigma;
+        let kind = GradientKind::Radial {
+            start_center: Point { x: 0.0, y: 0.0 },
+            start_radius: 0.0,
+            end_center: Point { x: 0.0, y: 0.0 },
+            end_radius: radius as f32,
+        };
+        let envelope = Gradient::from_fn(Extend::Pad, kind, GRATING_STOPS, |t| {
+            let distance = t as f64 * GABOR_EXTENT;
+            let alpha = 1.0 - (-distance * distance / 2.0).exp();
+            self.mean.with_alpha(self.mean.a * alpha as f32)
+        });
+        Geom::fill(self.circle(), envelope)
+            .with_transform(self.transform)
+            .draw(scene);
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        Some(
+            Geom::fill(self.circle(), self.mean)
+                .with_transform(self.transform)
+                .bounding_box(),
+        )
+    }
+}
@@ -0,0 +1,183 @@
+use std::any::Any;
+
+use super::accessibility::AccessTree;
+use super::affine::{Affine, Fit};
+use super::backend::RenderBackend;
+use super::scenes::Scene;
+use super::shapes::{Point, Rectangle};
+use super::user_data::{Hit, UserData};
+use super::Drawable;
+
+/// A container of drawables that are transformed together.
+///
+/// The children are recorded into a separate backend and appended to the scene. If `cache` is
+/// set, the recorded content is kept and re-used on subsequent frames (only the transform is
+/// re-applied) until [`Group::invalidate`] is called, which avoids re-encoding complex static
+/// content every frame.
+pub struct Group<Backend> {
+    /// The children of the group, drawn in order.
+    pub children: Vec<Box<dyn Drawable<Backend>>>,
+    /// The transform applied to all children.
+    pub transform: Affine,
+    /// Keep the recorded content of the children across frames.
+    pub cache: bool,
+    /// Whether the group (and all of its children) is drawn.
+    pub visible: bool,
+    /// Application data returned when a child without user data is hit-tested.
+    pub user_data: Option<UserData>,
+    /// The recorded content and accessibility nodes (if cached).
+    cached: Option<(Backend, AccessTree)>,
+}
+
+impl<Backend> Group<Backend> {
+    /// Create an empty group.
+    pub fn new() -> Self {
+        Self {
+            children: Vec::new(),
+            transform: Affine::identity(),
+            cache: false,
+            visible: true,
+            user_data: None,
+            cached: None,
+        }
+    }
+
+    /// Add a child to the group.
+    pub fn add_child(mut self, child: impl Drawable<Backend> + 'static) -> Self {
+        self.push(child);
+        self
+    }
+
+    /// Add a child to the group.
+    pub fn push(&mut self, child: impl Drawable<Backend> + 'static) {
+        self.children.push(Box::new(child));
+        self.invalidate();
+    }
+
+    /// Set the transform of the group.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Enable or disable caching of the recorded content.
+    pub fn with_cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Attach application data, e.g. the id of the entity the group shows.
+    pub fn with_user_data(mut self, data: impl Any + Send + Sync) -> Self {
+        self.user_data = Some(UserData::new(data));
+        self
+    }
+
+    /// Show or hide the group. Hidden groups keep their cached content.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Set the transform so that the children are centered in `target` (in the coordinates the
+    /// group is drawn in) and scaled according to `fit`. Returns `false` and keeps the transform
+    /// if a child has no [bounds](Drawable::bounds).
+    pub fn fit_into(&mut self, target: Rectangle, fit: Fit) -> bool {
+        match self.content_bounds() {
+            Some(content) => {
+                self.transform = Affine::fit(content, target, fit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The union of the bounds of the children, in group coordinates.
+    fn content_bounds(&self) -> Option<Rectangle> {
+        let mut union: Option<kurbo::Rect> = None;
+        for child in &self.children {
+            let bounds = child.bounds()?;
+            let bounds = kurbo::Rect::new(bounds.a.x, bounds.a.y, bounds.b.x, bounds.b.y);
+            union = Some(union.map_or(bounds, |union| union.union(bounds)));
+        }
+        // an empty group covers nothing, which is placed at the origin
+        let union = union.unwrap_or_default();
+        Some(Rectangle {
+            a: Point {
+                x: union.x0,
+                y: union.y0,
+            },
+            b: Point {
+                x: union.x1,
+                y: union.y1,
+            },
+        })
+    }
+
+    /// Discard the cached content so that the children are recorded again on the next draw.
+    pub fn invalidate(&mut self) {
+        self.cached = None;
+    }
+}
+
+impl<Backend> Default for Group<Backend> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for Group<Backend> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+        let transform = self.transform * scene.view_transform();
+
+        if let (true, Some((cached, accessibility))) = (self.cache, &self.cached) {
+            scene.backend.append(cached, transform);
+            scene.accessibility.append(accessibility, transform);
+            return;
+        }
+
+        // record the children in group coordinates
+        let mut child_scene = scene.child_scene(transform);
+        if self.cache {
+            child_scene.record_for_cache();
+        }
+        for child in &mut self.children {
+            child.draw(&mut child_scene);
+        }
+        scene.backend.append(&child_scene.backend, transform);
+        scene.accessibility.append(&child_scene.accessibility, transform);
+
+        if self.cache {
+            self.cached = Some((child_scene.backend, child_scene.accessibility));
+        }
+    }
+
+    fn hit_test(&self, point: Point) -> Option<Hit<'_>> {
+        if !self.visible || self.transform.determinant() == 0.0 {
+            return None;
+        }
+        // children are drawn in order, so the last one is on top
+        let local = self.transform.inverse().transform_point(point);
+        let hit = self.children.iter().rev().find_map(|child| child.hit_test(local))?;
+        Some(Hit {
+            user_data: hit.user_data.or(self.user_data.as_ref()),
+        })
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        let content = self.content_bounds()?;
+        let content = kurbo::Rect::new(content.a.x, content.a.y, content.b.x, content.b.y);
+        let bounds = kurbo::Affine::from(self.transform).transform_rect_bbox(content);
+        Some(Rectangle {
+            a: Point {
+                x: bounds.x0,
+                y: bounds.y0,
+            },
+            b: Point {
+                x: bounds.x1,
+                y: bounds.y1,
+            },
+        })
+    }
+}
@@ -0,0 +1,326 @@
+// window-system-independent input events
+use super::backend::RenderBackend;
+use super::scenes::Scene;
+use super::shapes::Point;
+use super::user_data::Hit;
+use super::Drawable;
+
+/// A button of a mouse or pen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointerButton {
+    /// The left mouse button (or the tip of a pen).
+    Primary,
+    /// The right mouse button.
+    Secondary,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+/// Whether a button or key was pressed or released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ButtonState {
+    Pressed,
+    Released,
+}
+
+/// The phase of a touch point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    /// The touch was taken over by the system (e.g. by a system gesture).
+    Cancelled,
+}
+
+/// The amount scrolled by a [`InputEvent::Scroll`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollDelta {
+    /// Lines (or rows) to scroll, e.g. from a mouse wheel.
+    Lines { x: f64, y: f64 },
+    /// Device pixels to scroll, e.g. from a touchpad.
+    Pixels { x: f64, y: f64 },
+}
+
+/// The modifier keys held down during a key event.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    /// The Windows, Command or Super key.
+    pub meta: bool,
+}
+
+/// A key that doesn't produce a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NamedKey {
+    Enter,
+    Escape,
+    Space,
+    Tab,
+    Backspace,
+    Delete,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    ArrowDown,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Shift,
+    Control,
+    Alt,
+    Meta,
+    /// A function key, `F(1)` to `F(12)`.
+    F(u8),
+}
+
+/// The meaning of a key, taking the keyboard layout into account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// A key that produces text (with the modifiers applied, e.g. `"A"` with shift).
+    Character(String),
+    Named(NamedKey),
+    /// A key without a meaning known to the crate.
+    Unidentified,
+}
+
+/// An input event, independent of the window system it came from.
+///
+/// Positions are in device pixels relative to the top left corner of the surface, like the
+/// scene's device space (see [`InputEvent::to_scene`] and [`hit_test`]). Events can be
+/// translated from winit with `WinitInput` (with the `winit` feature), or be created by hand,
+/// e.g. from SDL or in tests.
+#[derive(Debug, Clone)]
+pub enum InputEvent {
+    /// The pointer moved.
+    PointerMoved { position: Point },
+    /// A button was pressed or released at a position.
+    PointerButton {
+        button: PointerButton,
+        state: ButtonState,
+        position: Point,
+    },
+    /// The pointer left the surface.
+    PointerLeft,
+    /// The pointer scrolled at a position.
+    Scroll { delta: ScrollDelta, position: Point },
+    /// A key was pressed or released. `repeat` is set for presses generated by holding a key.
+    Key {
+        key: Key,
+        state: ButtonState,
+        repeat: bool,
+        modifiers: Modifiers,
+    },
+    /// A touch point started, moved or ended. The id identifies the finger until it ends.
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        position: Point,
+    },
+}
+
+impl InputEvent {
+    /// The position of the event, if it has one.
+    pub fn position(&self) -> Option<Point> {
+        match self {
+            InputEvent::PointerMoved { position }
+            | InputEvent::PointerButton { position, .. }
+            | InputEvent::Scroll { position, .. }
+            | InputEvent::Touch { position, .. } => Some(*position),
+            InputEvent::PointerLeft | InputEvent::Key { .. } => None,
+        }
+    }
+
+    /// The event with its position converted from device pixels to scene coordinates (see
+    /// [`Scene::screen_to_world`]).
+    pub fn to_scene<Backend: RenderBackend>(&self, scene: &Scene<Backend>) -> Self {
+        let mut event = self.clone();
+        match &mut event {
+            InputEvent::PointerMoved { position }
+            | InputEvent::PointerButton { position, .. }
+            | InputEvent::Scroll { position, .. }
+            | InputEvent::Touch { position, .. } => *position = scene.screen_to_world(*position),
+            InputEvent::PointerLeft | InputEvent::Key { .. } => {}
+        }
+        event
+    }
+}
+
+/// Find the part of a drawable (drawn directly into the scene) under the position of an event.
+/// Events without a position hit nothing.
+pub fn hit_test<'a, Backend: RenderBackend>(
+    scene: &Scene<Backend>,
+    drawable: &'a impl Drawable<Backend>,
+    event: &InputEvent,
+) -> Option<Hit<'a>> {
+    let position = event.position()?;
+    drawable.hit_test(scene.screen_to_world(position))
+}
+
+#[cfg(feature = "winit")]
+pub use winit_input::WinitInput;
+
+#[cfg(feature = "winit")]
+mod winit_input {
+    use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+    use winit::keyboard;
+
+    use super::*;
+
+    /// Translates winit window events into [`InputEvent`]s, keeping track of the state winit
+    /// only reports separately (the pointer position and the modifiers).
+    #[derive(Debug, Clone)]
+    pub struct WinitInput {
+        position: Point,
+        modifiers: Modifiers,
+    }
+
+    impl Default for WinitInput {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl WinitInput {
+        pub fn new() -> Self {
+            Self {
+                position: Point { x: 0.0, y: 0.0 },
+                modifiers: Modifiers::default(),
+            }
+        }
+
+        /// The last known pointer position, in device pixels.
+        pub fn position(&self) -> Point {
+            self.position
+        }
+
+        /// The modifier keys currently held down.
+        pub fn modifiers(&self) -> Modifiers {
+            self.modifiers
+        }
+
+        /// Translate a window event. Events that aren't input (and modifier changes, which are
+        /// attached to the following key events) return `None`.
+        pub fn translate(&mut self, event: &WindowEvent) -> Option<InputEvent> {
+            let state = |state: &ElementState| match state {
+                ElementState::Pressed => ButtonState::Pressed,
+                ElementState::Released => ButtonState::Released,
+            };
+            Some(match event {
+                WindowEvent::CursorMoved { position, .. } => {
+                    self.position = Point {
+                        x: position.x,
+                        y: position.y,
+                    };
+                    InputEvent::PointerMoved {
+                        position: self.position,
+                    }
+                }
+                WindowEvent::CursorLeft { .. } => InputEvent::PointerLeft,
+                WindowEvent::MouseInput {
+                    state: button_state,
+                    button,
+                    ..
+                } => InputEvent::PointerButton {
+                    button: match button {
+                        MouseButton::Left => PointerButton::Primary,
+                        MouseButton::Right => PointerButton::Secondary,
+                        MouseButton::Middle => PointerButton::Middle,
+                        MouseButton::Back => PointerButton::Back,
+                        MouseButton::Forward => PointerButton::Forward,
+                        MouseButton::Other(other) => PointerButton::Other(*other),
+                    },
+                    state: state(button_state),
+                    position: self.position,
+                },
+                WindowEvent::MouseWheel { delta, .. } => InputEvent::Scroll {
+                    delta: match delta {
+                        MouseScrollDelta::LineDelta(x, y) => ScrollDelta::Lines {
+                            x: *x as f64,
+                            y: *y as f64,
+                        },
+                        MouseScrollDelta::PixelDelta(delta) => ScrollDelta::Pixels { x: delta.x, y: delta.y },
+                    },
+                    position: self.position,
+                },
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    let modifiers = modifiers.state();
+                    self.modifiers = Modifiers {
+                        shift: modifiers.shift_key(),
+                        control: modifiers.control_key(),
+                        alt: modifiers.alt_key(),
+                        meta: modifiers.super_key(),
+                    };
+                    return None;
+                }
+                WindowEvent::KeyboardInput { event, .. } => InputEvent::Key {
+                    key: translate_key(&event.logical_key),
+                    state: state(&event.state),
+                    repeat: event.repeat,
+                    modifiers: self.modifiers,
+                },
+                WindowEvent::Touch(touch) => InputEvent::Touch {
+                    id: touch.id,
+                    phase: match touch.phase {
+                        winit::event::TouchPhase::Started => TouchPhase::Started,
+                        winit::event::TouchPhase::Moved => TouchPhase::Moved,
+                        winit::event::TouchPhase::Ended => TouchPhase::Ended,
+                        winit::event::TouchPhase::Cancelled => TouchPhase::Cancelled,
+                    },
+                    position: Point {
+                        x: touch.location.x,
+                        y: touch.location.y,
+                    },
+                },
+                _ => return None,
+            })
+        }
+    }
+
+    fn translate_key(key: &keyboard::Key) -> Key {
+        use keyboard::NamedKey as Winit;
+        let named = match key {
+            keyboard::Key::Character(text) => return Key::Character(text.to_string()),
+            keyboard::Key::Named(named) => named,
+            _ => return Key::Unidentified,
+        };
+        Key::Named(match named {
+            Winit::Enter => NamedKey::Enter,
+            Winit::Escape => NamedKey::Escape,
+            Winit::Space => NamedKey::Space,
+            Winit::Tab => NamedKey::Tab,
+            Winit::Backspace => NamedKey::Backspace,
+            Winit::Delete => NamedKey::Delete,
+            Winit::ArrowLeft => NamedKey::ArrowLeft,
+            Winit::ArrowRight => NamedKey::ArrowRight,
+            Winit::ArrowUp => NamedKey::ArrowUp,
+            Winit::ArrowDown => NamedKey::ArrowDown,
+            Winit::Home => NamedKey::Home,
+            Winit::End => NamedKey::End,
+            Winit::PageUp => NamedKey::PageUp,
+            Winit::PageDown => NamedKey::PageDown,
+            Winit::Shift => NamedKey::Shift,
+            Winit::Control => NamedKey::Control,
+            Winit::Alt => NamedKey::Alt,
+            Winit::Super | Winit::Meta => NamedKey::Meta,
+            Winit::F1 => NamedKey::F(1),
+            Winit::F2 => NamedKey::F(2),
+            Winit::F3 => NamedKey::F(3),
+            Winit::F4 => NamedKey::F(4),
+            Winit::F5 => NamedKey::F(5),
+            Winit::F6 => NamedKey::F(6),
+            Winit::F7 => NamedKey::F(7),
+            Winit::F8 => NamedKey::F(8),
+            Winit::F9 => NamedKey::F(9),
+            Winit::F10 => NamedKey::F(10),
+            Winit::F11 => NamedKey::F(11),
+            Winit::F12 => NamedKey::F(12),
+            _ => return Key::Unidentified,
+        })
+    }
+}
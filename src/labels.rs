@@ -0,0 +1,116 @@
+// label placement without overlaps
+use super::shapes::{Point, Rectangle};
+
+/// The bounding box of a label and how important it is to show.
+#[derive(Debug, Clone)]
+pub struct LabelBox {
+    /// The extents of the label (in scene coordinates).
+    pub bounds: Rectangle,
+    /// Labels with a higher priority are placed first and never moved for lower ones.
+    pub priority: i32,
+}
+
+/// What happens to a label that overlaps a label with a higher priority.
+#[derive(Debug, Clone, Copy)]
+pub enum DeclutterMode {
+    /// Hide the label.
+    Hide,
+    /// Move the label to the nearest free position within `max_distance`, trying positions in
+    /// steps of `step` in eight directions, and hide it if there is none.
+    Nudge { max_distance: f64, step: f64 },
+}
+
+/// Options for [`declutter`].
+#[derive(Debug, Clone, Copy)]
+pub struct DeclutterOptions {
+    pub mode: DeclutterMode,
+    /// The minimum space between labels.
+    pub padding: f64,
+}
+
+impl Default for DeclutterOptions {
+    fn default() -> Self {
+        Self {
+            mode: DeclutterMode::Hide,
+            padding: 0.0,
+        }
+    }
+}
+
+impl DeclutterOptions {
+    /// Move overlapping labels by up to `max_distance` instead of hiding them.
+    pub fn with_nudging(mut self, max_distance: f64, step: f64) -> Self {
+        self.mode = DeclutterMode::Nudge { max_distance, step };
+        self
+    }
+
+    /// Set the minimum space between labels.
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+/// Place labels so that they don't overlap, e.g. for dense automatically generated plots.
+///
+/// Labels are placed greedily from the highest to the lowest priority (and in order for equal
+/// priorities). Returns for each label the offset it has to be moved by, or `None` if it should
+/// be hidden.
+pub fn declutter(labels: &[LabelBox], options: &DeclutterOptions) -> Vec<Option<Point>> {
+    let mut order = (0..labels.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| std::cmp::Reverse(labels[i].priority));
+
+    let candidates = match options.mode {
+        DeclutterMode::Hide => vec![(0.0, 0.0)],
+        DeclutterMode::Nudge { max_distance, step } => nudge_offsets(max_distance, step),
+    };
+
+    let mut placed: Vec<[f64; 4]> = Vec::new();
+    let mut offsets = vec![None; labels.len()];
+    for i in order {
+        let bounds = &labels[i].bounds;
+        let (x0, x1) = (bounds.a.x.min(bounds.b.x), bounds.a.x.max(bounds.b.x));
+        let (y0, y1) = (bounds.a.y.min(bounds.b.y), bounds.a.y.max(bounds.b.y));
+        let free = candidates.iter().copied().find(|(dx, dy)| {
+            let rect = [x0 + dx, y0 + dy, x1 + dx, y1 + dy];
+            !placed.iter().any(|other| {
+                rect[0] < other[2] + options.padding
+                    && other[0] < rect[2] + options.padding
+                    && rect[1] < other[3] + options.padding
+                    && other[1] < rect[3] + options.padding
+            })
+        });
+        if let Some((dx, dy)) = free {
+            placed.push([x0 + dx, y0 + dy, x1 + dx, y1 + dy]);
+            offsets[i] = Some(Point { x: dx, y: dy });
+        }
+    }
+    offsets
+}
+
+/// The offsets tried when nudging a label, nearest first.
+fn nudge_offsets(max_distance: f64, step: f64) -> Vec<(f64, f64)> {
+    let mut offsets = vec![(0.0, 0.0)];
+    if step <= 0.0 {
+        return offsets;
+    }
+    let directions = [
+        (0.0, -1.0),
+        (0.0, 1.0),
+        (-1.0, 0.0),
+        (1.0, 0.0),
+        (-1.0, -1.0),
+        (1.0, -1.0),
+        (-1.0, 1.0),
+        (1.0, 1.0),
+    ];
+    let mut distance = step;
+    while distance <= max_distance {
+        for (dx, dy) in directions {
+            let length = f64::hypot(dx, dy);
+            offsets.push((dx / length * distance, dy / length * distance));
+        }
+        distance += step;
+    }
+    offsets
+}
@@ -0,0 +1,230 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::scenes::Scene;
+use super::shapes::{Path, Point, Rectangle, Shape};
+use super::styles::{CompositeMode, LayerEffect, MixMode};
+use super::Drawable;
+
+/// A container of drawables that are composited together into the scene, e.g. to blend a group
+/// with a mix mode, fade it with a common alpha or clip it to a shape.
+///
+/// Unlike [`Group`](crate::group::Group), the children are drawn into an isolated layer, so
+/// overlapping children are blended with each other first and the result is blended with the
+/// scene as a whole.
+pub struct Layer<Backend> {
+    /// The children of the layer, drawn in order.
+    pub children: Vec<Box<dyn Drawable<Backend>>>,
+    /// How the colors of the layer are mixed with the scene.
+    pub mix_mode: MixMode,
+    /// How the layer is composited with the scene.
+    pub composite_mode: CompositeMode,
+    /// The shape the layer is clipped to (in scene coordinates), or `None` for the whole scene.
+    pub clip: Option<Path>,
+    /// The transform of the clip shape.
+    pub clip_transform: Affine,
+    /// The opacity of the layer.
+    pub alpha: f32,
+    /// The transform applied to all children.
+    pub transform: Affine,
+    /// Effects applied to the children as a whole, in order, before the layer is composited.
+    pub effects: Vec<LayerEffect>,
+    /// Whether the layer (and all of its children) is drawn.
+    pub visible: bool,
+}
+
+impl<Backend> Layer<Backend> {
+    /// Create an empty, unclipped and opaque layer with normal blending.
+    pub fn new() -> Self {
+        Self {
+            children: Vec::new(),
+            mix_mode: MixMode::Normal,
+            composite_mode: CompositeMode::SourceOver,
+            clip: None,
+            clip_transform: Affine::identity(),
+            alpha: 1.0,
+            transform: Affine::identity(),
+            effects: Vec::new(),
+            visible: true,
+        }
+    }
+
+    /// Add a child to the layer.
+    pub fn add_child(mut self, child: impl Drawable<Backend> + 'static) -> Self {
+        self.push(child);
+        self
+    }
+
+    /// Add a child to the layer.
+    pub fn push(&mut self, child: impl Drawable<Backend> + 'static) {
+        self.children.push(Box::new(child));
+    }
+
+    /// Set the mix and composite modes.
+    pub fn with_blend(mut self, mix_mode: MixMode, composite_mode: CompositeMode) -> Self {
+        self.mix_mode = mix_mode;
+        self.composite_mode = composite_mode;
+        self
+    }
+
+    /// Clip the layer to a shape.
+    pub fn with_clip(mut self, clip: &impl Shape, transform: Affine) -> Self {
+        self.clip = Some(clip.to_path().into());
+        self.clip_transform = transform;
+        self
+    }
+
+    /// Set the opacity.
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Set the transform of the children.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Add an effect, e.g. a blur or a drop shadow.
+    pub fn with_effect(mut self, effect: LayerEffect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+}
+
+impl<Backend> Default for Layer<Backend> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for Layer<Backend> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+
+        // record the children in layer coordinates
+        let transform = self.transform * scene.view_transform();
+        let mut child_scene = scene.child_scene(transform);
+        for child in &mut self.children {
+            child.draw(&mut child_scene);
+        }
+
+        match &self.clip {
+            Some(clip) => {
+                let clip_transform = self.clip_transform * scene.view_transform();
+                scene
+                    .backend
+                    .push_layer(self.mix_mode, self.composite_mode, clip, clip_transform, self.alpha);
+            }
+            None => {
+                // clip to the whole scene, in device pixels
+                let bounds = Rectangle {
+                    a: Point { x: 0.0, y: 0.0 },
+                    b: Point {
+                        x: scene.width as f64,
+                        y: scene.height as f64,
+                    },
+                };
+                scene.backend.push_layer(
+                    self.mix_mode,
+                    self.composite_mode,
+                    &bounds,
+                    Affine::identity(),
+                    self.alpha,
+                );
+            }
+        }
+
+        if self.effects.is_empty() {
+            scene.backend.append(&child_scene.backend, transform);
+        } else {
+            let (width, height) = (scene.width, scene.height);
+            scene
+                .backend
+                .append_with_effects(&child_scene.backend, transform, &self.effects, width, height);
+        }
+        scene.backend.pop_layer();
+        scene.accessibility.append(&child_scene.accessibility, transform);
+    }
+}
+
+/// A container of drawables that leaves a fading trail: every frame, the content is drawn over
+/// the previous frame of the layer, faded by `decay`, e.g. for motion trails or phosphor-like
+/// persistence.
+///
+/// The trail is kept by the renderer, so it only works when the layer is drawn every frame: it
+/// is released after a frame that doesn't draw the layer.
+/// `decay` is applied once per rendered frame, so trails are shorter at higher refresh rates.
+/// At 8 bits per channel, very faint trails may not fade out completely.
+pub struct TrailLayer<Backend> {
+    /// The children of the layer, drawn in order.
+    pub children: Vec<Box<dyn Drawable<Backend>>>,
+    /// The opacity of the previous frame under the new one (0 for no trail, 1 to keep
+    /// everything).
+    pub decay: f32,
+    /// The transform applied to all children.
+    pub transform: Affine,
+    /// Whether the layer (and all of its children) is drawn.
+    pub visible: bool,
+    /// Identifies the previous frame of the layer in the renderer.
+    id: u64,
+}
+
+impl<Backend> TrailLayer<Backend> {
+    /// Create an empty trail layer.
+    pub fn new(decay: f32) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            children: Vec::new(),
+            decay,
+            transform: Affine::identity(),
+            visible: true,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Add a child to the layer.
+    pub fn add_child(mut self, child: impl Drawable<Backend> + 'static) -> Self {
+        self.push(child);
+        self
+    }
+
+    /// Add a child to the layer.
+    pub fn push(&mut self, child: impl Drawable<Backend> + 'static) {
+        self.children.push(Box::new(child));
+    }
+
+    /// Set the transform of the children.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for TrailLayer<Backend> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+
+        let transform = self.transform * scene.view_transform();
+        let mut child_scene = scene.child_scene(transform);
+        for child in &mut self.children {
+            child.draw(&mut child_scene);
+        }
+        let (width, height) = (scene.width, scene.height);
+        scene.backend.append_with_trail(
+            &child_scene.backend,
+            transform,
+            self.id,
+            self.decay.clamp(0.0, 1.0),
+            width,
+            height,
+        );
+        scene.accessibility.append(&child_scene.accessibility, transform);
+    }
+}
@@ -1,32 +1,103 @@
+pub mod accessibility;
 pub mod affine;
+pub mod atlas;
+pub mod backend;
 pub mod brushes;
+pub mod calibration;
+pub mod camera;
+pub mod checkerboard;
 pub mod colors;
+pub mod dots;
+pub mod error;
+pub mod export;
+pub mod fonts;
+pub mod gaze;
+pub mod gratings;
 pub mod geoms;
+pub mod gestures;
+pub mod group;
+pub mod input;
+pub mod labels;
+pub mod layer;
+pub mod locale;
+pub mod luminance;
+pub mod masks;
+pub mod noise;
 pub mod scenes;
+pub mod scheduler;
 pub mod shapes;
+pub mod snapshot;
+pub mod sprites;
+pub mod spatial;
 pub mod styles;
+pub mod table;
 pub mod text;
+pub mod user_data;
+pub mod viewport;
+#[cfg(feature = "vello-backend")]
 pub mod vello_backend;
+#[cfg(feature = "vello-backend")]
 pub mod prerenderd_scene;
+#[cfg(feature = "cpu-backend")]
+pub mod cpu_backend;
 pub mod effects;
+pub mod quality;
+pub mod random;
+pub mod recording;
+pub mod resources;
+pub mod roi;
+pub mod vector_asset;
+#[cfg(feature = "remote-preview")]
+pub mod remote_preview;
 
 // re-export the image crate
 pub use image;
 
+#[cfg(feature = "vello-backend")]
 pub type VelloScene = scenes::Scene<vello_backend::VelloBackend>;
 
+#[cfg(feature = "cpu-backend")]
+pub type CpuScene = scenes::Scene<cpu_backend::CpuBackend>;
+
 pub mod prelude {
     pub use super::affine::*;
+    pub use super::backend::*;
     pub use super::brushes::*;
+    pub use super::camera::*;
     pub use super::colors::*;
+    pub use super::error::RendererError;
     pub use super::geoms::*;
+    pub use super::group::*;
+    pub use super::layer::*;
+    pub use super::masks::*;
     pub use super::scenes::*;
     pub use super::shapes::*;
+    pub use super::spatial::*;
     pub use super::styles::*;
     pub use super::text::*;
+    pub use super::user_data::*;
+    pub use super::viewport::*;
+    #[cfg(feature = "vello-backend")]
     pub use super::VelloScene;
 }
 
 pub trait Drawable<Backend> {
     fn draw(&mut self, scene: &mut scenes::Scene<Backend>);
+
+    /// Find the topmost part of the drawable at a point, in the coordinates the drawable is
+    /// drawn in (scene coordinates for drawables drawn directly into a scene).
+    ///
+    /// Drawables that don't support hit-testing are never hit.
+    fn hit_test(&self, point: shapes::Point) -> Option<user_data::Hit<'_>> {
+        let _ = point;
+        None
+    }
+
+    /// The axis-aligned bounds of the drawable in the coordinates it is drawn in, used to skip it
+    /// when it is outside of a region (see [`spatial::IndexedGroup`]).
+    ///
+    /// Drawables without bounds are treated as covering everything.
+    fn bounds(&self) -> Option<shapes::Rectangle> {
+        None
+    }
 }
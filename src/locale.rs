@@ -0,0 +1,530 @@
+// locales and locale-aware formatting of numbers and dates
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A language with an optional script and region, parsed from a BCP 47 tag like `de-CH`,
+/// `zh-Hant-TW` or `sr-Latn`.
+///
+/// Locales select how numbers and dates are formatted (see [`Locale::format_number`] and
+/// [`Locale::format_date`]), which language text is shaped for (see
+/// [`RichText::with_locale`](crate::text::RichText::with_locale)) and which fonts fill in
+/// missing glyphs (see [`FontRegistry::runs_for_locale`](crate::fonts::FontRegistry::runs_for_locale)).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Locale {
+    /// The lowercase language code, e.g. `en`.
+    pub language: String,
+    /// The titlecase script code, e.g. `Hant`.
+    pub script: Option<String>,
+    /// The uppercase region code, e.g. `US` (or a numeric UN M.49 code like `419`).
+    pub region: Option<String>,
+}
+
+impl Locale {
+    /// Parse a BCP 47 tag (`-` or `_` separated). Variants and extensions are ignored. Returns
+    /// `None` if the tag doesn't start with a language code.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let mut parts = tag.split(['-', '_']);
+        let language = parts.next()?;
+        if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let mut locale = Self {
+            language: language.to_ascii_lowercase(),
+            script: None,
+            region: None,
+        };
+        for part in parts {
+            match part.len() {
+                4 if locale.script.is_none()
+                    && locale.region.is_none()
+                    && part.chars().all(|c| c.is_ascii_alphabetic()) =>
+                {
+                    let (first, rest) = part.split_at(1);
+                    locale.script = Some(first.to_ascii_uppercase() + &rest.to_ascii_lowercase());
+                }
+                2 if locale.region.is_none() && part.chars().all(|c| c.is_ascii_alphabetic()) => {
+                    locale.region = Some(part.to_ascii_uppercase());
+                }
+                3 if locale.region.is_none() && part.chars().all(|c| c.is_ascii_digit()) => {
+                    locale.region = Some(part.to_owned());
+                }
+                _ => break,
+            }
+        }
+        Some(locale)
+    }
+
+    /// The locale of the environment (from `LC_ALL`, `LC_MESSAGES` or `LANG`), e.g. `de_DE.UTF-8`.
+    pub fn from_env() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::parse(value.split(['.', '@']).next().unwrap_or_default()))
+    }
+
+    /// The tag of the locale, e.g. `zh-Hant-TW`.
+    pub fn tag(&self) -> String {
+        [Some(&self.language), self.script.as_ref(), self.region.as_ref()]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// The script of the locale, or the script its language is usually written in (e.g. `Hant`
+    /// for `zh-TW`), if known.
+    pub fn likely_script(&self) -> Option<&str> {
+        if let Some(script) = &self.script {
+            return Some(script);
+        }
+        Some(match self.language.as_str() {
+            "zh" if matches!(self.region.as_deref(), Some("TW" | "HK" | "MO")) => "Hant",
+            "zh" => "Hans",
+            "ja" => "Jpan",
+            "ko" => "Kore",
+            "ar" | "fa" | "ur" => "Arab",
+            "he" | "yi" => "Hebr",
+            "hi" | "mr" | "ne" => "Deva",
+            "bn" => "Beng",
+            "ta" => "Taml",
+            "th" => "Thai",
+            "el" => "Grek",
+            "ru" | "uk" | "bg" | "be" | "mk" | "sr" => "Cyrl",
+            "hy" => "Armn",
+            "ka" => "Geor",
+            _ if self.language.len() == 2 => "Latn",
+            _ => return None,
+        })
+    }
+
+    /// Whether text of the locale is written from right to left.
+    pub fn is_right_to_left(&self) -> bool {
+        matches!(self.likely_script(), Some("Arab" | "Hebr"))
+    }
+
+    /// How numbers are written in the locale.
+    pub fn number_format(&self) -> NumberFormat {
+        const NBSP: char = '\u{a0}';
+        const NARROW_NBSP: char = '\u{202f}';
+        let region = self.region.as_deref();
+        let english = NumberFormat::default();
+        let comma = NumberFormat {
+            decimal: ',',
+            group: '.',
+            ..english
+        };
+        let spaced = NumberFormat {
+            decimal: ',',
+            group: NBSP,
+            percent: Percent::SuffixSpaced,
+            ..english
+        };
+        match self.language.as_str() {
+            "en" if region == Some("IN") => NumberFormat {
+                grouping: Grouping::Indian,
+                ..english
+            },
+            "hi" | "mr" | "bn" => NumberFormat {
+                grouping: Grouping::Indian,
+                ..english
+            },
+            "de" if matches!(region, Some("CH" | "LI")) => NumberFormat {
+                group: '\u{2019}',
+                ..english
+            },
+            "de" if region == Some("AT") => spaced,
+            "de" | "da" | "el" | "id" | "ro" | "hr" | "sl" | "sr" => NumberFormat {
+                percent: Percent::SuffixSpaced,
+                ..comma
+            },
+            "it" | "nl" => comma,
+            "es" => NumberFormat {
+                min_grouping: 2,
+                percent: Percent::SuffixSpaced,
+                ..comma
+            },
+            "pt" if region == Some("PT") => NumberFormat {
+                min_grouping: 2,
+                ..spaced
+            },
+            "pt" => comma,
+            "tr" => NumberFormat {
+                percent: Percent::Prefix,
+                ..comma
+            },
+            "fr" if region == Some("CH") => NumberFormat {
+                decimal: '.',
+                group: NARROW_NBSP,
+                ..spaced
+            },
+            "fr" => NumberFormat {
+                group: NARROW_NBSP,
+                ..spaced
+            },
+            "pl" => NumberFormat {
+                min_grouping: 2,
+                ..spaced
+            },
+            "sv" | "nb" | "no" | "fi" | "et" | "lt" => NumberFormat {
+                minus: '\u{2212}',
+                ..spaced
+            },
+            "ru" | "uk" | "be" | "bg" | "cs" | "sk" | "hu" | "lv" => spaced,
+            _ => english,
+        }
+    }
+
+    /// Format a number with a fixed number of decimals, e.g. `1 234,5` for `fr` and
+    /// `1,234.5` for `en`.
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        self.number_format().format(value, decimals)
+    }
+
+    /// Format a fraction (0.25 for 25%) as a percentage, e.g. `25 %` for `de` and `%25` for `tr`.
+    pub fn format_percent(&self, fraction: f64, decimals: usize) -> String {
+        let format = self.number_format();
+        let number = format.format(fraction * 100.0, decimals);
+        match format.percent {
+            Percent::Suffix => format!("{number}%"),
+            Percent::SuffixSpaced => format!("{number}\u{a0}%"),
+            Percent::Prefix => format!("%{number}"),
+        }
+    }
+
+    /// Format a date, e.g. `3/5/2024` (short) or `March 5, 2024` (long) for `en-US` and
+    /// `05.03.2024` or `5. März 2024` for `de`. Locales without known formats use ISO 8601
+    /// (`2024-03-05`).
+    pub fn format_date(&self, date: Date, style: DateStyle) -> String {
+        let Date { year, month, day } = date;
+        let region = self.region.as_deref();
+        let us_english = self.language == "en" && matches!(region, None | Some("US" | "PH"));
+        let iso = format!("{year:04}-{month:02}-{day:02}");
+        match style {
+            DateStyle::Short => match self.language.as_str() {
+                "en" if us_english => format!("{month}/{day}/{year}"),
+                "en" if region == Some("CA") => iso,
+                "en" | "fr" | "es" | "it" | "pt" | "el" | "ca" | "id" | "vi" => {
+                    format!("{day:02}/{month:02}/{year}")
+                }
+                "de" | "ru" | "pl" | "tr" | "uk" | "nb" | "no" | "da" | "ro" | "fi" | "cs" | "sk" | "bg" => {
+                    format!("{day:02}.{month:02}.{year}")
+                }
+                "nl" => format!("{day:02}-{month:02}-{year}"),
+                "ja" | "zh" => format!("{year}/{month:02}/{day:02}"),
+                "ko" => format!("{year}. {month}. {day}."),
+                "hu" => format!("{year}. {month:02}. {day:02}."),
+                _ => iso,
+            },
+            DateStyle::Long => {
+                let month_name = |names: [&'static str; 12]| names[month.clamp(1, 12) as usize - 1];
+                match self.language.as_str() {
+                    "en" if us_english => format!("{} {day}, {year}", month_name(ENGLISH_MONTHS)),
+                    "en" => format!("{day} {} {year}", month_name(ENGLISH_MONTHS)),
+                    "de" => format!("{day}. {} {year}", month_name(GERMAN_MONTHS)),
+                    "fr" => format!("{day} {} {year}", month_name(FRENCH_MONTHS)),
+                    "es" => format!("{day} de {} de {year}", month_name(SPANISH_MONTHS)),
+                    "pt" => format!("{day} de {} de {year}", month_name(PORTUGUESE_MONTHS)),
+                    "it" => format!("{day} {} {year}", month_name(ITALIAN_MONTHS)),
+                    "nl" => format!("{day} {} {year}", month_name(DUTCH_MONTHS)),
+                    "ja" | "zh" => format!("{year}年{month}月{day}日"),
+                    "ko" => format!("{year}년 {month}월 {day}일"),
+                    _ => iso,
+                }
+            }
+        }
+    }
+
+    /// Format a time of day, with a 12-hour clock for `en` (except `en-GB` and `en-IE`) and a
+    /// 24-hour clock otherwise.
+    pub fn format_time(&self, hour: u32, minute: u32) -> String {
+        let twelve_hour = self.language == "en" && !matches!(self.region.as_deref(), Some("GB" | "IE"));
+        if twelve_hour {
+            let suffix = if hour < 12 { "AM" } else { "PM" };
+            let hour = match hour % 12 {
+                0 => 12,
+                hour => hour,
+            };
+            format!("{hour}:{minute:02}\u{a0}{suffix}")
+        } else {
+            format!("{hour:02}:{minute:02}")
+        }
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.tag())
+    }
+}
+
+impl std::str::FromStr for Locale {
+    type Err = String;
+
+    fn from_str(tag: &str) -> Result<Self, Self::Err> {
+        Self::parse(tag).ok_or_else(|| format!("invalid locale {tag:?}"))
+    }
+}
+
+/// How digits are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Grouping {
+    /// Groups of three digits (`1,234,567`).
+    #[default]
+    Thousands,
+    /// Three digits, then groups of two (`12,34,567`), as in India.
+    Indian,
+    /// No grouping.
+    None,
+}
+
+/// Where the percent sign goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Percent {
+    /// `25%`
+    #[default]
+    Suffix,
+    /// `25 %`, with a no-break space.
+    SuffixSpaced,
+    /// `%25`
+    Prefix,
+}
+
+/// The symbols and grouping numbers are written with (see [`Locale::number_format`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub decimal: char,
+    /// The separator between groups of digits.
+    pub group: char,
+    pub grouping: Grouping,
+    /// The fewest digits in front of the first group for it to be separated, e.g. 2 to write
+    /// `1234` but `12 345`.
+    pub min_grouping: usize,
+    pub minus: char,
+    pub percent: Percent,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            decimal: '.',
+            group: ',',
+            grouping: Grouping::Thousands,
+            min_grouping: 1,
+            minus: '-',
+            percent: Percent::Suffix,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Format a number with a fixed number of decimals.
+    pub fn format(&self, value: f64, decimals: usize) -> String {
+        if value.is_nan() {
+            return "NaN".into();
+        }
+        let mut text = String::new();
+        let rounded = format!("{:.*}", decimals, value.abs());
+        // rounding can turn small negative numbers into zero, which has no sign
+        if value.is_sign_negative() && rounded.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            text.push(self.minus);
+        }
+        if value.is_infinite() {
+            text.push('∞');
+            return text;
+        }
+
+        let (integer, fraction) = rounded.split_once('.').unwrap_or((&rounded, ""));
+        let digits = integer.chars().collect::<Vec<_>>();
+        // the sizes of the groups from the right
+        let (first, rest) = match self.grouping {
+            Grouping::Thousands => (3, 3),
+            Grouping::Indian => (3, 2),
+            Grouping::None => (usize::MAX, usize::MAX),
+        };
+        let grouped = digits.len() >= first.saturating_add(self.min_grouping);
+        for (i, digit) in digits.iter().enumerate() {
+            let remaining = digits.len() - i;
+            if grouped && i > 0 && remaining >= first && (remaining - first) % rest == 0 {
+                text.push(self.group);
+            }
+            text.push(*digit);
+        }
+        if !fraction.is_empty() {
+            text.push(self.decimal);
+            text.push_str(fraction);
+        }
+        text
+    }
+}
+
+/// A calendar date (in the proleptic Gregorian calendar).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    /// 1 to 12.
+    pub month: u32,
+    /// 1 to 31.
+    pub day: u32,
+}
+
+impl Date {
+    /// Returns `None` if the day doesn't exist.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return None,
+        };
+        (1..=days).contains(&day).then_some(Self { year, month, day })
+    }
+
+    /// The date (in UTC) of a number of days since 1970-01-01.
+    pub fn from_days_since_epoch(days: i64) -> Self {
+        // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Self {
+            year: year as i32,
+            month,
+            day,
+        }
+    }
+
+    /// The date (in UTC) of a point in time.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(error) => -(error.duration().as_secs_f64().ceil() as i64),
+        };
+        Self::from_days_since_epoch(seconds.div_euclid(86_400))
+    }
+
+    /// Today's date in UTC.
+    pub fn today() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+}
+
+/// How long a formatted date is (see [`Locale::format_date`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateStyle {
+    /// Numeric, e.g. `05.03.2024`.
+    #[default]
+    Short,
+    /// With the name of the month, e.g. `5. März 2024`.
+    Long,
+}
+
+const ENGLISH_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const GERMAN_MONTHS: [&str; 12] = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+];
+const FRENCH_MONTHS: [&str; 12] = [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+];
+const SPANISH_MONTHS: [&str; 12] = [
+    "enero",
+    "febrero",
+    "marzo",
+    "abril",
+    "mayo",
+    "junio",
+    "julio",
+    "agosto",
+    "septiembre",
+    "octubre",
+    "noviembre",
+    "diciembre",
+];
+const PORTUGUESE_MONTHS: [&str; 12] = [
+    "janeiro",
+    "fevereiro",
+    "março",
+    "abril",
+    "maio",
+    "junho",
+    "julho",
+    "agosto",
+    "setembro",
+    "outubro",
+    "novembro",
+    "dezembro",
+];
+const ITALIAN_MONTHS: [&str; 12] = [
+    "gennaio",
+    "febbraio",
+    "marzo",
+    "aprile",
+    "maggio",
+    "giugno",
+    "luglio",
+    "agosto",
+    "settembre",
+    "ottobre",
+    "novembre",
+    "dicembre",
+];
+const DUTCH_MONTHS: [&str; 12] = [
+    "januari",
+    "februari",
+    "maart",
+    "april",
+    "mei",
+    "juni",
+    "juli",
+    "augustus",
+    "september",
+    "oktober",
+    "november",
+    "december",
+];
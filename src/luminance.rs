@@ -0,0 +1,93 @@
+// luminance statistics of rendered frames
+use image::RgbaImage;
+
+use super::colors::srgb_to_linear;
+
+/// The number of bins of a [`LuminanceStats`] histogram.
+pub const LUMINANCE_BINS: usize = 256;
+
+/// The scale of the fixed-point luminance sums accumulated on the GPU.
+#[cfg(feature = "vello-backend")]
+pub(crate) const LUMINANCE_SUM_SCALE: f64 = 65535.0;
+
+/// The distribution of the relative luminance of a rendered frame, e.g. to verify that the mean
+/// luminance stays constant across conditions.
+///
+/// Luminance is relative (0 for black, 1 for white) and assumes an sRGB display, so multiply it
+/// by the peak luminance of the display (in cd/m²) for absolute values. Alpha is ignored, since
+/// rendered frames are opaque.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuminanceStats {
+    /// The number of pixels per luminance bin; bin `i` covers `[i / 256, (i + 1) / 256)`.
+    pub histogram: Vec<u32>,
+    /// The mean relative luminance.
+    pub mean: f64,
+    /// The number of pixels that were analyzed.
+    pub pixel_count: u64,
+}
+
+impl LuminanceStats {
+    /// Compute the statistics of an image on the CPU, e.g. of a frame read back with
+    /// `render_to_image`.
+    pub fn from_image(image: &RgbaImage) -> Self {
+        let mut histogram = vec![0; LUMINANCE_BINS];
+        let mut sum = 0.0;
+        for pixel in image.pixels() {
+            let [r, g, b, _] = pixel.0;
+            let luminance = relative_luminance(r, g, b);
+            histogram[luminance_bin(luminance)] += 1;
+            sum += luminance;
+        }
+        let pixel_count = image.width() as u64 * image.height() as u64;
+        Self {
+            histogram,
+            mean: if pixel_count == 0 {
+                0.0
+            } else {
+                sum / pixel_count as f64
+            },
+            pixel_count,
+        }
+    }
+
+    /// Create the statistics from a histogram and a sum of luminances in fixed point (see
+    /// [`LUMINANCE_SUM_SCALE`]), as computed on the GPU.
+    #[cfg(feature = "vello-backend")]
+    pub(crate) fn from_fixed_point(histogram: Vec<u32>, sum: u64) -> Self {
+        let pixel_count = histogram.iter().map(|&count| count as u64).sum::<u64>();
+        let mean = if pixel_count == 0 {
+            0.0
+        } else {
+            sum as f64 / LUMINANCE_SUM_SCALE / pixel_count as f64
+        };
+        Self {
+            histogram,
+            mean,
+            pixel_count,
+        }
+    }
+
+    /// The luminance below which the given fraction (0 to 1) of the pixels lie, at the resolution
+    /// of the histogram, e.g. `percentile(0.5)` for the median.
+    pub fn percentile(&self, fraction: f64) -> f64 {
+        let target = (fraction.clamp(0.0, 1.0) * self.pixel_count as f64).ceil() as u64;
+        let mut count = 0;
+        for (bin, &pixels) in self.histogram.iter().enumerate() {
+            count += pixels as u64;
+            if count >= target.max(1) {
+                return (bin + 1) as f64 / LUMINANCE_BINS as f64;
+            }
+        }
+        1.0
+    }
+}
+
+/// The relative luminance of an sRGB-encoded color.
+pub fn relative_luminance(r: u8, g: u8, b: u8) -> f64 {
+    let linear = |value: u8| srgb_to_linear(value as f32 / 255.0) as f64;
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+fn luminance_bin(luminance: f64) -> usize {
+    ((luminance * LUMINANCE_BINS as f64) as usize).min(LUMINANCE_BINS - 1)
+}
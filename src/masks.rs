@@ -0,0 +1,163 @@
+// reusable stacks of masks and clips
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::scenes::{MaskKind, Scene};
+use super::shapes::{Path, Point, Rectangle, Shape};
+use super::styles::{CompositeMode, MixMode};
+use super::Drawable;
+
+/// A step of a [`MaskSet`].
+pub enum MaskStep<Backend> {
+    /// Clip to a shape (in scene coordinates).
+    Clip { shape: Path, transform: Affine },
+    /// Mask by content. The drawables of a step are combined into a single mask.
+    Mask {
+        kind: MaskKind,
+        drawables: Vec<Box<dyn Drawable<Backend>>>,
+    },
+}
+
+/// A named stack of clips and masks that is defined once and applied to any content, e.g. an
+/// aperture combined with a raised-cosine window.
+///
+/// The content is only shown where all steps let it through. Applying the set uses as few layers
+/// as possible: the content is drawn into one layer, each mask is composited onto it with a layer
+/// of its own, and the first clip is applied to the content layer instead of a layer of its own.
+pub struct MaskSet<Backend> {
+    pub name: String,
+    /// The steps, in the order they were added.
+    pub steps: Vec<MaskStep<Backend>>,
+}
+
+impl<Backend> MaskSet<Backend> {
+    /// Create an empty set, which shows the content unchanged.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Add a clip.
+    pub fn with_clip(mut self, shape: &impl Shape, transform: Affine) -> Self {
+        self.steps.push(MaskStep::Clip {
+            shape: shape.to_path().into(),
+            transform,
+        });
+        self
+    }
+
+    /// Add a mask drawn by a single drawable.
+    pub fn with_mask(mut self, kind: MaskKind, mask: impl Drawable<Backend> + 'static) -> Self {
+        self.steps.push(MaskStep::Mask {
+            kind,
+            drawables: vec![Box::new(mask)],
+        });
+        self
+    }
+
+    /// Add a mask drawn by several drawables, e.g. a set of apertures.
+    pub fn with_masks(mut self, kind: MaskKind, masks: Vec<Box<dyn Drawable<Backend>>>) -> Self {
+        self.steps.push(MaskStep::Mask { kind, drawables: masks });
+        self
+    }
+
+    /// The number of layers pushed when the set is applied.
+    pub fn layer_count(&self) -> usize {
+        let clips = self
+            .steps
+            .iter()
+            .filter(|step| matches!(step, MaskStep::Clip { .. }))
+            .count();
+        let masks = self.steps.len() - clips;
+        if masks == 0 {
+            clips
+        } else {
+            masks + 1 + clips.saturating_sub(1)
+        }
+    }
+}
+
+impl<Backend: RenderBackend> MaskSet<Backend> {
+    /// Draw content through the masks and clips of the set.
+    pub fn apply(&mut self, scene: &mut Scene<Backend>, content: impl FnOnce(&mut Scene<Backend>)) {
+        let view_transform = scene.view_transform();
+        let bounds = Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: scene.width as f64,
+                y: scene.height as f64,
+            },
+        };
+
+        let mut clips = Vec::new();
+        let mut masks = Vec::new();
+        for step in &mut self.steps {
+            match step {
+                MaskStep::Clip { shape, transform } => clips.push((&*shape, *transform * view_transform)),
+                MaskStep::Mask { kind, drawables } => masks.push((*kind, drawables)),
+            }
+        }
+
+        // only the content layer can be clipped, since the mask layers inside it only cut it
+        // away, so all but the first clip need layers of their own
+        let mut clips = clips.into_iter();
+        let first_clip = if masks.is_empty() { None } else { clips.next() };
+        let mut pushed = 0;
+        for (shape, transform) in clips {
+            scene
+                .backend
+                .push_layer(MixMode::Normal, CompositeMode::SourceOver, shape, transform, 1.0);
+            pushed += 1;
+        }
+
+        if masks.is_empty() {
+            content(scene);
+        } else {
+            match first_clip {
+                Some((shape, transform)) => {
+                    scene
+                        .backend
+                        .push_layer(MixMode::Normal, CompositeMode::SourceOver, shape, transform, 1.0)
+                }
+                None => scene.backend.push_layer(
+                    MixMode::Normal,
+                    CompositeMode::SourceOver,
+                    &bounds,
+                    Affine::identity(),
+                    1.0,
+                ),
+            }
+            pushed += 1;
+            content(scene);
+
+            // each mask cuts away the content where it is transparent (or dark)
+            for (kind, drawables) in masks {
+                scene.backend.push_layer(
+                    MixMode::Normal,
+                    CompositeMode::DestinationIn,
+                    &bounds,
+                    Affine::identity(),
+                    1.0,
+                );
+                match kind {
+                    MaskKind::Alpha => {
+                        for drawable in drawables.iter_mut() {
+                            drawable.draw(scene);
+                        }
+                    }
+                    MaskKind::Luminance => scene.draw_luminance_mask(|mask_scene| {
+                        for drawable in drawables.iter_mut() {
+                            drawable.draw(mask_scene);
+                        }
+                    }),
+                }
+                scene.backend.pop_layer();
+            }
+        }
+
+        for _ in 0..pushed {
+            scene.backend.pop_layer();
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// procedural noise textures
+use std::sync::Arc;
+
+use super::brushes::{Brush, Extend, Image};
+use super::colors::RGBA;
+use super::random::Random;
+use super::styles::ImageFitMode;
+
+/// The distribution of a [`Noise`] texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Independent, uniformly distributed pixels.
+    White,
+    /// Noise with an amplitude spectrum falling with 1/f, approximated by summing white noise at
+    /// every octave (interpolated bilinearly), so that coarse and fine structure have the same
+    /// contrast. It is scaled to the RMS contrast of white noise, so its rare extreme values
+    /// are clipped.
+    Pink,
+    /// Independent pixels that are either dark or bright.
+    Binary,
+}
+
+/// A noise texture with a reproducible seed, to be drawn as an image brush, e.g. for noise masks
+/// or backgrounds.
+///
+/// The color of a pixel is `mean * (1 + contrast * value)` with values between -1 and 1 (pink
+/// noise has the same RMS as white noise, but can exceed them), clipped to the range of the
+/// color channels. The texture tiles (pink noise only approximately). Call [`Noise::regenerate`] every frame for
+/// dynamic noise.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    pub kind: NoiseKind,
+    /// The mean color (mid-gray by default).
+    pub mean: RGBA,
+    /// The contrast (0 to 1).
+    pub contrast: f64,
+    seed: u64,
+    random: Random,
+    image: Image,
+}
+
+impl Noise {
+    /// Create a noise texture of `width` x `height` pixels.
+    pub fn new(kind: NoiseKind, seed: u64, (width, height): (u32, u32)) -> Self {
+        let image = Image::new(&image::DynamicImage::ImageRgba8(image::RgbaImage::new(width, height)));
+        let mut noise = Self {
+            kind,
+            mean: RGBA::new(0.5, 0.5, 0.5, 1.0),
+            contrast: 1.0,
+            seed,
+            random: Random::new(seed),
+            image,
+        };
+        noise.generate();
+        noise
+    }
+
+    /// Create white noise.
+    pub fn white(seed: u64, size: (u32, u32)) -> Self {
+        Self::new(NoiseKind::White, seed, size)
+    }
+
+    /// Create pink (1/f) noise.
+    pub fn pink(seed: u64, size: (u32, u32)) -> Self {
+        Self::new(NoiseKind::Pink, seed, size)
+    }
+
+    /// Create binary noise.
+    pub fn binary(seed: u64, size: (u32, u32)) -> Self {
+        Self::new(NoiseKind::Binary, seed, size)
+    }
+
+    /// Set the mean color and regenerate the texture from the seed.
+    pub fn with_mean(mut self, mean: RGBA) -> Self {
+        self.mean = mean;
+        self.reset();
+        self
+    }
+
+    /// Set the contrast and regenerate the texture from the seed.
+    pub fn with_contrast(mut self, contrast: f64) -> Self {
+        self.contrast = contrast;
+        self.reset();
+        self
+    }
+
+    /// The seed the texture was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The current texture.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// A brush that repeats the texture at its natural size (one texel per scene unit).
+    pub fn brush(&self) -> Brush {
+        Brush::Image {
+            image: self.image.clone(),
+            x: 0.0,
+            y: 0.0,
+            fit_mode: ImageFitMode::Original,
+            edge_mode: Extend::Repeat,
+            edge_mode_y: None,
+            mapping: Default::default(),
+        }
+    }
+
+    /// Replace the texture with the next sample of the random sequence, e.g. every frame for
+    /// dynamic noise. The pixel buffer is only reused if nothing else holds on to it: once the
+    /// texture was drawn, the caches of the renderer do, so a new buffer is allocated.
+    pub fn regenerate(&mut self) {
+        self.generate();
+    }
+
+    /// Restart the random sequence from the seed, so that the first texture is generated again.
+    pub fn reset(&mut self) {
+        self.random = Random::new(self.seed);
+        self.generate();
+    }
+
+    fn generate(&mut self) {
+        let (width, height) = (self.image.width as usize, self.image.height as usize);
+        let values = match self.kind {
+            NoiseKind::White => (0..width * height).map(|_| self.random.range(-1.0, 1.0)).collect(),
+            NoiseKind::Binary => (0..width * height)
+                .map(|_| if self.random.next_f64() < 0.5 { -1.0 } else { 1.0 })
+                .collect(),
+            NoiseKind::Pink => pink_noise(&mut self.random, width, height),
+        };
+
+        let (mean, contrast) = (self.mean, self.contrast);
+        let channel =
+            |mean: f32, value: f64| ((mean as f64 * (1.0 + contrast * value)).clamp(0.0, 1.0) * 255.0).round() as u8;
+        let alpha = (mean.a.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let data = Arc::make_mut(&mut self.image.data);
+        for (pixel, value) in data.chunks_exact_mut(4).zip(values) {
+            pixel.copy_from_slice(&[
+                channel(mean.r, value),
+                channel(mean.g, value),
+                channel(mean.b, value),
+                alpha,
+            ]);
+        }
+    }
+}
+
+/// Pink noise as the sum of bilinearly interpolated white noise at every octave with the same
+/// amplitude, scaled to the RMS of white noise between -1 and 1 (`1 / sqrt(3)`).
+fn pink_noise(random: &mut Random, width: usize, height: usize) -> Vec<f64> {
+    let mut values = vec![0.0; width * height];
+    let mut cell = 1;
+    while cell <= width.max(height) {
+        // one random value per cell corner, wrapping around so that the texture tiles
+        let (columns, rows) = (width.div_ceil(cell), height.div_ceil(cell));
+        let grid = (0..columns * rows).map(|_| random.range(-1.0, 1.0)).collect::<Vec<_>>();
+        let at = |column: usize, row: usize| grid[(row % rows) * columns + column % columns];
+        for y in 0..height {
+            let (row, ty) = (y / cell, (y % cell) as f64 / cell as f64);
+            for x in 0..width {
+                let (column, tx) = (x / cell, (x % cell) as f64 / cell as f64);
+                let top = at(column, row) * (1.0 - tx) + at(column + 1, row) * tx;
+                let bottom = at(column, row + 1) * (1.0 - tx) + at(column + 1, row + 1) * tx;
+                values[y * width + x] += top * (1.0 - ty) + bottom * ty;
+            }
+        }
+        cell *= 2;
+    }
+
+    let rms = (values.iter().map(|value| value * value).sum::<f64>() / values.len().max(1) as f64).sqrt();
+    if rms > 0.0 {
+        let scale = 1.0 / (3.0_f64.sqrt() * rms);
+        for value in &mut values {
+            *value *= scale;
+        }
+    }
+    values
+}
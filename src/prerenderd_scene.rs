@@ -1,4 +1,5 @@
 use crate::affine::Affine;
+use crate::error::{RendererError, Result};
 
 pub use custom_debug::Debug;
 pub use vello_svg::usvg::Tree;
@@ -19,10 +20,11 @@ impl PrerenderedScene {
         Self { scene, width, height, transform }
     }
 
-    pub fn from_svg_string(svg: &str, transform: Affine) -> Self {
-        let tree = vello_svg::usvg::Tree::from_str(svg, &Default::default()).unwrap();
+    pub fn from_svg_string(svg: &str, transform: Affine) -> Result<Self> {
+        let tree = vello_svg::usvg::Tree::from_str(svg, &Default::default())
+            .map_err(|e| RendererError::Svg(e.to_string()))?;
         let scene = vello_svg::render_tree(&tree);
-        Self::new(scene, tree.size().width() as f64, tree.size().height() as f64, transform)
+        Ok(Self::new(scene, tree.size().width() as f64, tree.size().height() as f64, transform))
     }
 
     pub fn set_transform(&mut self, transform: Affine) {
@@ -0,0 +1,177 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::error::{RendererError, Result};
+use super::styles::Antialiasing;
+
+/// Settings that trade rendering quality for speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualitySettings {
+    /// The antialiasing method, or `None` to use the renderer's default.
+    pub antialiasing: Option<Antialiasing>,
+    /// Whether optional effects (gradient dithering, layer effects and the blur of shadows) are
+    /// drawn. Without them, shadows are filled without blurring.
+    pub effects: bool,
+    /// The width of the lookup textures used for gradients with many stops.
+    pub gradient_resolution: u32,
+}
+
+impl Default for QualitySettings {
+    fn default() -> Self {
+        Self {
+            antialiasing: None,
+            effects: true,
+            gradient_resolution: 1024,
+        }
+    }
+}
+
+/// A change of the quality level made by a [`QualityGovernor`].
+#[derive(Debug, Clone, Copy)]
+pub struct QualityChange {
+    /// The previous level.
+    pub from: usize,
+    /// The new level.
+    pub to: usize,
+    /// The average frame time that triggered the change.
+    pub average_frame_time: Duration,
+}
+
+/// A callback that is notified whenever a [`QualityGovernor`] changes the quality level.
+#[derive(Clone)]
+pub struct QualityHook(pub Arc<QualityHookFn>);
+
+/// The signature of a [`QualityHook`] callback.
+pub type QualityHookFn = dyn Fn(&QualityChange) + Send + Sync;
+
+impl QualityHook {
+    pub fn new(hook: impl Fn(&QualityChange) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for QualityHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("QualityHook")
+    }
+}
+
+/// Monitors frame times and steps through a list of quality levels to keep up with a target
+/// refresh rate.
+///
+/// When the average frame time over the last frames exceeds the target, the governor moves to
+/// the next (cheaper) level. When frames are comfortably faster than the target, it moves back
+/// towards the first level. Attach it to a scene with [`Scene::set_governor`] to have it apply
+/// its settings at the start of every frame.
+///
+/// [`Scene::set_governor`]: crate::scenes::Scene::set_governor
+#[derive(Debug, Clone)]
+pub struct QualityGovernor {
+    /// The frame time to stay below.
+    pub target_frame_time: Duration,
+    /// The quality levels, from best to cheapest.
+    pub levels: Vec<QualitySettings>,
+    /// Move to a cheaper level when the average frame time exceeds this fraction of the target.
+    pub degrade_threshold: f64,
+    /// Move to a better level when the average frame time is below this fraction of the target.
+    pub restore_threshold: f64,
+    /// The number of frames that are averaged before the level can change.
+    pub window: usize,
+    /// Called whenever the level changes.
+    pub hook: Option<QualityHook>,
+    level: usize,
+    frame_times: VecDeque<Duration>,
+}
+
+impl QualityGovernor {
+    /// Create a governor for a target refresh rate (in Hz) with a default list of levels:
+    /// the renderer's default, 8x multisampling, and area antialiasing without effects and
+    /// with coarser gradients.
+    pub fn new(target_refresh_rate: f64) -> Self {
+        let full = QualitySettings::default();
+        Self {
+            target_frame_time: Duration::from_secs_f64(1.0 / target_refresh_rate),
+            levels: vec![
+                full,
+                QualitySettings {
+                    antialiasing: Some(Antialiasing::Msaa8),
+                    ..full
+                },
+                QualitySettings {
+                    antialiasing: Some(Antialiasing::Area),
+                    effects: false,
+                    gradient_resolution: 256,
+                },
+            ],
+            degrade_threshold: 1.05,
+            restore_threshold: 0.7,
+            window: 30,
+            hook: None,
+            level: 0,
+            frame_times: VecDeque::new(),
+        }
+    }
+
+    /// Set the quality levels, from best to cheapest. Fails if there are no levels.
+    pub fn with_levels(mut self, levels: Vec<QualitySettings>) -> Result<Self> {
+        if levels.is_empty() {
+            return Err(RendererError::InvalidArgument(
+                "a quality governor needs at least one level".to_owned(),
+            ));
+        }
+        self.levels = levels;
+        self.level = self.level.min(self.levels.len() - 1);
+        Ok(self)
+    }
+
+    /// Set the callback that is notified when the level changes.
+    pub fn with_hook(mut self, hook: impl Fn(&QualityChange) + Send + Sync + 'static) -> Self {
+        self.hook = Some(QualityHook::new(hook));
+        self
+    }
+
+    /// The index of the current level.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// The settings of the current level.
+    pub fn settings(&self) -> QualitySettings {
+        self.levels[self.level]
+    }
+
+    /// Record the duration of a frame. Returns the change if the level changed.
+    pub fn record_frame(&mut self, frame_time: Duration) -> Option<QualityChange> {
+        self.frame_times.push_back(frame_time);
+        while self.frame_times.len() > self.window.max(1) {
+            self.frame_times.pop_front();
+        }
+        if self.frame_times.len() < self.window.max(1) {
+            return None;
+        }
+
+        let average = self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32;
+        let target = self.target_frame_time.as_secs_f64();
+        let to = if average.as_secs_f64() > target * self.degrade_threshold && self.level + 1 < self.levels.len() {
+            self.level + 1
+        } else if average.as_secs_f64() < target * self.restore_threshold && self.level > 0 {
+            self.level - 1
+        } else {
+            return None;
+        };
+
+        let change = QualityChange {
+            from: self.level,
+            to,
+            average_frame_time: average,
+        };
+        self.level = to;
+        // judge the new level on its own frames
+        self.frame_times.clear();
+        if let Some(hook) = &self.hook {
+            (hook.0)(&change);
+        }
+        Some(change)
+    }
+}
@@ -0,0 +1,461 @@
+// session recording and replay
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::brushes::Brush;
+use super::colors::RGBA;
+use super::error::{RendererError, Result};
+use super::geoms::{trim_path, Geom};
+use super::scenes::Scene;
+use super::shapes::{self, PathElement, Point, Shape};
+use super::styles::{Cap, FillStyle, Join, StrokeOptions, Style};
+use super::Drawable;
+
+/// Magic bytes at the start of every session file.
+const MAGIC: &[u8; 8] = b"RNDRSESS";
+/// Version of the session file format.
+const VERSION: u32 = 2;
+
+/// A single recorded frame.
+#[derive(Debug, Clone)]
+pub struct RecordedFrame {
+    /// The index of the frame within the session.
+    pub index: u64,
+    /// Time since the start of the recording.
+    pub timestamp: Duration,
+    /// The commands that produced the frame.
+    pub commands: SceneCommands,
+}
+
+/// A geom recorded as its outline, filled or stroked with a solid color.
+#[derive(Debug, Clone)]
+pub struct DrawCommand {
+    pub path: shapes::Path,
+    pub style: Style,
+    pub color: RGBA,
+    pub transform: Affine,
+    pub opacity: f32,
+    pub pixel_snap: bool,
+}
+
+/// The content of a frame as a list of drawing commands, which can be drawn into a scene and
+/// serialized into a session file.
+///
+/// Build the commands of a frame, draw them into the scene that is rendered and record them
+/// with [`SessionRecorder::record_frame`], so that [`SessionReplayer::replay`] draws the same.
+#[derive(Debug, Clone)]
+pub struct SceneCommands {
+    /// The background color of the scene.
+    pub background_color: RGBA,
+    /// The commands, in drawing order.
+    pub commands: Vec<DrawCommand>,
+}
+
+impl SceneCommands {
+    /// Create an empty list of commands.
+    pub fn new(background_color: RGBA) -> Self {
+        Self {
+            background_color,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Add a geom. Only geoms with solid brushes can be recorded; invisible geoms are skipped.
+    pub fn push<S: Shape>(&mut self, geom: &Geom<S>) -> Result<()> {
+        let Brush::Solid(color) = geom.brush else {
+            return Err(RendererError::InvalidArgument(
+                "only geoms with solid brushes can be recorded".into(),
+            ));
+        };
+        if !geom.visible {
+            return Ok(());
+        }
+        let path = match (geom.stroke_trim, &geom.style) {
+            (Some((start, end)), Style::Stroke(_)) => trim_path(&geom.shape.to_path(), start, end),
+            _ => geom.shape.to_path(),
+        };
+        self.commands.push(DrawCommand {
+            path: path.into(),
+            style: geom.style.clone(),
+            color,
+            transform: geom.transform,
+            opacity: geom.opacity,
+            pixel_snap: geom.pixel_snap,
+        });
+        Ok(())
+    }
+
+    /// Serialize the commands (with all numbers in little-endian byte order).
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        put_color(&mut out, self.background_color);
+        put_u64(&mut out, self.commands.len() as u64);
+        for command in &self.commands {
+            put_u64(&mut out, command.path.elements.len() as u64);
+            for element in &command.path.elements {
+                let (tag, points) = match element {
+                    PathElement::MoveTo(p) => (0, vec![*p]),
+                    PathElement::LineTo(p) => (1, vec![*p]),
+                    PathElement::QuadTo(p1, p2) => (2, vec![*p1, *p2]),
+                    PathElement::CurveTo(p1, p2, p3) => (3, vec![*p1, *p2, *p3]),
+                    PathElement::Close => (4, vec![]),
+                };
+                out.push(tag);
+                for point in points {
+                    put_f64(&mut out, point.x);
+                    put_f64(&mut out, point.y);
+                }
+            }
+            match &command.style {
+                Style::Fill(rule) => {
+                    out.push(0);
+                    out.push(matches!(rule, FillStyle::EvenOdd) as u8);
+                }
+                Style::Stroke(options) => {
+                    out.push(1);
+                    put_f64(&mut out, options.width);
+                    out.push(options.join as u8);
+                    put_f64(&mut out, options.miter_limit);
+                    out.push(options.start_cap as u8);
+                    out.push(options.end_cap as u8);
+                    put_u64(&mut out, options.dash_pattern.len() as u64);
+                    for dash in &options.dash_pattern {
+                        put_f64(&mut out, *dash);
+                    }
+                    put_f64(&mut out, options.dash_offset);
+                }
+            }
+            put_color(&mut out, command.color);
+            for coefficient in command.transform.0 {
+                put_f64(&mut out, coefficient);
+            }
+            out.extend_from_slice(&command.opacity.to_le_bytes());
+            out.push(command.pixel_snap as u8);
+        }
+        out
+    }
+
+    /// Deserialize commands serialized with [`SceneCommands::encode`].
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let mut input = Input(bytes);
+        let background_color = input.color()?;
+        let count = input.u64()?;
+        let mut commands = Vec::new();
+        for _ in 0..count {
+            let element_count = input.u64()?;
+            let mut elements = Vec::new();
+            for _ in 0..element_count {
+                elements.push(match input.u8()? {
+                    0 => PathElement::MoveTo(input.point()?),
+                    1 => PathElement::LineTo(input.point()?),
+                    2 => PathElement::QuadTo(input.point()?, input.point()?),
+                    3 => PathElement::CurveTo(input.point()?, input.point()?, input.point()?),
+                    4 => PathElement::Close,
+                    tag => return Err(invalid_data(format!("unknown path element {tag}"))),
+                });
+            }
+            let style = match input.u8()? {
+                0 => Style::Fill(if input.u8()? != 0 { FillStyle::EvenOdd } else { FillStyle::NonZero }),
+                1 => {
+                    let width = input.f64()?;
+                    let join = match input.u8()? {
+                        0 => Join::Bevel,
+                        1 => Join::Miter,
+                        2 => Join::Round,
+                        join => return Err(invalid_data(format!("unknown join {join}"))),
+                    };
+                    let miter_limit = input.f64()?;
+                    let (start_cap, end_cap) = (input.cap()?, input.cap()?);
+                    let dash_count = input.u64()?;
+                    let dash_pattern = (0..dash_count).map(|_| input.f64()).collect::<io::Result<Vec<_>>>()?;
+                    Style::Stroke(StrokeOptions {
+                        width,
+                        join,
+                        miter_limit,
+                        start_cap,
+                        end_cap,
+                        dash_pattern,
+                        dash_offset: input.f64()?,
+                    })
+                }
+                style => return Err(invalid_data(format!("unknown style {style}"))),
+            };
+            let color = input.color()?;
+            let mut transform = [0.0; 6];
+            for coefficient in &mut transform {
+                *coefficient = input.f64()?;
+            }
+            commands.push(DrawCommand {
+                path: shapes::Path { elements },
+                style,
+                color,
+                transform: Affine(transform),
+                opacity: input.f32()?,
+                pixel_snap: input.u8()? != 0,
+            });
+        }
+        if !input.0.is_empty() {
+            return Err(invalid_data(format!("{} unexpected bytes after the commands", input.0.len())));
+        }
+        Ok(Self {
+            background_color,
+            commands,
+        })
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for SceneCommands {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        for command in &self.commands {
+            let mut geom = Geom::new(command.path.clone(), command.style.clone(), command.color)
+                .with_transform(command.transform)
+                .with_opacity(command.opacity);
+            geom.pixel_snap = command.pixel_snap;
+            geom.draw(scene);
+        }
+    }
+}
+
+/// Records the drawing commands of every frame, together with a timestamp, to disk.
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+    start: Instant,
+    next_index: u64,
+}
+
+impl SessionRecorder {
+    /// Create a new session file at the given path, overwriting any existing file.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+            next_index: 0,
+        })
+    }
+
+    /// Record a frame, timestamped relative to the creation of the recorder.
+    pub fn record_frame(&mut self, commands: &SceneCommands) -> io::Result<()> {
+        let timestamp = self.start.elapsed();
+        self.record_frame_at(timestamp, commands)
+    }
+
+    /// Record a frame with an explicit timestamp (e.g. the presentation time of the frame).
+    pub fn record_frame_at(&mut self, timestamp: Duration, commands: &SceneCommands) -> io::Result<()> {
+        let payload = commands.encode();
+        self.writer.write_all(&self.next_index.to_le_bytes())?;
+        self.writer.write_all(&(timestamp.as_nanos() as u64).to_le_bytes())?;
+        self.writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+        self.writer.write_all(&payload)?;
+        self.next_index += 1;
+        Ok(())
+    }
+
+    /// The number of frames recorded so far.
+    pub fn frame_count(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Flush all pending frames to disk and close the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Replays a session recorded with [`SessionRecorder`].
+#[derive(Debug, Clone)]
+pub struct SessionReplayer {
+    frames: Vec<RecordedFrame>,
+}
+
+impl SessionReplayer {
+    /// Load a session file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a session file".into()));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data(format!("unsupported session file version {}", version)));
+        }
+
+        // the header and the frames read so far
+        let mut position = (MAGIC.len() + 4) as u64;
+        let mut frames = Vec::new();
+        // a file can only end at a frame boundary
+        while position < file_len {
+            let header = read_u64(&mut reader).and_then(|index| {
+                let timestamp = Duration::from_nanos(read_u64(&mut reader)?);
+                Ok((index, timestamp, read_u64(&mut reader)?))
+            });
+            let (index, timestamp, payload_len) = match header {
+                Ok(header) => header,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Err(invalid_data(format!("the file ends within the header of frame {}", frames.len())));
+                }
+                Err(e) => return Err(e),
+            };
+            position += 24;
+            // don't trust the declared length with the allocation
+            let remaining = file_len.saturating_sub(position);
+            if payload_len > remaining {
+                return Err(invalid_data(format!(
+                    "frame {index} has a payload of {payload_len} bytes, but only {remaining} bytes are left"
+                )));
+            }
+            let mut payload = vec![0u8; payload_len as usize];
+            reader.read_exact(&mut payload)?;
+            position += payload_len;
+
+            frames.push(RecordedFrame {
+                index,
+                timestamp,
+                commands: SceneCommands::decode(&payload)?,
+            });
+        }
+
+        Ok(Self { frames })
+    }
+
+    /// All recorded frames, in order.
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    /// The timestamp of the last recorded frame.
+    pub fn duration(&self) -> Duration {
+        self.frames.last().map(|f| f.timestamp).unwrap_or_default()
+    }
+
+    /// Draw every frame into a scene as fast as possible, calling `render` after each frame was
+    /// drawn (e.g. to render it to an image). The frame clock of the scene follows the recorded
+    /// timestamps, so the replay is deterministic.
+    pub fn replay<Backend: RenderBackend>(
+        &self,
+        scene: &mut Scene<Backend>,
+        mut render: impl FnMut(&mut Scene<Backend>, &RecordedFrame),
+    ) {
+        for frame in &self.frames {
+            draw_frame(scene, frame);
+            render(scene, frame);
+        }
+    }
+
+    /// Draw every frame into a scene like [`SessionReplayer::replay`], waiting between frames to
+    /// reproduce the original timing.
+    pub fn replay_realtime<Backend: RenderBackend>(
+        &self,
+        scene: &mut Scene<Backend>,
+        mut render: impl FnMut(&mut Scene<Backend>, &RecordedFrame),
+    ) {
+        let start = Instant::now();
+        for frame in &self.frames {
+            if let Some(wait) = frame.timestamp.checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+            draw_frame(scene, frame);
+            render(scene, frame);
+        }
+    }
+}
+
+/// Start a new frame of the scene at the timestamp of a recorded frame and draw its commands.
+fn draw_frame<Backend: RenderBackend>(scene: &mut Scene<Backend>, frame: &RecordedFrame) {
+    scene.begin_frame(frame.timestamp);
+    scene.background_color = frame.commands.background_color;
+    scene.draw(frame.commands.clone());
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_f64(out: &mut Vec<u8>, value: f64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_color(out: &mut Vec<u8>, color: RGBA) {
+    for value in [color.r, color.g, color.b, color.a] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// The unread part of serialized commands.
+struct Input<'a>(&'a [u8]);
+
+impl Input<'_> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        if self.0.len() < N {
+            return Err(invalid_data("the commands end unexpectedly".into()));
+        }
+        let (bytes, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+
+    fn point(&mut self) -> io::Result<Point> {
+        Ok(Point {
+            x: self.f64()?,
+            y: self.f64()?,
+        })
+    }
+
+    fn color(&mut self) -> io::Result<RGBA> {
+        Ok(RGBA::new(self.f32()?, self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    fn cap(&mut self) -> io::Result<Cap> {
+        match self.u8()? {
+            0 => Ok(Cap::Butt),
+            1 => Ok(Cap::Square),
+            2 => Ok(Cap::Round),
+            cap => Err(invalid_data(format!("unknown cap {cap}"))),
+        }
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
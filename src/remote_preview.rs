@@ -0,0 +1,198 @@
+// remote preview over websocket
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageResult, RgbaImage};
+use tungstenite::{Message, WebSocket};
+
+use super::colors::RGBA;
+use super::scenes::Scene;
+
+/// How long a client thread blocks on reading before it checks for new frames.
+const CLIENT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The image format used to stream frames to remote clients.
+#[derive(Debug, Clone, Copy)]
+pub enum FrameEncoding {
+    /// Lossless PNG frames.
+    Png,
+    /// Lossy JPEG frames with the given quality (1-100).
+    Jpeg { quality: u8 },
+}
+
+/// A command sent by a remote client.
+///
+/// Commands are plain text messages of the form `<command> <args...>`.
+#[derive(Debug, Clone)]
+pub enum PreviewCommand {
+    /// `background <r> <g> <b> [a]`: change the background color of the scene.
+    SetBackground(RGBA),
+    /// Any other message, passed through to the application unchanged.
+    Custom(String),
+}
+
+impl FromStr for PreviewCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        match parts.next() {
+            Some("background") => {
+                let values = parts
+                    .map(|p| p.parse::<f32>().map_err(|e| format!("invalid color component `{}`: {}", p, e)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match values.as_slice() {
+                    [r, g, b] => Ok(PreviewCommand::SetBackground(RGBA::new(*r, *g, *b, 1.0))),
+                    [r, g, b, a] => Ok(PreviewCommand::SetBackground(RGBA::new(*r, *g, *b, *a))),
+                    _ => Err(format!("expected 3 or 4 color components, got {}", values.len())),
+                }
+            }
+            Some(_) => Ok(PreviewCommand::Custom(s.to_string())),
+            None => Err("empty command".to_string()),
+        }
+    }
+}
+
+impl PreviewCommand {
+    /// Apply the command to a scene. Custom commands are ignored.
+    pub fn apply<Backend>(&self, scene: &mut Scene<Backend>) {
+        match self {
+            PreviewCommand::SetBackground(color) => scene.background_color = *color,
+            PreviewCommand::Custom(_) => {}
+        }
+    }
+}
+
+/// A WebSocket server that streams rendered frames to remote clients and receives commands
+/// from them.
+///
+/// Every connected client receives the most recent frame passed to [`RemotePreviewServer::send_frame`];
+/// frames are dropped for clients that can't keep up. Text messages sent by clients are parsed into
+/// [`PreviewCommand`]s and can be collected with [`RemotePreviewServer::poll_commands`].
+pub struct RemotePreviewServer {
+    /// The address the server is listening on.
+    local_addr: SocketAddr,
+    /// Frame channels of all connected clients.
+    clients: Arc<Mutex<Vec<Sender<Arc<Vec<u8>>>>>>,
+    /// Commands received from all clients.
+    commands: Receiver<PreviewCommand>,
+    /// The encoding used for outgoing frames.
+    pub encoding: FrameEncoding,
+}
+
+impl RemotePreviewServer {
+    /// Start listening for WebSocket connections on the given address.
+    pub fn bind(addr: impl ToSocketAddrs, encoding: FrameEncoding) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let (command_tx, commands) = mpsc::channel();
+
+        let accept_clients = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (frame_tx, frame_rx) = mpsc::channel();
+                accept_clients.lock().unwrap().push(frame_tx);
+                let command_tx = command_tx.clone();
+                thread::spawn(move || serve_client(stream, frame_rx, command_tx));
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            clients,
+            commands,
+            encoding,
+        })
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The number of currently connected clients.
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Encode a rendered frame and send it to all connected clients.
+    pub fn send_frame(&self, frame: &RgbaImage) -> ImageResult<()> {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.is_empty() {
+            return Ok(());
+        }
+
+        let mut data = Vec::new();
+        match self.encoding {
+            FrameEncoding::Png => {
+                PngEncoder::new(&mut data).encode(frame.as_raw(), frame.width(), frame.height(), ColorType::Rgba8)?
+            }
+            FrameEncoding::Jpeg { quality } => JpegEncoder::new_with_quality(&mut data, quality).encode(
+                frame.as_raw(),
+                frame.width(),
+                frame.height(),
+                ColorType::Rgba8,
+            )?,
+        }
+
+        // drop clients whose connection has been closed
+        let data = Arc::new(data);
+        clients.retain(|client| client.send(data.clone()).is_ok());
+        Ok(())
+    }
+
+    /// Return all commands received since the last call.
+    pub fn poll_commands(&self) -> Vec<PreviewCommand> {
+        self.commands.try_iter().collect()
+    }
+}
+
+fn serve_client(stream: TcpStream, frames: Receiver<Arc<Vec<u8>>>, commands: Sender<PreviewCommand>) {
+    // the handshake blocks; the timeout only applies to polling the established connection
+    let mut socket: WebSocket<TcpStream> = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(_) => return,
+    };
+    if socket.get_ref().set_read_timeout(Some(CLIENT_POLL_INTERVAL)).is_err() {
+        return;
+    }
+
+    loop {
+        // only send the most recent frame if the client fell behind
+        let mut latest = None;
+        loop {
+            match frames.try_recv() {
+                Ok(frame) => latest = Some(frame),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return,
+            }
+        }
+        if let Some(frame) = latest {
+            if socket.send(Message::Binary(frame.as_ref().clone())).is_err() {
+                return;
+            }
+        }
+
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if let Ok(command) = text.parse() {
+                    if commands.send(command).is_err() {
+                        return;
+                    }
+                }
+            }
+            Ok(Message::Close(_)) => return,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => return,
+        }
+    }
+}
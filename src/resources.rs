@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// A kind of cached resource that can be limited by a [`ResourceBudget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// Images replaced by GPU textures, offscreen layer textures and cached image data (e.g.
+    /// gradient lookup textures), measured in bytes.
+    GpuImages,
+    /// Decoded bitmap glyphs, measured in glyphs.
+    Glyphs,
+    /// Prerendered scenes, measured in scenes.
+    PrerenderedScenes,
+}
+
+/// A callback that is notified when a budget is exceeded, before the least recently used
+/// resources are evicted.
+///
+/// The callback receives the kind of resource, the amount in use and the limit.
+#[derive(Clone)]
+pub struct BudgetCallback(pub Arc<BudgetCallbackFn>);
+
+/// The signature of a [`BudgetCallback`].
+pub type BudgetCallbackFn = dyn Fn(ResourceKind, usize, usize) + Send + Sync;
+
+impl BudgetCallback {
+    pub fn new(callback: impl Fn(ResourceKind, usize, usize) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+}
+
+impl std::fmt::Debug for BudgetCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BudgetCallback")
+    }
+}
+
+/// Limits for the resources a scene keeps cached, so that long-running sessions don't slowly
+/// exhaust (video) memory. Resources beyond a limit are evicted, least recently used first.
+///
+/// All limits are unlimited by default.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceBudget {
+    /// The maximum size of images kept on the GPU, in bytes (see [`ResourceKind::GpuImages`]).
+    /// Images drawn in the current frame are never evicted, and the textures of images the
+    /// application still holds are only freed once those images are dropped.
+    pub max_gpu_image_bytes: Option<usize>,
+    /// The maximum number of cached bitmap glyphs.
+    pub max_cached_glyphs: Option<usize>,
+    /// The maximum number of cached prerendered scenes.
+    pub max_prerendered_scenes: Option<usize>,
+    /// Called whenever a limit is exceeded.
+    pub on_exceeded: Option<BudgetCallback>,
+}
+
+impl ResourceBudget {
+    /// Set the maximum size of images kept on the GPU, in bytes.
+    pub fn with_max_gpu_image_bytes(mut self, bytes: usize) -> Self {
+        self.max_gpu_image_bytes = Some(bytes);
+        self
+    }
+
+    /// Set the maximum number of cached bitmap glyphs.
+    pub fn with_max_cached_glyphs(mut self, glyphs: usize) -> Self {
+        self.max_cached_glyphs = Some(glyphs);
+        self
+    }
+
+    /// Set the maximum number of cached prerendered scenes.
+    pub fn with_max_prerendered_scenes(mut self, scenes: usize) -> Self {
+        self.max_prerendered_scenes = Some(scenes);
+        self
+    }
+
+    /// Set the callback that is called whenever a limit is exceeded.
+    pub fn with_callback(mut self, callback: impl Fn(ResourceKind, usize, usize) + Send + Sync + 'static) -> Self {
+        self.on_exceeded = Some(BudgetCallback::new(callback));
+        self
+    }
+
+    /// The limit for a kind of resource.
+    pub fn limit(&self, kind: ResourceKind) -> Option<usize> {
+        match kind {
+            ResourceKind::GpuImages => self.max_gpu_image_bytes,
+            ResourceKind::Glyphs => self.max_cached_glyphs,
+            ResourceKind::PrerenderedScenes => self.max_prerendered_scenes,
+        }
+    }
+
+    /// Evict the least recently used entries of a cache until it fits the limit for `kind`,
+    /// notifying the callback if the limit was exceeded. Returns the evicted entries.
+    pub fn enforce<K: Eq + Hash + Clone, V>(&self, kind: ResourceKind, cache: &mut LruCache<K, V>) -> Vec<(K, V)> {
+        self.enforce_shared(kind, cache, 0, u64::MAX)
+    }
+
+    /// Like [`enforce`](Self::enforce) for a cache that shares the limit with other resources
+    /// of the same kind costing `other`. Entries used at or after `since` (see
+    /// [`LruCache::clock`]) are kept, e.g. because they are needed for the current frame and
+    /// would be re-created right away.
+    pub fn enforce_shared<K: Eq + Hash + Clone, V>(
+        &self,
+        kind: ResourceKind,
+        cache: &mut LruCache<K, V>,
+        other: usize,
+        since: u64,
+    ) -> Vec<(K, V)> {
+        match self.limit(kind) {
+            Some(limit) if cache.cost() + other > limit => {
+                if let Some(callback) = &self.on_exceeded {
+                    (callback.0)(kind, cache.cost() + other, limit);
+                }
+                cache.evict_unused_to(limit.saturating_sub(other), since)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A cache that keeps track of the total cost of its entries and the order they were used in.
+#[derive(Debug, Clone)]
+pub struct LruCache<K, V> {
+    /// The entries with their cost and the time they were last used.
+    entries: HashMap<K, (V, usize, u64)>,
+    cost: usize,
+    clock: u64,
+    /// The time of the last call to [`LruCache::begin_frame`].
+    frame_start: u64,
+}
+
+impl<K, V> Default for LruCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            cost: 0,
+            clock: 0,
+            frame_start: 0,
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The total cost of all entries.
+    pub fn cost(&self) -> usize {
+        self.cost
+    }
+
+    /// The time of the last use of the cache. Entries used later get a later time.
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    /// All entries, in no particular order (without marking them as used).
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(key, (value, _, _))| (key, value))
+    }
+
+    /// Whether the cache contains an entry for the key (without marking it as used).
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Get an entry and mark it as used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|entry| {
+            entry.2 = clock;
+            &entry.0
+        })
+    }
+
+    /// Insert or replace an entry and mark it as used.
+    pub fn insert(&mut self, key: K, value: V, cost: usize) {
+        self.clock += 1;
+        if let Some((_, old_cost, _)) = self.entries.insert(key, (value, cost, self.clock)) {
+            self.cost -= old_cost;
+        }
+        self.cost += cost;
+    }
+
+    /// Get an entry, creating it if it doesn't exist, and mark it as used.
+    pub fn get_or_insert_with(&mut self, key: K, cost: usize, value: impl FnOnce() -> V) -> &V {
+        self.clock += 1;
+        let clock = self.clock;
+        let entry = self.entries.entry(key).or_insert_with(|| {
+            self.cost += cost;
+            (value(), cost, clock)
+        });
+        entry.2 = clock;
+        &entry.0
+    }
+
+    /// Remove an entry.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(value, cost, _)| {
+            self.cost -= cost;
+            value
+        })
+    }
+
+    /// Remove all entries for which `predicate` returns `true`. Returns the removed entries.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&K, &V) -> bool) -> Vec<(K, V)> {
+        let keys = self
+            .entries
+            .iter()
+            .filter(|(key, (value, _, _))| predicate(key, value))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter_map(|key| self.remove(&key).map(|value| (key, value)))
+            .collect()
+    }
+
+    /// Remove the least recently used entries until the total cost is at most `max_cost`.
+    pub fn evict_to(&mut self, max_cost: usize) -> Vec<(K, V)> {
+        self.evict_unused_to(max_cost, u64::MAX)
+    }
+
+    /// Remove the least recently used entries that weren't used at or after `since` until the
+    /// total cost is at most `max_cost`.
+    pub fn evict_unused_to(&mut self, max_cost: usize, since: u64) -> Vec<(K, V)> {
+        if self.cost <= max_cost {
+            return Vec::new();
+        }
+        let mut candidates = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.2 < since)
+            .map(|(key, entry)| (entry.2, key.clone()))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|(used, _)| *used);
+
+        let mut evicted = Vec::new();
+        for (_, key) in candidates {
+            if self.cost <= max_cost {
+                break;
+            }
+            if let Some(value) = self.remove(&key) {
+                evicted.push((key, value));
+            }
+        }
+        evicted
+    }
+
+    /// Remove the entries that weren't used since the last call, e.g. at the start of a frame
+    /// to drop what the previous frame didn't use. Returns the removed entries.
+    pub fn begin_frame(&mut self) -> Vec<(K, V)> {
+        let evicted = self.evict_unused_to(0, self.frame_start);
+        self.clock += 1;
+        self.frame_start = self.clock;
+        evicted
+    }
+
+    /// Remove all entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.cost = 0;
+    }
+}
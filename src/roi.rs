@@ -0,0 +1,156 @@
+// regions of interest
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::Duration;
+
+use kurbo::Shape as _;
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::brushes::Brush;
+use super::colors::RGBA;
+use super::scenes::Scene;
+use super::shapes::{Path, Point, Shape};
+use super::styles::{StrokeOptions, Style};
+use super::Drawable;
+
+/// A named region of the scene, e.g. the area of a stimulus that gaze samples are assigned to.
+#[derive(Debug, Clone)]
+pub struct Roi {
+    pub name: String,
+    /// The outline of the region.
+    pub shape: Path,
+    /// The transform of the shape (in scene coordinates).
+    pub transform: Affine,
+}
+
+impl Roi {
+    /// Whether a point (in scene coordinates) is inside the region.
+    pub fn contains(&self, point: Point) -> bool {
+        if self.transform.determinant() == 0.0 {
+            return false;
+        }
+        let local = self.transform.inverse().transform_point(point);
+        self.shape.to_path().contains(kurbo::Point::new(local.x, local.y))
+    }
+}
+
+/// A set of regions of interest that can be hit-tested against gaze or pointer samples and
+/// optionally outlined on screen.
+#[derive(Debug, Clone)]
+pub struct RoiSet {
+    /// The regions, in the order they were added.
+    pub regions: Vec<Roi>,
+    /// Whether the outlines of the regions are drawn.
+    pub visible: bool,
+    /// The color of the outlines.
+    pub color: RGBA,
+    /// The width of the outlines, in device pixels.
+    pub line_width: f64,
+}
+
+impl RoiSet {
+    /// Create an empty set whose outlines are hidden.
+    pub fn new() -> Self {
+        Self {
+            regions: Vec::new(),
+            visible: false,
+            color: RGBA::new(1.0, 0.0, 1.0, 0.8),
+            line_width: 2.0,
+        }
+    }
+
+    /// Add a region. A region with the same name is replaced.
+    pub fn add(&mut self, name: impl Into<String>, shape: &impl Shape, transform: Affine) {
+        let roi = Roi {
+            name: name.into(),
+            shape: shape.to_path().into(),
+            transform,
+        };
+        match self.regions.iter_mut().find(|region| region.name == roi.name) {
+            Some(region) => *region = roi,
+            None => self.regions.push(roi),
+        }
+    }
+
+    /// Remove a region.
+    pub fn remove(&mut self, name: &str) -> Option<Roi> {
+        let index = self.regions.iter().position(|region| region.name == name)?;
+        Some(self.regions.remove(index))
+    }
+
+    /// Show or hide the outlines of the regions.
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    /// The names of the regions that contain a point (in scene coordinates).
+    pub fn hit_test(&self, point: Point) -> Vec<&str> {
+        self.regions
+            .iter()
+            .filter(|region| region.contains(point))
+            .map(|region| region.name.as_str())
+            .collect()
+    }
+}
+
+impl Default for RoiSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for RoiSet {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+        let style = Style::Stroke(StrokeOptions::new(scene.pixels_to_units(self.line_width)));
+        let brush = Brush::Solid(self.color);
+        for region in &self.regions {
+            let transform = region.transform * scene.view_transform();
+            scene
+                .backend
+                .draw_shape(&region.shape, &style, &brush, transform, None);
+        }
+    }
+}
+
+/// Writes the regions hit by samples to a CSV file, one row per sample, e.g. next to a
+/// [`SessionRecorder`](crate::recording::SessionRecorder) file with the same frame indices.
+///
+/// The columns are `frame`, `timestamp` (in seconds), `x`, `y` and `rois`, the names of the
+/// regions containing the sample separated by `;`.
+pub struct RoiRecorder {
+    writer: BufWriter<File>,
+}
+
+impl RoiRecorder {
+    /// Create a new file at the given path, overwriting any existing file.
+    pub fn create(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "frame,timestamp,x,y,rois")?;
+        Ok(Self { writer })
+    }
+
+    /// Hit-test a sample (in scene coordinates) and record the result.
+    pub fn record(&mut self, frame: u64, timestamp: Duration, point: Point, regions: &RoiSet) -> io::Result<()> {
+        // names are quoted, so they can contain commas
+        let hits = regions.hit_test(point).join(";").replace('"', "\"\"");
+        writeln!(
+            self.writer,
+            "{},{},{},{},\"{}\"",
+            frame,
+            timestamp.as_secs_f64(),
+            point.x,
+            point.y,
+            hits
+        )
+    }
+
+    /// Flush all pending rows to disk and close the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -1,23 +1,436 @@
-use super::affine::Affine;
-use super::colors;
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
 
-use super::styles::CompositeMode;
+use super::accessibility::AccessTree;
+use super::affine::{Affine, Fit};
+use super::backend::RenderBackend;
+use super::camera::Camera;
+use super::colors::{self, ColorManagement};
+use super::quality::{QualityGovernor, QualitySettings};
+use super::brushes::{Brush, Image};
+use super::shapes::{Point, Rectangle, Shape};
+use super::styles::{CompositeMode, FillStyle, Style};
 use super::styles::MixMode;
+use super::Drawable;
+
+/// The clock of a scene, advanced by [`Scene::begin_frame`].
+///
+/// Animated content (e.g. noise or drifting brushes) should derive its state from this clock
+/// rather than from wall-clock time, so it stays in sync with the presented frames.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameInfo {
+    /// The index of the current frame, starting at 0.
+    pub index: u64,
+    /// The time of the current frame (e.g. its expected presentation time), relative to an
+    /// application-defined start.
+    pub time: Duration,
+    /// The time since the previous frame.
+    pub delta: Duration,
+}
+
+/// How the content drawn as a mask (see [`Scene::with_mask`]) affects the masked content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskKind {
+    /// Show the content where the mask is opaque.
+    Alpha,
+    /// Show the content where the mask is bright: the luminance of the mask (times its alpha)
+    /// is used as its alpha, so black and transparent areas hide the content and gray areas
+    /// fade it.
+    Luminance,
+}
+
+/// How the target is cleared before a scene is rendered onto it.
+#[derive(Debug, Clone, Default)]
+pub enum Clear {
+    /// Fill the target with the background color of the scene.
+    #[default]
+    Color,
+    /// Fill the target with a brush (in scene coordinates), e.g. a gradient.
+    Brush(Box<Brush>),
+    /// Keep the previously rendered frame and draw on top of it, e.g. to let content accumulate
+    /// into a trail without drawing its whole history every frame. The first frame is cleared
+    /// with the background color. Only supported by renderers that keep their frames (e.g.
+    /// [`VelloRenderer`](crate::vello_backend::VelloRenderer)); others clear with the background
+    /// color.
+    Preserve,
+}
 
 // A Scene that can be rendered onto.
 #[derive(Debug, Clone)]
 pub struct Scene<Backend> {
     /// The background color of the scene.
     pub background_color: colors::RGBA,
+    /// How the target is cleared before the scene is rendered.
+    pub clear: Clear,
     pub width: u32,
     pub height: u32,
+    /// Snap axis-aligned edges and strokes of all geoms to the pixel grid after the global
+    /// transform, so that hairlines and borders render crisp.
+    pub pixel_snapping: bool,
+    /// Skip geoms whose bounding box lies entirely outside of the viewport extended by this
+    /// margin (in device pixels), e.g. when the camera shows a small part of a large world.
+    /// `None` disables culling. Content that is cached (e.g. by a [`Group`](crate::group::Group)
+    /// with caching) is never culled, since it may be drawn again with another transform.
+    pub cull_margin: Option<f64>,
+    /// The camera (pan, zoom, rotation) applied on top of the global transform.
+    pub camera: Camera,
+    /// The current frame index and time.
+    pub frame: FrameInfo,
+    /// Number of frames started so far.
+    frames_started: u64,
+    /// The quality settings used for drawing and rendering.
+    pub quality: QualitySettings,
+    /// An optional governor that adapts the quality to the frame times (see [`Scene::set_governor`]).
+    pub governor: Option<QualityGovernor>,
+    /// How colors are converted and blended (see [`Scene::set_color_management`]).
+    pub color_management: ColorManagement,
+    /// The accessibility tree of the current frame, built by the
+    /// [`Accessible`](crate::accessibility::Accessible) drawables drawn into the scene.
+    pub accessibility: AccessTree,
+    /// The global transforms to restore when the layers started with [`SceneTrait::start_layer`]
+    /// end.
+    layer_transforms: Vec<Affine>,
+    /// The transform from the device space of a child scene to the device pixels of the scene
+    /// it ends up in (see [`Scene::child_scene`]), the identity for other scenes.
+    outer_transform: Affine,
+    /// Whether the content is recorded to be drawn again later, so that nothing may be culled.
+    recording_cache: bool,
     // Backend specifics data.
     pub backend: Backend,
 }
 
+impl<Backend> Scene<Backend> {
+    /// Create a scene with the given backend.
+    pub(crate) fn with_backend(background_color: colors::RGBA, width: u32, height: u32, backend: Backend) -> Self {
+        Self {
+            background_color,
+            clear: Clear::Color,
+            width,
+            height,
+            pixel_snapping: false,
+            cull_margin: None,
+            camera: Camera::default(),
+            frame: FrameInfo::default(),
+            frames_started: 0,
+            quality: QualitySettings::default(),
+            governor: None,
+            color_management: ColorManagement::default(),
+            accessibility: AccessTree::default(),
+            layer_transforms: Vec::new(),
+            outer_transform: Affine::identity(),
+            recording_cache: false,
+            backend,
+        }
+    }
+
+    /// draw a renderable object.
+    pub fn draw(&mut self, mut object: impl Drawable<Backend>) {
+        // Draw the object.
+        object.draw(self);
+    }
+}
+
+impl<Backend: RenderBackend> Scene<Backend> {
+    /// Discard the content of the previous frame and advance the frame clock to `time`.
+    pub fn begin_frame(&mut self, time: Duration) {
+        self.backend.begin_scene();
+        self.accessibility.clear();
+        self.frame = FrameInfo {
+            index: self.frames_started,
+            time,
+            delta: if self.frames_started == 0 { Duration::ZERO } else { time.saturating_sub(self.frame.time) },
+        };
+        self.frames_started += 1;
+
+        if let Some(governor) = &mut self.governor {
+            if self.frame.index > 0 && governor.record_frame(self.frame.delta).is_some() {
+                let quality = governor.settings();
+                self.set_quality(quality);
+            }
+        }
+    }
+
+    /// Set how the target is cleared before the scene is rendered.
+    pub fn set_clear(&mut self, clear: Clear) {
+        self.clear = clear;
+    }
+
+    /// The content of the scene drawn on top of its clear brush, or `None` if the scene is not
+    /// cleared with a brush.
+    pub(crate) fn content_with_clear_brush(&self) -> Option<Backend> {
+        let Clear::Brush(brush) = &self.clear else {
+            return None;
+        };
+        let bounds = Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: self.width as f64,
+                y: self.height as f64,
+            },
+        };
+        let mut backend = self.backend.create_child();
+        backend.draw_shape(
+            &bounds,
+            &Style::Fill(FillStyle::NonZero),
+            brush,
+            Affine::identity(),
+            Some(self.view_transform()),
+        );
+        backend.append(&self.backend, Affine::identity());
+        Some(backend)
+    }
+
+    /// Set the quality settings used for drawing and rendering.
+    pub fn set_quality(&mut self, quality: QualitySettings) {
+        self.quality = quality;
+        self.backend.set_quality(&quality);
+    }
+
+    /// Set how colors are converted and blended when drawing and rendering. Content that was
+    /// already drawn keeps the previous color management, so set it before drawing a frame.
+    pub fn set_color_management(&mut self, color_management: ColorManagement) {
+        self.color_management = color_management;
+        self.backend.set_color_management(&color_management);
+    }
+
+    /// Let a governor adapt the quality to the frame times measured by [`Scene::begin_frame`],
+    /// or remove it with `None` (the current quality settings are kept).
+    pub fn set_governor(&mut self, governor: Option<QualityGovernor>) {
+        if let Some(governor) = &governor {
+            self.set_quality(governor.settings());
+        }
+        self.governor = governor;
+    }
+
+    /// The transform from scene coordinates to device pixels (camera and global transform).
+    pub fn view_transform(&self) -> Affine {
+        self.camera.transform() * self.backend.global_transform()
+    }
+
+    /// The transform from scene coordinates to device pixels, without the camera.
+    pub fn global_transform(&self) -> Affine {
+        self.backend.global_transform()
+    }
+
+    /// Replace the transform from scene coordinates to device pixels.
+    pub fn set_global_transform(&mut self, transform: Affine) {
+        self.backend.set_global_transform(transform);
+    }
+
+    /// Apply `transform` (in scene coordinates) before the current global transform until the
+    /// returned guard is dropped.
+    ///
+    /// The guard dereferences to the scene, so everything drawn through it is transformed.
+    pub fn push_global_transform(&mut self, transform: Affine) -> GlobalTransformGuard<'_, Backend> {
+        let previous = self.backend.global_transform();
+        self.backend.set_global_transform(transform * previous);
+        GlobalTransformGuard { scene: self, previous }
+    }
+
+    /// Convert a point in device pixels to scene coordinates.
+    pub fn screen_to_world(&self, point: Point) -> Point {
+        self.view_transform().inverse().transform_point(point)
+    }
+
+    /// Convert a point in scene coordinates to device pixels.
+    pub fn world_to_screen(&self, point: Point) -> Point {
+        self.view_transform().transform_point(point)
+    }
+
+    /// Convert a length in device pixels to scene units under the current view transform, e.g.
+    /// to keep hairline strokes or fixation dots the same size on screen while zooming.
+    ///
+    /// For transforms that scale unevenly, this uses the geometric mean of the scale factors.
+    pub fn pixels_to_units(&self, pixels: f64) -> f64 {
+        pixels / self.units_to_pixels(1.0)
+    }
+
+    /// Convert a length in scene units to device pixels under the current view transform.
+    /// See [`Scene::pixels_to_units`].
+    pub fn units_to_pixels(&self, units: f64) -> f64 {
+        units * self.view_transform().determinant().abs().sqrt()
+    }
+
+    /// The transform that fits a drawable into the visible part of the scene (centered and scaled
+    /// according to `fit`), e.g. for SVGs or images of unknown size. Apply it after the transform
+    /// of the drawable, e.g. `geom.transform = geom.transform * fit`.
+    ///
+    /// Returns `None` for drawables without [bounds](Drawable::bounds).
+    pub fn fit(&self, drawable: &impl Drawable<Backend>, fit: Fit) -> Option<Affine> {
+        let viewport = kurbo::Rect::new(0.0, 0.0, self.width as f64, self.height as f64);
+        let viewport = kurbo::Affine::from(self.view_transform().inverse()).transform_rect_bbox(viewport);
+        let target = Rectangle {
+            a: Point {
+                x: viewport.x0,
+                y: viewport.y0,
+            },
+            b: Point {
+                x: viewport.x1,
+                y: viewport.y1,
+            },
+        };
+        Some(Affine::fit(drawable.bounds()?, target, fit))
+    }
+
+    /// Draw content masked by the alpha channel of an image, e.g. an alpha-only mask.
+    ///
+    /// The top left corner of the mask is placed at the origin of `mask_transform`; content
+    /// outside of the mask image is hidden.
+    pub fn draw_masked(&mut self, mask: &Image, mask_transform: Affine, content: impl FnOnce(&mut Scene<Backend>)) {
+        let transform = mask_transform * self.view_transform();
+        let bounds = Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: mask.width as f64,
+                y: mask.height as f64,
+            },
+        };
+
+        self.backend
+            .push_layer(MixMode::Normal, CompositeMode::SourceOver, &bounds, transform, 1.0);
+        self.backend.draw_image(mask, transform);
+        // keep the content only where the mask is opaque
+        self.backend
+            .push_layer(MixMode::Normal, CompositeMode::SourceIn, &bounds, transform, 1.0);
+        content(self);
+        self.backend.pop_layer();
+        self.backend.pop_layer();
+    }
+
+    /// Draw content masked by other content, e.g. an aperture or a window drawn as a shape.
+    ///
+    /// Everything drawn by `mask` is only used as the mask: with [`MaskKind::Alpha`], the content
+    /// is shown where the mask is opaque. With [`MaskKind::Luminance`], the content is shown
+    /// where the mask is bright, so that grayscale masks scale its opacity.
+    pub fn with_mask(
+        &mut self,
+        kind: MaskKind,
+        mask: impl FnOnce(&mut Scene<Backend>),
+        content: impl FnOnce(&mut Scene<Backend>),
+    ) {
+        // the layers cover the whole scene, in device pixels
+        let bounds = Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: self.width as f64,
+                y: self.height as f64,
+            },
+        };
+
+        self.backend
+            .push_layer(MixMode::Normal, CompositeMode::SourceOver, &bounds, Affine::identity(), 1.0);
+        match kind {
+            MaskKind::Alpha => {
+                mask(self);
+                self.backend
+                    .push_layer(MixMode::Normal, CompositeMode::SourceIn, &bounds, Affine::identity(), 1.0);
+                content(self);
+            }
+            MaskKind::Luminance => {
+                content(self);
+                // keep the content only where the mask is bright
+                self.backend
+                    .push_layer(MixMode::Normal, CompositeMode::DestinationIn, &bounds, Affine::identity(), 1.0);
+                self.draw_luminance_mask(mask);
+            }
+        }
+        self.backend.pop_layer();
+        self.backend.pop_layer();
+    }
+
+    /// Draw content with its luminance (times its alpha) converted to alpha, to be used as a
+    /// mask by the enclosing layer.
+    pub(crate) fn draw_luminance_mask(&mut self, mask: impl FnOnce(&mut Scene<Backend>)) {
+        let mut mask_scene = self.child_scene(Affine::identity());
+        mask_scene.camera = self.camera;
+        mask_scene.backend.set_global_transform(self.backend.global_transform());
+        mask(&mut mask_scene);
+        let (width, height) = (self.width, self.height);
+        self.backend
+            .append_luminance_mask(&mask_scene.backend, Affine::identity(), width, height);
+    }
+
+    /// The transform from scene coordinates to the device pixels the content ends up in, which
+    /// differs from [`Scene::view_transform`] for child scenes, e.g. to cull content.
+    pub(crate) fn device_transform(&self) -> Affine {
+        self.view_transform() * self.outer_transform
+    }
+
+    /// Whether the content is recorded to be drawn again later (see [`Scene::record_for_cache`]),
+    /// so that nothing may be culled.
+    pub(crate) fn recording_cache(&self) -> bool {
+        self.recording_cache
+    }
+
+    /// Record the content of the scene to be drawn again later, possibly with another transform,
+    /// which disables culling.
+    pub(crate) fn record_for_cache(&mut self) {
+        self.recording_cache = true;
+    }
+
+    /// Create an empty scene whose content is recorded in untransformed scene coordinates, to be
+    /// appended to this scene later with `transform`.
+    pub(crate) fn child_scene(&self, transform: Affine) -> Scene<Backend> {
+        Scene {
+            background_color: colors::RGBA::TRANSPARENT,
+            clear: Clear::Color,
+            width: self.width,
+            height: self.height,
+            pixel_snapping: self.pixel_snapping,
+            // content is culled with the device transform, which includes the transform the child
+            // scene is appended with
+            cull_margin: self.cull_margin,
+            camera: Camera::default(),
+            frame: self.frame,
+            frames_started: self.frames_started,
+            quality: self.quality,
+            governor: None,
+            color_management: self.color_management,
+            // the nodes are added to this scene when the child scene is appended
+            accessibility: AccessTree::default(),
+            layer_transforms: Vec::new(),
+            outer_transform: transform * self.outer_transform,
+            recording_cache: self.recording_cache,
+            backend: self.backend.create_child(),
+        }
+    }
+}
+
+/// Restores the previous global transform of a scene when dropped.
+///
+/// Returned by [`Scene::push_global_transform`].
+pub struct GlobalTransformGuard<'a, Backend: RenderBackend> {
+    scene: &'a mut Scene<Backend>,
+    previous: Affine,
+}
+
+impl<Backend: RenderBackend> Deref for GlobalTransformGuard<'_, Backend> {
+    type Target = Scene<Backend>;
+
+    fn deref(&self) -> &Self::Target {
+        self.scene
+    }
+}
+
+impl<Backend: RenderBackend> DerefMut for GlobalTransformGuard<'_, Backend> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.scene
+    }
+}
+
+impl<Backend: RenderBackend> Drop for GlobalTransformGuard<'_, Backend> {
+    fn drop(&mut self) {
+        self.scene.backend.set_global_transform(self.previous);
+    }
+}
+
 pub trait SceneTrait<Backend, ClipShape: Clone> {
     fn scene_mut(&mut self) -> &mut Scene<Backend>;
     fn scene(&self) -> &Scene<Backend>;
+    /// Start a layer that is clipped to a shape and composited with the given modes when it ends.
+    ///
+    /// If a layer transform is given, it is applied (in scene coordinates) to the clip and to
+    /// everything drawn until the layer ends, so that the layer moves as a unit.
     fn start_layer(
         &mut self,
         mix_mode: MixMode,
@@ -60,3 +473,44 @@ pub trait SceneTrait<Backend, ClipShape: Clone> {
         self.end_layer();
     }
 }
+
+impl<Backend: RenderBackend, ClipShape: Shape> SceneTrait<Backend, ClipShape> for Scene<Backend> {
+    fn scene_mut(&mut self) -> &mut Scene<Backend> {
+        self
+    }
+
+    fn scene(&self) -> &Scene<Backend> {
+        self
+    }
+
+    fn start_layer(
+        &mut self,
+        mix_mode: MixMode,
+        composite_mode: CompositeMode,
+        clip: ClipShape,
+        clip_transform: Affine,
+        layer_transform: Option<Affine>,
+        alpha: f32,
+    ) {
+        let previous = self.backend.global_transform();
+        if let Some(layer_transform) = layer_transform {
+            // content is drawn with the camera applied before the global transform, so the layer
+            // transform is moved in front of the camera
+            let camera = self.camera.transform();
+            self.backend
+                .set_global_transform(camera.inverse() * layer_transform * camera * previous);
+        }
+        self.layer_transforms.push(previous);
+        let clip_transform = clip_transform * self.view_transform();
+
+        self.backend
+            .push_layer(mix_mode, composite_mode, &clip, clip_transform, alpha);
+    }
+
+    fn end_layer(&mut self) {
+        self.backend.pop_layer();
+        if let Some(previous) = self.layer_transforms.pop() {
+            self.backend.set_global_transform(previous);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// changes queued against future frames
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::scenes::FrameInfo;
+
+/// When a scheduled change is executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// At the start of the frame with this index (see [`FrameInfo::index`]).
+    Frame(u64),
+    /// At the start of the first frame presented at or after this time (see [`FrameInfo::time`]).
+    ///
+    /// Frame times jitter around the vsync grid, so a frame counts as presented at the time if it
+    /// is less than half a frame early.
+    Time(Duration),
+}
+
+impl Trigger {
+    fn is_due(&self, frame: &FrameInfo) -> bool {
+        match *self {
+            Trigger::Frame(index) => frame.index >= index,
+            Trigger::Time(time) => frame.time + frame.delta / 2 >= time,
+        }
+    }
+
+    /// Whether the trigger should have fired on an earlier frame.
+    fn is_late(&self, frame: &FrameInfo) -> bool {
+        match *self {
+            Trigger::Frame(index) => frame.index > index,
+            Trigger::Time(time) => frame.time > time + frame.delta / 2,
+        }
+    }
+}
+
+/// Identifies a change queued on a [`Scheduler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScheduleId(u64);
+
+/// A change that was executed by [`Scheduler::run`].
+#[derive(Debug, Clone, Copy)]
+pub struct Executed {
+    pub id: ScheduleId,
+    pub trigger: Trigger,
+    /// The frame the change was executed at.
+    pub frame: FrameInfo,
+    /// Whether the change was executed after its trigger, e.g. because it was queued too late or
+    /// frames were dropped.
+    pub late: bool,
+}
+
+/// The signature of a scheduled change.
+pub type ScheduledChange<T> = dyn FnOnce(&mut T) + Send;
+
+/// A queue of changes to a render state `T` (e.g. the scene to show) that are executed at
+/// future frames, e.g. "swap to scene B at frame 601".
+///
+/// Application threads queue changes on clones of the scheduler, and the render loop executes
+/// the changes that are due with [`Scheduler::run`] right after [`Scene::begin_frame`], so
+/// changes always take effect with a whole frame and never race with drawing.
+///
+/// [`Scene::begin_frame`]: crate::scenes::Scene::begin_frame
+pub struct Scheduler<T> {
+    queue: Arc<Mutex<Queue<T>>>,
+}
+
+struct Queue<T> {
+    next_id: u64,
+    changes: Vec<(ScheduleId, Trigger, Box<ScheduledChange<T>>)>,
+}
+
+impl<T> Scheduler<T> {
+    pub fn new() -> Self {
+        Self {
+            queue: Arc::new(Mutex::new(Queue {
+                next_id: 0,
+                changes: Vec::new(),
+            })),
+        }
+    }
+
+    /// Queue a change. Changes that are due at the same frame are executed in the order they were
+    /// queued.
+    pub fn schedule(&self, trigger: Trigger, change: impl FnOnce(&mut T) + Send + 'static) -> ScheduleId {
+        let mut queue = self.queue.lock().unwrap();
+        let id = ScheduleId(queue.next_id);
+        queue.next_id += 1;
+        queue.changes.push((id, trigger, Box::new(change)));
+        id
+    }
+
+    /// Queue a change for the frame with the given index.
+    pub fn at_frame(&self, index: u64, change: impl FnOnce(&mut T) + Send + 'static) -> ScheduleId {
+        self.schedule(Trigger::Frame(index), change)
+    }
+
+    /// Queue a change for the first frame presented at the given time.
+    pub fn at_time(&self, time: Duration, change: impl FnOnce(&mut T) + Send + 'static) -> ScheduleId {
+        self.schedule(Trigger::Time(time), change)
+    }
+
+    /// Remove a change that was not executed yet. Returns whether it was still queued.
+    pub fn cancel(&self, id: ScheduleId) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        let count = queue.changes.len();
+        queue.changes.retain(|(queued, _, _)| *queued != id);
+        queue.changes.len() != count
+    }
+
+    /// Remove all changes that were not executed yet.
+    pub fn clear(&self) {
+        self.queue.lock().unwrap().changes.clear();
+    }
+
+    /// The number of changes that were not executed yet.
+    pub fn pending(&self) -> usize {
+        self.queue.lock().unwrap().changes.len()
+    }
+
+    /// Execute the changes that are due at a frame, in the order they were queued. Call it in the
+    /// render loop once per frame, after the frame was started and before it is drawn.
+    ///
+    /// Changes may queue further changes, which are executed from the next frame on.
+    pub fn run(&self, frame: &FrameInfo, target: &mut T) -> Vec<Executed> {
+        // take the due changes out first, so that the lock is not held while they run
+        let due = {
+            let mut queue = self.queue.lock().unwrap();
+            let (due, pending) = std::mem::take(&mut queue.changes)
+                .into_iter()
+                .partition::<Vec<_>, _>(|(_, trigger, _)| trigger.is_due(frame));
+            queue.changes = pending;
+            due
+        };
+
+        due.into_iter()
+            .map(|(id, trigger, change)| {
+                change(target);
+                Executed {
+                    id,
+                    trigger,
+                    frame: *frame,
+                    late: trigger.is_late(frame),
+                }
+            })
+            .collect()
+    }
+}
+
+impl<T> Clone for Scheduler<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T> Default for Scheduler<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> std::fmt::Debug for Scheduler<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scheduler").field("pending", &self.pending()).finish()
+    }
+}
@@ -1,10 +1,74 @@
+use super::styles::StrokeOptions;
+
+/// Tolerance (in scene units) used when converting curved shapes into Bézier paths.
+pub const PATH_TOLERANCE: f64 = 0.1;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
 }
 
-pub trait Shape: Clone {}
+pub trait Shape: Clone {
+    /// The outline of the shape as a Bézier path.
+    fn to_path(&self) -> kurbo::BezPath;
+
+    /// The shape as an axis-aligned rectangle, if it is one.
+    fn as_rect(&self) -> Option<Rectangle> {
+        None
+    }
+
+    /// The axis-aligned bounding box of the outline.
+    fn bounding_box(&self) -> Rectangle {
+        let bounds = kurbo::Shape::bounding_box(&self.to_path());
+        Rectangle {
+            a: Point {
+                x: bounds.x0,
+                y: bounds.y0,
+            },
+            b: Point {
+                x: bounds.x1,
+                y: bounds.y1,
+            },
+        }
+    }
+
+    /// Whether the shape encloses an area and can be filled. Shapes that can't are stroke-only.
+    fn is_fillable(&self) -> bool {
+        true
+    }
+
+    /// The filled outline covered by stroking the shape with the given options.
+    fn to_stroke_outline(&self, options: &StrokeOptions) -> Path {
+        let stroke: kurbo::Stroke = options.clone().into();
+        let path = self.to_path();
+        kurbo::stroke(
+            path.elements().iter().copied(),
+            &stroke,
+            &kurbo::StrokeOpts::default(),
+            PATH_TOLERANCE,
+        )
+        .into()
+    }
+}
+
+/// A shape that encloses an area, so that [`Geom::fill`](crate::geoms::Geom::fill) can fill it.
+///
+/// Stroke-only shapes like [`Line`] and [`Polyline`] don't implement it, so filling them is
+/// rejected when compiling:
+///
+/// ```compile_fail
+/// use renderer::colors::RGBA;
+/// use renderer::geoms::Geom;
+/// use renderer::shapes::{Line, Point};
+///
+/// let line = Line { start: Point { x: 0.0, y: 0.0 }, end: Point { x: 10.0, y: 0.0 } };
+/// Geom::fill(line, RGBA::WHITE);
+/// ```
+///
+/// Code that is generic over [`Shape`] can use [`Geom::try_fill`](crate::geoms::Geom::try_fill)
+/// instead, which checks [`Shape::is_fillable`] at runtime.
+pub trait Fillable: Shape {}
 
 #[derive(Debug, Clone)]
 pub struct Circle {
@@ -12,6 +76,17 @@ pub struct Circle {
     pub radius: f64,
 }
 
+#[derive(Debug, Clone)]
+pub struct Ellipse {
+    pub center: Point,
+    /// Radius along the x axis (before rotation).
+    pub radius_x: f64,
+    /// Radius along the y axis (before rotation).
+    pub radius_y: f64,
+    /// Rotation around the center, in radians.
+    pub rotation: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct Rectangle {
     pub a: Point,
@@ -25,6 +100,451 @@ pub struct RoundedRectangle {
     pub radius: f64,
 }
 
-impl Shape for Circle {}
-impl Shape for Rectangle {}
-impl Shape for RoundedRectangle {}
+impl Shape for Circle {
+    fn to_path(&self) -> kurbo::BezPath {
+        kurbo::Shape::to_path(&kurbo::Circle::new(self.center, self.radius), PATH_TOLERANCE)
+    }
+}
+
+impl Fillable for Circle {}
+
+/// A circular arc. Filling an arc fills the area between the arc and its chord; stroke it for
+/// e.g. progress indicators.
+#[derive(Debug, Clone)]
+pub struct Arc {
+    pub center: Point,
+    pub radius: f64,
+    /// Angle of the start point in radians, measured from the positive x axis towards positive y.
+    pub start_angle: f64,
+    /// Angle covered by the arc in radians (negative values sweep in the opposite direction).
+    pub sweep: f64,
+}
+
+/// A ring between two concentric circles, e.g. for annulus-shaped apertures.
+///
+/// The inner circle winds in the opposite direction of the outer one, so the hole is left
+/// empty with both the even-odd and the non-zero fill rule.
+#[derive(Debug, Clone)]
+pub struct Annulus {
+    pub center: Point,
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+}
+
+impl Shape for Arc {
+    fn to_path(&self) -> kurbo::BezPath {
+        let arc = circular_arc(self.center, self.radius, self.start_angle, self.sweep);
+        kurbo::Shape::to_path(&arc, PATH_TOLERANCE)
+    }
+}
+
+impl Fillable for Arc {}
+
+impl Shape for Annulus {
+    fn to_path(&self) -> kurbo::BezPath {
+        let mut path = kurbo::BezPath::new();
+        let tau = std::f64::consts::TAU;
+        for (radius, sweep) in [(self.outer_radius, tau), (self.inner_radius, -tau)] {
+            if radius > 0.0 {
+                let circle = circular_arc(self.center, radius, 0.0, sweep);
+                path.extend(kurbo::Shape::path_elements(&circle, PATH_TOLERANCE));
+                path.close_path();
+            }
+        }
+        path
+    }
+}
+
+impl Fillable for Annulus {}
+
+fn circular_arc(center: Point, radius: f64, start_angle: f64, sweep: f64) -> kurbo::Arc {
+    kurbo::Arc {
+        center: center.into(),
+        radii: kurbo::Vec2::new(radius, radius),
+        start_angle,
+        sweep_angle: sweep,
+        x_rotation: 0.0,
+    }
+}
+
+/// A straight line segment. Lines can only be stroked.
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub start: Point,
+    pub end: Point,
+}
+
+/// An open chain of straight line segments. Polylines can only be stroked.
+#[derive(Debug, Clone)]
+pub struct Polyline {
+    pub points: Vec<Point>,
+}
+
+impl Shape for Line {
+    fn to_path(&self) -> kurbo::BezPath {
+        let mut path = kurbo::BezPath::new();
+        path.move_to(self.start);
+        path.line_to(self.end);
+        path
+    }
+
+    fn is_fillable(&self) -> bool {
+        false
+    }
+}
+
+impl Shape for Polyline {
+    fn to_path(&self) -> kurbo::BezPath {
+        let mut path = kurbo::BezPath::new();
+        for (i, point) in self.points.iter().enumerate() {
+            if i == 0 {
+                path.move_to(*point);
+            } else {
+                path.line_to(*point);
+            }
+        }
+        path
+    }
+
+    fn is_fillable(&self) -> bool {
+        false
+    }
+}
+
+/// A closed polygon through a list of points.
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    pub points: Vec<Point>,
+}
+
+/// A regular polygon with `sides` corners on a circle.
+#[derive(Debug, Clone)]
+pub struct RegularPolygon {
+    pub center: Point,
+    /// Distance from the center to the corners.
+    pub radius: f64,
+    pub sides: u32,
+    /// Rotation in radians. With no rotation, the first corner points up (towards negative y).
+    pub rotation: f64,
+}
+
+impl RegularPolygon {
+    /// The corners of the polygon.
+    pub fn points(&self) -> Vec<Point> {
+        (0..self.sides)
+            .map(|i| {
+                let angle = self.rotation - std::f64::consts::FRAC_PI_2
+                    + i as f64 * std::f64::consts::TAU / self.sides as f64;
+                Point {
+                    x: self.center.x + self.radius * angle.cos(),
+                    y: self.center.y + self.radius * angle.sin(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Shape for Polygon {
+    fn to_path(&self) -> kurbo::BezPath {
+        Path::polygon(&self.points).to_path()
+    }
+}
+
+impl Fillable for Polygon {}
+
+impl Shape for RegularPolygon {
+    fn to_path(&self) -> kurbo::BezPath {
+        Path::polygon(&self.points()).to_path()
+    }
+}
+
+impl Fillable for RegularPolygon {}
+
+impl Shape for Ellipse {
+    fn to_path(&self) -> kurbo::BezPath {
+        let ellipse = kurbo::Ellipse::new(self.center, (self.radius_x, self.radius_y), self.rotation);
+        kurbo::Shape::to_path(&ellipse, PATH_TOLERANCE)
+    }
+}
+
+impl Fillable for Ellipse {}
+
+impl Shape for Rectangle {
+    fn to_path(&self) -> kurbo::BezPath {
+        kurbo::Shape::to_path(&kurbo::Rect::new(self.a.x, self.a.y, self.b.x, self.b.y), PATH_TOLERANCE)
+    }
+
+    fn as_rect(&self) -> Option<Rectangle> {
+        Some(self.clone())
+    }
+}
+
+impl Fillable for Rectangle {}
+
+impl Shape for RoundedRectangle {
+    fn to_path(&self) -> kurbo::BezPath {
+        let rect = kurbo::RoundedRect::new(self.a.x, self.a.y, self.b.x, self.b.y, self.radius);
+        kurbo::Shape::to_path(&rect, PATH_TOLERANCE)
+    }
+}
+
+impl Fillable for RoundedRectangle {}
+
+impl From<Point> for kurbo::Point {
+    fn from(point: Point) -> Self {
+        kurbo::Point::new(point.x, point.y)
+    }
+}
+
+impl From<kurbo::Point> for Point {
+    fn from(point: kurbo::Point) -> Self {
+        Point { x: point.x, y: point.y }
+    }
+}
+
+/// A segment of a [`Path`].
+#[derive(Debug, Clone, Copy)]
+pub enum PathElement {
+    /// Start a new subpath at the point.
+    MoveTo(Point),
+    /// A straight line to the point.
+    LineTo(Point),
+    /// A quadratic Bézier curve with a control point and an end point.
+    QuadTo(Point, Point),
+    /// A cubic Bézier curve with two control points and an end point.
+    CurveTo(Point, Point, Point),
+    /// Close the current subpath.
+    Close,
+}
+
+/// An arbitrary outline made of lines and Bézier curves.
+#[derive(Debug, Clone, Default)]
+pub struct Path {
+    pub elements: Vec<PathElement>,
+}
+
+impl Path {
+    /// Create an empty path.
+    pub fn new() -> Self {
+        Self { elements: Vec::new() }
+    }
+
+    /// Start building a path segment by segment.
+    pub fn builder() -> PathBuilder {
+        PathBuilder::new()
+    }
+
+    /// Create a closed polygon through the points.
+    pub fn polygon(points: &[Point]) -> Self {
+        let mut elements = Vec::with_capacity(points.len() + 1);
+        for (i, point) in points.iter().enumerate() {
+            elements.push(if i == 0 { PathElement::MoveTo(*point) } else { PathElement::LineTo(*point) });
+        }
+        if !points.is_empty() {
+            elements.push(PathElement::Close);
+        }
+        Self { elements }
+    }
+
+    /// Return a copy of the path with all corners between straight segments rounded with the
+    /// given radius. The radius is reduced where segments are too short to fit it.
+    ///
+    /// Subpaths that contain curves are copied unchanged.
+    pub fn round_corners(&self, radius: f64) -> Path {
+        let mut elements = Vec::with_capacity(self.elements.len() * 2);
+        let mut start = 0;
+        while start < self.elements.len() {
+            // a subpath ends before the next move or after a close
+            let mut end = start + 1;
+            while end < self.elements.len() && !matches!(self.elements[end], PathElement::MoveTo(_)) {
+                end += 1;
+                if matches!(self.elements[end - 1], PathElement::Close) {
+                    break;
+                }
+            }
+            let subpath = &self.elements[start..end];
+            start = end;
+
+            let mut points = Vec::with_capacity(subpath.len());
+            let mut closed = false;
+            let mut polyline = true;
+            for element in subpath {
+                match element {
+                    PathElement::MoveTo(p) | PathElement::LineTo(p) => points.push(*p),
+                    PathElement::Close => closed = true,
+                    PathElement::QuadTo(..) | PathElement::CurveTo(..) => polyline = false,
+                }
+            }
+            if !polyline || points.len() < 3 {
+                elements.extend_from_slice(subpath);
+                continue;
+            }
+            if closed && points.len() > 1 && points_equal(points[0], points[points.len() - 1]) {
+                points.pop();
+            }
+            round_polyline(&points, closed, radius, &mut elements);
+        }
+        Path { elements }
+    }
+}
+
+/// Builds a [`Path`] segment by segment.
+///
+/// ```ignore
+/// let arrow = PathBuilder::new()
+///     .move_to(Point { x: 0.0, y: 0.0 })
+///     .line_to(Point { x: 100.0, y: 0.0 })
+///     .quad_to(Point { x: 120.0, y: 10.0 }, Point { x: 100.0, y: 20.0 })
+///     .close()
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PathBuilder {
+    elements: Vec<PathElement>,
+}
+
+impl PathBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        Self { elements: Vec::new() }
+    }
+
+    /// Start a new subpath at the point.
+    pub fn move_to(mut self, point: Point) -> Self {
+        self.elements.push(PathElement::MoveTo(point));
+        self
+    }
+
+    /// Add a straight line to the point.
+    pub fn line_to(mut self, point: Point) -> Self {
+        self.elements.push(PathElement::LineTo(point));
+        self
+    }
+
+    /// Add a quadratic Bézier curve with a control point to the point.
+    pub fn quad_to(mut self, control: Point, point: Point) -> Self {
+        self.elements.push(PathElement::QuadTo(control, point));
+        self
+    }
+
+    /// Add a cubic Bézier curve with two control points to the point.
+    pub fn cubic_to(mut self, control1: Point, control2: Point, point: Point) -> Self {
+        self.elements.push(PathElement::CurveTo(control1, control2, point));
+        self
+    }
+
+    /// Close the current subpath with a straight line to its start.
+    pub fn close(mut self) -> Self {
+        self.elements.push(PathElement::Close);
+        self
+    }
+
+    /// Finish the path.
+    pub fn build(self) -> Path {
+        Path { elements: self.elements }
+    }
+}
+
+fn points_equal(a: Point, b: Point) -> bool {
+    a.x == b.x && a.y == b.y
+}
+
+/// A rounded corner: the point where it leaves the incoming segment, two cubic control points,
+/// and the point where it joins the outgoing segment.
+fn rounded_corner(prev: Point, corner: Point, next: Point, radius: f64) -> (Point, Point, Point, Point) {
+    let (ux, uy) = (prev.x - corner.x, prev.y - corner.y);
+    let (vx, vy) = (next.x - corner.x, next.y - corner.y);
+    let (lu, lv) = (ux.hypot(uy), vx.hypot(vy));
+    if lu == 0.0 || lv == 0.0 || radius <= 0.0 {
+        return (corner, corner, corner, corner);
+    }
+    let (ux, uy, vx, vy) = (ux / lu, uy / lu, vx / lv, vy / lv);
+
+    // interior angle between the two segments
+    let theta = (ux * vx + uy * vy).clamp(-1.0, 1.0).acos();
+    if theta < 1e-6 || (std::f64::consts::PI - theta) < 1e-6 {
+        return (corner, corner, corner, corner);
+    }
+
+    // distance from the corner to the tangent points, limited to half of each segment
+    let distance = (radius / (theta / 2.0).tan()).min(lu / 2.0).min(lv / 2.0);
+    let radius = distance * (theta / 2.0).tan();
+    // cubic approximation of a circular arc spanning the turning angle
+    let handle = 4.0 / 3.0 * ((std::f64::consts::PI - theta) / 4.0).tan() * radius;
+
+    let start = Point {
+        x: corner.x + ux * distance,
+        y: corner.y + uy * distance,
+    };
+    let end = Point {
+        x: corner.x + vx * distance,
+        y: corner.y + vy * distance,
+    };
+    let c1 = Point {
+        x: start.x - ux * handle,
+        y: start.y - uy * handle,
+    };
+    let c2 = Point {
+        x: end.x - vx * handle,
+        y: end.y - vy * handle,
+    };
+    (start, c1, c2, end)
+}
+
+fn round_polyline(points: &[Point], closed: bool, radius: f64, out: &mut Vec<PathElement>) {
+    let n = points.len();
+    if closed {
+        let corners: Vec<_> = (0..n)
+            .map(|i| rounded_corner(points[(i + n - 1) % n], points[i], points[(i + 1) % n], radius))
+            .collect();
+        out.push(PathElement::MoveTo(corners[0].3));
+        for (start, c1, c2, end) in corners.iter().skip(1).chain(corners.iter().take(1)) {
+            out.push(PathElement::LineTo(*start));
+            out.push(PathElement::CurveTo(*c1, *c2, *end));
+        }
+        out.push(PathElement::Close);
+    } else {
+        out.push(PathElement::MoveTo(points[0]));
+        for i in 1..n - 1 {
+            let (start, c1, c2, end) = rounded_corner(points[i - 1], points[i], points[i + 1], radius);
+            out.push(PathElement::LineTo(start));
+            out.push(PathElement::CurveTo(c1, c2, end));
+        }
+        out.push(PathElement::LineTo(points[n - 1]));
+    }
+}
+
+impl Shape for Path {
+    fn to_path(&self) -> kurbo::BezPath {
+        let mut path = kurbo::BezPath::new();
+        for element in &self.elements {
+            match *element {
+                PathElement::MoveTo(p) => path.move_to(p),
+                PathElement::LineTo(p) => path.line_to(p),
+                PathElement::QuadTo(c, p) => path.quad_to(c, p),
+                PathElement::CurveTo(c1, c2, p) => path.curve_to(c1, c2, p),
+                PathElement::Close => path.close_path(),
+            }
+        }
+        path
+    }
+}
+
+impl Fillable for Path {}
+
+impl From<kurbo::BezPath> for Path {
+    fn from(path: kurbo::BezPath) -> Self {
+        let elements = path
+            .elements()
+            .iter()
+            .map(|element| match *element {
+                kurbo::PathEl::MoveTo(p) => PathElement::MoveTo(p.into()),
+                kurbo::PathEl::LineTo(p) => PathElement::LineTo(p.into()),
+                kurbo::PathEl::QuadTo(c, p) => PathElement::QuadTo(c.into(), p.into()),
+                kurbo::PathEl::CurveTo(c1, c2, p) => PathElement::CurveTo(c1.into(), c2.into(), p.into()),
+                kurbo::PathEl::ClosePath => PathElement::Close,
+            })
+            .collect();
+        Path { elements }
+    }
+}
@@ -0,0 +1,70 @@
+use std::hash::Hasher;
+
+use super::backend::RenderBackend;
+use super::scenes::{Clear, Scene};
+
+/// A backend whose encoded content can be serialized deterministically.
+pub trait SnapshotBackend {
+    /// Serialize everything that affects the rendered output.
+    ///
+    /// The result only depends on what was drawn (not on addresses or ids assigned at runtime),
+    /// so two backends that would render the same produce the same snapshot. Numbers are written
+    /// in little-endian byte order, so snapshots are the same on every platform.
+    fn snapshot(&self) -> Vec<u8>;
+}
+
+/// A 64-bit FNV-1a hasher, which (unlike the standard library's default hasher) is guaranteed
+/// to produce the same hashes across runs and Rust versions.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotHasher(u64);
+
+impl Default for SnapshotHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for SnapshotHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+impl<Backend: RenderBackend + SnapshotBackend> Scene<Backend> {
+    /// Serialize the content of the scene, e.g. to check in tests that a refactor didn't change
+    /// what would be rendered without rendering on a GPU. See [`SnapshotBackend::snapshot`].
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut snapshot = Vec::new();
+        let color = self.background_color;
+        for value in [color.r, color.g, color.b, color.a] {
+            snapshot.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        snapshot.extend_from_slice(&self.width.to_le_bytes());
+        snapshot.extend_from_slice(&self.height.to_le_bytes());
+        // a clear brush is drawn below the content
+        match &self.clear {
+            Clear::Color => snapshot.push(0),
+            Clear::Brush(_) => snapshot.push(1),
+            Clear::Preserve => snapshot.push(2),
+        }
+        match self.content_with_clear_brush() {
+            Some(content) => snapshot.extend_from_slice(&content.snapshot()),
+            None => snapshot.extend_from_slice(&self.backend.snapshot()),
+        }
+        snapshot
+    }
+
+    /// A stable hash of [`Scene::snapshot`], for compact assertions.
+    pub fn snapshot_hash(&self) -> u64 {
+        let mut hasher = SnapshotHasher::default();
+        hasher.write(&self.snapshot());
+        hasher.finish()
+    }
+}
@@ -0,0 +1,312 @@
+// spatial index over large sets of drawables
+use std::any::Any;
+use std::cell::OnceCell;
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::scenes::Scene;
+use super::shapes::{Point, Rectangle};
+use super::user_data::{Hit, UserData};
+use super::Drawable;
+
+/// The maximum number of entries per node of the R-tree.
+const NODE_CAPACITY: usize = 16;
+
+/// A container of drawables like a [`Group`](crate::group::Group), whose children are indexed
+/// by their [bounds](Drawable::bounds) in an R-tree, so that only the children in view are drawn
+/// and hit-testing only visits the children at the point. Use it for scenes with tens of
+/// thousands of objects, e.g. maps or large plots.
+///
+/// The index is built lazily on the next draw or hit test after the children changed. Children
+/// changed through [`IndexedGroup::get_mut`] are re-indexed automatically; call
+/// [`IndexedGroup::invalidate`] after moving children by other means. Children without bounds
+/// are always drawn and hit-tested.
+pub struct IndexedGroup<Backend> {
+    children: Vec<Box<dyn Drawable<Backend>>>,
+    /// The transform applied to all children.
+    pub transform: Affine,
+    /// Whether the group (and all of its children) is drawn.
+    pub visible: bool,
+    /// Application data returned when a child without user data is hit-tested.
+    pub user_data: Option<UserData>,
+    index: OnceCell<SpatialIndex>,
+}
+
+impl<Backend> IndexedGroup<Backend> {
+    /// Create an empty group.
+    pub fn new() -> Self {
+        Self {
+            children: Vec::new(),
+            transform: Affine::identity(),
+            visible: true,
+            user_data: None,
+            index: OnceCell::new(),
+        }
+    }
+
+    /// Add a child to the group.
+    pub fn add_child(mut self, child: impl Drawable<Backend> + 'static) -> Self {
+        self.push(child);
+        self
+    }
+
+    /// Add a child to the group.
+    pub fn push(&mut self, child: impl Drawable<Backend> + 'static) {
+        self.children.push(Box::new(child));
+        self.invalidate();
+    }
+
+    /// Set the transform of the group.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Attach application data, e.g. the id of the layer the group shows.
+    pub fn with_user_data(mut self, data: impl Any + Send + Sync) -> Self {
+        self.user_data = Some(UserData::new(data));
+        self
+    }
+
+    /// The children of the group, in drawing order.
+    pub fn children(&self) -> &[Box<dyn Drawable<Backend>>] {
+        &self.children
+    }
+
+    /// A child of the group, which is re-indexed in case it moves.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Box<dyn Drawable<Backend>>> {
+        self.invalidate();
+        self.children.get_mut(index)
+    }
+
+    /// Remove and return a child.
+    pub fn remove(&mut self, index: usize) -> Box<dyn Drawable<Backend>> {
+        self.invalidate();
+        self.children.remove(index)
+    }
+
+    /// The number of children.
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Whether the group has no children.
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Discard the index so that it is rebuilt from the bounds of the children.
+    pub fn invalidate(&mut self) {
+        self.index = OnceCell::new();
+    }
+
+    /// The indices of the children whose bounds intersect a region (in group coordinates), in
+    /// drawing order.
+    pub fn query(&self, region: Rectangle) -> Vec<usize> {
+        self.index().query(to_rect(region))
+    }
+
+    fn index(&self) -> &SpatialIndex {
+        self.index.get_or_init(|| SpatialIndex::new(&self.children))
+    }
+}
+
+impl<Backend> Default for IndexedGroup<Backend> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for IndexedGroup<Backend> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        if !self.visible {
+            return;
+        }
+        let transform = self.transform * scene.view_transform();
+        let device_transform = self.transform * scene.device_transform();
+        if device_transform.determinant() == 0.0 {
+            return;
+        }
+
+        // the viewport in group coordinates, also when the group is drawn into a child scene
+        let margin = scene.cull_margin.unwrap_or(0.0);
+        let viewport = kurbo::Rect::new(0.0, 0.0, scene.width as f64, scene.height as f64).inflate(margin, margin);
+        let viewport = kurbo::Affine::from(device_transform.inverse()).transform_rect_bbox(viewport);
+        let visible = if scene.recording_cache() {
+            // cached content may be drawn again with another viewport
+            (0..self.children.len()).collect()
+        } else {
+            self.index().query(viewport)
+        };
+
+        // record the children in group coordinates
+        let mut child_scene = scene.child_scene(transform);
+        for index in visible {
+            self.children[index].draw(&mut child_scene);
+        }
+        scene.backend.append(&child_scene.backend, transform);
+        scene.accessibility.append(&child_scene.accessibility, transform);
+    }
+
+    fn hit_test(&self, point: Point) -> Option<Hit<'_>> {
+        if !self.visible || self.transform.determinant() == 0.0 {
+            return None;
+        }
+        let local = self.transform.inverse().transform_point(point);
+        let candidates = self.index().query(kurbo::Rect::new(local.x, local.y, local.x, local.y));
+        // children are drawn in order, so the last one is on top
+        let hit = candidates
+            .into_iter()
+            .rev()
+            .find_map(|index| self.children[index].hit_test(local))?;
+        Some(Hit {
+            user_data: hit.user_data.or(self.user_data.as_ref()),
+        })
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        if !self.index().unbounded.is_empty() {
+            return None;
+        }
+        let bounds = self
+            .index()
+            .root
+            .as_ref()
+            .map_or_else(kurbo::Rect::default, |root| root.bounds);
+        let bounds = kurbo::Affine::from(self.transform).transform_rect_bbox(bounds);
+        Some(Rectangle {
+            a: Point {
+                x: bounds.x0,
+                y: bounds.y0,
+            },
+            b: Point {
+                x: bounds.x1,
+                y: bounds.y1,
+            },
+        })
+    }
+}
+
+/// A static R-tree over the bounds of drawables, bulk-loaded with sort-tile-recursive packing.
+struct SpatialIndex {
+    root: Option<Node>,
+    /// The drawables without bounds.
+    unbounded: Vec<usize>,
+}
+
+struct Node {
+    bounds: kurbo::Rect,
+    entries: Entries,
+}
+
+enum Entries {
+    Leaf(Vec<(kurbo::Rect, usize)>),
+    Branch(Vec<Node>),
+}
+
+impl SpatialIndex {
+    fn new<Backend>(children: &[Box<dyn Drawable<Backend>>]) -> Self {
+        let mut items = Vec::new();
+        let mut unbounded = Vec::new();
+        for (index, child) in children.iter().enumerate() {
+            match child.bounds() {
+                Some(bounds) => items.push((to_rect(bounds), index)),
+                None => unbounded.push(index),
+            }
+        }
+        if items.is_empty() {
+            return Self { root: None, unbounded };
+        }
+
+        let mut nodes = pack(items)
+            .into_iter()
+            .map(|entries| Node::new(Entries::Leaf(entries)))
+            .collect::<Vec<_>>();
+        while nodes.len() > 1 {
+            let items = nodes.into_iter().map(|node| (node.bounds, node)).collect();
+            nodes = pack(items)
+                .into_iter()
+                .map(|entries| Node::new(Entries::Branch(entries.into_iter().map(|(_, node)| node).collect())))
+                .collect();
+        }
+        Self {
+            root: nodes.pop(),
+            unbounded,
+        }
+    }
+
+    /// The indices of the drawables whose bounds intersect `region` (or that have no bounds),
+    /// sorted.
+    fn query(&self, region: kurbo::Rect) -> Vec<usize> {
+        let mut found = self.unbounded.clone();
+        if let Some(root) = &self.root {
+            root.query(region, &mut found);
+        }
+        found.sort_unstable();
+        found
+    }
+}
+
+impl Node {
+    fn new(entries: Entries) -> Self {
+        let bounds = match &entries {
+            Entries::Leaf(entries) => union(entries.iter().map(|(bounds, _)| *bounds)),
+            Entries::Branch(nodes) => union(nodes.iter().map(|node| node.bounds)),
+        };
+        Self { bounds, entries }
+    }
+
+    fn query(&self, region: kurbo::Rect, found: &mut Vec<usize>) {
+        if !overlaps(self.bounds, region) {
+            return;
+        }
+        match &self.entries {
+            Entries::Leaf(entries) => found.extend(
+                entries
+                    .iter()
+                    .filter(|(bounds, _)| overlaps(*bounds, region))
+                    .map(|(_, index)| *index),
+            ),
+            Entries::Branch(nodes) => {
+                for node in nodes {
+                    node.query(region, found);
+                }
+            }
+        }
+    }
+}
+
+/// Group items into nodes of at most [`NODE_CAPACITY`] entries: sort them into vertical slices
+/// by x, then each slice by y, so that the nodes cover compact areas.
+fn pack<T>(mut items: Vec<(kurbo::Rect, T)>) -> Vec<Vec<(kurbo::Rect, T)>> {
+    let node_count = items.len().div_ceil(NODE_CAPACITY);
+    let slice_count = (node_count as f64).sqrt().ceil() as usize;
+    let slice_size = slice_count * NODE_CAPACITY;
+
+    items.sort_by(|(a, _), (b, _)| a.center().x.total_cmp(&b.center().x));
+    let mut nodes = Vec::with_capacity(node_count);
+    while !items.is_empty() {
+        let rest = items.split_off(slice_size.min(items.len()));
+        let mut slice = std::mem::replace(&mut items, rest);
+        slice.sort_by(|(a, _), (b, _)| a.center().y.total_cmp(&b.center().y));
+        while !slice.is_empty() {
+            let rest = slice.split_off(NODE_CAPACITY.min(slice.len()));
+            nodes.push(std::mem::replace(&mut slice, rest));
+        }
+    }
+    nodes
+}
+
+fn union(mut rects: impl Iterator<Item = kurbo::Rect>) -> kurbo::Rect {
+    let first = rects.next().unwrap_or_default();
+    rects.fold(first, |union, rect| union.union(rect))
+}
+
+/// Whether two rectangles overlap or touch, also for empty ones (e.g. a point).
+fn overlaps(a: kurbo::Rect, b: kurbo::Rect) -> bool {
+    a.x0 <= b.x1 && b.x0 <= a.x1 && a.y0 <= b.y1 && b.y0 <= a.y1
+}
+
+fn to_rect(rectangle: Rectangle) -> kurbo::Rect {
+    kurbo::Rect::new(rectangle.a.x, rectangle.a.y, rectangle.b.x, rectangle.b.y).abs()
+}
@@ -0,0 +1,205 @@
+// previews of many drawables tiled into one image
+use super::affine::{Affine, Fit};
+use super::backend::RenderBackend;
+use super::colors::RGBA;
+use super::geoms::Geom;
+use super::scenes::Scene;
+use super::shapes::{Point, Rectangle};
+use super::styles::{CompositeMode, MixMode};
+use super::Drawable;
+
+/// The signature of a callback that draws the label of a tile into a rectangle (in scene
+/// coordinates), see [`SpriteStrip::with_labels`].
+pub type LabelFn<Backend> = dyn FnMut(&mut Scene<Backend>, &str, Rectangle);
+
+/// A sheet of labeled previews of drawables, e.g. of all conditions of a stimulus set for
+/// documentation or a quick visual check of generated stimuli.
+///
+/// Each drawable is fitted into its tile (by its [bounds](Drawable::bounds); drawables without
+/// bounds are drawn from the top left corner of the tile) and clipped to it. Tiles are placed in
+/// rows of `columns`, so a single row gives a strip. Create a scene of [`SpriteStrip::size`],
+/// draw the strip into it and render the scene to an image, e.g. with
+/// `Scene::render_to_image`.
+pub struct SpriteStrip<Backend> {
+    tiles: Vec<(String, Box<dyn Drawable<Backend>>)>,
+    /// The size of the area of a tile the drawable is fitted into.
+    pub tile_width: f64,
+    pub tile_height: f64,
+    /// The number of tiles per row (`None` puts all tiles into one row).
+    pub columns: Option<usize>,
+    /// The space around the drawable of a tile.
+    pub padding: f64,
+    /// How drawables are scaled into their tiles.
+    pub fit: Fit,
+    /// An optional outline around each tile.
+    pub border: Option<RGBA>,
+    /// The height of the space below each tile for its label.
+    pub label_height: f64,
+    label: Option<Box<LabelFn<Backend>>>,
+}
+
+impl<Backend> SpriteStrip<Backend> {
+    /// Create an empty strip with tiles of the given size, without labels.
+    pub fn new(tile_width: f64, tile_height: f64) -> Self {
+        Self {
+            tiles: Vec::new(),
+            tile_width,
+            tile_height,
+            columns: None,
+            padding: 0.0,
+            fit: Fit::Contain,
+            border: None,
+            label_height: 0.0,
+            label: None,
+        }
+    }
+
+    /// Add a tile.
+    pub fn add(mut self, label: impl Into<String>, drawable: impl Drawable<Backend> + 'static) -> Self {
+        self.push(label, drawable);
+        self
+    }
+
+    /// Add a tile.
+    pub fn push(&mut self, label: impl Into<String>, drawable: impl Drawable<Backend> + 'static) {
+        self.tiles.push((label.into(), Box::new(drawable)));
+    }
+
+    /// Set the number of tiles per row.
+    pub fn with_columns(mut self, columns: usize) -> Self {
+        self.columns = Some(columns.max(1));
+        self
+    }
+
+    /// Set the space around the drawable of a tile.
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set how drawables are scaled into their tiles.
+    pub fn with_fit(mut self, fit: Fit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// Draw an outline around each tile.
+    pub fn with_border(mut self, color: RGBA) -> Self {
+        self.border = Some(color);
+        self
+    }
+
+    /// Reserve `height` below each tile for its label, which is drawn by `label` (e.g. as text,
+    /// which depends on the backend).
+    pub fn with_labels(
+        mut self,
+        height: f64,
+        label: impl FnMut(&mut Scene<Backend>, &str, Rectangle) + 'static,
+    ) -> Self {
+        self.label_height = height;
+        self.label = Some(Box::new(label));
+        self
+    }
+
+    /// The number of tiles.
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Whether the strip has no tiles.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// The size of the whole strip, in scene units (pixels for an untransformed scene).
+    pub fn size(&self) -> (u32, u32) {
+        let columns = self.column_count();
+        let rows = self.tiles.len().div_ceil(columns).max(1);
+        let (width, height) = self.cell_size();
+        (
+            (width * columns as f64).ceil() as u32,
+            (height * rows as f64).ceil() as u32,
+        )
+    }
+
+    /// The rectangle of a tile (including its padding and label).
+    pub fn tile_rect(&self, index: usize) -> Rectangle {
+        let columns = self.column_count();
+        let (width, height) = self.cell_size();
+        let (x, y) = ((index % columns) as f64 * width, (index / columns) as f64 * height);
+        Rectangle {
+            a: Point { x, y },
+            b: Point {
+                x: x + width,
+                y: y + height,
+            },
+        }
+    }
+
+    fn column_count(&self) -> usize {
+        self.columns.unwrap_or(self.tiles.len()).max(1)
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        (
+            self.tile_width + 2.0 * self.padding,
+            self.tile_height + 2.0 * self.padding + self.label_height,
+        )
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for SpriteStrip<Backend> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        for index in 0..self.tiles.len() {
+            let cell = self.tile_rect(index);
+            let area = Rectangle {
+                a: Point {
+                    x: cell.a.x + self.padding,
+                    y: cell.a.y + self.padding,
+                },
+                b: Point {
+                    x: cell.b.x - self.padding,
+                    y: cell.b.y - self.padding - self.label_height,
+                },
+            };
+            let (label, drawable) = &mut self.tiles[index];
+            let transform = match drawable.bounds() {
+                Some(bounds) => Affine::fit(bounds, area, self.fit),
+                None => Affine::translate(area.a.x, area.a.y),
+            };
+
+            // keep drawables that overflow their tile out of the neighboring tiles
+            let view = scene.view_transform();
+            scene
+                .backend
+                .push_layer(MixMode::Normal, CompositeMode::SourceOver, &cell, view, 1.0);
+            drawable.draw(&mut scene.push_global_transform(transform));
+            scene.backend.pop_layer();
+
+            if let Some(color) = self.border {
+                Geom::stroke(cell.clone(), 1.0, color).draw(scene);
+            }
+            if let Some(draw_label) = &mut self.label {
+                let rect = Rectangle {
+                    a: Point {
+                        x: cell.a.x,
+                        y: cell.b.y - self.label_height,
+                    },
+                    b: cell.b,
+                };
+                draw_label(scene, label, rect);
+            }
+        }
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        let (width, height) = self.size();
+        Some(Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: width as f64,
+                y: height as f64,
+            },
+        })
+    }
+}
@@ -1,3 +1,4 @@
+use super::colors::RGBA;
 use super::scenes::Scene;
 
 #[derive(Debug, Clone)]
@@ -6,7 +7,7 @@ pub enum Style {
     Stroke(StrokeOptions),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FillStyle {
     NonZero,
     EvenOdd,
@@ -24,6 +25,7 @@ pub struct StrokeOptions {
 }
 
 impl StrokeOptions {
+    /// Create new stroke options with the given width, miter joins and butt caps.
     pub fn new(width: f64) -> Self {
         Self {
             width,
@@ -35,43 +37,144 @@ impl StrokeOptions {
             dash_offset: 0.0,
         }
     }
+
+    /// Set the join style.
+    pub fn join(mut self, join: Join) -> Self {
+        self.join = join;
+        self
+    }
+
+    /// Set the miter limit.
+    pub fn miter_limit(mut self, limit: f64) -> Self {
+        self.miter_limit = limit;
+        self
+    }
+
+    /// Set both the start and end cap.
+    pub fn caps(mut self, cap: Cap) -> Self {
+        self.start_cap = cap;
+        self.end_cap = cap;
+        self
+    }
+
+    /// Set the start cap.
+    pub fn start_cap(mut self, cap: Cap) -> Self {
+        self.start_cap = cap;
+        self
+    }
+
+    /// Set the end cap.
+    pub fn end_cap(mut self, cap: Cap) -> Self {
+        self.end_cap = cap;
+        self
+    }
+
+    /// Set the dash pattern (alternating dash and gap lengths) and the dash offset.
+    pub fn dash(mut self, pattern: &[f64], offset: f64) -> Self {
+        self.dash_pattern = pattern.to_vec();
+        self.dash_offset = offset;
+        self
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Default for StrokeOptions {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl From<StrokeOptions> for kurbo::Stroke {
+    fn from(style: StrokeOptions) -> Self {
+        kurbo::Stroke::new(style.width)
+            .with_join(style.join.into())
+            .with_miter_limit(style.miter_limit)
+            .with_start_cap(style.start_cap.into())
+            .with_end_cap(style.end_cap.into())
+            .with_dashes(style.dash_offset, style.dash_pattern)
+    }
+}
+
+impl From<Join> for kurbo::Join {
+    fn from(join: Join) -> Self {
+        match join {
+            Join::Bevel => kurbo::Join::Bevel,
+            Join::Miter => kurbo::Join::Miter,
+            Join::Round => kurbo::Join::Round,
+        }
+    }
+}
+
+impl From<Cap> for kurbo::Cap {
+    fn from(cap: Cap) -> Self {
+        match cap {
+            Cap::Butt => kurbo::Cap::Butt,
+            Cap::Square => kurbo::Cap::Square,
+            Cap::Round => kurbo::Cap::Round,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Join {
     Bevel,
     Miter,
     Round,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Cap {
     Butt,
     Square,
     Round,
 }
 
-pub type Dashes = Vec<[f64; 4]>;
+/// Alternating dash and gap lengths.
+pub type Dashes = Vec<f64>;
 
+/// How an image is placed in the rectangle of an image geom (see `GeomTrait::new_image`).
 #[derive(Debug, Clone, Copy)]
 pub enum ImageFitMode {
-    // Original size of the image buffer.
+    /// The original size of the image buffer, at the top left corner of the rectangle.
     Original,
-    // Stretch the image to fill the shape.
+    /// Stretch the image to fill the rectangle.
     Fill,
-    // Use exact width and height.
+    /// Use an exact width and height, at the top left corner of the rectangle.
     Exact {
         width: f64,
         height: f64,
     },
+    /// Scale the image uniformly so that it fits entirely into the rectangle, centered.
+    Contain,
+    /// Scale the image uniformly so that it covers the whole rectangle, centered and cropped.
+    Cover,
 }
 
 
+/// How the colors of a layer are mixed with the colors below it, as in the separable and
+/// non-separable blend modes of the W3C compositing specification.
 #[derive(Debug, Clone, Copy)]
 pub enum MixMode {
     Normal,
     Clip,
     Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    /// The hue of the layer with the saturation and luminosity below it.
+    Hue,
+    /// The saturation of the layer with the hue and luminosity below it.
+    Saturation,
+    /// The hue and saturation of the layer with the luminosity below it.
+    Color,
+    /// The luminosity of the layer with the hue and saturation below it.
+    Luminosity,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -88,3 +191,43 @@ pub enum CompositeMode {
     Copy,
     Xor,
 }
+
+/// An effect applied to a layer as a whole when it is composited into the scene (see
+/// [`Layer::with_effect`](crate::layer::Layer::with_effect)).
+///
+/// Effects are optional: they are skipped when [`QualitySettings::effects`] is off and by
+/// backends that don't support them.
+///
+/// [`QualitySettings::effects`]: crate::quality::QualitySettings::effects
+#[derive(Debug, Clone, Copy)]
+pub enum LayerEffect {
+    /// A Gaussian blur with a standard deviation of `sigma` device pixels.
+    Blur { sigma: f64 },
+    /// A blurred copy of the layer's alpha, filled with a color and drawn behind the layer.
+    /// The offset and `sigma` are in device pixels.
+    DropShadow { dx: f64, dy: f64, sigma: f64, color: RGBA },
+}
+
+impl LayerEffect {
+    /// A Gaussian blur.
+    pub fn blur(sigma: f64) -> Self {
+        Self::Blur { sigma }
+    }
+
+    /// A drop shadow.
+    pub fn drop_shadow(dx: f64, dy: f64, sigma: f64, color: RGBA) -> Self {
+        Self::DropShadow { dx, dy, sigma, color }
+    }
+}
+
+/// The antialiasing method used to rasterize content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Antialiasing {
+    /// Analytic area coverage (fastest, can show seams where shapes touch).
+    Area,
+    /// 8x multisampling.
+    Msaa8,
+    /// 16x multisampling.
+    #[default]
+    Msaa16,
+}
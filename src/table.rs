@@ -0,0 +1,175 @@
+// tables of text
+use super::affine::Affine;
+use super::brushes::Brush;
+use super::colors::RGBA;
+use super::text::Alignment;
+
+/// A cell of a [`Table`].
+#[derive(Debug, Clone)]
+pub struct TableCell {
+    /// The text of the cell (a single line).
+    pub text: String,
+    /// The text color, or `None` to use the color of the table.
+    pub color: Option<RGBA>,
+    /// An optional brush that fills the cell behind the text.
+    pub background: Option<Brush>,
+}
+
+impl TableCell {
+    /// Create a cell with the color of the table and no background.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: None,
+            background: None,
+        }
+    }
+
+    /// Set the text color.
+    pub fn with_color(mut self, color: RGBA) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Set the background.
+    pub fn with_background(mut self, background: Brush) -> Self {
+        self.background = Some(background);
+        self
+    }
+}
+
+impl From<&str> for TableCell {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<String> for TableCell {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+/// How the width of a column of a [`Table`] is determined.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ColumnWidth {
+    /// Fit the widest text of the column.
+    #[default]
+    Auto,
+    /// A fixed width (including the padding). Longer text overflows the cell.
+    Fixed(f64),
+}
+
+/// The lines drawn around and between the cells of a [`Table`].
+#[derive(Debug, Clone)]
+pub struct TableBorder {
+    pub brush: Brush,
+    pub width: f64,
+}
+
+/// A grid of text cells, e.g. to show a summary of results or a condition matrix.
+///
+/// The top left corner of the table is at the origin of `transform`. Rows are as high as the
+/// line height of the font plus the padding, and columns are sized from their text unless a
+/// fixed width is set.
+#[derive(Debug, Clone)]
+pub struct Table<T> {
+    /// The cells, row by row. Rows can have different numbers of cells.
+    pub rows: Vec<Vec<TableCell>>,
+    pub font: T,
+    pub size: f32,
+    /// The default text color.
+    pub color: RGBA,
+    /// The weight of the text (the `wght` axis of variable fonts).
+    pub weight: f32,
+    /// The weight of the first row, to mark it as a header.
+    pub header_weight: Option<f32>,
+    /// The widths of the columns. Columns without an entry are sized automatically.
+    pub columns: Vec<ColumnWidth>,
+    /// The alignment of the text within its cell.
+    pub alignment: Alignment,
+    /// The space between the text and the edges of its cell.
+    pub padding: f64,
+    pub border: Option<TableBorder>,
+    /// An optional brush that fills the whole table behind the cells.
+    pub background: Option<Brush>,
+    pub transform: Affine,
+    /// Whether the table is drawn.
+    pub visible: bool,
+}
+
+impl<T> Table<T> {
+    /// Create a table with automatically sized, left-aligned columns and no border.
+    pub fn new(rows: Vec<Vec<TableCell>>, font: T, size: f32, color: RGBA) -> Self {
+        Self {
+            rows,
+            font,
+            size,
+            color,
+            weight: 400.0,
+            header_weight: None,
+            columns: Vec::new(),
+            alignment: Alignment::Left,
+            padding: size as f64 / 2.0,
+            border: None,
+            background: None,
+            transform: Affine::identity(),
+            visible: true,
+        }
+    }
+
+    /// Draw the first row with a different weight, e.g. 700 for a bold header.
+    pub fn with_header(mut self, weight: f32) -> Self {
+        self.header_weight = Some(weight);
+        self
+    }
+
+    /// Set the widths of the columns.
+    pub fn with_columns(mut self, columns: Vec<ColumnWidth>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Set the alignment of the text within its cell.
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Set the padding.
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Draw lines around and between the cells.
+    pub fn with_border(mut self, brush: Brush, width: f64) -> Self {
+        self.border = Some(TableBorder { brush, width });
+        self
+    }
+
+    /// Fill the table behind the cells.
+    pub fn with_background(mut self, background: Brush) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// The number of columns (the length of the longest row).
+    pub fn column_count(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// The weight of the text in a row.
+    pub fn row_weight(&self, row: usize) -> f32 {
+        match self.header_weight {
+            Some(weight) if row == 0 => weight,
+            _ => self.weight,
+        }
+    }
+}
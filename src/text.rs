@@ -1,4 +1,15 @@
-use super::{affine::Affine, colors::RGBA, shapes::Point};
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::{
+    affine::Affine,
+    brushes::Brush,
+    colors::RGBA,
+    fonts::FontData,
+    locale::Locale,
+    shapes::{Point, Rectangle, Shape},
+    styles::{FillStyle, Join, StrokeOptions},
+};
 
 /// A piece of formatted text.
 #[derive(Debug, Clone)]
@@ -8,22 +19,429 @@ pub struct FormatedText<T> {
     pub text: String,
     pub size: f32,
     pub color: RGBA,
+    /// A brush (e.g. a gradient or image) that fills the glyphs instead of `color`. Its
+    /// coordinates are relative to the origin of the text. Color glyphs (emoji) keep their colors.
+    pub brush: Option<Brush>,
+    /// The weight of the text (the `wght` axis of variable fonts, e.g. 400 for regular and 700
+    /// for bold).
     pub weight: f32,
+    /// The width of the text in percent of the normal width (the `wdth` axis of variable fonts).
+    pub width: f32,
     pub font: T,
     pub style: FontStyle,
     pub alignment: Alignment,
     pub vertical_alignment: VerticalAlignment,
+    /// The maximum width of a line. Longer lines are wrapped at spaces, and every line is
+    /// aligned on its own.
+    pub max_width: Option<f64>,
     pub transform: Affine,
     pub glyph_transform: Option<Affine>,
+    /// How the glyphs are rasterized.
+    pub rendering: TextRendering,
+    /// An optional outline around the glyphs.
+    pub stroke: Option<TextStroke>,
+    /// An optional box drawn behind the text.
+    pub background: Option<TextBackground>,
+    /// An optional callback that can move or hide glyphs after layout.
+    pub glyph_hook: Option<GlyphHook>,
+    /// The opacity of the text (including its outline, background and color glyphs).
+    pub opacity: f32,
+    /// The locale of the text, which selects language-specific glyph forms when shaping (e.g.
+    /// Serbian Cyrillic or Turkish ligatures). `None` guesses from the text. If the font has
+    /// fallbacks, glyphs it doesn't have are taken from the fonts of the locale first.
+    pub locale: Option<Locale>,
+    /// Whether the text is drawn.
+    pub visible: bool,
+}
+
+/// A callback that can move or hide individual glyphs after layout, for effects like waves,
+/// jitter or typewriter reveals.
+///
+/// The callback receives the index of the glyph within the laid out text and the positioned
+/// glyph, and returns whether the glyph should be drawn.
+#[derive(Clone)]
+pub struct GlyphHook(pub Arc<GlyphHookFn>);
+
+/// The signature of a [`GlyphHook`] callback.
+pub type GlyphHookFn = dyn Fn(usize, &mut Glyph) -> bool + Send + Sync;
+
+impl GlyphHook {
+    pub fn new(hook: impl Fn(usize, &mut Glyph) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for GlyphHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("GlyphHook")
+    }
+}
+
+/// A run of text with its own styling within a [`RichText`].
+#[derive(Debug, Clone)]
+pub struct TextSpan<T> {
+    pub text: String,
+    pub font: T,
+    pub size: f32,
+    pub color: RGBA,
+    /// The weight of the span (the `wght` axis of variable fonts).
+    pub weight: f32,
+    /// Draw a line below the span.
+    pub underline: bool,
+    /// Draw a line through the span.
+    pub strikethrough: bool,
+}
+
+impl<T> TextSpan<T> {
+    /// Create a new span with regular weight and no decorations.
+    pub fn new(text: impl Into<String>, font: T, size: f32, color: RGBA) -> Self {
+        Self {
+            text: text.into(),
+            font,
+            size,
+            color,
+            weight: 400.0,
+            underline: false,
+            strikethrough: false,
+        }
+    }
+
+    /// Set the weight.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Draw a line below the span.
+    pub fn with_underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    /// Draw a line through the span.
+    pub fn with_strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
 }
 
+/// A paragraph made of differently styled spans, e.g. for instructions that mix bold keywords
+/// and colored highlights.
+///
+/// The baseline of the first line starts at the origin of `transform`. Lines are broken at
+/// `\n` and, with a maximum width, at spaces.
 #[derive(Debug, Clone)]
+pub struct RichText<T> {
+    pub spans: Vec<TextSpan<T>>,
+    /// The alignment of each line relative to the origin.
+    pub alignment: Alignment,
+    /// The maximum width of a line.
+    pub max_width: Option<f64>,
+    pub transform: Affine,
+    /// How the glyphs are rasterized.
+    pub rendering: TextRendering,
+    /// The locale of the text (see [`FormatedText::locale`]).
+    pub locale: Option<Locale>,
+    /// Whether the text is drawn.
+    pub visible: bool,
+}
+
+impl<T> RichText<T> {
+    /// Create a new left-aligned paragraph without a maximum width.
+    pub fn new(spans: Vec<TextSpan<T>>) -> Self {
+        Self {
+            spans,
+            alignment: Alignment::Left,
+            max_width: None,
+            transform: Affine::identity(),
+            rendering: TextRendering::default(),
+            locale: None,
+            visible: true,
+        }
+    }
+
+    /// Set the alignment.
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Set the maximum width of a line.
+    pub fn with_max_width(mut self, max_width: f64) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Set how the glyphs are rasterized.
+    pub fn with_rendering(mut self, rendering: TextRendering) -> Self {
+        self.rendering = rendering;
+        self
+    }
+
+    /// Set the locale the text is shaped for.
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+}
+
+/// How the glyph outlines of a text are rasterized.
+///
+/// The defaults suit most text. Hinting can make small, high-contrast text crisper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRendering {
+    /// The fill rule of the glyph outlines.
+    pub fill: FillStyle,
+    /// Fit the outlines to the pixel grid. This sharpens small text that is not rotated or
+    /// skewed, but slightly changes the shapes of the glyphs.
+    pub hint: bool,
+}
+
+impl Default for TextRendering {
+    fn default() -> Self {
+        Self {
+            fill: FillStyle::NonZero,
+            hint: false,
+        }
+    }
+}
+
+impl TextRendering {
+    /// Set the fill rule.
+    pub fn with_fill(mut self, fill: FillStyle) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Enable or disable hinting.
+    pub fn with_hinting(mut self, hint: bool) -> Self {
+        self.hint = hint;
+        self
+    }
+}
+
+/// A line of text that follows a shape, e.g. a label around a circular stimulus.
+///
+/// Each glyph is placed on the outline of the shape at its distance along the outline and
+/// rotated to follow its direction. The top of the glyphs points to the left of the direction
+/// the outline is drawn in, so text runs clockwise around circles. Glyphs past the end of an
+/// open outline are not drawn; on closed outlines they wrap around.
+#[derive(Debug, Clone)]
+pub struct TextOnPath<T, S> {
+    pub text: String,
+    pub font: T,
+    pub size: f32,
+    pub color: RGBA,
+    /// The weight of the text (the `wght` axis of variable fonts).
+    pub weight: f32,
+    /// The shape whose outline the text follows.
+    pub shape: S,
+    /// The distance along the outline the text is aligned to.
+    pub offset: f64,
+    /// Whether the text starts, is centered or ends at `offset`.
+    pub alignment: Alignment,
+    /// The distance of the baseline from the outline, towards the top of the glyphs.
+    pub baseline_shift: f64,
+    pub transform: Affine,
+    /// How the glyphs are rasterized.
+    pub rendering: TextRendering,
+    /// Whether the text is drawn.
+    pub visible: bool,
+}
+
+impl<T, S: Shape> TextOnPath<T, S> {
+    /// Create text that starts at the beginning of the outline of a shape.
+    pub fn new(text: impl Into<String>, font: T, size: f32, color: RGBA, shape: S) -> Self {
+        Self {
+            text: text.into(),
+            font,
+            size,
+            color,
+            weight: 400.0,
+            shape,
+            offset: 0.0,
+            alignment: Alignment::Left,
+            baseline_shift: 0.0,
+            transform: Affine::identity(),
+            rendering: TextRendering::default(),
+            visible: true,
+        }
+    }
+
+    /// Set the weight.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Set the distance along the outline the text is aligned to, and how it is aligned.
+    pub fn with_offset(mut self, offset: f64, alignment: Alignment) -> Self {
+        self.offset = offset;
+        self.alignment = alignment;
+        self
+    }
+
+    /// Set the distance of the baseline from the outline.
+    pub fn with_baseline_shift(mut self, shift: f64) -> Self {
+        self.baseline_shift = shift;
+        self
+    }
+
+    /// Set the transform.
+    pub fn with_transform(mut self, transform: Affine) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Place shaped glyphs (positioned along a baseline, with the total advance `width`) on the
+    /// outline. Returns each glyph at the origin with the transform that places it.
+    pub fn place_glyphs(&self, glyphs: &[Glyph], width: f32) -> Vec<(Glyph, Affine)> {
+        const ACCURACY: f64 = 1e-3;
+        use kurbo::{ParamCurve, ParamCurveArclen, ParamCurveDeriv, PathSeg};
+
+        let path = self.shape.to_path();
+        let segments: Vec<_> = path
+            .segments()
+            .map(|segment| (segment, segment.arclen(ACCURACY)))
+            .filter(|(_, length)| *length > 0.0)
+            .collect();
+        let length: f64 = segments.iter().map(|(_, length)| length).sum();
+        if length <= 0.0 {
+            return Vec::new();
+        }
+        let closed = matches!(path.elements().last(), Some(kurbo::PathEl::ClosePath));
+
+        let start = match self.alignment {
+            Alignment::Left => self.offset,
+            Alignment::Center => self.offset - width as f64 / 2.0,
+            Alignment::Right => self.offset - width as f64,
+        };
+        let mut placed = Vec::new();
+        for (i, glyph) in glyphs.iter().enumerate() {
+            // place the glyph by its center, so it sits on curved outlines without gaps
+            let next_x = glyphs.get(i + 1).map_or(width, |next| next.x);
+            let advance = (next_x - glyph.x).max(0.0) as f64;
+            let mut distance = start + glyph.x as f64 + advance / 2.0;
+            if closed {
+                distance = distance.rem_euclid(length);
+            } else if !(0.0..=length).contains(&distance) {
+                continue;
+            }
+
+            let mut remaining = distance;
+            let Some((segment, t)) = segments.iter().find_map(|(segment, segment_length)| {
+                if remaining <= *segment_length {
+                    Some((segment, segment.inv_arclen(remaining, ACCURACY)))
+                } else {
+                    remaining -= segment_length;
+                    None
+                }
+            }) else {
+                continue;
+            };
+            let point = segment.eval(t);
+            let tangent = match segment {
+                PathSeg::Line(line) => line.p1 - line.p0,
+                PathSeg::Quad(quad) => quad.deriv().eval(t).to_vec2(),
+                PathSeg::Cubic(cubic) => cubic.deriv().eval(t).to_vec2(),
+            };
+            let angle = tangent.y.atan2(tangent.x);
+
+            let transform = Affine::translate(-advance / 2.0, glyph.y as f64 - self.baseline_shift)
+                * Affine::rotate(angle)
+                * Affine::translate(point.x, point.y);
+            placed.push((Glyph { x: 0.0, y: 0.0, ..*glyph }, transform));
+        }
+        placed
+    }
+}
+
+/// An outline around the glyphs of a text, e.g. to keep text legible on top of arbitrary
+/// stimuli.
+#[derive(Debug, Clone)]
+pub struct TextStroke {
+    /// The color of the outline.
+    pub color: RGBA,
+    /// The width, joins and dashes of the outline.
+    pub options: StrokeOptions,
+    /// Whether the glyphs are also filled with the text color. The fill is drawn on top of the
+    /// outline, so only the outer half of the outline stays visible.
+    pub fill: bool,
+}
+
+impl TextStroke {
+    /// An outline of the given color and width with round joins, drawn behind the filled glyphs.
+    pub fn outline(color: RGBA, width: f64) -> Self {
+        Self {
+            color,
+            options: StrokeOptions::new(width).join(Join::Round),
+            fill: true,
+        }
+    }
+
+    /// Stroke the glyphs instead of filling them (hollow text).
+    pub fn only(color: RGBA, width: f64) -> Self {
+        Self {
+            fill: false,
+            ..Self::outline(color, width)
+        }
+    }
+}
+
+/// A box drawn behind the measured extents of a text.
+#[derive(Debug, Clone)]
+pub struct TextBackground {
+    /// The brush used to fill the box.
+    pub brush: Brush,
+    /// Space between the text extents and the edges of the box.
+    pub padding: f64,
+    /// Corner radius of the box.
+    pub corner_radius: f64,
+}
+
+impl TextBackground {
+    /// Create a new background with the given brush and no padding or rounding.
+    pub fn new(brush: Brush) -> Self {
+        Self {
+            brush,
+            padding: 0.0,
+            corner_radius: 0.0,
+        }
+    }
+
+    /// Set the padding.
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set the corner radius.
+    pub fn with_corner_radius(mut self, corner_radius: f64) -> Self {
+        self.corner_radius = corner_radius;
+        self
+    }
+}
+
+/// The style of the text.
+///
+/// Italic and oblique use the `ital` or `slnt` axis of variable fonts if available and are
+/// otherwise synthesized by slanting the glyphs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum FontStyle {
     Normal,
     Italic,
-    // Oblique,
+    Oblique,
 }
 
+/// The slant of synthesized italic and oblique text, in degrees.
+pub const SYNTHETIC_OBLIQUE_ANGLE: f32 = 12.0;
+
 /// Alignment of the text.
 #[derive(Debug, Clone)]
 pub enum Alignment {
@@ -45,3 +463,183 @@ pub enum VerticalAlignment {
     /// Align the text to the bottom.
     Bottom,
 }
+
+/// Break text into lines at `\n` and, if a maximum width is given, at spaces so that lines fit
+/// into the width (a single word that is too long gets a line of its own).
+///
+/// `width_of` measures a part of the text, given as a byte range, e.g. by shaping it. Returns
+/// the byte range of each line (without the space it was broken at).
+pub fn break_lines(text: &str, max_width: Option<f32>, width_of: impl Fn(Range<usize>) -> f32) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut paragraph_start = 0;
+    for paragraph in text.split('\n') {
+        let paragraph_end = paragraph_start + paragraph.len();
+        let Some(max_width) = max_width else {
+            lines.push(paragraph_start..paragraph_end);
+            paragraph_start = paragraph_end + 1;
+            continue;
+        };
+
+        let (mut line_start, mut line_end) = (paragraph_start, paragraph_start);
+        let mut word_start = paragraph_start;
+        for word in paragraph.split(' ') {
+            let word_end = word_start + word.len();
+            // the whole line is measured, since shaping (e.g. kerning) can change the width of a
+            // word next to others
+            if line_end > line_start && width_of(line_start..word_end) > max_width {
+                lines.push(line_start..line_end);
+                line_start = word_start;
+            }
+            line_end = word_end;
+            word_start = word_end + 1;
+        }
+        lines.push(line_start..line_end);
+        paragraph_start = paragraph_end + 1;
+    }
+    lines
+}
+
+/// A font parsed for shaping, so that many lines can be shaped with the same font without
+/// reading it again.
+pub struct Shaper<'a> {
+    face: rustybuzz::Face<'a>,
+    scale: f32,
+}
+
+impl<'a> Shaper<'a> {
+    /// Read a font for shaping at `font_size` with the given variation axis settings.
+    ///
+    /// `font_data` is the data of a font file and `index` the index of the font within a
+    /// collection. Returns `None` if the font can't be read.
+    pub fn new(font_data: &'a [u8], index: u32, font_size: f32, variations: &[(&str, f32)]) -> Option<Self> {
+        let mut face = rustybuzz::Face::from_slice(font_data, index)?;
+        let variations = variations
+            .iter()
+            .filter_map(|(tag, value)| {
+                let tag: [u8; 4] = tag.as_bytes().try_into().ok()?;
+                Some(rustybuzz::Variation {
+                    tag: rustybuzz::ttf_parser::Tag::from_bytes(&tag),
+                    value: *value,
+                })
+            })
+            .collect::<Vec<_>>();
+        face.set_variations(&variations);
+        let scale = font_size / face.units_per_em() as f32;
+        Some(Self { face, scale })
+    }
+
+    /// Shape a line of text, so that ligatures, kerning, complex scripts and emoji sequences get
+    /// the right glyphs and positions.
+    ///
+    /// A locale selects the language-specific forms of the font; without one, the language is
+    /// guessed from the text. Returns the glyphs, positioned in pixels relative to the start of
+    /// the baseline, and the advance of the line.
+    pub fn shape(&self, text: &str, locale: Option<&Locale>) -> (Vec<Glyph>, f32) {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        if let Some(language) = locale.and_then(|locale| locale.tag().parse().ok()) {
+            buffer.set_language(language);
+        }
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(&self.face, &[], buffer);
+
+        // shaping offsets and advances are y-up, glyph positions are y-down
+        let scale = self.scale;
+        let (mut pen_x, mut pen_y) = (0.0, 0.0);
+        let glyphs = output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .map(|(info, position)| {
+                let glyph = Glyph {
+                    id: info.glyph_id,
+                    x: pen_x + position.x_offset as f32 * scale,
+                    y: pen_y - position.y_offset as f32 * scale,
+                };
+                pen_x += position.x_advance as f32 * scale;
+                pen_y -= position.y_advance as f32 * scale;
+                glyph
+            })
+            .collect();
+        (glyphs, pen_x)
+    }
+}
+
+/// Shape a line of text with a font (see [`Shaper::shape`]). To shape several lines with the
+/// same font, create a [`Shaper`] once instead.
+///
+/// Returns the glyphs and the advance of the line, or `None` if the font can't be read.
+pub fn shape_line(
+    font_data: &[u8],
+    index: u32,
+    font_size: f32,
+    variations: &[(&str, f32)],
+    text: &str,
+    locale: Option<&Locale>,
+) -> Option<(Vec<Glyph>, f32)> {
+    Some(Shaper::new(font_data, index, font_size, variations)?.shape(text, locale))
+}
+
+/// A positioned glyph, ready to be drawn by a backend.
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    /// The glyph id within the font.
+    pub id: u32,
+    /// The x position of the glyph origin.
+    pub x: f32,
+    /// The y position of the glyph origin.
+    pub y: f32,
+}
+
+/// The result of laying out a text.
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    /// The positioned glyphs.
+    pub glyphs: Vec<Glyph>,
+    /// The extents of the text (from the ascent of the first line to the descent of the last).
+    pub bounds: Rectangle,
+    /// The translation that applies the horizontal and vertical alignment.
+    pub alignment_offset: Point,
+    /// The fonts of the glyphs, as the index of the first glyph of each run and the fallback
+    /// font of the run (`None` for the font of the text). Glyphs before the first run use the
+    /// font of the text.
+    pub font_runs: Vec<(usize, Option<FontData>)>,
+}
+
+impl TextLayout {
+    /// Let a hook move or remove the laid out glyphs. The bounds are left unchanged.
+    pub fn apply_glyph_hook(&mut self, hook: &GlyphHook) {
+        // the number of glyphs kept before each glyph, to move the starts of the runs
+        let mut kept_before = Vec::with_capacity(self.glyphs.len() + 1);
+        let mut kept = 0;
+        self.glyphs.retain_mut(|glyph| {
+            kept_before.push(kept);
+            let keep = (hook.0)(kept_before.len() - 1, glyph);
+            kept += keep as usize;
+            keep
+        });
+        kept_before.push(kept);
+        for (start, _) in &mut self.font_runs {
+            *start = kept_before[*start];
+        }
+    }
+
+    /// The glyphs of each run with its fallback font (`None` for the font of the text).
+    pub fn glyph_runs(&self) -> Vec<(Option<&FontData>, &[Glyph])> {
+        let mut starts = self
+            .font_runs
+            .iter()
+            .map(|(start, font)| (*start, font.as_ref()))
+            .collect::<Vec<_>>();
+        if starts.first().is_none_or(|(start, _)| *start > 0) {
+            starts.insert(0, (0, None));
+        }
+        let ends = starts.iter().skip(1).map(|(start, _)| *start).chain([self.glyphs.len()]);
+        starts
+            .iter()
+            .zip(ends)
+            .filter(|((start, _), end)| start < end)
+            .map(|(&(start, font), end)| (font, &self.glyphs[start..end]))
+            .collect()
+    }
+}
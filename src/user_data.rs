@@ -0,0 +1,38 @@
+// application data attached to drawables
+use std::any::Any;
+use std::sync::Arc;
+
+/// Application data attached to a drawable, e.g. the id of the domain entity it shows, so that
+/// hit-tested objects can be mapped back to it.
+#[derive(Clone)]
+pub struct UserData(pub Arc<dyn Any + Send + Sync>);
+
+impl UserData {
+    pub fn new(data: impl Any + Send + Sync) -> Self {
+        Self(Arc::new(data))
+    }
+
+    /// The data if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+
+    /// Whether the data is of type `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.0.is::<T>()
+    }
+}
+
+impl std::fmt::Debug for UserData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UserData")
+    }
+}
+
+/// The result of a [hit test](crate::Drawable::hit_test).
+#[derive(Debug, Clone, Copy)]
+pub struct Hit<'a> {
+    /// The user data of the innermost drawable at the point that has user data, e.g. of the
+    /// group containing a geom without user data.
+    pub user_data: Option<&'a UserData>,
+}
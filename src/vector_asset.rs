@@ -0,0 +1,636 @@
+// pre-processed vector content stored on disk
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::brushes::{Brush, ColorStop, Extend, Gradient, GradientKind};
+use super::colors::RGBA;
+use super::scenes::Scene;
+use super::shapes::{Path, PathElement, Point, Rectangle};
+use super::styles::{Cap, FillStyle, Join, StrokeOptions, Style};
+use super::Drawable;
+
+/// Magic bytes at the start of every vector asset file.
+const MAGIC: &[u8; 8] = b"RNDRVECT";
+/// Version of the vector asset file format.
+const VERSION: u32 = 1;
+
+/// A single filled or stroked path of a [`VectorAsset`].
+#[derive(Debug, Clone)]
+pub struct VectorCommand {
+    pub path: Path,
+    pub style: Style,
+    pub brush: Brush,
+    pub transform: Affine,
+    pub brush_transform: Option<Affine>,
+}
+
+/// Vector content (e.g. a parsed SVG) reduced to a flat list of paths that can be stored on disk.
+///
+/// Parsing and converting large SVGs is expensive. Converting them once with
+/// [`VectorAsset::from_svg`] and [`VectorAsset::save`] lets applications load the prepared paths
+/// at startup with [`VectorAsset::load`] instead. Wrap the asset in a cached
+/// [`Group`](crate::group::Group) to avoid re-encoding the paths every frame.
+#[derive(Debug, Clone)]
+pub struct VectorAsset {
+    /// The width of the content.
+    pub width: f64,
+    /// The height of the content.
+    pub height: f64,
+    /// The paths, in drawing order.
+    pub commands: Vec<VectorCommand>,
+    /// The transform applied to all paths when drawing the asset.
+    pub transform: Affine,
+}
+
+impl VectorAsset {
+    /// Write the asset to a file, overwriting any existing file.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Load an asset from a file.
+    pub fn load(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Serialize the asset. Fails for image brushes, which can't be stored.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        write_f64s(writer, &[self.width, self.height])?;
+        write_affine(writer, self.transform)?;
+        writer.write_all(&(self.commands.len() as u64).to_le_bytes())?;
+
+        for command in &self.commands {
+            write_affine(writer, command.transform)?;
+            match command.brush_transform {
+                Some(transform) => {
+                    writer.write_all(&[1])?;
+                    write_affine(writer, transform)?;
+                }
+                None => writer.write_all(&[0])?,
+            }
+            write_style(writer, &command.style)?;
+            write_brush(writer, &command.brush)?;
+            write_path(writer, &command.path)?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize an asset written with [`VectorAsset::write_to`].
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a vector asset file".to_string()));
+        }
+        let version = read_u32(reader)?;
+        if version != VERSION {
+            return Err(invalid_data(format!("unsupported vector asset version {}", version)));
+        }
+
+        let width = read_f64(reader)?;
+        let height = read_f64(reader)?;
+        let transform = read_affine(reader)?;
+        let count = read_u64(reader)?;
+
+        let mut commands = Vec::new();
+        for _ in 0..count {
+            let transform = read_affine(reader)?;
+            let brush_transform = match read_u8(reader)? {
+                0 => None,
+                _ => Some(read_affine(reader)?),
+            };
+            commands.push(VectorCommand {
+                transform,
+                brush_transform,
+                style: read_style(reader)?,
+                brush: read_brush(reader)?,
+                path: read_path(reader)?,
+            });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            commands,
+            transform,
+        })
+    }
+
+    pub fn set_transform(&mut self, transform: Affine) {
+        self.transform = transform;
+    }
+}
+
+impl<Backend: RenderBackend> Drawable<Backend> for VectorAsset {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        let view = self.transform * scene.view_transform();
+        for command in &self.commands {
+            scene.backend.draw_shape(
+                &command.path,
+                &command.style,
+                &command.brush,
+                command.transform * view,
+                command.brush_transform,
+            );
+        }
+    }
+
+    fn bounds(&self) -> Option<Rectangle> {
+        let bounds = kurbo::Rect::new(0.0, 0.0, self.width, self.height);
+        let bounds = kurbo::Affine::from(self.transform).transform_rect_bbox(bounds);
+        Some(Rectangle {
+            a: Point {
+                x: bounds.x0,
+                y: bounds.y0,
+            },
+            b: Point {
+                x: bounds.x1,
+                y: bounds.y1,
+            },
+        })
+    }
+}
+
+#[cfg(feature = "vello-backend")]
+mod svg {
+    use std::io;
+
+    use vello_svg::usvg;
+
+    use super::*;
+
+    impl VectorAsset {
+        /// Convert an SVG document into a vector asset.
+        ///
+        /// Paths with solid or gradient fills and strokes are supported; the opacity of groups is
+        /// multiplied into the paints of their children. Documents that contain images, text,
+        /// patterns, clip paths, masks or filters are rejected.
+        pub fn from_svg(svg: &str, transform: Affine) -> io::Result<Self> {
+            let tree = usvg::Tree::from_str(svg, &Default::default()).map_err(|e| invalid_data(e.to_string()))?;
+            let mut commands = Vec::new();
+            convert_group(tree.root(), 1.0, &mut commands)?;
+            Ok(Self {
+                width: tree.size().width() as f64,
+                height: tree.size().height() as f64,
+                commands,
+                transform,
+            })
+        }
+    }
+
+    fn unsupported(feature: &str) -> io::Error {
+        invalid_data(format!("unsupported SVG feature: {}", feature))
+    }
+
+    fn convert_group(group: &usvg::Group, opacity: f32, commands: &mut Vec<VectorCommand>) -> io::Result<()> {
+        if group.clip_path().is_some() {
+            return Err(unsupported("clip path"));
+        }
+        if group.mask().is_some() {
+            return Err(unsupported("mask"));
+        }
+        if !group.filters().is_empty() {
+            return Err(unsupported("filter"));
+        }
+        let opacity = opacity * group.opacity().get();
+
+        for node in group.children() {
+            match node {
+                usvg::Node::Group(group) => convert_group(group, opacity, commands)?,
+                usvg::Node::Path(path) => convert_path(path, opacity, commands)?,
+                usvg::Node::Image(_) => return Err(unsupported("image")),
+                usvg::Node::Text(_) => return Err(unsupported("text")),
+            }
+        }
+        Ok(())
+    }
+
+    fn convert_path(path: &usvg::Path, opacity: f32, commands: &mut Vec<VectorCommand>) -> io::Result<()> {
+        if !path.is_visible() {
+            return Ok(());
+        }
+
+        let point = |p: usvg::tiny_skia_path::Point| Point {
+            x: p.x as f64,
+            y: p.y as f64,
+        };
+        let mut elements = Vec::new();
+        for segment in path.data().segments() {
+            elements.push(match segment {
+                usvg::tiny_skia_path::PathSegment::MoveTo(p) => PathElement::MoveTo(point(p)),
+                usvg::tiny_skia_path::PathSegment::LineTo(p) => PathElement::LineTo(point(p)),
+                usvg::tiny_skia_path::PathSegment::QuadTo(c, p) => PathElement::QuadTo(point(c), point(p)),
+                usvg::tiny_skia_path::PathSegment::CubicTo(c1, c2, p) => {
+                    PathElement::CurveTo(point(c1), point(c2), point(p))
+                }
+                usvg::tiny_skia_path::PathSegment::Close => PathElement::Close,
+            });
+        }
+        let shape = Path { elements };
+        let transform = convert_transform(path.abs_transform());
+
+        let fill = path.fill().map(|fill| {
+            let style = Style::Fill(match fill.rule() {
+                usvg::FillRule::NonZero => FillStyle::NonZero,
+                usvg::FillRule::EvenOdd => FillStyle::EvenOdd,
+            });
+            (style, fill.paint(), fill.opacity().get())
+        });
+        let stroke = path.stroke().map(|stroke| {
+            let join = match stroke.linejoin() {
+                usvg::LineJoin::Miter | usvg::LineJoin::MiterClip => Join::Miter,
+                usvg::LineJoin::Round => Join::Round,
+                usvg::LineJoin::Bevel => Join::Bevel,
+            };
+            let cap = match stroke.linecap() {
+                usvg::LineCap::Butt => Cap::Butt,
+                usvg::LineCap::Round => Cap::Round,
+                usvg::LineCap::Square => Cap::Square,
+            };
+            let dashes = stroke
+                .dasharray()
+                .map(|dashes| dashes.iter().map(|d| *d as f64).collect::<Vec<_>>())
+                .unwrap_or_default();
+            let options = StrokeOptions::new(stroke.width().get() as f64)
+                .join(join)
+                .miter_limit(stroke.miterlimit().get() as f64)
+                .caps(cap)
+                .dash(&dashes, stroke.dashoffset() as f64);
+            (Style::Stroke(options), stroke.paint(), stroke.opacity().get())
+        });
+
+        let painted = match path.paint_order() {
+            usvg::PaintOrder::FillAndStroke => [fill, stroke],
+            usvg::PaintOrder::StrokeAndFill => [stroke, fill],
+        };
+        for (style, paint, paint_opacity) in painted.into_iter().flatten() {
+            let (brush, brush_transform) = convert_paint(paint, opacity * paint_opacity)?;
+            commands.push(VectorCommand {
+                path: shape.clone(),
+                style,
+                brush,
+                transform,
+                brush_transform,
+            });
+        }
+        Ok(())
+    }
+
+    fn convert_paint(paint: &usvg::Paint, opacity: f32) -> io::Result<(Brush, Option<Affine>)> {
+        let color = |color: usvg::Color, alpha: f32| {
+            RGBA::new(
+                color.red as f32 / 255.0,
+                color.green as f32 / 255.0,
+                color.blue as f32 / 255.0,
+                alpha,
+            )
+        };
+        let gradient = |base: &usvg::BaseGradient, kind: GradientKind| {
+            let extend = match base.spread_method() {
+                usvg::SpreadMethod::Pad => Extend::Pad,
+                usvg::SpreadMethod::Reflect => Extend::Reflect,
+                usvg::SpreadMethod::Repeat => Extend::Repeat,
+            };
+            let stops = base
+                .stops()
+                .iter()
+                .map(|stop| ColorStop {
+                    offset: stop.offset().get(),
+                    color: color(stop.color(), stop.opacity().get() * opacity),
+                })
+                .collect();
+            let gradient = Gradient {
+                extend,
+                kind,
+                stops,
+                contrast: 1.0,
+                phase: 0.0,
+                dither: false,
+            };
+            (Brush::Gradient(gradient), Some(convert_transform(base.transform())))
+        };
+
+        match paint {
+            usvg::Paint::Color(c) => Ok((Brush::Solid(color(*c, opacity)), None)),
+            usvg::Paint::LinearGradient(g) => Ok(gradient(
+                g,
+                GradientKind::Linear {
+                    start: Point {
+                        x: g.x1() as f64,
+                        y: g.y1() as f64,
+                    },
+                    end: Point {
+                        x: g.x2() as f64,
+                        y: g.y2() as f64,
+                    },
+                },
+            )),
+            usvg::Paint::RadialGradient(g) => Ok(gradient(
+                g,
+                GradientKind::Radial {
+                    start_center: Point {
+                        x: g.fx() as f64,
+                        y: g.fy() as f64,
+                    },
+                    start_radius: 0.0,
+                    end_center: Point {
+                        x: g.cx() as f64,
+                        y: g.cy() as f64,
+                    },
+                    end_radius: g.r().get(),
+                },
+            )),
+            usvg::Paint::Pattern(_) => Err(unsupported("pattern")),
+        }
+    }
+
+    fn convert_transform(t: usvg::Transform) -> Affine {
+        Affine([t.sx, t.ky, t.kx, t.sy, t.tx, t.ty].map(|v| v as f64))
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_f64s(writer: &mut impl Write, values: &[f64]) -> io::Result<()> {
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_f32s(writer: &mut impl Write, values: &[f32]) -> io::Result<()> {
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_affine(writer: &mut impl Write, affine: Affine) -> io::Result<()> {
+    write_f64s(writer, &affine.0)
+}
+
+fn write_style(writer: &mut impl Write, style: &Style) -> io::Result<()> {
+    match style {
+        Style::Fill(FillStyle::NonZero) => writer.write_all(&[0, 0]),
+        Style::Fill(FillStyle::EvenOdd) => writer.write_all(&[0, 1]),
+        Style::Stroke(options) => {
+            let join = match options.join {
+                Join::Bevel => 0,
+                Join::Miter => 1,
+                Join::Round => 2,
+            };
+            let cap = |cap: Cap| match cap {
+                Cap::Butt => 0,
+                Cap::Square => 1,
+                Cap::Round => 2,
+            };
+            writer.write_all(&[1, join, cap(options.start_cap), cap(options.end_cap)])?;
+            write_f64s(writer, &[options.width, options.miter_limit, options.dash_offset])?;
+            writer.write_all(&(options.dash_pattern.len() as u64).to_le_bytes())?;
+            write_f64s(writer, &options.dash_pattern)
+        }
+    }
+}
+
+fn write_brush(writer: &mut impl Write, brush: &Brush) -> io::Result<()> {
+    match brush {
+        Brush::Solid(c) => {
+            writer.write_all(&[0])?;
+            write_f32s(writer, &[c.r, c.g, c.b, c.a])
+        }
+        Brush::Gradient(gradient) => {
+            let extend = match gradient.extend {
+                Extend::Pad => 0,
+                Extend::Repeat => 1,
+                Extend::Reflect => 2,
+            };
+            writer.write_all(&[1, extend, gradient.dither as u8])?;
+            write_f32s(writer, &[gradient.contrast, gradient.phase])?;
+            match gradient.kind {
+                GradientKind::Linear { start, end } => {
+                    writer.write_all(&[0])?;
+                    write_f64s(writer, &[start.x, start.y, end.x, end.y])?;
+                }
+                GradientKind::Radial {
+                    start_center,
+                    start_radius,
+                    end_center,
+                    end_radius,
+                } => {
+                    writer.write_all(&[1])?;
+                    write_f64s(writer, &[start_center.x, start_center.y, end_center.x, end_center.y])?;
+                    write_f32s(writer, &[start_radius, end_radius])?;
+                }
+                GradientKind::Sweep {
+                    center,
+                    start_angle,
+                    end_angle,
+                } => {
+                    writer.write_all(&[2])?;
+                    write_f64s(writer, &[center.x, center.y])?;
+                    write_f32s(writer, &[start_angle, end_angle])?;
+                }
+            }
+            writer.write_all(&(gradient.stops.len() as u64).to_le_bytes())?;
+            for stop in &gradient.stops {
+                let c = stop.color;
+                write_f32s(writer, &[stop.offset, c.r, c.g, c.b, c.a])?;
+            }
+            Ok(())
+        }
+        Brush::Image { .. } => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "image brushes can't be stored in vector assets",
+        )),
+    }
+}
+
+fn write_path(writer: &mut impl Write, path: &Path) -> io::Result<()> {
+    writer.write_all(&(path.elements.len() as u64).to_le_bytes())?;
+    for element in &path.elements {
+        match *element {
+            PathElement::MoveTo(p) => {
+                writer.write_all(&[0])?;
+                write_f64s(writer, &[p.x, p.y])?;
+            }
+            PathElement::LineTo(p) => {
+                writer.write_all(&[1])?;
+                write_f64s(writer, &[p.x, p.y])?;
+            }
+            PathElement::QuadTo(c, p) => {
+                writer.write_all(&[2])?;
+                write_f64s(writer, &[c.x, c.y, p.x, p.y])?;
+            }
+            PathElement::CurveTo(c1, c2, p) => {
+                writer.write_all(&[3])?;
+                write_f64s(writer, &[c1.x, c1.y, c2.x, c2.y, p.x, p.y])?;
+            }
+            PathElement::Close => writer.write_all(&[4])?,
+        }
+    }
+    Ok(())
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
+}
+
+fn read_point(reader: &mut impl Read) -> io::Result<Point> {
+    Ok(Point {
+        x: read_f64(reader)?,
+        y: read_f64(reader)?,
+    })
+}
+
+fn read_rgba(reader: &mut impl Read) -> io::Result<RGBA> {
+    Ok(RGBA::new(read_f32(reader)?, read_f32(reader)?, read_f32(reader)?, read_f32(reader)?))
+}
+
+fn read_affine(reader: &mut impl Read) -> io::Result<Affine> {
+    let mut coefficients = [0.0; 6];
+    for c in &mut coefficients {
+        *c = read_f64(reader)?;
+    }
+    Ok(Affine(coefficients))
+}
+
+fn read_style(reader: &mut impl Read) -> io::Result<Style> {
+    match read_u8(reader)? {
+        0 => Ok(Style::Fill(match read_u8(reader)? {
+            0 => FillStyle::NonZero,
+            _ => FillStyle::EvenOdd,
+        })),
+        1 => {
+            let join = match read_u8(reader)? {
+                0 => Join::Bevel,
+                1 => Join::Miter,
+                _ => Join::Round,
+            };
+            let cap = |cap: u8| match cap {
+                0 => Cap::Butt,
+                1 => Cap::Square,
+                _ => Cap::Round,
+            };
+            let start_cap = cap(read_u8(reader)?);
+            let end_cap = cap(read_u8(reader)?);
+            let width = read_f64(reader)?;
+            let miter_limit = read_f64(reader)?;
+            let dash_offset = read_f64(reader)?;
+            let dash_pattern = (0..read_u64(reader)?)
+                .map(|_| read_f64(reader))
+                .collect::<io::Result<Vec<_>>>()?;
+            Ok(Style::Stroke(StrokeOptions {
+                width,
+                join,
+                miter_limit,
+                start_cap,
+                end_cap,
+                dash_pattern,
+                dash_offset,
+            }))
+        }
+        tag => Err(invalid_data(format!("invalid style tag {}", tag))),
+    }
+}
+
+fn read_brush(reader: &mut impl Read) -> io::Result<Brush> {
+    match read_u8(reader)? {
+        0 => Ok(Brush::Solid(read_rgba(reader)?)),
+        1 => {
+            let extend = match read_u8(reader)? {
+                0 => Extend::Pad,
+                1 => Extend::Repeat,
+                _ => Extend::Reflect,
+            };
+            let dither = read_u8(reader)? != 0;
+            let contrast = read_f32(reader)?;
+            let phase = read_f32(reader)?;
+            let kind = match read_u8(reader)? {
+                0 => GradientKind::Linear {
+                    start: read_point(reader)?,
+                    end: read_point(reader)?,
+                },
+                1 => GradientKind::Radial {
+                    start_center: read_point(reader)?,
+                    end_center: read_point(reader)?,
+                    start_radius: read_f32(reader)?,
+                    end_radius: read_f32(reader)?,
+                },
+                2 => GradientKind::Sweep {
+                    center: read_point(reader)?,
+                    start_angle: read_f32(reader)?,
+                    end_angle: read_f32(reader)?,
+                },
+                tag => return Err(invalid_data(format!("invalid gradient tag {}", tag))),
+            };
+            let stops = (0..read_u64(reader)?)
+                .map(|_| {
+                    Ok(ColorStop {
+                        offset: read_f32(reader)?,
+                        color: read_rgba(reader)?,
+                    })
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+
+            Ok(Brush::Gradient(Gradient {
+                extend,
+                kind,
+                stops,
+                contrast,
+                phase,
+                dither,
+            }))
+        }
+        tag => Err(invalid_data(format!("invalid brush tag {}", tag))),
+    }
+}
+
+fn read_path(reader: &mut impl Read) -> io::Result<Path> {
+    let elements = (0..read_u64(reader)?)
+        .map(|_| {
+            Ok(match read_u8(reader)? {
+                0 => PathElement::MoveTo(read_point(reader)?),
+                1 => PathElement::LineTo(read_point(reader)?),
+                2 => PathElement::QuadTo(read_point(reader)?, read_point(reader)?),
+                3 => PathElement::CurveTo(read_point(reader)?, read_point(reader)?, read_point(reader)?),
+                4 => PathElement::Close,
+                tag => return Err(invalid_data(format!("invalid path element tag {}", tag))),
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok(Path { elements })
+}
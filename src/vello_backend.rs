@@ -1,37 +1,290 @@
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use crate::brushes::Extend;
-use image::GenericImageView;
 use vello::peniko::BlendMode;
 use vello::RendererOptions;
 use wgpu::util::DeviceExt;
 
-use super::brushes::{Gradient, GradientKind, Image};
-use super::scenes::SceneTrait;
-use super::text::{Alignment, FormatedText, VerticalAlignment};
-use crate::geoms::Geom;
+use super::brushes::{Gradient, GradientKind, Image, ImageFilter, ImageFormat, ImageMapping};
+use super::text::{
+    break_lines, shape_line, Alignment, FontStyle, FormatedText, Glyph, RichText, Shaper, TextLayout,
+    TextOnPath, TextRendering, TextSpan, VerticalAlignment, SYNTHETIC_OBLIQUE_ANGLE,
+};
+use crate::backend::RenderBackend;
 use crate::prerenderd_scene::PrerenderedScene;
-use crate::shapes::Shape;
-use crate::styles::{CompositeMode, FillStyle, MixMode, StrokeOptions, Style};
-use crate::{affine::Affine, scenes::Scene, Drawable};
+use crate::calibration::{DisplayCalibration, CALIBRATION_LUT_SIZE};
+use crate::error::{RendererError, Result};
+use crate::fonts::{same_face, FontData, FontRegistry};
+use crate::gratings::{GpuGrating, MAX_GRATING_WAVES};
+use crate::labels::{declutter, DeclutterOptions, LabelBox};
+use crate::locale::Locale;
+use crate::luminance::{LuminanceStats, LUMINANCE_BINS};
+use crate::quality::QualitySettings;
+use crate::resources::{LruCache, ResourceBudget, ResourceKind};
+use crate::snapshot::SnapshotBackend;
+use crate::sprites::SpriteStrip;
+use crate::shapes::{Path, Point, Rectangle, RoundedRectangle, Shape};
+use crate::styles::{Antialiasing, Cap, CompositeMode, FillStyle, LayerEffect, MixMode, StrokeOptions, Style};
+use crate::table::{ColumnWidth, Table};
+use crate::{affine::Affine, scenes::Clear, scenes::Scene, Drawable};
+use crate::colors::{ColorManagement, ColorSpace};
 
 use super::{
     brushes::{Brush, ColorStop},
     colors::RGBA,
-    shapes::{Circle, Point, Rectangle, RoundedRectangle},
 };
 
-
+#[derive(Clone)]
 pub struct VelloBackend {
     /// The Vello scene.
     pub vello_scene: vello::Scene,
     /// The global transform.
-    pub global_transform: Affine,
-    /// array of
-    pub gpu_images: Vec<(
-        vello::peniko::Image,
-        wgpu::ImageCopyTextureBase<Arc<wgpu::Texture>>,
-    )>,
+    global_transform: Affine,
+    /// The images with GPU textures drawn in the scene, keyed by the id of their data, so that
+    /// each is handed to the renderer once per frame however often it is drawn.
+    pub gpu_images: HashMap<u64, (vello::peniko::Image, wgpu::ImageCopyTextureBase<Arc<wgpu::Texture>>)>,
+    /// Lookup textures of gradients with many stops, keyed by a hash of the color ramp.
+    pub gradient_luts: LruCache<u64, vello::peniko::Image>,
+    /// Image data with an [`ImageMapping`] applied, keyed by the original data and the mapping.
+    /// The original data is kept alive so that its address can't be re-used by other images.
+    pub mapped_images: LruCache<(usize, u64), (Arc<Vec<u8>>, Arc<Vec<u8>>)>,
+    /// The blobs image data is encoded with, keyed by the address of the data, so that brushes
+    /// sharing their data (e.g. the sprites of an [`Atlas`](crate::atlas::Atlas)) are uploaded
+    /// once. The data is kept alive so that its address can't be re-used by other images, until
+    /// a frame doesn't draw it.
+    pub image_blobs: LruCache<usize, (Arc<Vec<u8>>, vello::peniko::Blob<u8>)>,
+    /// Images enlarged to approximate [`ImageFilter::Nearest`], keyed by the address of the
+    /// original data and the factor. The original data is kept alive like in `image_blobs`.
+    pub nearest_images: LruCache<(usize, u32), (Arc<Vec<u8>>, Image)>,
+    /// The images drawn for image brushes that extend differently along x and y, keyed by the
+    /// address of the pixel data and the extend modes. The data is kept alive like in
+    /// `image_blobs`.
+    pub axis_images: LruCache<(usize, Extend, Extend), (Arc<Vec<u8>>, Vec<vello::peniko::Image>)>,
+    /// Decoded bitmap glyphs, keyed by font, glyph id and font size.
+    pub bitmap_glyphs: LruCache<(u64, u32, u32), Option<(Image, Affine)>>,
+    /// Prerendered SVGs, keyed by a hash of the source.
+    pub prerendered_scenes: LruCache<u64, PrerenderedScene>,
+    /// Content rendered in separate passes with their own antialiasing method.
+    pub aa_layers: Vec<AaLayer>,
+    /// Gratings generated by a compute pass at render time.
+    pub grating_passes: Vec<GratingPass>,
+    /// Content rendered in separate passes and post-processed with layer effects.
+    pub effect_layers: Vec<EffectLayer>,
+    /// Content rendered in separate passes over the faded previous frame of the same layer.
+    pub trail_layers: Vec<TrailLayerPass>,
+    /// Limits for the cached resources of the scene.
+    pub budget: ResourceBudget,
+    /// The quality settings of the scene (see [`Scene::set_quality`]).
+    pub quality: QualitySettings,
+    /// How colors are converted (see [`Scene::set_color_management`]).
+    pub color_management: ColorManagement,
+    /// Glyph runs collected by [`Scene::batch_glyphs`], or `None` if glyphs are drawn immediately.
+    glyph_batches: Option<Vec<GlyphBatch>>,
+    /// The placeholders of layers and gratings, shared with child backends.
+    placeholders: Arc<Mutex<PlaceholderPool>>,
+}
+
+/// Placeholder images for content that is rendered at render time (layers and gratings). They
+/// are handed out in drawing order and re-used across frames, so that the renderer can keep the
+/// textures that replace them.
+#[derive(Default)]
+struct PlaceholderPool {
+    /// The placeholders of each size.
+    images: HashMap<(u32, u32), Vec<vello::peniko::Image>>,
+    /// The number of placeholders of each size handed out in the current frame.
+    used: HashMap<(u32, u32), usize>,
+}
+
+impl PlaceholderPool {
+    /// The next placeholder of a size in the current frame.
+    fn next(&mut self, width: u32, height: u32) -> vello::peniko::Image {
+        let used = self.used.entry((width, height)).or_default();
+        let images = self.images.entry((width, height)).or_default();
+        if images.len() <= *used {
+            images.push(placeholder_image(width, height));
+        }
+        *used += 1;
+        images[*used - 1].clone()
+    }
+
+    /// Start a new frame and drop the placeholders the last frame didn't use.
+    fn begin_frame(&mut self) {
+        let used = std::mem::take(&mut self.used);
+        self.images.retain(|size, images| {
+            images.truncate(used.get(size).copied().unwrap_or(0));
+            !images.is_empty()
+        });
+    }
+}
+
+/// An image without data that is drawn in place of a texture.
+fn placeholder_image(width: u32, height: u32) -> vello::peniko::Image {
+    // the placeholder is never uploaded since it is always overridden by a texture
+    let blob = vello::peniko::Blob::new(Arc::new(Vec::new()));
+    vello::peniko::Image::new(blob, vello::peniko::Format::Rgba8, width, height)
+}
+
+/// Glyph runs that share everything but their position, merged into a single draw call.
+#[derive(Clone)]
+struct GlyphBatch {
+    font: vello::peniko::Font,
+    font_size: f32,
+    variations: Vec<(String, f32)>,
+    color: RGBA,
+    /// The transform of the first run; the glyphs of later runs are moved into its space.
+    transform: Affine,
+    glyph_transform: Option<Affine>,
+    rendering: TextRendering,
+    glyphs: Vec<Glyph>,
+}
+
+/// Content that is rendered offscreen with its own antialiasing method and composited into the
+/// scene as an image (see [`Scene::draw_with_antialiasing`]).
+#[derive(Clone)]
+pub struct AaLayer {
+    /// The antialiasing method of the layer.
+    pub antialiasing: Antialiasing,
+    /// The content of the layer, in device space.
+    pub content: VelloBackend,
+    /// The image drawn in place of the layer, replaced with the rendered layer at render time.
+    pub placeholder: vello::peniko::Image,
+}
+
+/// Content that is rendered offscreen, post-processed with [`LayerEffect`]s and composited into
+/// the scene as an image (see [`RenderBackend::append_with_effects`]).
+#[derive(Clone)]
+pub struct EffectLayer {
+    /// Whether the luminance of the content is converted to alpha before the effects are
+    /// applied (see [`RenderBackend::append_luminance_mask`]).
+    pub luminance_mask: bool,
+    /// The effects, applied in order.
+    pub effects: Vec<LayerEffect>,
+    /// The content of the layer, in device space.
+    pub content: VelloBackend,
+    /// The image drawn in place of the layer, replaced with the processed layer at render time.
+    pub placeholder: vello::peniko::Image,
+}
+
+/// Content that is rendered offscreen over its own previous frame (see
+/// [`TrailLayer`](crate::layer::TrailLayer)) and composited into the scene as an image.
+#[derive(Clone)]
+pub struct TrailLayerPass {
+    /// The id of the layer, which identifies its previous frame.
+    pub id: u64,
+    /// The opacity of the previous frame under the new content.
+    pub decay: f32,
+    /// The content of the layer, in device space.
+    pub content: VelloBackend,
+    /// The image drawn in place of the layer, replaced with the rendered layer at render time.
+    pub placeholder: vello::peniko::Image,
+}
+
+/// The uniforms of the effect shader (`assets/shaders/effects.wgsl`).
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct EffectParams {
+    color: [f32; 4],
+    offset: [i32; 2],
+    direction: [i32; 2],
+    sigma: f32,
+    radius: i32,
+    mode: u32,
+    _padding: u32,
+}
+
+/// The modes of the effect shader.
+const EFFECT_MODE_BLUR: u32 = 0;
+const EFFECT_MODE_SHADOW: u32 = 1;
+const EFFECT_MODE_COMPOSITE: u32 = 2;
+const EFFECT_MODE_LUMINANCE_MASK: u32 = 3;
+/// The largest blur radius, in pixels, to bound the cost of very wide blurs.
+const EFFECT_MAX_RADIUS: f64 = 128.0;
+
+/// A [`GpuGrating`] that is generated into a texture at render time (see [`VelloRenderer`]).
+#[derive(Clone)]
+pub struct GratingPass {
+    /// The parameters of the compute pass.
+    pub params: GratingParams,
+    /// The image drawn in place of the grating, replaced with the generated texture at render time.
+    pub placeholder: vello::peniko::Image,
+}
+
+/// The uniforms of the grating shader (`assets/shaders/grating.wgsl`).
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct GratingParams {
+    mean: [f32; 4],
+    size: [f32; 2],
+    count: u32,
+    _padding: u32,
+    /// Spatial frequency, orientation, phase and contrast of each wave.
+    waves: [[f32; 4]; MAX_GRATING_WAVES],
+}
+
+/// The largest texture generated for a grating, in pixels per side.
+const GRATING_MAX_TEXTURE_SIZE: f64 = 8192.0;
+
+/// Linear gradients with at least this many stops are drawn from a lookup texture.
+const GRADIENT_LUT_MIN_STOPS: usize = 32;
+/// Maximum number of cached gradient lookup textures.
+const GRADIENT_LUT_CACHE_SIZE: usize = 64;
+/// Maximum number of cached mapped images.
+const MAPPED_IMAGE_CACHE_SIZE: usize = 64;
+/// Maximum number of cached image blobs.
+const IMAGE_BLOB_CACHE_SIZE: usize = 256;
+/// The largest factor images are enlarged by to approximate nearest neighbor sampling.
+const NEAREST_MAX_UPSCALE: u32 = 8;
+/// The largest number of pixels of an image enlarged to approximate nearest neighbor sampling.
+const NEAREST_MAX_PIXELS: u64 = 4096 * 4096;
+/// Maximum number of cached enlarged images.
+const NEAREST_IMAGE_CACHE_SIZE: usize = 64;
+/// Maximum number of cached images of brushes that extend differently along x and y.
+const AXIS_IMAGE_CACHE_SIZE: usize = 64;
+/// Width and height of the tiled gradient dither texture.
+const DITHER_NOISE_SIZE: u32 = 64;
+/// Maximum number of luminance measurements waiting for the GPU; frames are not measured while
+/// the GPU falls behind.
+const MAX_PENDING_LUMINANCE_READBACKS: usize = 3;
+
+/// The uniforms of the calibration shader.
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct CalibrationParams {
+    size: u32,
+    linear_input: u32,
+    srgb_target: u32,
+    _padding: u32,
+}
+
+/// The GPU resources of a [`DisplayCalibration`].
+struct CalibrationStage {
+    /// The lookup tables of all channels, one after the other.
+    lut: wgpu::Buffer,
+    /// The pipeline for the format of the last surface, created when it is first needed.
+    pipeline: Option<(wgpu::TextureFormat, wgpu::RenderPipeline, wgpu::BindGroupLayout)>,
+    /// The frame before calibration, re-created when the size of the surface changes.
+    frame: Option<wgpu::Texture>,
+}
+
+/// The uniforms of the color conversion shader.
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct ConvertParams {
+    rows: [[f32; 4]; 3],
+    encode: u32,
+    _padding: [u32; 3],
+}
+
+/// The uniforms of the luminance shader.
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct LuminanceParams {
+    encoded: u32,
+    bgra: u32,
 }
 
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
@@ -54,10 +307,78 @@ pub struct VelloRenderer {
     pub gamma_buffer: wgpu::Buffer,
     /// The bind group
     pub bind_group: wgpu::BindGroup,
+    /// The antialiasing method used unless a render call overrides it.
+    pub antialiasing: Antialiasing,
+    /// Images that are currently replaced by GPU textures, keyed by the id of their data, with
+    /// whether vello holds a color converted copy of the texture instead. The replacements
+    /// persist across frames until they are evicted or invalidated, or the images are dropped.
+    overridden_images: LruCache<u64, (vello::peniko::Image, Arc<wgpu::Texture>, bool)>,
+    /// The color management the replacements of GPU images were converted with.
+    image_color_management: ColorManagement,
+    /// The compute pipeline that converts the colors of GPU images, created when it is first
+    /// needed.
+    convert_pipeline: Option<(wgpu::ComputePipeline, wgpu::BindGroupLayout)>,
+    /// The compute pipeline that generates gratings, created when it is first needed.
+    grating_pipeline: Option<(wgpu::ComputePipeline, wgpu::BindGroupLayout)>,
+    /// The compute pipeline that applies layer effects, created when it is first needed.
+    effect_pipeline: Option<(wgpu::ComputePipeline, wgpu::BindGroupLayout)>,
+    /// The textures that replace the placeholders of layers and gratings, keyed by the id of
+    /// the placeholder. They are re-used across frames and released when their placeholder
+    /// isn't drawn in a render.
+    targets: HashMap<u64, OffscreenTarget>,
+    /// The placeholders of the copy of the last frame of a scene rendered with
+    /// [`Clear::Preserve`] and of the new frame.
+    preserved_frame: Option<(vello::peniko::Image, vello::peniko::Image)>,
+    /// The placeholders of the copies of the last frames of the trail layers, keyed by the id of
+    /// the layer.
+    trails: HashMap<u64, vello::peniko::Image>,
+    /// The compute pipeline that measures luminance, created when it is first needed.
+    luminance_pipeline: Option<(wgpu::ComputePipeline, wgpu::BindGroupLayout)>,
+    /// Whether the luminance of every rendered frame is measured.
+    luminance_measurement: bool,
+    /// Luminance measurements that are still being read back, oldest first.
+    pending_luminance: VecDeque<(wgpu::Buffer, Receiver<std::result::Result<(), wgpu::BufferAsyncError>>)>,
+    /// The most recent finished luminance measurement.
+    luminance_stats: Option<LuminanceStats>,
+    /// The compute pipeline that encodes linear frames for the output, created when it is first
+    /// needed.
+    encode_pipeline: Option<(wgpu::ComputePipeline, wgpu::BindGroupLayout)>,
+    /// The calibration applied to frames rendered to surfaces.
+    calibration: Option<CalibrationStage>,
+}
+
+/// A texture that replaces a placeholder image when rendering (see [`VelloRenderer::target`]).
+struct OffscreenTarget {
+    placeholder: vello::peniko::Image,
+    texture: Arc<wgpu::Texture>,
+    /// Whether the placeholder was drawn since the unused targets were last released.
+    used: bool,
+}
+
+/// Options for a single render call.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderParams {
+    /// The antialiasing method for this call, or `None` to use the renderer's default.
+    pub antialiasing: Option<Antialiasing>,
+}
+
+impl RenderParams {
+    /// Set the antialiasing method.
+    pub fn with_antialiasing(mut self, antialiasing: Antialiasing) -> Self {
+        self.antialiasing = Some(antialiasing);
+        self
+    }
 }
 
 impl VelloRenderer {
-    pub fn new(device: &wgpu::Device, surface_format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+    /// Create a new renderer that uses the given antialiasing method by default.
+    pub fn new(
+        device: &wgpu::Device,
+        surface_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        antialiasing: Antialiasing,
+    ) -> Result<Self> {
         let renderer = vello::Renderer::new(
             &device,
             RendererOptions {
@@ -67,7 +388,7 @@ impl VelloRenderer {
                 num_init_threads: std::num::NonZeroUsize::new(1),
             },
         )
-            .unwrap();
+        .map_err(|e| RendererError::Gpu(e.to_string()))?;
 
 
         // create a render pipeline
@@ -76,13 +397,28 @@ impl VelloRenderer {
         let gamma_buffer = Self::create_uniform_buffer(device);
         let bind_group = Self::create_bind_group(device, &texture);
 
-        Self {
+        Ok(Self {
             renderer,
             render_pipeline,
             texture,
             gamma_buffer,
             bind_group,
-        }
+            antialiasing,
+            overridden_images: LruCache::new(),
+            image_color_management: ColorManagement::default(),
+            convert_pipeline: None,
+            grating_pipeline: None,
+            effect_pipeline: None,
+            targets: HashMap::new(),
+            preserved_frame: None,
+            trails: HashMap::new(),
+            luminance_pipeline: None,
+            luminance_measurement: false,
+            pending_luminance: VecDeque::new(),
+            luminance_stats: None,
+            encode_pipeline: None,
+            calibration: None,
+        })
     }
 
     /// Re-size the texture
@@ -96,104 +432,1501 @@ impl VelloRenderer {
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        surface: &wgpu::SurfaceTexture,
-        scene: &Scene<VelloBackend>,
-    ) {
-        let vello_scene = &scene.backend.vello_scene;
-        let render_params = vello::RenderParams {
-            base_color: scene.background_color.into(),
-            width: surface.texture.width(),
-            height: surface.texture.height(),
-            antialiasing_method: vello::AaConfig::Msaa16,
-        };
-        // (interim) replace the images with GPU textures.
-        for (image, wgpu_texture) in &scene.backend.gpu_images {
+        surface: &wgpu::SurfaceTexture,
+        scene: &Scene<VelloBackend>,
+    ) -> Result<()> {
+        self.render_to_surface_with(device, queue, surface, scene, &RenderParams::default())
+    }
+
+    /// Render the scene to a WGPU surface with options for this call only.
+    pub fn render_to_surface_with(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface: &wgpu::SurfaceTexture,
+        scene: &Scene<VelloBackend>,
+        params: &RenderParams,
+    ) -> Result<()> {
+        let (width, height) = (surface.texture.width(), surface.texture.height());
+        let since = self.overridden_images.clock() + 1;
+        let calibrated = self.calibration.is_some();
+        if !calibrated && scene.color_management.needs_output_encoding() && !surface.texture.format().is_srgb() {
+            return Err(RendererError::Gpu(
+                "linear blending needs an sRGB surface format to encode the output".to_string(),
+            ));
+        }
+        let antialiasing = params
+            .antialiasing
+            .or(scene.quality.antialiasing)
+            .unwrap_or(self.antialiasing);
+        let cleared = self.apply_clear(device, queue, scene, width, height, antialiasing)?;
+        let backend = cleared.as_ref().unwrap_or(&scene.backend);
+        let render_params = vello::RenderParams {
+            base_color: scene.color_management.convert(scene.background_color).into(),
+            width,
+            height,
+            antialiasing_method: antialiasing.into(),
+        };
+        self.prepare_backend(device, queue, backend, width, height, antialiasing)?;
+        if calibrated {
+            let color_management = scene.color_management;
+            let linear = color_management.linear_blending || color_management.output == ColorSpace::LinearSrgb;
+            self.render_calibrated(device, queue, &backend.vello_scene, surface, &render_params, linear)?;
+        } else {
+            self.renderer
+                .render_to_surface(device, queue, &backend.vello_scene, surface, &render_params)
+                .map_err(|e| RendererError::Gpu(e.to_string()))?;
+        }
+        // surfaces can only be measured if they were configured to be sampled
+        if self.luminance_measurement && surface.texture.usage().contains(wgpu::TextureUsages::TEXTURE_BINDING) {
+            self.measure_luminance(device, queue, &surface.texture)?;
+        }
+        self.release_unused_targets();
+        self.release_dropped_images();
+        self.enforce_budget(&scene.backend, since);
+        Ok(())
+    }
+
+    /// Render the scene into a new texture (without gamma correction), e.g. to pre-render stimuli
+    /// or to post-process the result on the GPU.
+    ///
+    /// The texture is `Rgba8Unorm` and can be sampled, copied from and used as a storage texture.
+    pub fn render_to_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene<VelloBackend>,
+        width: u32,
+        height: u32,
+    ) -> Result<wgpu::Texture> {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            label: Some("Offscreen Scene"),
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.render_to_texture_view(device, queue, &view, width, height, scene)?;
+        if self.luminance_measurement {
+            self.measure_luminance(device, queue, &texture)?;
+        }
+        Ok(texture)
+    }
+
+    /// Render the scene offscreen and read the result back into an image, e.g. to save reference
+    /// screenshots or static stimuli with [`save_png`](crate::export::save_png), which tags the
+    /// file with the color space of the render.
+    ///
+    /// This blocks until the GPU has finished rendering.
+    pub fn render_to_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene<VelloBackend>,
+        width: u32,
+        height: u32,
+    ) -> Result<image::RgbaImage> {
+        let texture = self.render_to_texture(device, queue, scene, width, height)?;
+
+        // rows of texture copies have to be aligned
+        let row_bytes = width as usize * 4;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+        let padded_row_bytes = row_bytes.div_ceil(alignment) * alignment;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_row_bytes * height as usize) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes as u32),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| RendererError::Readback(e.to_string()))?
+            .map_err(|e| RendererError::Readback(e.to_string()))?;
+
+        let data = slice
+            .get_mapped_range()
+            .chunks_exact(padded_row_bytes)
+            .flat_map(|row| row[..row_bytes].to_vec())
+            .collect();
+        buffer.unmap();
+        image::RgbaImage::from_raw(width, height, data)
+            .ok_or_else(|| RendererError::Readback("invalid readback buffer size".to_string()))
+    }
+
+    /// Render the scene to a view of an existing WGPU texture.
+    pub fn render_to_texture_view(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+        scene: &Scene<VelloBackend>,
+    ) -> Result<()> {
+        self.render_to_texture_view_with(device, queue, texture, width, height, scene, &RenderParams::default())
+    }
+
+    /// Render the scene to a view of an existing WGPU texture with options for this call only.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_to_texture_view_with(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+        scene: &Scene<VelloBackend>,
+        params: &RenderParams,
+    ) -> Result<()> {
+        let since = self.overridden_images.clock() + 1;
+        let antialiasing = params
+            .antialiasing
+            .or(scene.quality.antialiasing)
+            .unwrap_or(self.antialiasing);
+        let cleared = self.apply_clear(device, queue, scene, width, height, antialiasing)?;
+        let backend = cleared.as_ref().unwrap_or(&scene.backend);
+        let render_params = vello::RenderParams {
+            base_color: scene.color_management.convert(scene.background_color).into(),
+            width,
+            height,
+            antialiasing_method: antialiasing.into(),
+        };
+
+        self.prepare_backend(device, queue, backend, width, height, antialiasing)?;
+        if scene.color_management.needs_output_encoding() {
+            // vello can't render to sRGB textures, so the linear frame is encoded in a second pass
+            let linear = Self::create_offscreen_texture(device, width, height, "Linear Frame");
+            let view = linear.create_view(&wgpu::TextureViewDescriptor::default());
+            self.renderer
+                .render_to_texture(device, queue, &backend.vello_scene, &view, &render_params)
+                .map_err(|e| RendererError::Gpu(e.to_string()))?;
+            self.encode_output(device, queue, &linear, texture);
+        } else {
+            self.renderer
+                .render_to_texture(device, queue, &backend.vello_scene, texture, &render_params)
+                .map_err(|e| RendererError::Gpu(e.to_string()))?;
+        }
+        self.release_unused_targets();
+        self.release_dropped_images();
+        self.enforce_budget(&scene.backend, since);
+        Ok(())
+    }
+
+    /// Measure the luminance of every frame rendered with [`render_to_surface`](Self::render_to_surface)
+    /// or [`render_to_texture`](Self::render_to_texture), and make the results available through
+    /// [`luminance_stats`](Self::luminance_stats).
+    ///
+    /// Surfaces are only measured if they were configured with
+    /// [`TEXTURE_BINDING`](wgpu::TextureUsages::TEXTURE_BINDING) usage.
+    pub fn set_luminance_measurement(&mut self, enabled: bool) {
+        self.luminance_measurement = enabled;
+    }
+
+    /// Start measuring the luminance histogram and mean of a texture with
+    /// [`TEXTURE_BINDING`](wgpu::TextureUsages::TEXTURE_BINDING) usage, e.g. a rendered frame.
+    ///
+    /// This doesn't block: the result is read back asynchronously and returned by
+    /// [`luminance_stats`](Self::luminance_stats) once the GPU has finished, usually a frame or
+    /// two later. The texture is skipped if too many measurements are still pending.
+    pub fn measure_luminance(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) -> Result<()> {
+        if !texture.usage().contains(wgpu::TextureUsages::TEXTURE_BINDING) {
+            return Err(RendererError::Gpu("the texture can't be sampled to measure its luminance".to_string()));
+        }
+        self.poll_luminance(device);
+        if self.pending_luminance.len() >= MAX_PENDING_LUMINANCE_READBACKS {
+            return Ok(());
+        }
+
+        let (pipeline, layout) = self
+            .luminance_pipeline
+            .get_or_insert_with(|| Self::create_luminance_pipeline(device));
+        let format = texture.format();
+        let params = LuminanceParams {
+            // sRGB texture formats are linearized when they are loaded
+            encoded: !format.is_srgb() as u32,
+            bgra: matches!(format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb) as u32,
+        };
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Luminance Params"),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        // the histogram followed by the low and high word of the sum, zeroed on creation
+        let stats_size = ((LUMINANCE_BINS + 2) * 4) as u64;
+        let stats = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Luminance Stats"),
+            size: stats_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Luminance Readback"),
+            size: stats_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Luminance Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: stats.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Luminance Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Luminance Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            // the shader uses workgroups of 8x8 texels
+            pass.dispatch_workgroups(texture.width().div_ceil(8), texture.height().div_ceil(8), 1);
+        }
+        encoder.copy_buffer_to_buffer(&stats, 0, &readback, 0, stats_size);
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        readback.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.pending_luminance.push_back((readback, receiver));
+        Ok(())
+    }
+
+    /// The most recent luminance measurement that the GPU has finished (see
+    /// [`set_luminance_measurement`](Self::set_luminance_measurement) and
+    /// [`measure_luminance`](Self::measure_luminance)), without waiting for pending ones.
+    pub fn luminance_stats(&mut self, device: &wgpu::Device) -> Option<&LuminanceStats> {
+        self.poll_luminance(device);
+        self.luminance_stats.as_ref()
+    }
+
+    /// Read back the luminance measurements that the GPU has finished.
+    fn poll_luminance(&mut self, device: &wgpu::Device) {
+        device.poll(wgpu::Maintain::Poll);
+        while let Some((_, receiver)) = self.pending_luminance.front() {
+            let finished = match receiver.try_recv() {
+                Ok(result) => result.is_ok(),
+                Err(std::sync::mpsc::TryRecvError::Empty) => return,
+                // the mapping was cancelled, e.g. because the device was lost
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => false,
+            };
+            let Some((readback, _)) = self.pending_luminance.pop_front() else {
+                return;
+            };
+            if !finished {
+                continue;
+            }
+
+            let words: Vec<u32> = readback
+                .slice(..)
+                .get_mapped_range()
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect();
+            readback.unmap();
+            let sum = words[LUMINANCE_BINS] as u64 | ((words[LUMINANCE_BINS + 1] as u64) << 32);
+            self.luminance_stats = Some(LuminanceStats::from_fixed_point(words[..LUMINANCE_BINS].to_vec(), sum));
+        }
+    }
+
+    fn create_luminance_pipeline(device: &wgpu::Device) -> (wgpu::ComputePipeline, wgpu::BindGroupLayout) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Luminance Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/shaders/luminance.wgsl").into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Luminance Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Luminance Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Luminance Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        (pipeline, bind_group_layout)
+    }
+
+    /// Apply a calibration to all frames rendered with [`render_to_surface`](Self::render_to_surface)
+    /// before they are presented, or remove it with `None`. Offscreen renders are not calibrated.
+    ///
+    /// The calibration maps sRGB-encoded values (frames rendered in linear light are encoded
+    /// first) to the values sent to the display, so it also works with sRGB surface formats and
+    /// linear blending on other surface formats.
+    pub fn set_calibration(&mut self, device: &wgpu::Device, calibration: Option<&DisplayCalibration>) {
+        self.calibration = calibration.map(|calibration| CalibrationStage {
+            lut: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Calibration LUT"),
+                contents: bytemuck::cast_slice(&calibration.resampled()),
+                usage: wgpu::BufferUsages::STORAGE,
+            }),
+            pipeline: None,
+            frame: None,
+        });
+    }
+
+    /// Render a scene into an offscreen frame and draw it to a surface through the calibration.
+    fn render_calibrated(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vello_scene: &vello::Scene,
+        surface: &wgpu::SurfaceTexture,
+        render_params: &vello::RenderParams,
+        linear: bool,
+    ) -> Result<()> {
+        let Some(stage) = &mut self.calibration else {
+            return Ok(());
+        };
+        let (width, height) = (render_params.width, render_params.height);
+        let frame = match stage.frame.take() {
+            Some(frame) if (frame.width(), frame.height()) == (width, height) => frame,
+            _ => Self::create_offscreen_texture(device, width, height, "Calibration Frame"),
+        };
+        let frame_view = frame.create_view(&wgpu::TextureViewDescriptor::default());
+        self.renderer
+            .render_to_texture(device, queue, vello_scene, &frame_view, render_params)
+            .map_err(|e| RendererError::Gpu(e.to_string()))?;
+
+        let format = surface.texture.format();
+        if !matches!(&stage.pipeline, Some((pipeline_format, ..)) if *pipeline_format == format) {
+            let (pipeline, layout) = Self::create_calibration_pipeline(device, format);
+            stage.pipeline = Some((format, pipeline, layout));
+        }
+        let Some((_, pipeline, layout)) = &stage.pipeline else {
+            return Ok(());
+        };
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Calibration Params"),
+            contents: bytemuck::bytes_of(&CalibrationParams {
+                size: CALIBRATION_LUT_SIZE as u32,
+                linear_input: linear as u32,
+                srgb_target: format.is_srgb() as u32,
+                _padding: 0,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Calibration Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&frame_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: stage.lut.as_entire_binding(),
+                },
+            ],
+        });
+
+        let surface_view = surface.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Calibration Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Calibration Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &surface_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            // a single triangle covers the whole surface
+            pass.draw(0..3, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+        stage.frame = Some(frame);
+        Ok(())
+    }
+
+    fn create_calibration_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> (wgpu::RenderPipeline, wgpu::BindGroupLayout) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Calibration Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/shaders/calibration.wgsl").into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Calibration Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Calibration Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Calibration Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        (pipeline, bind_group_layout)
+    }
+
+    /// Encode a frame rendered in linear light with the sRGB transfer function.
+    fn encode_output(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        input: &wgpu::Texture,
+        output: &wgpu::TextureView,
+    ) {
+        let (pipeline, layout) = self
+            .encode_pipeline
+            .get_or_insert_with(|| Self::create_encode_pipeline(device));
+        let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Encode Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&input_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(output),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Encode Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Encode Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            // the shader uses workgroups of 8x8 texels
+            pass.dispatch_workgroups(input.width().div_ceil(8), input.height().div_ceil(8), 1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn create_encode_pipeline(device: &wgpu::Device) -> (wgpu::ComputePipeline, wgpu::BindGroupLayout) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Encode Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/shaders/encode.wgsl").into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Encode Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Encode Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Encode Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        (pipeline, bind_group_layout)
+    }
+
+    /// Render the scene to a WGPU surface but sets up its own render pass.
+    pub fn render_to_surface2(&mut self,
+                              device: &wgpu::Device,
+                              queue: &wgpu::Queue,
+                              surface: &wgpu::SurfaceTexture,
+                              scene: &Scene<VelloBackend>,
+    ) -> Result<()> {
+        // create texture view
+        let texture_view = self.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // render the scene
+        let (width, height) = (surface.texture.width(), surface.texture.height());
+        self.render_to_texture_view(device, queue, &texture_view, width, height, scene)?;
+
+
+        // create a new render pass
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+
+
+        let surface_texture_view = surface.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+
+        {
+            // bind the render pass
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &surface_texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            // bind the render pipeline
+            render_pass.set_pipeline(&self.render_pipeline);
+            // bind the bind group
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            // draw the quad
+            render_pass.draw(0..6, 0..1);
+        }
+
+        // submit the render pass
+        queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
+
+    /// Render everything a backend draws as textures (gratings and layers rendered in their own
+    /// passes) and replace the images with GPU textures. Layers without their own antialiasing
+    /// method are rendered with `antialiasing`, the method of the content they are drawn into.
+    fn prepare_backend(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        backend: &VelloBackend,
+        width: u32,
+        height: u32,
+        antialiasing: Antialiasing,
+    ) -> Result<()> {
+        self.render_gratings(device, queue, backend);
+        self.render_aa_layers(device, queue, backend, width, height)?;
+        self.render_effect_layers(device, queue, backend, width, height, antialiasing)?;
+        self.render_trail_layers(device, queue, backend, width, height, antialiasing)?;
+        // images converted with another color management are converted again
+        if self.image_color_management != backend.color_management {
+            for (_, (image, _, _)) in self.overridden_images.remove_where(|_, _| true) {
+                self.renderer.override_image(&image, None);
+            }
+            self.image_color_management = backend.color_management;
+        }
+        for (image, wgpu_texture) in backend.gpu_images.values() {
+            self.override_image(device, queue, image, wgpu_texture);
+        }
+        Ok(())
+    }
+
+    /// Apply the [`Clear`] behavior of a scene. Returns the content to render in place of the
+    /// scene's own content, if it differs.
+    fn apply_clear(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene<VelloBackend>,
+        width: u32,
+        height: u32,
+        antialiasing: Antialiasing,
+    ) -> Result<Option<VelloBackend>> {
+        match scene.clear {
+            Clear::Color => Ok(None),
+            Clear::Brush(_) => Ok(scene.content_with_clear_brush()),
+            Clear::Preserve => {
+                // draw the new content over a copy of the previous frame, and copy the result
+                // for the next frame
+                let (previous, frame) = match self.preserved_frame.take() {
+                    Some((previous, frame)) if (previous.width, previous.height) == (width, height) => {
+                        (previous, frame)
+                    }
+                    // new textures are cleared, so the first frame is drawn over nothing
+                    _ => (placeholder_image(width, height), placeholder_image(width, height)),
+                };
+                let previous_texture = self.target(device, &previous, "Preserved Frame");
+                let mut content = scene.backend.create_child();
+                content.vello_scene.draw_image(&previous, vello::kurbo::Affine::IDENTITY);
+                content.append(&scene.backend, Affine::identity());
+                self.prepare_backend(device, queue, &content, width, height, antialiasing)?;
+
+                let texture = self.target(device, &frame, "Preserved Frame");
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                let render_params = vello::RenderParams {
+                    base_color: scene.color_management.convert(scene.background_color).into(),
+                    width,
+                    height,
+                    antialiasing_method: antialiasing.into(),
+                };
+                self.renderer
+                    .render_to_texture(device, queue, &content.vello_scene, &view, &render_params)
+                    .map_err(|e| RendererError::Gpu(e.to_string()))?;
+                Self::copy_texture(device, queue, &texture, &previous_texture);
+
+                let mut shown = scene.backend.create_child();
+                shown.vello_scene.draw_image(&frame, vello::kurbo::Affine::IDENTITY);
+                self.preserved_frame = Some((previous, frame));
+                Ok(Some(shown))
+            }
+        }
+    }
+
+    /// Forget the frame kept for scenes that are rendered with [`Clear::Preserve`], so that the
+    /// next frame starts from the background color again.
+    pub fn clear_preserved_frame(&mut self) {
+        self.preserved_frame = None;
+    }
+
+    /// The texture that replaces a placeholder. It is created and registered with vello when the
+    /// placeholder is first drawn, re-used while the placeholder is drawn in every render and
+    /// released by [`release_unused_targets`](Self::release_unused_targets) otherwise.
+    fn target(&mut self, device: &wgpu::Device, placeholder: &vello::peniko::Image, label: &str) -> Arc<wgpu::Texture> {
+        let target = self.targets.entry(placeholder.data.id()).or_insert_with(|| {
+            let (width, height) = (placeholder.width, placeholder.height);
+            let texture = Arc::new(Self::create_offscreen_texture(device, width, height, label));
+            self.renderer
+                .override_image(placeholder, Some(Self::texture_copy(texture.clone())));
+            OffscreenTarget {
+                placeholder: placeholder.clone(),
+                texture,
+                used: false,
+            }
+        });
+        target.used = true;
+        target.texture.clone()
+    }
+
+    /// Release the textures of the placeholders that weren't drawn since the last call, e.g.
+    /// because their layers were removed from the scene, and the previous frames that were
+    /// copied into them.
+    fn release_unused_targets(&mut self) {
+        let renderer = &mut self.renderer;
+        self.targets.retain(|_, target| {
+            if !target.used {
+                renderer.override_image(&target.placeholder, None);
+            }
+            std::mem::take(&mut target.used)
+        });
+        // forget the previous frames of trail layers that weren't drawn
+        let targets = &self.targets;
+        self.trails.retain(|_, previous| targets.contains_key(&previous.data.id()));
+        if let Some((previous, _)) = &self.preserved_frame {
+            if !self.targets.contains_key(&previous.data.id()) {
+                self.preserved_frame = None;
+            }
+        }
+    }
+
+    /// Copy the whole of a texture into another texture of the same size.
+    fn copy_texture(device: &wgpu::Device, queue: &wgpu::Queue, source: &wgpu::Texture, destination: &wgpu::Texture) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Copy Encoder"),
+        });
+        encoder.copy_texture_to_texture(source.as_image_copy(), destination.as_image_copy(), source.size());
+        queue.submit(Some(encoder.finish()));
+    }
+
+    /// The whole of a texture, as used to override images.
+    fn texture_copy(texture: Arc<wgpu::Texture>) -> wgpu::ImageCopyTextureBase<Arc<wgpu::Texture>> {
+        wgpu::ImageCopyTextureBase {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        }
+    }
+
+    /// Render the antialiasing layers of a backend (including nested layers) offscreen and
+    /// replace their placeholders with the rendered textures.
+    fn render_aa_layers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        backend: &VelloBackend,
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        for layer in &backend.aa_layers {
+            self.prepare_backend(device, queue, &layer.content, width, height, layer.antialiasing)?;
+
+            let texture = self.target(device, &layer.placeholder, "Antialiasing Layer");
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let render_params = vello::RenderParams {
+                base_color: vello::peniko::Color::TRANSPARENT,
+                width,
+                height,
+                antialiasing_method: layer.antialiasing.into(),
+            };
+            self.renderer
+                .render_to_texture(device, queue, &layer.content.vello_scene, &view, &render_params)
+                .map_err(|e| RendererError::Gpu(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Render the effect layers of a backend (including nested layers) offscreen, apply their
+    /// effects and replace their placeholders with the results.
+    fn render_effect_layers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        backend: &VelloBackend,
+        width: u32,
+        height: u32,
+        antialiasing: Antialiasing,
+    ) -> Result<()> {
+        for layer in &backend.effect_layers {
+            self.prepare_backend(device, queue, &layer.content, width, height, antialiasing)?;
+
+            let target = self.target(device, &layer.placeholder, "Effect Layer");
+            let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            let render_params = vello::RenderParams {
+                base_color: vello::peniko::Color::TRANSPARENT,
+                width,
+                height,
+                antialiasing_method: antialiasing.into(),
+            };
             self.renderer
-                .override_image(image, Some(wgpu_texture.clone()));
+                .render_to_texture(device, queue, &layer.content.vello_scene, &view, &render_params)
+                .map_err(|e| RendererError::Gpu(e.to_string()))?;
+
+            let pipeline = self
+                .effect_pipeline
+                .get_or_insert_with(|| Self::create_effect_pipeline(device));
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Effect Encoder"),
+            });
+            // the effects are applied to new textures, and the result is copied back
+            let mut result: Option<wgpu::Texture> = None;
+            if layer.luminance_mask {
+                let params = EffectParams {
+                    color: [0.0; 4],
+                    offset: [0, 0],
+                    direction: [0, 0],
+                    sigma: 0.0,
+                    radius: 0,
+                    mode: EFFECT_MODE_LUMINANCE_MASK,
+                    _padding: 0,
+                };
+                result = Some(Self::effect_pass(device, &mut encoder, pipeline, &params, &target, &target));
+            }
+            for effect in &layer.effects {
+                let texture = result.as_ref().unwrap_or(&*target);
+                let (sigma, shadow) = match *effect {
+                    LayerEffect::Blur { sigma } => (sigma, None),
+                    LayerEffect::DropShadow { dx, dy, sigma, color } => (sigma, Some((dx, dy, color))),
+                };
+                let sigma = sigma.max(0.0);
+                let mut params = EffectParams {
+                    color: [0.0; 4],
+                    offset: [0, 0],
+                    direction: [1, 0],
+                    sigma: sigma as f32,
+                    radius: (3.0 * sigma).ceil().min(EFFECT_MAX_RADIUS) as i32,
+                    mode: EFFECT_MODE_BLUR,
+                    _padding: 0,
+                };
+                if let Some((dx, dy, color)) = shadow {
+                    params.color = [color.r * color.a, color.g * color.a, color.b * color.a, color.a];
+                    params.offset = [dx.round() as i32, dy.round() as i32];
+                    params.mode = EFFECT_MODE_SHADOW;
+                }
+
+                // the blur is separable, so it's done in a horizontal and a vertical pass
+                let horizontal = Self::effect_pass(device, &mut encoder, pipeline, &params, texture, texture);
+                params.offset = [0, 0];
+                params.direction = [0, 1];
+                params.mode = EFFECT_MODE_BLUR;
+                let blurred = Self::effect_pass(device, &mut encoder, pipeline, &params, &horizontal, &horizontal);
+                let next = match shadow {
+                    Some(_) => {
+                        params.mode = EFFECT_MODE_COMPOSITE;
+                        Self::effect_pass(device, &mut encoder, pipeline, &params, &blurred, texture)
+                    }
+                    None => blurred,
+                };
+                result = Some(next);
+            }
+            if let Some(result) = &result {
+                encoder.copy_texture_to_texture(result.as_image_copy(), target.as_image_copy(), result.size());
+            }
+            queue.submit(Some(encoder.finish()));
         }
-        self.renderer
-            .render_to_surface(device, queue, vello_scene, surface, &render_params);
+        Ok(())
     }
 
-    /// Render the scene to a WGPU texture.
-    pub fn render_to_texture(
+    /// Render the trail layers of a backend over their faded previous frames and replace their
+    /// placeholders with the results, which are copied for the next frame.
+    fn render_trail_layers(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        texture: &wgpu::TextureView,
+        backend: &VelloBackend,
         width: u32,
         height: u32,
-        scene: &Scene<VelloBackend>,
-    ) {
-        // print the texture format
+        antialiasing: Antialiasing,
+    ) -> Result<()> {
+        for layer in &backend.trail_layers {
+            self.prepare_backend(device, queue, &layer.content, width, height, antialiasing)?;
 
-        let vello_scene = &scene.backend.vello_scene;
-        let render_params = vello::RenderParams {
-            base_color: scene.background_color.into(),
-            width: width,
-            height: height,
-            antialiasing_method: vello::AaConfig::Msaa16,
-        };
+            // the previous frame is drawn from a copy of the last rendered frame of the layer
+            let previous = match self.trails.get(&layer.id) {
+                Some(previous) if (previous.width, previous.height) == (width, height) => previous.clone(),
+                // new textures are cleared, so the first frame is drawn over nothing
+                _ => placeholder_image(width, height),
+            };
+            self.trails.insert(layer.id, previous.clone());
+            let previous_texture = self.target(device, &previous, "Trail Layer");
+
+            let mut vello_scene = vello::Scene::new();
+            let bounds = vello::kurbo::Rect::new(0.0, 0.0, width as f64, height as f64);
+            vello_scene.push_layer(
+                BlendMode::new(vello::peniko::Mix::Normal, vello::peniko::Compose::SrcOver),
+                layer.decay,
+                vello::kurbo::Affine::IDENTITY,
+                &bounds,
+            );
+            vello_scene.draw_image(&previous, vello::kurbo::Affine::IDENTITY);
+            vello_scene.pop_layer();
+            vello_scene.append(&layer.content.vello_scene, None);
 
-        // (interim) replace the images with GPU textures.
-        for (image, wgpu_texture) in &scene.backend.gpu_images {
+            let texture = self.target(device, &layer.placeholder, "Trail Layer");
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let render_params = vello::RenderParams {
+                base_color: vello::peniko::Color::TRANSPARENT,
+                width,
+                height,
+                antialiasing_method: antialiasing.into(),
+            };
             self.renderer
-                .override_image(image, Some(wgpu_texture.clone()));
+                .render_to_texture(device, queue, &vello_scene, &view, &render_params)
+                .map_err(|e| RendererError::Gpu(e.to_string()))?;
+            Self::copy_texture(device, queue, &texture, &previous_texture);
         }
-        self.renderer
-            .render_to_texture(device, queue, vello_scene, texture, &render_params).expect("Failed to render to texture");
+        Ok(())
     }
 
-    /// Render the scene to a WGPU surface but sets up its own render pass.
-    pub fn render_to_surface2(&mut self,
-                              device: &wgpu::Device,
-                              queue: &wgpu::Queue,
-                              surface: &wgpu::SurfaceTexture,
-                              scene: &Scene<VelloBackend>,
-    ) {
-        // create texture view
-        let texture_view = self.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    /// Forget the previous frames of all trail layers, so that their trails start over.
+    pub fn clear_trails(&mut self) {
+        self.trails.clear();
+    }
 
-        // render the scene
-        self.render_to_texture(device, queue, &texture_view, surface.texture.width(), surface.texture.height(), scene);
+    /// Record a pass of the effect shader into a new texture of the size of the input.
+    fn effect_pass(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        (pipeline, layout): &(wgpu::ComputePipeline, wgpu::BindGroupLayout),
+        params: &EffectParams,
+        input: &wgpu::Texture,
+        base: &wgpu::Texture,
+    ) -> wgpu::Texture {
+        let (width, height) = (input.width(), input.height());
+        let output = Self::create_offscreen_texture(device, width, height, "Effect Layer");
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Effect Params"),
+            contents: bytemuck::bytes_of(params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+        let base_view = base.create_view(&wgpu::TextureViewDescriptor::default());
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Effect Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&input_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&base_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&output_view),
+                },
+            ],
+        });
 
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Effect Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            // the shader uses workgroups of 8x8 texels
+            pass.dispatch_workgroups(width.div_ceil(8), height.div_ceil(8), 1);
+        }
+        output
+    }
+
+    /// A texture that can be rendered to by vello, read by shaders and used to override images.
+    fn create_offscreen_texture(device: &wgpu::Device, width: u32, height: u32, label: &str) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            label: Some(label),
+            view_formats: &[],
+        })
+    }
+
+    fn create_effect_pipeline(device: &wgpu::Device) -> (wgpu::ComputePipeline, wgpu::BindGroupLayout) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Effect Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/shaders/effects.wgsl").into()),
+        });
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Effect Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Effect Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Effect Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        (pipeline, bind_group_layout)
+    }
+
+    /// Generate the gratings of a backend into textures and replace their placeholders.
+    fn render_gratings(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, backend: &VelloBackend) {
+        if backend.grating_passes.is_empty() {
+            return;
+        }
+        let textures = backend
+            .grating_passes
+            .iter()
+            .map(|grating| self.target(device, &grating.placeholder, "Grating"))
+            .collect::<Vec<_>>();
+        let (pipeline, layout) = self
+            .grating_pipeline
+            .get_or_insert_with(|| Self::create_grating_pipeline(device));
 
-        // create a new render pass
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Render Encoder"),
+            label: Some("Grating Encoder"),
         });
+        for (grating, texture) in backend.grating_passes.iter().zip(&textures) {
+            let (width, height) = (grating.placeholder.width, grating.placeholder.height);
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Grating Params"),
+                contents: bytemuck::bytes_of(&grating.params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Grating Bind Group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                ],
+            });
 
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Grating Pass"),
+                    timestamp_writes: None,
+                });
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                // the shader uses workgroups of 8x8 texels
+                pass.dispatch_workgroups(width.div_ceil(8), height.div_ceil(8), 1);
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+    }
 
-        let surface_texture_view = surface.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    fn create_grating_pipeline(device: &wgpu::Device) -> (wgpu::ComputePipeline, wgpu::BindGroupLayout) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grating Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/shaders/grating.wgsl").into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Grating Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grating Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Grating Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        (pipeline, bind_group_layout)
+    }
 
+    /// Replace an image with a GPU texture when rendering and mark it as used. The replacement
+    /// is registered with vello once and kept across frames, keyed by the id of the image data,
+    /// which stays the same until the image is moved to the GPU again or invalidated.
+    ///
+    /// Unless the color management of the images is the identity, vello gets a copy of the
+    /// texture with converted colors.
+    fn override_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &vello::peniko::Image,
+        texture: &wgpu::ImageCopyTextureBase<Arc<wgpu::Texture>>,
+    ) {
+        if self.overridden_images.get(&image.data.id()).is_some() {
+            return;
+        }
+        let bytes = image.width as usize * image.height as usize * 4;
+        if self.image_color_management.is_identity() {
+            self.overridden_images
+                .insert(image.data.id(), (image.clone(), texture.texture.clone(), false), bytes);
+            self.renderer.override_image(image, Some(texture.clone()));
+        } else {
+            let converted = self.convert_image(device, queue, texture);
+            self.overridden_images
+                .insert(image.data.id(), (image.clone(), texture.texture.clone(), true), 2 * bytes);
+            self.renderer
+                .override_image(image, Some(Self::texture_copy(Arc::new(converted))));
+        }
+    }
+
+    /// A copy of a mip level of a GPU image with its colors converted by the color management of
+    /// the images.
+    fn convert_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::ImageCopyTextureBase<Arc<wgpu::Texture>>,
+    ) -> wgpu::Texture {
+        let (pipeline, layout) = self
+            .convert_pipeline
+            .get_or_insert_with(|| Self::create_convert_pipeline(device));
+        let size = texture.texture.size().mip_level_size(texture.mip_level, wgpu::TextureDimension::D2);
+        let converted = Self::create_offscreen_texture(device, size.width, size.height, "Converted Image");
+
+        let (matrix, encode) = self.image_color_management.linear_transform();
+        let params = ConvertParams {
+            rows: matrix.map(|[r, g, b]| [r, g, b, 0.0]),
+            encode: encode as u32,
+            _padding: [0; 3],
+        };
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Convert Params"),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let input_view = texture.texture.create_view(&wgpu::TextureViewDescriptor {
+            base_mip_level: texture.mip_level,
+            mip_level_count: Some(1),
+            ..Default::default()
+        });
+        let output_view = converted.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Convert Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&input_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&output_view),
+                },
+            ],
+        });
 
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Convert Encoder"),
+        });
         {
-            // bind the render pass
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &surface_texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::RED),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Convert Pass"),
                 timestamp_writes: None,
-                occlusion_query_set: None,
             });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            // the shader uses workgroups of 8x8 texels
+            pass.dispatch_workgroups(size.width.div_ceil(8), size.height.div_ceil(8), 1);
+        }
+        queue.submit(Some(encoder.finish()));
+        converted
+    }
 
-            // bind the render pipeline
-            render_pass.set_pipeline(&self.render_pipeline);
-            // bind the bind group
-            render_pass.set_bind_group(0, &self.bind_group, &[]);
-            // draw the quad
-            render_pass.draw(0..6, 0..1);
+    fn create_convert_pipeline(device: &wgpu::Device) -> (wgpu::ComputePipeline, wgpu::BindGroupLayout) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Convert Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("assets/shaders/convert.wgsl").into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Convert Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Convert Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Convert Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        (pipeline, bind_group_layout)
+    }
+
+    /// Unregister the GPU images that can't be drawn anymore, because all images that held their
+    /// textures were dropped or moved to the GPU again.
+    fn release_dropped_images(&mut self) {
+        // the only references left are the ones kept here and by vello (unless it holds a
+        // converted copy), one each for every registered mip level of the texture
+        let mut references = HashMap::new();
+        for (_, (_, texture, converted)) in self.overridden_images.iter() {
+            *references.entry(Arc::as_ptr(texture)).or_insert(0) += if *converted { 1 } else { 2 };
+        }
+        let dropped = self
+            .overridden_images
+            .remove_where(|_, (_, texture, _)| Arc::strong_count(texture) <= references[&Arc::as_ptr(texture)]);
+        for (_, (image, _, _)) in dropped {
+            self.renderer.override_image(&image, None);
         }
+    }
 
-        // submit the render pass
-        queue.submit(Some(encoder.finish()));
+    /// Make the renderer pick up new contents of the GPU texture of an image, e.g. after writing
+    /// to it with `queue.write_texture`.
+    ///
+    /// Registered textures are only copied when they are first drawn, so changes are not visible
+    /// until the image is invalidated. This gives the image a new id; clones of the image made
+    /// before keep showing the old contents.
+    pub fn invalidate_image(&mut self, image: &mut Image) {
+        let Some(blob) = image.gpu_blob.take() else {
+            return;
+        };
+        if let Some((vello_image, _, _)) = self.overridden_images.remove(&blob.id()) {
+            self.renderer.override_image(&vello_image, None);
+        }
+        image.gpu_blob = Some(image.new_gpu_blob());
+    }
+
+    /// Release the least recently used GPU images that weren't drawn since `since` (see
+    /// [`LruCache::clock`]) while the images, the layer textures and the image caches of a scene
+    /// exceed its image budget.
+    fn enforce_budget(&mut self, backend: &VelloBackend, since: u64) {
+        let targets = self
+            .targets
+            .values()
+            .map(|target| target.texture.width() as usize * target.texture.height() as usize * 4)
+            .sum::<usize>();
+        let other = targets + backend.gradient_luts.cost() + backend.mapped_images.cost();
+        let evicted = backend
+            .budget
+            .enforce_shared(ResourceKind::GpuImages, &mut self.overridden_images, other, since);
+        for (_, (image, _, _)) in evicted {
+            self.renderer.override_image(&image, None);
+        }
     }
 
     fn create_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
@@ -350,13 +2083,34 @@ impl VelloRenderer {
 }
 
 impl VelloBackend {
+    /// The next placeholder of a size for content rendered at render time (see
+    /// [`PlaceholderPool`]).
+    fn placeholder(&self, width: u32, height: u32) -> vello::peniko::Image {
+        self.placeholders.lock().expect("placeholder lock poisoned").next(width, height)
+    }
+
     /// Create a new Vello backend.
     pub fn new(width: u32, height: u32) -> Self {
         Self {
             vello_scene: vello::Scene::new(),
             global_transform: Affine::translate(width as f64 / 2.0, height as f64 / 2.0),
-            gpu_images: Vec::new(),
-
+            gpu_images: HashMap::new(),
+            gradient_luts: LruCache::new(),
+            mapped_images: LruCache::new(),
+            image_blobs: LruCache::new(),
+            nearest_images: LruCache::new(),
+            axis_images: LruCache::new(),
+            bitmap_glyphs: LruCache::new(),
+            prerendered_scenes: LruCache::new(),
+            aa_layers: Vec::new(),
+            grating_passes: Vec::new(),
+            effect_layers: Vec::new(),
+            trail_layers: Vec::new(),
+            budget: ResourceBudget::default(),
+            quality: QualitySettings::default(),
+            color_management: ColorManagement::default(),
+            glyph_batches: None,
+            placeholders: Arc::default(),
         }
     }
 }
@@ -364,39 +2118,107 @@ impl VelloBackend {
 impl Scene<VelloBackend> {
     /// Create a new scene.
     pub fn new(background_color: RGBA, width: u32, height: u32) -> Self {
-        Self {
-            background_color,
-            width,
-            height,
-            backend: VelloBackend::new(width, height),
+        Self::with_backend(background_color, width, height, VelloBackend::new(width, height))
+    }
+
+    /// Set the limits for the resources cached by the scene and the renderers that render it.
+    pub fn set_resource_budget(&mut self, budget: ResourceBudget) {
+        self.backend.budget = budget;
+    }
+
+    /// Parse and prerender an SVG, or return the cached result if the same SVG was prerendered
+    /// before. The cache is limited by [`ResourceBudget::max_prerendered_scenes`].
+    pub fn prerender_svg(&mut self, svg: &str) -> Result<PrerenderedScene> {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        svg.hash(&mut hasher);
+        let key = hasher.finish();
+        let scene = match self.backend.prerendered_scenes.get(&key) {
+            Some(scene) => scene.clone(),
+            None => {
+                let scene = PrerenderedScene::from_svg_string(svg, Affine::identity())?;
+                self.backend.prerendered_scenes.insert(key, scene.clone(), 1);
+                scene
+            }
+        };
+        self.backend
+            .budget
+            .enforce(ResourceKind::PrerenderedScenes, &mut self.backend.prerendered_scenes);
+        Ok(scene)
+    }
+
+    /// Render the scene at its size into an image (see [`VelloRenderer::render_to_image`]).
+    pub fn render_to_image(
+        &self,
+        renderer: &mut VelloRenderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<image::RgbaImage> {
+        renderer.render_to_image(device, queue, self, self.width, self.height)
+    }
+
+    /// Draw content with the glyph runs of its text merged by font, size, color and orientation,
+    /// which makes encoding scenes with many small labels (e.g. plot ticks or table cells) much
+    /// cheaper.
+    ///
+    /// Text ends up on top of the other content drawn in `content` within the same layer (the
+    /// batches are flushed whenever a layer is pushed or popped), so use this for labels that
+    /// don't overlap other elements of the batch.
+    pub fn batch_glyphs(&mut self, content: impl FnOnce(&mut Scene<VelloBackend>)) {
+        if self.backend.glyph_batches.is_some() {
+            // already batching
+            content(self);
+            return;
         }
+        self.backend.glyph_batches = Some(Vec::new());
+        content(self);
+        self.backend.flush_glyph_batches();
+        self.backend.glyph_batches = None;
     }
 
-    /// draw a renderable object.
-    pub fn draw(&mut self, mut object: impl Drawable<VelloBackend>) {
-        // Draw the object.
-        object.draw(self);
+    /// Draw content with a different antialiasing method than the rest of the scene (e.g. fast
+    /// area antialiasing for a stimulus and multisampling for the UI around it).
+    ///
+    /// The content is rendered in a separate offscreen pass at the full device resolution and
+    /// composited into the scene at this point. Draw into the top-level scene: if the scene is
+    /// appended somewhere else with a transform (e.g. as part of a group), the rendered layer is
+    /// transformed as an image.
+    pub fn draw_with_antialiasing(&mut self, antialiasing: Antialiasing, content: impl FnOnce(&mut Scene<VelloBackend>)) {
+        let mut layer = self.child_scene(Affine::identity());
+        layer.camera = self.camera;
+        layer.backend.set_global_transform(self.backend.global_transform());
+        content(&mut layer);
+
+        let placeholder = self.backend.placeholder(self.width, self.height);
+        self.backend
+            .vello_scene
+            .draw_image(&placeholder, vello::kurbo::Affine::IDENTITY);
+        self.accessibility.append(&layer.accessibility, Affine::identity());
+        self.backend.aa_layers.push(AaLayer {
+            antialiasing,
+            content: layer.backend,
+            placeholder,
+        });
     }
 }
 
 // Textures
 impl Image {
-    /// Create a new texture from an image::DynamicImage.
-    pub fn new(image: &image::DynamicImage) -> Self {
-        // create a peniko image
-        let data = Arc::new(image.clone().into_rgba8().into_vec());
+    /// Move the texture to the GPU. If the image is drawn shrunk, its mip levels are made on the
+    /// CPU and uploaded every frame; use [`to_gpu_with_mipmaps`](Self::to_gpu_with_mipmaps)
+    /// for images that are usually shrunk.
+    pub fn to_gpu(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.upload(device, queue, 1);
+    }
 
-        return Self {
-            gpu_texture: None,
-            data,
-            width: image.width(),
-            height: image.height(),
-        };
+    /// Move the texture and all of its mip levels to the GPU (only the texture if mipmapping is
+    /// disabled).
+    pub fn to_gpu_with_mipmaps(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let levels = if self.mipmaps { self.mip_level_count() } else { 1 };
+        self.upload(device, queue, levels);
     }
 
-    /// Move the texture to the GPU.
-    pub fn to_gpu(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        let data = &self.data;
+    /// Upload the first `levels` mip levels into a new texture.
+    fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, levels: u32) {
         // create a new wgpu texture
         let wgpu_tetxure = device.create_texture(&wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
@@ -404,7 +2226,7 @@ impl Image {
                 height: self.height,
                 depth_or_array_layers: 1,
             },
-            mip_level_count: 1,
+            mip_level_count: levels,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8Unorm,
@@ -415,136 +2237,1067 @@ impl Image {
             view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
         });
 
-        // write the image to the texture
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &wgpu_tetxure,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            data.as_ref(),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * self.width),
-                rows_per_image: Some(self.height),
-            },
-            wgpu::Extent3d {
-                width: self.width,
-                height: self.height,
-                depth_or_array_layers: 1,
-            },
-        );
+        // write the image and its mip levels to the texture
+        for level in 0..levels {
+            let image = self.mip_level(level);
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &wgpu_tetxure,
+                    mip_level: level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                image.to_rgba8().as_ref(),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * image.width),
+                    rows_per_image: Some(image.height),
+                },
+                wgpu::Extent3d {
+                    width: image.width,
+                    height: image.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
 
         self.gpu_texture = Some(Arc::new(wgpu_tetxure));
+        self.gpu_blob = Some(self.new_gpu_blob());
+        self.gpu_mip_level = 0;
+        self.gpu_mip_blobs = (1..levels).map(|level| self.mip_level(level).new_gpu_blob()).collect();
     }
-}
-
-impl<S: IntoVelloShape + Shape> Drawable<VelloBackend> for Geom<S> {
-    fn draw(&mut self, scene: &mut Scene<VelloBackend>) {
-        let transform = (scene.backend.global_transform * self.transform).into();
 
-        let brush_transform = self.brush_transform.map(|t| t.into());
+    /// The image enlarged by an integer factor, repeating each pixel, to approximate nearest
+    /// neighbor sampling with bilinear filtering.
+    fn upscale_nearest(&self, factor: u32) -> Image {
+        let channels = match self.format {
+            ImageFormat::Rgba8 => 4,
+            ImageFormat::Alpha8 => 1,
+        };
+        let (width, height) = (self.width * factor, self.height * factor);
+        let mut data = Vec::with_capacity((width * height * channels) as usize);
+        for y in 0..height {
+            let row = ((y / factor) * self.width * channels) as usize;
+            for x in 0..self.width {
+                let pixel = &self.data[row + (x * channels) as usize..][..channels as usize];
+                for _ in 0..factor {
+                    data.extend_from_slice(pixel);
+                }
+            }
+        }
+        self.with_pixels(data, width, height)
+    }
 
-        // convert the brush
-        let new_brush = &self.brush.as_brush_or_brushref();
+    /// A blob with a new id for the data of the image.
+    fn new_gpu_blob(&self) -> vello::peniko::Blob<u8> {
+        match self.format {
+            ImageFormat::Rgba8 => vello::peniko::Blob::new(self.data.clone()),
+            _ => vello::peniko::Blob::new(self.rgba8_data()),
+        }
+    }
+}
 
-        // if brush is an image
-        if let Brush::Image { image, .. } = &self.brush {
-            if let Some(gpu_texture) = &image.gpu_texture {
-                scene.backend.gpu_images.push((
-                    new_brush.clone().try_into().unwrap(),
+impl VelloBackend {
+    /// Register the GPU texture of an image (if any) so that it replaces the image data when
+    /// rendering. Images drawn from other data than their GPU blob (e.g. with a mapping) keep it.
+    fn register_gpu_image(&mut self, image: &Image, vello_image: &vello::peniko::Image) {
+        let drawn_from_gpu_blob = image.gpu_blob.as_ref().is_some_and(|blob| blob.id() == vello_image.data.id());
+        if let (Some(gpu_texture), true) = (&image.gpu_texture, drawn_from_gpu_blob) {
+            self.gpu_images.entry(vello_image.data.id()).or_insert_with(|| {
+                (
+                    vello_image.clone(),
                     wgpu::ImageCopyTextureBase {
                         texture: gpu_texture.clone(),
-                        mip_level: 0,
+                        mip_level: image.gpu_mip_level,
                         origin: wgpu::Origin3d::ZERO,
                         aspect: wgpu::TextureAspect::All,
                     },
-                ));
+                )
+            });
+        }
+    }
+
+    /// Returns a lookup-texture brush and the transform mapping it onto the gradient line if the
+    /// gradient has enough stops to make sampling a texture cheaper than encoding every stop.
+    fn gradient_lut(&mut self, gradient: &Gradient) -> Option<(vello::peniko::Image, Affine)> {
+        let GradientKind::Linear { start, end } = gradient.kind else {
+            return None;
+        };
+        if gradient.stops.len() < GRADIENT_LUT_MIN_STOPS {
+            return None;
+        }
+
+        let resolution = self.quality.gradient_resolution.max(1);
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        gradient.hash_ramp(&mut hasher);
+        resolution.hash(&mut hasher);
+        let key = hasher.finish();
+
+        if self.gradient_luts.len() >= GRADIENT_LUT_CACHE_SIZE && !self.gradient_luts.contains_key(&key) {
+            self.gradient_luts.clear();
+        }
+        let image = self
+            .gradient_luts
+            .get_or_insert_with(key, resolution as usize * 4, || {
+                let lut = gradient.to_lut(resolution);
+                let blob = vello::peniko::Blob::new(lut.data.clone());
+                vello::peniko::Image::new(blob, vello::peniko::Format::Rgba8, lut.width, lut.height)
+                    .with_extend((&gradient.extend).into())
+            })
+            .clone();
+        let other = self.mapped_images.cost();
+        self.budget
+            .enforce_shared(ResourceKind::GpuImages, &mut self.gradient_luts, other, u64::MAX);
+
+        // map image x in [0, resolution] onto the gradient line, image y onto its normal
+        let (dx, dy) = (end.x - start.x, end.y - start.y);
+        let length = dx.hypot(dy).max(f64::EPSILON);
+        let n = resolution as f64;
+        let transform = Affine([dx / n, dy / n, -dy / length, dx / length, start.x, start.y]);
+
+        Some((image, transform))
+    }
+
+    /// The pixel data of an image with the mapping and the color management applied (cached).
+    fn mapped_image_data(&mut self, image: &Image, mapping: &ImageMapping) -> Arc<Vec<u8>> {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        mapping.hash(&mut hasher);
+        self.color_management.hash(&mut hasher);
+        let key = (Arc::as_ptr(&image.data) as usize, hasher.finish());
+        let color_management = self.color_management;
+
+        if self.mapped_images.len() >= MAPPED_IMAGE_CACHE_SIZE && !self.mapped_images.contains_key(&key) {
+            self.mapped_images.clear();
+        }
+        let bytes = image.width as usize * image.height as usize * 4;
+        let data = self
+            .mapped_images
+            .get_or_insert_with(key, bytes, || {
+                let mut data = mapping.apply(&image.to_rgba8());
+                color_management.convert_pixels(&mut data);
+                (image.data.clone(), Arc::new(data))
+            })
+            .1
+            .clone();
+        let other = self.gradient_luts.cost();
+        self.budget
+            .enforce_shared(ResourceKind::GpuImages, &mut self.mapped_images, other, u64::MAX);
+        data
+    }
+
+    /// The RGBA8 pixel data of an image brush.
+    fn image_brush_data(&mut self, image: &Image, mapping: &ImageMapping) -> Arc<Vec<u8>> {
+        if mapping.is_identity() && image.format == ImageFormat::Rgba8 && self.color_management.is_identity() {
+            image.data.clone()
+        } else {
+            self.mapped_image_data(image, mapping)
+        }
+    }
+
+    /// The data of an image as a blob. Images on the GPU keep the blob they were registered with,
+    /// so that the renderer can recognize them across frames (and converts their colors on the
+    /// GPU), unless they are drawn with a mapping, which is applied to their pixel data like for
+    /// other images.
+    fn image_blob(&mut self, image: &Image, mapping: &ImageMapping) -> vello::peniko::Blob<u8> {
+        match (&image.gpu_texture, &image.gpu_blob) {
+            (Some(_), Some(blob)) if mapping.is_identity() => blob.clone(),
+            _ => {
+                let data = self.image_brush_data(image, mapping);
+                self.data_blob(data)
+            }
+        }
+    }
+
+    /// The blob of image data (cached by the address of the data).
+    fn data_blob(&mut self, data: Arc<Vec<u8>>) -> vello::peniko::Blob<u8> {
+        let key = Arc::as_ptr(&data) as usize;
+        if self.image_blobs.len() >= IMAGE_BLOB_CACHE_SIZE && !self.image_blobs.contains_key(&key) {
+            self.image_blobs.clear();
+        }
+        self.image_blobs
+            .get_or_insert_with(key, data.len(), || (data.clone(), vello::peniko::Blob::new(data.clone())))
+            .1
+            .clone()
+    }
+
+    /// The image to sample instead of `image` when it is drawn with `transform` (from image
+    /// pixels to device pixels), and the transform from its pixels to the pixels of `image`:
+    /// a mip level if the image is shrunk, or an enlarged copy if it is sampled with
+    /// [`ImageFilter::Nearest`] and enlarged.
+    fn sampled_image(&mut self, image: &Image, transform: Affine) -> Option<(Image, Affine)> {
+        if let Some(mip) = image.mip_level_for(transform) {
+            return Some(mip);
+        }
+        if image.filter != ImageFilter::Nearest {
+            return None;
+        }
+
+        // enlarge the image to about the size it is drawn at, so that bilinear filtering only
+        // blends the pixels along their edges
+        let [a, b, c, d, _, _] = transform.0;
+        let scale = a.hypot(b).max(c.hypot(d));
+        let pixels = image.width as u64 * image.height as u64;
+        let mut factor = (scale.ceil().max(1.0) as u32).min(NEAREST_MAX_UPSCALE);
+        while factor > 1 && pixels * (factor as u64).pow(2) > NEAREST_MAX_PIXELS {
+            factor -= 1;
+        }
+        if factor <= 1 {
+            return None;
+        }
+
+        let key = (Arc::as_ptr(&image.data) as usize, factor);
+        if self.nearest_images.len() >= NEAREST_IMAGE_CACHE_SIZE && !self.nearest_images.contains_key(&key) {
+            self.nearest_images.clear();
+        }
+        let bytes = image.data.len() * (factor as usize).pow(2);
+        let upscaled = self
+            .nearest_images
+            .get_or_insert_with(key, bytes, || {
+                (image.data.clone(), image.upscale_nearest(factor).with_filter(ImageFilter::Linear))
+            })
+            .1
+            .clone();
+        Some((upscaled, Affine::scale(1.0 / factor as f64)))
+    }
+
+    /// Convert a brush, registering GPU textures of image brushes.
+    fn prepare_brush<'a>(&mut self, brush: &'a Brush) -> VelloBrushOrBrushRef<'a> {
+        if let Brush::Image { image, edge_mode, mapping, .. } = brush {
+            let blob = self.image_blob(image, mapping);
+            let vello_image = vello::peniko::Image::new(blob, vello::peniko::Format::Rgba8, image.width, image.height)
+                .with_extend(edge_mode.into());
+            self.register_gpu_image(image, &vello_image);
+            return VelloBrushOrBrushRef::Brush(vello::peniko::Brush::Image(vello_image));
+        }
+        brush.as_brush_or_brushref()
+    }
+
+    /// Remove glyphs that have no outline but a bitmap (e.g. in bitmap-only emoji fonts) and
+    /// return them as images, positioned relative to the text origin. The glyph transform (e.g.
+    /// a synthetic oblique) is applied to each glyph like to outline glyphs.
+    fn take_bitmap_glyphs(
+        &mut self,
+        font: &VelloFont,
+        font_size: f32,
+        glyphs: &mut Vec<Glyph>,
+        glyph_transform: Option<Affine>,
+    ) -> Vec<(Image, Affine)> {
+        let font_ref = vello_font_to_font_ref(&font.font).expect("font was validated when it was loaded");
+        let outlines = vello::skrifa::MetadataProvider::outline_glyphs(&font_ref);
+        let strikes = vello::skrifa::bitmap::BitmapStrikes::new(&font_ref);
+        let size = vello::skrifa::instance::Size::new(font_size);
+        let font_id = font.font.data.id();
+
+        let mut images = Vec::new();
+        glyphs.retain(|glyph| {
+            let gid = vello::skrifa::GlyphId::new(glyph.id);
+            if outlines.get(gid).is_some() {
+                return true;
+            }
+            let bitmap = self
+                .bitmap_glyphs
+                .get_or_insert_with((font_id, glyph.id, font_size.to_bits()), 1, || {
+                    let bitmap = strikes.glyph_for_size(size, gid)?;
+                    let image = decode_bitmap_glyph(&bitmap)?;
+                    // bearings are in pixels of the strike, with y pointing up
+                    let top = match bitmap.placement_origin {
+                        vello::skrifa::bitmap::Origin::TopLeft => bitmap.bearing_y,
+                        vello::skrifa::bitmap::Origin::BottomLeft => bitmap.bearing_y + bitmap.height as f32,
+                    };
+                    let transform = Affine::translate(bitmap.bearing_x as f64, -top as f64)
+                        * Affine::scale((font_size / bitmap.ppem_x) as f64);
+                    Some((image, transform))
+                });
+            match bitmap {
+                Some((image, transform)) => {
+                    let position = Affine::translate(glyph.x as f64, glyph.y as f64);
+                    let transform = match glyph_transform {
+                        Some(glyph_transform) => *transform * glyph_transform * position,
+                        None => *transform * position,
+                    };
+                    images.push((image.clone(), transform));
+                    false
+                }
+                None => true,
+            }
+        });
+        self.budget.enforce(ResourceKind::Glyphs, &mut self.bitmap_glyphs);
+        images
+    }
+
+    /// Encode the pending glyph batches (see [`Scene::batch_glyphs`]) into the scene, so that
+    /// they end up in the current layer. Batching goes on if it was enabled.
+    fn flush_glyph_batches(&mut self) {
+        let Some(batches) = self.glyph_batches.as_mut().map(std::mem::take) else {
+            return;
+        };
+        for batch in batches {
+            let variations = batch
+                .variations
+                .iter()
+                .map(|(tag, value)| (tag.as_str(), *value))
+                .collect::<Vec<_>>();
+            self.encode_glyphs(
+                &batch.font,
+                batch.font_size,
+                &variations,
+                &batch.glyphs,
+                &Brush::Solid(batch.color),
+                batch.transform,
+                batch.glyph_transform,
+                batch.rendering,
+                None,
+            );
+        }
+    }
+
+    /// Encode a run of glyphs into the scene.
+    #[allow(clippy::too_many_arguments)]
+    fn encode_glyphs(
+        &mut self,
+        font: &vello::peniko::Font,
+        font_size: f32,
+        variations: &[(&str, f32)],
+        glyphs: &[Glyph],
+        brush: &Brush,
+        transform: Affine,
+        glyph_transform: Option<Affine>,
+        rendering: TextRendering,
+        stroke: Option<&StrokeOptions>,
+    ) {
+        let font_ref = vello_font_to_font_ref(font).expect("font was validated when it was loaded");
+        let axes = vello::skrifa::MetadataProvider::axes(&font_ref);
+        let var_loc = axes.location(variations.iter().copied());
+
+        let brush = &self.prepare_brush(brush);
+        let stroke = stroke.map(|stroke| vello::kurbo::Stroke::from(stroke.clone()));
+        let glyphs = glyphs.iter().map(|g| vello::Glyph {
+            id: g.id,
+            x: g.x,
+            y: g.y,
+        });
+        let builder = self
+            .vello_scene
+            .draw_glyphs(font)
+            .font_size(font_size)
+            .transform(transform.into())
+            .glyph_transform(glyph_transform.map(|t| t.into()))
+            .normalized_coords(var_loc.coords())
+            .brush(brush)
+            .hint(rendering.hint);
+        match &stroke {
+            Some(stroke) => builder.draw(stroke, glyphs),
+            None => builder.draw(vello::peniko::Fill::from(rendering.fill), glyphs),
+        }
+    }
+
+    /// Draw glyphs that have color layers (COLR/CPAL, e.g. in emoji fonts) and remove them from
+    /// the run. Layers that use the text color are drawn with `foreground`, and the glyph
+    /// transform is applied like for outline glyphs.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_color_glyphs(
+        &mut self,
+        font: &VelloFont,
+        font_size: f32,
+        variations: &[(&str, f32)],
+        glyphs: &mut Vec<Glyph>,
+        foreground: RGBA,
+        transform: Affine,
+        glyph_transform: Option<Affine>,
+    ) {
+        use vello::skrifa::MetadataProvider;
+        let font_ref = vello_font_to_font_ref(&font.font).expect("font was validated when it was loaded");
+        let color_glyphs = font_ref.color_glyphs();
+        let location = font_ref.axes().location(variations.iter().copied());
+        let units_per_em = font_ref.metrics(vello::skrifa::instance::Size::unscaled(), &location).units_per_em;
+        let scale = (font_size / units_per_em as f32) as f64;
+        let color_management = self.color_management;
+        let palette = color_palette(&font_ref)
+            .into_iter()
+            .map(|color| {
+                let [r, g, b, a] = [color.r, color.g, color.b, color.a].map(|value| value as f32 / 255.0);
+                color_management.convert(RGBA::new(r, g, b, a)).into()
+            })
+            .collect::<Vec<_>>();
+
+        glyphs.retain(|glyph| {
+            let Some(color_glyph) = color_glyphs.get(vello::skrifa::GlyphId::new(glyph.id)) else {
+                return true;
+            };
+            // color glyphs are defined in font units with y pointing up, and the glyph transform
+            // applies to the scaled, y-down glyph before it is moved to its position
+            let em_transform = Affine([scale, 0.0, 0.0, -scale, 0.0, 0.0]);
+            let position = Affine([1.0, 0.0, 0.0, 1.0, glyph.x as f64, glyph.y as f64]);
+            let glyph_transform = match glyph_transform {
+                Some(glyph_transform) => em_transform * glyph_transform * position * transform,
+                None => em_transform * position * transform,
+            };
+            let mut painter = ColorGlyphPainter {
+                scene: &mut self.vello_scene,
+                outlines: font_ref.outline_glyphs(),
+                location: &location,
+                palette: &palette,
+                foreground: color_management.convert(foreground).into(),
+                transforms: vec![glyph_transform],
+                layers: 0,
+            };
+            // a glyph that fails to paint is drawn as its outline instead, after closing the
+            // layers it left open
+            let failed = color_glyph.paint(&location, &mut painter).is_err();
+            for _ in 0..painter.layers {
+                painter.scene.pop_layer();
+            }
+            failed
+        });
+    }
+
+    /// Fill or stroke a path with an image brush that extends differently along x and y.
+    ///
+    /// Images only have a single extend mode, so a repeated axis combined with a reflected one
+    /// uses an image that is mirrored along the reflected axis, and a padded axis is drawn in three
+    /// bands (before, on and after the image), filled with the first column/row, the image and the
+    /// last column/row respectively. GPU textures of the image are not used.
+    #[allow(clippy::too_many_arguments)]
+    fn encode_image_per_axis(
+        &mut self,
+        path: &vello::kurbo::BezPath,
+        style: &Style,
+        image: &Image,
+        data: Arc<Vec<u8>>,
+        extend_x: Extend,
+        extend_y: Extend,
+        transform: Affine,
+        brush_transform: Affine,
+    ) {
+        let images = self.axis_images(image, data, extend_x, extend_y);
+        let brush =
+            |image: &vello::peniko::Image| VelloBrushOrBrushRef::Brush(vello::peniko::Brush::Image(image.clone()));
+        if let [mirrored] = images.as_slice() {
+            self.encode_path(path, style, &brush(mirrored), transform, Some(brush_transform));
+            return;
+        }
+
+        // the extent of the path in image space
+        let inverse: vello::kurbo::Affine = brush_transform.inverse().into();
+        let bounds = vello::kurbo::Shape::bounding_box(&(inverse * path.clone()));
+        let (w, h) = (image.width as f64, image.height as f64);
+        let bands = if extend_x == Extend::Pad {
+            let (x0, x1) = (bounds.x0.min(0.0), bounds.x1.max(w));
+            [
+                vello::kurbo::Rect::new(x0, bounds.y0, 0.0, bounds.y1),
+                vello::kurbo::Rect::new(0.0, bounds.y0, w, bounds.y1),
+                vello::kurbo::Rect::new(w, bounds.y0, x1, bounds.y1),
+            ]
+        } else {
+            let (y0, y1) = (bounds.y0.min(0.0), bounds.y1.max(h));
+            [
+                vello::kurbo::Rect::new(bounds.x0, y0, bounds.x1, 0.0),
+                vello::kurbo::Rect::new(bounds.x0, 0.0, bounds.x1, h),
+                vello::kurbo::Rect::new(bounds.x0, h, bounds.x1, y1),
+            ]
+        };
+
+        let clip_transform = (brush_transform * transform).into();
+        for (band, band_image) in bands.into_iter().zip(&images) {
+            if band.is_zero_area() {
+                continue;
+            }
+            self.vello_scene
+                .push_layer(vello::peniko::Mix::Clip, 1.0, clip_transform, &band);
+            self.encode_path(path, style, &brush(band_image), transform, Some(brush_transform));
+            self.vello_scene.pop_layer();
+        }
+    }
+
+    /// The images [`encode_image_per_axis`](Self::encode_image_per_axis) draws for the pixel data
+    /// of an image (cached, so that they are uploaded once): the mirrored image if neither axis
+    /// is padded, otherwise the first column/row, the image and the last column/row.
+    fn axis_images(
+        &mut self,
+        image: &Image,
+        data: Arc<Vec<u8>>,
+        extend_x: Extend,
+        extend_y: Extend,
+    ) -> Vec<vello::peniko::Image> {
+        let key = (Arc::as_ptr(&data) as usize, extend_x, extend_y);
+        if let Some((_, images)) = self.axis_images.get(&key) {
+            return images.clone();
+        }
+        if self.axis_images.len() >= AXIS_IMAGE_CACHE_SIZE {
+            self.axis_images.clear();
+        }
+
+        let (width, height) = (image.width as usize, image.height as usize);
+        let row = |y: usize| data[y * width * 4..(y + 1) * width * 4].to_vec();
+        let column = |x: usize| (0..height).flat_map(|y| row(y)[x * 4..x * 4 + 4].to_vec()).collect::<Vec<_>>();
+        let new_image = |blob: vello::peniko::Blob<u8>, width: usize, height: usize, extend: Extend| {
+            vello::peniko::Image::new(blob, vello::peniko::Format::Rgba8, width as u32, height as u32)
+                .with_extend(extend.into())
+        };
+        let from_pixels = |data: Vec<u8>, width: usize, height: usize, extend: Extend| {
+            new_image(vello::peniko::Blob::new(Arc::new(data)), width, height, extend)
+        };
+
+        let images = if extend_x != Extend::Pad && extend_y != Extend::Pad {
+            // repeating an image and its mirror image is the same as reflecting it
+            if extend_x == Extend::Reflect {
+                let data = (0..height)
+                    .flat_map(|y| {
+                        let row = row(y);
+                        let mirror = row.chunks_exact(4).rev().flatten().copied().collect::<Vec<_>>();
+                        [row, mirror].concat()
+                    })
+                    .collect();
+                vec![from_pixels(data, width * 2, height, Extend::Repeat)]
+            } else {
+                let mut mirrored = data.to_vec();
+                mirrored.extend((0..height).rev().flat_map(row));
+                vec![from_pixels(mirrored, width, height * 2, Extend::Repeat)]
+            }
+        } else if extend_x == Extend::Pad {
+            vec![
+                from_pixels(column(0), 1, height, extend_y),
+                new_image(self.data_blob(data.clone()), width, height, extend_y),
+                from_pixels(column(width - 1), 1, height, extend_y),
+            ]
+        } else {
+            vec![
+                from_pixels(row(0), width, 1, extend_x),
+                new_image(self.data_blob(data.clone()), width, height, extend_x),
+                from_pixels(row(height - 1), width, 1, extend_x),
+            ]
+        };
+        let bytes = data.len() * 2;
+        self.axis_images.insert(key, (data, images.clone()), bytes);
+        images
+    }
+
+    /// Blend blue noise into a filled path, giving `(1 - w) * color + w * noise` with a weight
+    /// `w` of one 8-bit step. The noise is drawn without a layer, so that it is added to the
+    /// colors before they are quantized to 8 bits.
+    fn draw_dither_noise(&mut self, path: &vello::kurbo::BezPath, fill: FillStyle, transform: Affine) {
+        // keep the noise aligned with device pixels
+        self.vello_scene.fill(
+            fill.into(),
+            transform.into(),
+            &vello::peniko::Brush::Image(dither_noise().clone()),
+            Some(transform.inverse().into()),
+            path,
+        );
+    }
+
+    /// Fill or stroke a shape with a brush whose colors were already converted by the color
+    /// management.
+    fn draw_converted_shape(
+        &mut self,
+        shape: &impl Shape,
+        style: &Style,
+        brush: &Brush,
+        transform: Affine,
+        brush_transform: Option<Affine>,
+    ) {
+        if let Brush::Image { image, .. } = brush {
+            let brush_transform = brush_transform.unwrap_or(Affine::identity());
+            if let Some((sampled, to_image)) = self.sampled_image(image, brush_transform * transform) {
+                let mut brush = brush.clone();
+                if let Brush::Image { image, .. } = &mut brush {
+                    *image = sampled;
+                }
+                self.draw_converted_shape(shape, style, &brush, transform, Some(to_image * brush_transform));
+                return;
+            }
+        }
+
+        let path = shape.to_path();
+
+        if let (Brush::Gradient(gradient), Style::Fill(fill)) = (brush, style) {
+            if gradient.dither && self.quality.effects {
+                // blending in the noise pulls the colors towards its mean of half a step, which
+                // the gradient is stretched to cancel
+                let mut gradient = gradient.clone();
+                gradient.dither = false;
+                let stretch = |value: f32| (value - DITHER_WEIGHT / 2.0) / (1.0 - DITHER_WEIGHT);
+                for stop in &mut gradient.stops {
+                    let c = &mut stop.color;
+                    (c.r, c.g, c.b) = (stretch(c.r), stretch(c.g), stretch(c.b));
+                }
+                self.draw_converted_shape(shape, style, &Brush::Gradient(gradient), transform, brush_transform);
+                self.draw_dither_noise(&path, *fill, transform);
+                return;
+            }
+        }
+
+        if let Brush::Image {
+            image,
+            edge_mode,
+            edge_mode_y: Some(edge_mode_y),
+            mapping,
+            ..
+        } = brush
+        {
+            if edge_mode != edge_mode_y && image.width > 0 && image.height > 0 {
+                let data = self.image_brush_data(image, mapping);
+                let brush_transform = brush_transform.unwrap_or(Affine::identity());
+                self.encode_image_per_axis(&path, style, image, data, *edge_mode, *edge_mode_y, transform, brush_transform);
+                return;
+            }
+        }
+
+        // gradients with many stops are sampled from a lookup texture instead
+        if let Brush::Gradient(gradient) = brush {
+            if let Some((lut, lut_transform)) = self.gradient_lut(gradient) {
+                let brush_transform = lut_transform * brush_transform.unwrap_or(Affine::identity());
+                let lut = VelloBrushOrBrushRef::Brush(vello::peniko::Brush::Image(lut));
+                self.encode_path(&path, style, &lut, transform, Some(brush_transform));
+                return;
             }
         }
 
-        let shape = &self.shape.clone().into_vello_shape();
-        // match the style (stroke or fill)
+        let brush = &self.prepare_brush(brush);
+        self.encode_path(&path, style, brush, transform, brush_transform);
+    }
 
-        match self.style.clone() {
+    /// Fill or stroke a path according to the style.
+    fn encode_path<'a>(
+        &mut self,
+        path: &vello::kurbo::BezPath,
+        style: &Style,
+        brush: &'a VelloBrushOrBrushRef<'a>,
+        transform: Affine,
+        brush_transform: Option<Affine>,
+    ) {
+        let brush_transform = brush_transform.map(|t| t.into());
+        match style.clone() {
             Style::Fill(style) => {
-                // fill the shape
-                scene.backend.vello_scene.fill(
-                    style.into(),
-                    transform,
-                    new_brush,
-                    brush_transform,
-                    &shape,
-                );
+                self.vello_scene
+                    .fill(style.into(), transform.into(), brush, brush_transform, path);
             }
             Style::Stroke(style) => {
-                scene.backend.vello_scene.stroke(
-                    &style.into(),
-                    transform,
-                    new_brush,
-                    brush_transform,
-                    &shape,
-                );
+                self.vello_scene
+                    .stroke(&style.into(), transform.into(), brush, brush_transform, path);
             }
         }
     }
 }
 
-impl<ClipShape: IntoVelloShape + Shape> SceneTrait<VelloBackend, ClipShape>
-for Scene<VelloBackend>
-{
-    fn scene_mut(&mut self) -> &mut Scene<VelloBackend> {
-        self
+impl SnapshotBackend for VelloBackend {
+    fn snapshot(&self) -> Vec<u8> {
+        use vello::encoding::Patch;
+
+        // the values in little-endian byte order; all fields of the encoding's types have the same
+        // size, which is the alignment of the type
+        fn write<T: bytemuck::Pod>(snapshot: &mut Vec<u8>, values: &[T]) {
+            snapshot.extend_from_slice(&(values.len() as u64).to_le_bytes());
+            let bytes: &[u8] = bytemuck::cast_slice(values);
+            if cfg!(target_endian = "big") {
+                for field in bytes.chunks(std::mem::align_of::<T>()) {
+                    snapshot.extend(field.iter().rev());
+                }
+            } else {
+                snapshot.extend_from_slice(bytes);
+            }
+        }
+        fn write_image(snapshot: &mut Vec<u8>, image: &vello::peniko::Image) {
+            write(snapshot, &[image.width, image.height]);
+            write(snapshot, image.data.as_ref());
+            snapshot.push(image.extend as u8);
+        }
+
+        let mut snapshot = Vec::new();
+        let encoding = self.vello_scene.encoding();
+        write(&mut snapshot, &encoding.path_tags);
+        write(&mut snapshot, &encoding.path_data);
+        write(&mut snapshot, &encoding.draw_tags);
+        write(&mut snapshot, &encoding.draw_data);
+        write(&mut snapshot, &encoding.transforms);
+        write(&mut snapshot, &encoding.styles);
+        write(
+            &mut snapshot,
+            &[encoding.n_paths, encoding.n_path_segments, encoding.n_clips, encoding.n_open_clips],
+        );
+
+        // resources are identified by their content instead of their (runtime) ids
+        let resources = &encoding.resources;
+        for stop in &resources.color_stops {
+            write(&mut snapshot, &[stop.offset]);
+            write(&mut snapshot, &[stop.color.r, stop.color.g, stop.color.b, stop.color.a]);
+        }
+        for patch in &resources.patches {
+            match patch {
+                Patch::Ramp {
+                    draw_data_offset,
+                    stops,
+                    extend,
+                    ..
+                } => {
+                    write(&mut snapshot, &[*draw_data_offset as u64, stops.start as u64, stops.end as u64]);
+                    snapshot.push(*extend as u8);
+                }
+                Patch::GlyphRun { index } => write(&mut snapshot, &[*index as u64]),
+                Patch::Image {
+                    image,
+                    draw_data_offset,
+                    ..
+                } => {
+                    write(&mut snapshot, &[*draw_data_offset as u64]);
+                    write_image(&mut snapshot, image);
+                }
+            }
+        }
+        for run in &resources.glyph_runs {
+            write(&mut snapshot, run.font.data.as_ref());
+            write(&mut snapshot, &[run.font.index]);
+            write(&mut snapshot, &[run.transform]);
+            write(&mut snapshot, run.glyph_transform.as_slice());
+            write(&mut snapshot, &[run.font_size]);
+            write(&mut snapshot, &[run.style]);
+            let coords = resources.normalized_coords[run.normalized_coords.clone()].iter();
+            write(&mut snapshot, &coords.map(|coord| coord.to_bits()).collect::<Vec<_>>());
+            for glyph in &resources.glyphs[run.glyphs.clone()] {
+                write(&mut snapshot, &[glyph.x, glyph.y]);
+                write(&mut snapshot, &[glyph.id]);
+            }
+        }
+
+        // images replaced by textures (ordered by their content, as their ids depend on the order
+        // the images were created in) and layers with their own antialiasing
+        let mut gpu_images = self
+            .gpu_images
+            .values()
+            .map(|(image, _)| {
+                let mut bytes = Vec::new();
+                write_image(&mut bytes, image);
+                bytes
+            })
+            .collect::<Vec<_>>();
+        gpu_images.sort();
+        snapshot.extend(gpu_images.concat());
+        for layer in &self.aa_layers {
+            snapshot.push(layer.antialiasing as u8);
+            write_image(&mut snapshot, &layer.placeholder);
+            snapshot.extend_from_slice(&layer.content.snapshot());
+        }
+        snapshot
+    }
+}
+
+impl RenderBackend for VelloBackend {
+    type Font = VelloFont;
+
+    fn begin_scene(&mut self) {
+        self.vello_scene.reset();
+        self.gpu_images.clear();
+        self.aa_layers.clear();
+        self.grating_passes.clear();
+        self.effect_layers.clear();
+        self.trail_layers.clear();
+        // images with new data every frame (e.g. noise or video) would otherwise keep every
+        // frame alive
+        self.image_blobs.begin_frame();
+        self.nearest_images.begin_frame();
+        self.axis_images.begin_frame();
+        self.placeholders.lock().expect("placeholder lock poisoned").begin_frame();
     }
 
-    fn scene(&self) -> &Scene<VelloBackend> {
-        self
+    fn create_child(&self) -> Self {
+        Self {
+            vello_scene: vello::Scene::new(),
+            global_transform: Affine::identity(),
+            gpu_images: HashMap::new(),
+            gradient_luts: LruCache::new(),
+            mapped_images: LruCache::new(),
+            image_blobs: LruCache::new(),
+            nearest_images: LruCache::new(),
+            axis_images: LruCache::new(),
+            bitmap_glyphs: LruCache::new(),
+            prerendered_scenes: LruCache::new(),
+            aa_layers: Vec::new(),
+            grating_passes: Vec::new(),
+            effect_layers: Vec::new(),
+            trail_layers: Vec::new(),
+            budget: self.budget.clone(),
+            quality: self.quality,
+            color_management: self.color_management,
+            glyph_batches: None,
+            placeholders: self.placeholders.clone(),
+        }
+    }
+
+    fn global_transform(&self) -> Affine {
+        self.global_transform
+    }
+
+    fn set_quality(&mut self, quality: &QualitySettings) {
+        self.quality = *quality;
+    }
+
+    fn set_color_management(&mut self, color_management: &ColorManagement) {
+        self.color_management = *color_management;
+    }
+
+    fn set_global_transform(&mut self, transform: Affine) {
+        self.global_transform = transform;
+    }
+
+    fn draw_shape(
+        &mut self,
+        shape: &impl Shape,
+        style: &Style,
+        brush: &Brush,
+        transform: Affine,
+        brush_transform: Option<Affine>,
+    ) {
+        if self.color_management.is_identity() {
+            self.draw_converted_shape(shape, style, brush, transform, brush_transform);
+        } else {
+            let brush = brush.map_colors(|color| self.color_management.convert(color));
+            self.draw_converted_shape(shape, style, &brush, transform, brush_transform);
+        }
+    }
+
+    fn draw_glyphs(
+        &mut self,
+        font: &Self::Font,
+        font_size: f32,
+        variations: &[(&str, f32)],
+        glyphs: &[Glyph],
+        brush: &Brush,
+        transform: Affine,
+        glyph_transform: Option<Affine>,
+        rendering: TextRendering,
+        stroke: Option<&StrokeOptions>,
+    ) {
+        let converted;
+        let brush = if self.color_management.is_identity() {
+            brush
+        } else {
+            converted = brush.map_colors(|color| self.color_management.convert(color));
+            &converted
+        };
+        if let (Some(batches), Brush::Solid(color), None) = (&mut self.glyph_batches, brush, stroke) {
+            if add_to_glyph_batch(
+                batches,
+                font,
+                font_size,
+                variations,
+                glyphs,
+                *color,
+                transform,
+                glyph_transform,
+                rendering,
+            ) {
+                return;
+            }
+        }
+        self.encode_glyphs(
+            &font.font,
+            font_size,
+            variations,
+            glyphs,
+            brush,
+            transform,
+            glyph_transform,
+            rendering,
+            stroke,
+        );
     }
 
-    fn start_layer(
+    fn push_layer(
         &mut self,
         mix_mode: MixMode,
         composite_mode: CompositeMode,
-        clip: ClipShape,
+        clip: &impl Shape,
         clip_transform: Affine,
-        layer_transform: Option<Affine>,
         alpha: f32,
     ) {
-        // error if a layer transform is provided
-        if layer_transform.is_some() {
-            todo!();
-        }
-        let clip_shape = clip.into_vello_shape();
-        let global_transform = self.backend.global_transform;
-        let clip_transform = (global_transform * clip_transform).into();
-
-        self.backend.vello_scene.push_layer(
+        // batched glyphs drawn before the layer belong below it
+        self.flush_glyph_batches();
+        self.vello_scene.push_layer(
             BlendMode::new(mix_mode.into(), composite_mode.into()),
             alpha,
-            clip_transform,
-            &clip_shape,
+            clip_transform.into(),
+            &clip.to_path(),
         );
     }
 
-    fn end_layer(&mut self) {
-        self.backend.vello_scene.pop_layer();
+    fn pop_layer(&mut self) {
+        // batched glyphs drawn inside the layer belong to it
+        self.flush_glyph_batches();
+        self.vello_scene.pop_layer();
+    }
+
+    fn draw_blurred_rounded_rect(&mut self, rect: &RoundedRectangle, color: RGBA, std_dev: f64, transform: Affine) {
+        if !self.quality.effects {
+            // blurring is an optional effect, so the rectangle is filled without it
+            self.draw_shape(
+                rect,
+                &Style::Fill(FillStyle::NonZero),
+                &Brush::Solid(color),
+                transform,
+                None,
+            );
+            return;
+        }
+        let bounds = vello::kurbo::Rect::new(rect.a.x, rect.a.y, rect.b.x, rect.b.y);
+        self.vello_scene
+            .draw_blurred_rounded_rect(
+                transform.into(),
+                bounds,
+                self.color_management.convert(color).into(),
+                rect.radius,
+                std_dev,
+            );
+    }
+
+    fn draw_image(&mut self, image: &Image, transform: Affine) {
+        if let Some((sampled, to_image)) = self.sampled_image(image, transform) {
+            self.draw_image(&sampled, to_image * transform);
+            return;
+        }
+        let blob = self.image_blob(image, &ImageMapping::default());
+        let vello_image = vello::peniko::Image::new(blob, vello::peniko::Format::Rgba8, image.width, image.height);
+        self.register_gpu_image(image, &vello_image);
+        self.vello_scene.draw_image(&vello_image, transform.into());
+    }
+
+    fn append(&mut self, other: &Self, transform: Affine) {
+        self.vello_scene.append(&other.vello_scene, Some(transform.into()));
+        self.gpu_images
+            .extend(other.gpu_images.iter().map(|(id, image)| (*id, image.clone())));
+        self.aa_layers.extend(other.aa_layers.iter().cloned());
+        self.grating_passes.extend(other.grating_passes.iter().cloned());
+        self.effect_layers.extend(other.effect_layers.iter().cloned());
+        self.trail_layers.extend(other.trail_layers.iter().cloned());
+    }
+
+    fn append_with_effects(
+        &mut self,
+        other: &Self,
+        transform: Affine,
+        effects: &[LayerEffect],
+        width: u32,
+        height: u32,
+    ) {
+        if effects.is_empty() || !self.quality.effects {
+            self.append(other, transform);
+            return;
+        }
+
+        let mut content = self.create_child();
+        content.append(other, transform);
+        let placeholder = self.placeholder(width, height);
+        self.vello_scene.draw_image(&placeholder, vello::kurbo::Affine::IDENTITY);
+        let color_management = self.color_management;
+        self.effect_layers.push(EffectLayer {
+            luminance_mask: false,
+            effects: effects
+                .iter()
+                .map(|effect| match *effect {
+                    LayerEffect::DropShadow { dx, dy, sigma, color } => LayerEffect::DropShadow {
+                        dx,
+                        dy,
+                        sigma,
+                        color: color_management.convert(color),
+                    },
+                    effect => effect,
+                })
+                .collect(),
+            content,
+            placeholder,
+        });
+    }
+
+    fn append_luminance_mask(&mut self, mask: &Self, transform: Affine, width: u32, height: u32) {
+        let mut content = self.create_child();
+        content.append(mask, transform);
+        let placeholder = self.placeholder(width, height);
+        self.vello_scene.draw_image(&placeholder, vello::kurbo::Affine::IDENTITY);
+        self.effect_layers.push(EffectLayer {
+            luminance_mask: true,
+            effects: Vec::new(),
+            content,
+            placeholder,
+        });
+    }
+
+    fn append_with_trail(&mut self, other: &Self, transform: Affine, id: u64, decay: f32, width: u32, height: u32) {
+        let mut content = self.create_child();
+        content.append(other, transform);
+        let placeholder = self.placeholder(width, height);
+        self.vello_scene.draw_image(&placeholder, vello::kurbo::Affine::IDENTITY);
+        self.trail_layers.push(TrailLayerPass {
+            id,
+            decay,
+            content,
+            placeholder,
+        });
     }
 }
 
 // allow converting different types into the vello types
 
-// Point2D
-impl From<Point> for vello::kurbo::Point {
-    fn from(point: Point) -> Self {
-        vello::kurbo::Point::new(point.x, point.y)
+/// The weight the dither noise is blended in with: its alpha of one 8-bit step.
+const DITHER_WEIGHT: f32 = 1.0 / 255.0;
+
+/// A tiled texture of gray blue noise with an alpha of one step, used to dither gradients.
+fn dither_noise() -> &'static vello::peniko::Image {
+    static NOISE: OnceLock<vello::peniko::Image> = OnceLock::new();
+    NOISE.get_or_init(|| {
+        let data = blue_noise(DITHER_NOISE_SIZE)
+            .into_iter()
+            .flat_map(|noise| {
+                let value = (noise * 255.0).round() as u8;
+                [value, value, value, 1]
+            })
+            .collect::<Vec<_>>();
+        let blob = vello::peniko::Blob::new(Arc::new(data));
+        vello::peniko::Image::new(blob, vello::peniko::Format::Rgba8, DITHER_NOISE_SIZE, DITHER_NOISE_SIZE)
+            .with_extend(vello::peniko::Extend::Repeat)
+    })
+}
+
+/// A tileable square of blue noise, which has almost no energy at low frequencies, so that it is
+/// hard to see: the ranks of the pixels in a void-and-cluster ordering, between 0 and 1.
+///
+/// Every pixel is put into the largest void left by the previous ones, measured with a Gaussian
+/// that wraps around the edges.
+fn blue_noise(size: u32) -> Vec<f32> {
+    const SIGMA: f32 = 1.5;
+    const RADIUS: i32 = 5;
+    let count = (size * size) as usize;
+    // a tiny jitter decides between equally empty pixels without a regular pattern
+    let mut energy = (0..count as u32)
+        .map(|i| (i.wrapping_mul(0x9e37_79b9) >> 8) as f32 / (1 << 24) as f32 * 1e-3)
+        .collect::<Vec<_>>();
+    let mut ranks = vec![0.0; count];
+    for rank in 0..count {
+        let (index, _) = energy
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .expect("the noise has pixels");
+        ranks[index] = rank as f32 / (count - 1).max(1) as f32;
+        let (x, y) = ((index as u32 % size) as i32, (index as u32 / size) as i32);
+        for dy in -RADIUS..=RADIUS {
+            for dx in -RADIUS..=RADIUS {
+                let (nx, ny) = ((x + dx).rem_euclid(size as i32), (y + dy).rem_euclid(size as i32));
+                energy[(ny as u32 * size + nx as u32) as usize] +=
+                    (-((dx * dx + dy * dy) as f32) / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+        energy[index] = f32::INFINITY;
     }
+    ranks
 }
 
-// Affine
-impl From<Affine> for vello::kurbo::Affine {
-    fn from(affine: Affine) -> Self {
-        vello::kurbo::Affine::new(affine.0)
+// Antialiasing
+impl From<Antialiasing> for vello::AaConfig {
+    fn from(antialiasing: Antialiasing) -> Self {
+        match antialiasing {
+            Antialiasing::Area => vello::AaConfig::Area,
+            Antialiasing::Msaa8 => vello::AaConfig::Msaa8,
+            Antialiasing::Msaa16 => vello::AaConfig::Msaa16,
+        }
     }
 }
 
@@ -559,26 +3312,20 @@ impl From<FillStyle> for vello::peniko::Fill {
     }
 }
 
-// StrokeStyle
-impl From<StrokeOptions> for vello::kurbo::Stroke {
-    fn from(style: StrokeOptions) -> Self {
-        vello::kurbo::Stroke {
-            width: style.width,
-            ..Default::default()
-        }
-    }
-}
-
 // BrushRef (this needs to be refactored)
 impl<'a> Brush {
     fn as_brush_or_brushref(&'a self) -> VelloBrushOrBrushRef<'a> {
         match self {
-            Brush::Image { image, fit_mode, edge_mode, x, y } => {
+            Brush::Image { image, edge_mode, .. } => {
                 // note that offsets and fit mode are already applied when the geom is created and part
                 // of the brush transform
 
                 // create peniko::Image
-                let blob = vello::peniko::Blob::new(image.data.clone());
+                let data = match image.format {
+                    ImageFormat::Rgba8 => image.data.clone(),
+                    ImageFormat::Alpha8 => image.rgba8_data(),
+                };
+                let blob = vello::peniko::Blob::new(data);
                 let image = vello::peniko::Image::new(blob, vello::peniko::Format::Rgba8, image.width, image.height);
                 let image = image.with_extend(edge_mode.into());
 
@@ -616,41 +3363,11 @@ impl<'a> From<&'a VelloBrushOrBrushRef<'a>> for vello::peniko::BrushRef<'a> {
 impl<'a> TryFrom<&'a VelloBrushOrBrushRef<'a>> for vello::peniko::Image {
     type Error = &'static str;
 
-    fn try_from(brush: &'a VelloBrushOrBrushRef<'a>) -> Result<Self, Self::Error> {
+    fn try_from(brush: &'a VelloBrushOrBrushRef<'a>) -> std::result::Result<Self, Self::Error> {
         match brush {
             VelloBrushOrBrushRef::Brush(vello::peniko::Brush::Image(image)) => Ok(image.clone()),
             _ => Err("Not an image brush"),
-        }
-    }
-}
-
-// implement vello Shape trait for different shapes
-trait IntoVelloShape {
-    type VelloShape: vello::kurbo::Shape;
-    fn into_vello_shape(self) -> Self::VelloShape;
-}
-
-// rectangle
-impl IntoVelloShape for Rectangle {
-    type VelloShape = vello::kurbo::Rect;
-    fn into_vello_shape(self) -> Self::VelloShape {
-        vello::kurbo::Rect::new(self.a.x, self.a.y, self.b.x, self.b.y)
-    }
-}
-
-// rounded rectangle
-impl IntoVelloShape for RoundedRectangle {
-    type VelloShape = vello::kurbo::RoundedRect;
-    fn into_vello_shape(self) -> Self::VelloShape {
-        vello::kurbo::RoundedRect::new(self.a.x, self.a.y, self.b.x, self.b.y, self.radius)
-    }
-}
-
-// circle
-impl IntoVelloShape for Circle {
-    type VelloShape = vello::kurbo::Circle;
-    fn into_vello_shape(self) -> Self::VelloShape {
-        vello::kurbo::Circle::new(self.center, self.radius)
+        }
     }
 }
 
@@ -673,6 +3390,20 @@ impl From<MixMode> for vello::peniko::Mix {
             MixMode::Normal => vello::peniko::Mix::Normal,
             MixMode::Clip => vello::peniko::Mix::Clip,
             MixMode::Multiply => vello::peniko::Mix::Multiply,
+            MixMode::Screen => vello::peniko::Mix::Screen,
+            MixMode::Overlay => vello::peniko::Mix::Overlay,
+            MixMode::Darken => vello::peniko::Mix::Darken,
+            MixMode::Lighten => vello::peniko::Mix::Lighten,
+            MixMode::ColorDodge => vello::peniko::Mix::ColorDodge,
+            MixMode::ColorBurn => vello::peniko::Mix::ColorBurn,
+            MixMode::HardLight => vello::peniko::Mix::HardLight,
+            MixMode::SoftLight => vello::peniko::Mix::SoftLight,
+            MixMode::Difference => vello::peniko::Mix::Difference,
+            MixMode::Exclusion => vello::peniko::Mix::Exclusion,
+            MixMode::Hue => vello::peniko::Mix::Hue,
+            MixMode::Saturation => vello::peniko::Mix::Saturation,
+            MixMode::Color => vello::peniko::Mix::Color,
+            MixMode::Luminosity => vello::peniko::Mix::Luminosity,
         }
     }
 }
@@ -763,8 +3494,8 @@ impl From<GradientKind> for vello::peniko::GradientKind {
 impl From<Gradient> for vello::peniko::Gradient {
     fn from(gradient: Gradient) -> Self {
         vello::peniko::Gradient {
+            stops: gradient.resolved_stops().into_iter().map(|stop| stop.into()).collect(),
             kind: gradient.kind.into(),
-            stops: gradient.stops.into_iter().map(|stop| stop.into()).collect(),
             extend: gradient.extend.into(),
         }
     }
@@ -772,106 +3503,1179 @@ impl From<Gradient> for vello::peniko::Gradient {
 
 // Text
 #[derive(Debug, Clone)]
-pub struct VelloFont(vello::peniko::Font);
+pub struct VelloFont {
+    font: vello::peniko::Font,
+    data: FontData,
+    /// The fonts used for characters the font has no glyphs for.
+    fallback: Option<Arc<FontFallback>>,
+}
+
+/// The fallback fonts of a [`VelloFont`], loaded once per face.
+#[derive(Debug)]
+struct FontFallback {
+    registry: Arc<FontRegistry>,
+    /// The loaded faces (`None` if a face can't be loaded), keyed by their data and index.
+    fonts: Mutex<HashMap<(usize, u32), Option<VelloFont>>>,
+}
 
 impl VelloFont {
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        let blob = vello::peniko::Blob::new(Arc::new(bytes.to_vec()));
-        let font = vello::peniko::Font::new(blob, 0);
+    /// Load a font from the data of a font file (the first font of a collection).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::from_data(&FontData::new(Arc::new(bytes.to_vec()), 0))
+    }
+
+    /// Load a font face, e.g. one found by a [`FontRegistry`].
+    ///
+    /// Glyphs are cached per loaded font, so load each face once and reuse it.
+    pub fn from_data(data: &FontData) -> Result<Self> {
+        let blob = vello::peniko::Blob::new(data.data.clone());
+        let font = vello::peniko::Font::new(blob, data.index);
+        vello_font_to_font_ref(&font)?;
+        // the font also needs to be readable for shaping
+        rustybuzz::Face::from_slice(&data.data, data.index).ok_or(RendererError::Font)?;
+
+        Ok(Self {
+            font,
+            data: data.clone(),
+            fallback: None,
+        })
+    }
+
+    /// Draw characters the font has no glyphs for with fonts of a registry (see
+    /// [`FontRegistry::runs`]). Texts with a locale prefer the families of their locale.
+    pub fn with_fallback(mut self, registry: Arc<FontRegistry>) -> Self {
+        self.fallback = Some(Arc::new(FontFallback {
+            registry,
+            fonts: Mutex::default(),
+        }));
+        self
+    }
+
+    /// Split text into runs of this font and of fallback fonts for the characters it has no
+    /// glyphs for. With a locale, the families of the locale are searched first (see
+    /// [`FontRegistry::runs_for_locale`]).
+    fn runs<'a>(
+        &self,
+        text: &'a str,
+        weight: f32,
+        style: &FontStyle,
+        locale: Option<&Locale>,
+    ) -> Vec<(&'a str, VelloFont)> {
+        let Some(fallback) = &self.fallback else {
+            return vec![(text, self.clone())];
+        };
+        let weight = weight.round().clamp(1.0, 1000.0) as u16;
+        let runs = match locale {
+            Some(locale) => fallback
+                .registry
+                .runs_for_locale(&self.data, text, locale, weight, style.clone()),
+            None => fallback.registry.runs(&self.data, text, weight, style.clone()),
+        };
+        runs.into_iter()
+            .map(|(run, data)| (run, self.fallback_font(Some(&data))))
+            .collect()
+    }
 
-        Self(font)
+    /// The font of a face found by the registry (`None` and faces that can't be loaded are
+    /// drawn with this font).
+    fn fallback_font(&self, data: Option<&FontData>) -> VelloFont {
+        let (Some(fallback), Some(data)) = (&self.fallback, data) else {
+            return self.clone();
+        };
+        if same_face(data, &self.data) {
+            return self.clone();
+        }
+        let key = (Arc::as_ptr(&data.data) as usize, data.index);
+        let mut fonts = fallback.fonts.lock().expect("font cache lock poisoned");
+        fonts
+            .entry(key)
+            .or_insert_with(|| Self::from_data(data).ok())
+            .clone()
+            .unwrap_or_else(|| self.clone())
     }
 }
 
-impl Drawable<VelloBackend> for FormatedText<VelloFont> {
-    fn draw(&mut self, scene: &mut Scene<VelloBackend>) {
-        let transform: vello::kurbo::Affine =
-            (self.transform * scene.backend.global_transform).into();
+/// Shapes parts of a text with the fonts of its runs (see [`VelloFont::runs`]), reading each font
+/// once.
+struct RunShaper<'a> {
+    text: &'a str,
+    /// The byte offset where each run starts and the index of its font in `shapers`.
+    runs: Vec<(usize, usize)>,
+    shapers: Vec<(&'a VelloFont, Shaper<'a>)>,
+    locale: Option<&'a Locale>,
+}
+
+impl<'a> RunShaper<'a> {
+    fn new(
+        text: &'a str,
+        runs: &'a [(&'a str, VelloFont)],
+        size: f32,
+        variations: impl Fn(&VelloFont) -> Vec<(&'static str, f32)>,
+        locale: Option<&'a Locale>,
+    ) -> Self {
+        let mut shapers: Vec<(&VelloFont, Shaper)> = Vec::new();
+        let mut starts = Vec::new();
+        let mut start = 0;
+        for (run, font) in runs {
+            let index = match shapers.iter().position(|(other, _)| same_face(&other.data, &font.data)) {
+                Some(index) => index,
+                None => {
+                    let shaper = Shaper::new(&font.data.data, font.data.index, size, &variations(font))
+                        .expect("font was validated when it was loaded");
+                    shapers.push((font, shaper));
+                    shapers.len() - 1
+                }
+            };
+            starts.push((start, index));
+            start += run.len();
+        }
+        Self {
+            text,
+            runs: starts,
+            shapers,
+            locale,
+        }
+    }
+
+    /// Shape a part of the text, given as a byte range. Returns the runs of glyphs, positioned
+    /// relative to the start of the part, with their fonts and the advance of the part.
+    fn shape(&self, range: Range<usize>) -> (Vec<(VelloFont, Vec<Glyph>)>, f32) {
+        let ends = self.runs.iter().skip(1).map(|(start, _)| *start).chain([self.text.len()]);
+        let mut pen_x = 0.0;
+        let mut shaped = Vec::new();
+        for (&(start, index), end) in self.runs.iter().zip(ends) {
+            let (start, end) = (start.max(range.start), end.min(range.end));
+            if start >= end {
+                continue;
+            }
+            let (font, shaper) = &self.shapers[index];
+            let (glyphs, width) = shaper.shape(&self.text[start..end], self.locale);
+            let glyphs = glyphs
+                .into_iter()
+                .map(|glyph| Glyph {
+                    x: pen_x + glyph.x,
+                    ..glyph
+                })
+                .collect();
+            shaped.push(((*font).clone(), glyphs));
+            pen_x += width;
+        }
+        (shaped, pen_x)
+    }
 
-        let font = &self.font.0;
+    /// The advance of a part of the text.
+    fn width(&self, range: Range<usize>) -> f32 {
+        self.shape(range).1
+    }
+}
+
+impl FormatedText<VelloFont> {
+    /// The variation axis settings for the weight, width and style of the text, and the glyph
+    /// transform that synthesizes a slant if the font has no axis for the style.
+    fn variations(&self, font_ref: &vello::skrifa::FontRef) -> (Vec<(&'static str, f32)>, Option<Affine>) {
+        let axes = vello::skrifa::MetadataProvider::axes(font_ref);
+        let has_axis = |tag: &[u8; 4]| axes.iter().any(|axis| axis.tag() == vello::skrifa::Tag::new(tag));
+
+        let mut variations = vec![("wght", self.weight), ("wdth", self.width)];
+        let slanted = match self.style {
+            FontStyle::Normal => return (variations, None),
+            FontStyle::Italic if has_axis(b"ital") => {
+                variations.push(("ital", 1.0));
+                return (variations, None);
+            }
+            FontStyle::Italic | FontStyle::Oblique if has_axis(b"slnt") => {
+                // positive angles of the slant axis lean to the left
+                variations.push(("slnt", -SYNTHETIC_OBLIQUE_ANGLE));
+                return (variations, None);
+            }
+            FontStyle::Italic | FontStyle::Oblique => {
+                // glyph outlines are y-down, so shift the top of each glyph to the right
+                let skew = (SYNTHETIC_OBLIQUE_ANGLE as f64).to_radians().tan();
+                Affine([1.0, 0.0, -skew, 1.0, 0.0, 0.0])
+            }
+        };
+        (variations, Some(slanted))
+    }
+
+    /// The axis-aligned extents of the text in scene coordinates (with its transform applied).
+    pub fn scene_bounds(&self) -> Rectangle {
+        let layout = self.layout();
+        let transform = Affine::translate(layout.alignment_offset.x, layout.alignment_offset.y) * self.transform;
+        let (a, b) = (layout.bounds.a, layout.bounds.b);
+        let corners = [a, Point { x: b.x, y: a.y }, b, Point { x: a.x, y: b.y }].map(|p| transform.transform_point(p));
+        let (xs, ys) = (corners.map(|p| p.x), corners.map(|p| p.y));
+        Rectangle {
+            a: Point {
+                x: xs.into_iter().fold(f64::INFINITY, f64::min),
+                y: ys.into_iter().fold(f64::INFINITY, f64::min),
+            },
+            b: Point {
+                x: xs.into_iter().fold(f64::NEG_INFINITY, f64::max),
+                y: ys.into_iter().fold(f64::NEG_INFINITY, f64::max),
+            },
+        }
+    }
+
+    /// Hide or move texts that overlap texts with a higher priority (see [`declutter`]).
+    ///
+    /// Hidden texts are made invisible and moved texts get a translation appended to their
+    /// transform. Texts that are already invisible are ignored.
+    pub fn declutter(texts: &mut [Self], priority: impl Fn(&Self) -> i32, options: &DeclutterOptions) {
+        let mut visible = texts.iter_mut().filter(|text| text.visible).collect::<Vec<_>>();
+        let labels = visible
+            .iter()
+            .map(|text| LabelBox {
+                bounds: text.scene_bounds(),
+                priority: priority(text),
+            })
+            .collect::<Vec<_>>();
+        for (text, offset) in visible.iter_mut().zip(declutter(&labels, options)) {
+            match offset {
+                Some(offset) => text.transform = text.transform * Affine::translate(offset.x, offset.y),
+                None => text.visible = false,
+            }
+        }
+    }
+
+    /// Lay out the text and measure its extents.
+    pub fn layout(&self) -> TextLayout {
+        let font = &self.font.font;
         let font_size = vello::skrifa::instance::Size::new(self.size);
         let text = &self.text;
 
-        let font_ref = vello_font_to_font_ref(font).expect("Failed to load font");
+        let font_ref = vello_font_to_font_ref(font).expect("font was validated when it was loaded");
         let axes = vello::skrifa::MetadataProvider::axes(&font_ref);
-        let variations = [("wght", 100.0), ("wdth", 500.0)];
+        let (variations, _) = self.variations(&font_ref);
         let var_loc = axes.location(variations.iter().copied());
 
-        let charmap = vello::skrifa::MetadataProvider::charmap(&font_ref);
         let metrics = vello::skrifa::MetadataProvider::metrics(&font_ref, font_size, &var_loc);
         let line_height = metrics.ascent - metrics.descent + metrics.leading;
-        let glyph_metrics =
-            vello::skrifa::MetadataProvider::glyph_metrics(&font_ref, font_size, &var_loc);
 
-        let mut pen_x = (self.x * 2.0) as f32;
-        let mut pen_y = (self.y * 2.0) as f32;
+        let start_x = (self.x * 2.0) as f32;
+        let start_y = (self.y * 2.0) as f32;
+        let run_variations = |font: &VelloFont| {
+            let font_ref = vello_font_to_font_ref(&font.font).expect("font was validated when it was loaded");
+            self.variations(&font_ref).0
+        };
+        // lines are broken with the widths of the shaped text, so that they fit into the maximum
+        // width with kerning and ligatures
+        let runs = self.font.runs(text, self.weight, &self.style, self.locale.as_ref());
+        let shaper = RunShaper::new(text, &runs, self.size, run_variations, self.locale.as_ref());
+        let lines = break_lines(text, self.max_width.map(|w| w as f32), |range| shaper.width(range))
+            .into_iter()
+            .map(|line| shaper.shape(line))
+            .collect::<Vec<_>>();
+        let block_width = lines.iter().map(|(_, width)| *width).fold(0.0, f32::max);
+
+        // align every line within the block, the block itself is aligned by the offset below
+        let alignment = match self.alignment {
+            Alignment::Left => 0.0,
+            Alignment::Center => 0.5,
+            Alignment::Right => 1.0,
+        };
+        let mut pen_y = start_y;
+        let mut glyphs = Vec::new();
+        let mut font_runs = Vec::new();
+        for (i, (runs, width)) in lines.into_iter().enumerate() {
+            if i > 0 {
+                pen_y += line_height;
+            }
+            let pen_x = start_x + (block_width - width) * alignment;
+            for (font, run) in runs {
+                let fallback = (!same_face(&font.data, &self.font.data)).then_some(font.data);
+                font_runs.push((glyphs.len(), fallback));
+                glyphs.extend(run.into_iter().map(|glyph| Glyph {
+                    x: pen_x + glyph.x,
+                    y: pen_y + glyph.y,
+                    ..glyph
+                }));
+            }
+        }
+        let (min_x, max_x) = (start_x, start_x + block_width);
+
+        let text_width = max_x as f64;
+        let text_height = pen_y as f64 + line_height as f64;
+
+        let transform_x = -text_width * alignment as f64;
 
-        let brush_color: vello::peniko::Color = self.color.into();
+        let transform_y = match self.vertical_alignment {
+            VerticalAlignment::Top => 0.0,
+            VerticalAlignment::Middle => text_height / 2.0,
+            VerticalAlignment::Bottom => text_height,
+        };
+
+        TextLayout {
+            glyphs,
+            bounds: Rectangle {
+                a: Point {
+                    x: min_x as f64,
+                    y: (start_y - metrics.ascent) as f64,
+                },
+                b: Point {
+                    x: max_x as f64,
+                    y: (pen_y - metrics.descent) as f64,
+                },
+            },
+            alignment_offset: Point {
+                x: transform_x,
+                y: transform_y,
+            },
+            font_runs,
+        }
+    }
+}
+
+impl Drawable<VelloBackend> for FormatedText<VelloFont> {
+    fn draw(&mut self, scene: &mut Scene<VelloBackend>) {
+        if !self.visible || self.opacity <= 0.0 {
+            return;
+        }
+        if self.opacity >= 1.0 {
+            self.draw_opaque(scene);
+            return;
+        }
+
+        // the parts of the text (outline, fill, color glyphs) overlap, so they are faded together
+        let bounds = Rectangle {
+            a: Point { x: 0.0, y: 0.0 },
+            b: Point {
+                x: scene.width as f64,
+                y: scene.height as f64,
+            },
+        };
+        scene.backend.push_layer(
+            MixMode::Normal,
+            CompositeMode::SourceOver,
+            &bounds,
+            Affine::identity(),
+            self.opacity,
+        );
+        self.draw_opaque(scene);
+        scene.backend.pop_layer();
+    }
+}
+
+impl FormatedText<VelloFont> {
+    /// Draw the text without its opacity.
+    fn draw_opaque(&mut self, scene: &mut Scene<VelloBackend>) {
+        let mut layout = self.layout();
+        let transform = Affine::translate(layout.alignment_offset.x, layout.alignment_offset.y)
+            * self.transform
+            * scene.view_transform();
+
+        if let Some(background) = &self.background {
+            let padding = background.padding;
+            let bounds = &layout.bounds;
+            let rect = RoundedRectangle {
+                a: Point {
+                    x: bounds.a.x - padding,
+                    y: bounds.a.y - padding,
+                },
+                b: Point {
+                    x: bounds.b.x + padding,
+                    y: bounds.b.y + padding,
+                },
+                radius: background.corner_radius,
+            };
+            scene.backend.draw_shape(
+                &rect,
+                &Style::Fill(FillStyle::NonZero),
+                &background.brush,
+                transform,
+                None,
+            );
+        }
+
+        if let Some(hook) = &self.glyph_hook {
+            layout.apply_glyph_hook(hook);
+        }
+
+        for (fallback, glyphs) in layout.glyph_runs() {
+            let font = self.font.fallback_font(fallback);
+            self.draw_run(scene, &font, glyphs.to_vec(), transform);
+        }
+    }
+
+    /// Draw glyphs of the text that use the same font.
+    fn draw_run(&self, scene: &mut Scene<VelloBackend>, font: &VelloFont, mut glyphs: Vec<Glyph>, transform: Affine) {
+        let font_ref = vello_font_to_font_ref(&font.font).expect("font was validated when it was loaded");
+        let (variations, slant) = self.variations(&font_ref);
+
+        let glyph_transform = match (slant, self.glyph_transform) {
+            (Some(slant), Some(glyph_transform)) => Some(slant * glyph_transform),
+            (slant, glyph_transform) => slant.or(glyph_transform),
+        };
+
+        // glyphs with color layers or bitmaps (e.g. emoji) are drawn separately
+        scene.backend.draw_color_glyphs(
+            font,
+            self.size,
+            &variations,
+            &mut glyphs,
+            self.color,
+            transform,
+            glyph_transform,
+        );
+        let bitmap_glyphs = scene.backend.take_bitmap_glyphs(font, self.size, &mut glyphs, glyph_transform);
+        for (image, bitmap_transform) in bitmap_glyphs {
+            scene.backend.draw_image(&image, bitmap_transform * transform);
+        }
+        if let Some(stroke) = &self.stroke {
+            scene.backend.draw_glyphs(
+                font,
+                self.size,
+                &variations,
+                &glyphs,
+                &Brush::Solid(stroke.color),
+                transform,
+                glyph_transform,
+                self.rendering,
+                Some(&stroke.options),
+            );
+        }
+        if self.stroke.as_ref().is_none_or(|stroke| stroke.fill) {
+            let brush = self.brush.clone().unwrap_or(Brush::Solid(self.color));
+            scene.backend.draw_glyphs(
+                font,
+                self.size,
+                &variations,
+                &glyphs,
+                &brush,
+                transform,
+                glyph_transform,
+                self.rendering,
+                None,
+            );
+        }
+    }
+}
+
+/// A shaped word (with the spaces after it) of a span in a [`RichText`].
+struct RichTextPiece {
+    span: usize,
+    /// The glyphs with their fonts (the font of the span or a fallback font).
+    runs: Vec<(VelloFont, Vec<Glyph>)>,
+    width: f32,
+    /// The width without the trailing spaces, which don't count at the end of a line.
+    trimmed_width: f32,
+    /// Whether a line break follows the piece.
+    line_break: bool,
+}
+
+impl Drawable<VelloBackend> for RichText<VelloFont> {
+    fn draw(&mut self, scene: &mut Scene<VelloBackend>) {
+        if !self.visible || self.spans.is_empty() {
+            return;
+        }
+        let transform = self.transform * scene.view_transform();
+        let variations = |span: &TextSpan<VelloFont>| [("wght", span.weight)];
+
+        let metrics = self
+            .spans
+            .iter()
+            .map(|span| {
+                let font_ref = vello_font_to_font_ref(&span.font.font).expect("font was validated when it was loaded");
+                let axes = vello::skrifa::MetadataProvider::axes(&font_ref);
+                let var_loc = axes.location(variations(span));
+                let size = vello::skrifa::instance::Size::new(span.size);
+                vello::skrifa::MetadataProvider::metrics(&font_ref, size, &var_loc)
+            })
+            .collect::<Vec<_>>();
 
-        let glyphs = text
-            .chars()
-            .filter_map(|ch| {
-                if ch == '\n' {
-                    pen_y += line_height;
-                    pen_x = 0.0;
-                    return None;
+        // shape every span word by word, so lines can be broken between words
+        let mut pieces = Vec::new();
+        for (index, span) in self.spans.iter().enumerate() {
+            let locale = self.locale.as_ref();
+            let font_runs = span.font.runs(&span.text, span.weight, &FontStyle::Normal, locale);
+            let shaper = RunShaper::new(&span.text, &font_runs, span.size, |_| variations(span).to_vec(), locale);
+            let mut start = 0;
+            for (i, paragraph) in span.text.split('\n').enumerate() {
+                if i > 0 {
+                    pieces.push(RichTextPiece {
+                        span: index,
+                        runs: Vec::new(),
+                        width: 0.0,
+                        trimmed_width: 0.0,
+                        line_break: true,
+                    });
+                }
+                for word in paragraph.split_inclusive(' ') {
+                    let (runs, width) = shaper.shape(start..start + word.len());
+                    let trimmed = word.trim_end_matches(' ').len();
+                    let trimmed_width = match trimmed == word.len() {
+                        true => width,
+                        false => shaper.width(start..start + trimmed),
+                    };
+                    pieces.push(RichTextPiece {
+                        span: index,
+                        runs,
+                        width,
+                        trimmed_width,
+                        line_break: false,
+                    });
+                    start += word.len();
+                }
+                // skip the line break
+                start += 1;
+            }
+        }
+
+        // lines as lists of pieces with their x position
+        let max_width = self.max_width.map(|w| w as f32);
+        let mut lines: Vec<Vec<(&RichTextPiece, f32)>> = vec![Vec::new()];
+        let mut pen_x = 0.0;
+        for piece in &pieces {
+            let line = lines.last_mut().expect("there is always a line");
+            if max_width.is_some_and(|max_width| pen_x + piece.trimmed_width > max_width) && !line.is_empty() {
+                lines.push(Vec::new());
+                pen_x = 0.0;
+            }
+            lines.last_mut().expect("there is always a line").push((piece, pen_x));
+            pen_x += piece.width;
+            if piece.line_break {
+                lines.push(Vec::new());
+                pen_x = 0.0;
+            }
+        }
+
+        let alignment = match self.alignment {
+            Alignment::Left => 0.0,
+            Alignment::Center => 0.5,
+            Alignment::Right => 1.0,
+        };
+        let mut baseline = 0.0;
+        let mut previous_descent: Option<f32> = None;
+        let mut last_span = 0;
+        for line in lines {
+            // the tallest span of the line determines the line spacing
+            let spans = line.iter().map(|(piece, _)| piece.span).collect::<Vec<_>>();
+            last_span = spans.last().copied().unwrap_or(last_span);
+            let ascent = spans.iter().map(|&i| metrics[i].ascent).fold(metrics[last_span].ascent, f32::max);
+            let descent = spans.iter().map(|&i| -metrics[i].descent).fold(-metrics[last_span].descent, f32::max);
+            let leading = spans.iter().map(|&i| metrics[i].leading).fold(metrics[last_span].leading, f32::max);
+            if let Some(previous_descent) = previous_descent {
+                baseline += previous_descent + leading + ascent;
+            }
+            previous_descent = Some(descent);
+
+            // spaces at the end of the line are neither aligned nor decorated
+            let line_width = line
+                .iter()
+                .rfind(|(piece, _)| piece.trimmed_width > 0.0)
+                .map(|(piece, x)| x + piece.trimmed_width)
+                .unwrap_or(0.0);
+            let offset = -line_width * alignment;
+            for (piece, x) in line {
+                let span = &self.spans[piece.span];
+                let decoration_width = piece.width.min(line_width - x);
+                let x = offset + x;
+                let brush = Brush::Solid(span.color);
+                for (font, glyphs) in &piece.runs {
+                    let mut glyphs = glyphs
+                        .iter()
+                        .map(|glyph| Glyph {
+                            x: x + glyph.x,
+                            y: baseline + glyph.y,
+                            ..*glyph
+                        })
+                        .collect::<Vec<_>>();
+
+                    scene.backend.draw_color_glyphs(
+                        font,
+                        span.size,
+                        &variations(span),
+                        &mut glyphs,
+                        span.color,
+                        transform,
+                        None,
+                    );
+                    let bitmap_glyphs = scene.backend.take_bitmap_glyphs(font, span.size, &mut glyphs, None);
+                    for (image, glyph_transform) in bitmap_glyphs {
+                        scene.backend.draw_image(&image, glyph_transform * transform);
+                    }
+                    scene.backend.draw_glyphs(
+                        font,
+                        span.size,
+                        &variations(span),
+                        &glyphs,
+                        &brush,
+                        transform,
+                        None,
+                        self.rendering,
+                        None,
+                    );
+                }
+
+                // decoration offsets are the distance from the baseline to the top of the line, y-up
+                let decorations = [
+                    (span.underline, metrics[piece.span].underline),
+                    (span.strikethrough, metrics[piece.span].strikeout),
+                ];
+                for (_, decoration) in decorations.into_iter().filter(|(enabled, _)| *enabled) {
+                    let Some(decoration) = decoration.filter(|_| decoration_width > 0.0) else {
+                        continue;
+                    };
+                    let top = (baseline - decoration.offset) as f64;
+                    let rect = Rectangle {
+                        a: Point { x: x as f64, y: top },
+                        b: Point {
+                            x: (x + decoration_width) as f64,
+                            y: top + decoration.thickness as f64,
+                        },
+                    };
+                    scene
+                        .backend
+                        .draw_shape(&rect, &Style::Fill(FillStyle::NonZero), &brush, transform, None);
+                }
+            }
+        }
+    }
+}
+
+impl SpriteStrip<VelloBackend> {
+    /// Label the tiles with centered text below them.
+    pub fn with_font(self, font: VelloFont, size: f32, color: RGBA) -> Self {
+        let height = size as f64 * 1.5;
+        self.with_labels(height, move |scene, label, rect| {
+            // put the baseline so that the line is centered vertically
+            let baseline = (rect.a.y + rect.b.y) / 2.0 + size as f64 * 0.35;
+            let mut text = RichText::new(vec![TextSpan::new(label, font.clone(), size, color)])
+                .with_alignment(Alignment::Center)
+                .with_max_width(rect.b.x - rect.a.x)
+                .with_transform(Affine::translate((rect.a.x + rect.b.x) / 2.0, baseline));
+            text.draw(scene);
+        })
+    }
+}
+
+impl Drawable<VelloBackend> for Table<VelloFont> {
+    fn draw(&mut self, scene: &mut Scene<VelloBackend>) {
+        if !self.visible || self.rows.is_empty() {
+            return;
+        }
+        let transform = self.transform * scene.view_transform();
+        let font = &self.font.font;
+        let font_ref = vello_font_to_font_ref(font).expect("font was validated when it was loaded");
+        let axes = vello::skrifa::MetadataProvider::axes(&font_ref);
+        let var_loc = axes.location([("wght", self.weight)]);
+        let size = vello::skrifa::instance::Size::new(self.size);
+        let metrics = vello::skrifa::MetadataProvider::metrics(&font_ref, size, &var_loc);
+
+        // shape every cell to measure the columns
+        let shaped = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(row, cells)| {
+                let variations = [("wght", self.row_weight(row))];
+                cells
+                    .iter()
+                    .map(|cell| {
+                        shape_line(font.data.as_ref(), font.index, self.size, &variations, &cell.text, None)
+                            .expect("font was validated when it was loaded")
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let widths = (0..self.column_count())
+            .map(|column| match self.columns.get(column).copied().unwrap_or_default() {
+                ColumnWidth::Fixed(width) => width,
+                ColumnWidth::Auto => {
+                    let text_width = shaped
+                        .iter()
+                        .filter_map(|row| row.get(column))
+                        .map(|(_, width)| *width as f64)
+                        .fold(0.0, f64::max);
+                    text_width + 2.0 * self.padding
                 }
-                let gid = charmap.map(ch).unwrap_or_default();
-                let advance = glyph_metrics.advance_width(gid).unwrap_or_default();
-                let x = pen_x;
-                pen_x += advance;
-                Some(vello::Glyph {
-                    id: gid.to_u32(),
-                    x,
-                    y: pen_y,
-                })
             })
             .collect::<Vec<_>>();
+        let row_height = (metrics.ascent - metrics.descent) as f64 + 2.0 * self.padding;
+        let table_width: f64 = widths.iter().sum();
+        let table_height = row_height * self.rows.len() as f64;
+        let fill = Style::Fill(FillStyle::NonZero);
+        let cell_rect = |x: f64, y: f64, width: f64| Rectangle {
+            a: Point { x, y },
+            b: Point {
+                x: x + width,
+                y: y + row_height,
+            },
+        };
 
-        let text_width = pen_x as f64;
-        let text_height = pen_y as f64 + line_height as f64;
+        if let Some(background) = &self.background {
+            let rect = Rectangle {
+                a: Point { x: 0.0, y: 0.0 },
+                b: Point {
+                    x: table_width,
+                    y: table_height,
+                },
+            };
+            scene.backend.draw_shape(&rect, &fill, background, transform, None);
+        }
 
-        let transform_x = match self.alignment {
+        let alignment = match self.alignment {
             Alignment::Left => 0.0,
-            Alignment::Center => -text_width / 2.0,
-            Alignment::Right => -text_width,
+            Alignment::Center => 0.5,
+            Alignment::Right => 1.0,
         };
+        for (row, (cells, shaped_cells)) in self.rows.iter().zip(&shaped).enumerate() {
+            let variations = [("wght", self.row_weight(row))];
+            let top = row as f64 * row_height;
+            let baseline = top + self.padding + metrics.ascent as f64;
+            let mut left = 0.0;
+            for ((cell, (glyphs, text_width)), width) in cells.iter().zip(shaped_cells).zip(&widths) {
+                if let Some(background) = &cell.background {
+                    scene
+                        .backend
+                        .draw_shape(&cell_rect(left, top, *width), &fill, background, transform, None);
+                }
 
-        let transform_y = match self.vertical_alignment {
-            VerticalAlignment::Top => 0.0,
-            VerticalAlignment::Middle => text_height / 2.0,
-            VerticalAlignment::Bottom => text_height,
+                let free = width - 2.0 * self.padding - *text_width as f64;
+                let x = left + self.padding + free * alignment;
+                let mut glyphs = glyphs
+                    .iter()
+                    .map(|glyph| Glyph {
+                        x: glyph.x + x as f32,
+                        y: glyph.y + baseline as f32,
+                        ..*glyph
+                    })
+                    .collect::<Vec<_>>();
+                let color = cell.color.unwrap_or(self.color);
+                scene.backend.draw_color_glyphs(
+                    &self.font,
+                    self.size,
+                    &variations,
+                    &mut glyphs,
+                    color,
+                    transform,
+                    None,
+                );
+                let bitmap_glyphs = scene.backend.take_bitmap_glyphs(&self.font, self.size, &mut glyphs, None);
+                for (image, glyph_transform) in bitmap_glyphs {
+                    scene.backend.draw_image(&image, glyph_transform * transform);
+                }
+                scene.backend.draw_glyphs(
+                    &self.font,
+                    self.size,
+                    &variations,
+                    &glyphs,
+                    &Brush::Solid(color),
+                    transform,
+                    None,
+                    TextRendering::default(),
+                    None,
+                );
+                left += width;
+            }
+        }
+
+        if let Some(border) = &self.border {
+            let mut path = Path::builder();
+            for row in 0..=self.rows.len() {
+                let y = row as f64 * row_height;
+                path = path.move_to(Point { x: 0.0, y }).line_to(Point { x: table_width, y });
+            }
+            let mut x = 0.0;
+            for width in std::iter::once(&0.0).chain(&widths) {
+                x += width;
+                path = path.move_to(Point { x, y: 0.0 }).line_to(Point { x, y: table_height });
+            }
+            let style = Style::Stroke(StrokeOptions::new(border.width).caps(Cap::Square));
+            scene
+                .backend
+                .draw_shape(&path.build(), &style, &border.brush, transform, None);
+        }
+    }
+}
+
+impl<S: Shape> Drawable<VelloBackend> for TextOnPath<VelloFont, S> {
+    fn draw(&mut self, scene: &mut Scene<VelloBackend>) {
+        if !self.visible {
+            return;
+        }
+        let transform = self.transform * scene.view_transform();
+        let variations = [("wght", self.weight)];
+        let font = &self.font.font;
+        let (glyphs, width) = shape_line(font.data.as_ref(), font.index, self.size, &variations, &self.text, None)
+            .expect("font was validated when it was loaded");
+
+        let brush = Brush::Solid(self.color);
+        for (glyph, glyph_transform) in self.place_glyphs(&glyphs, width) {
+            let transform = glyph_transform * transform;
+            let mut glyphs = vec![glyph];
+            scene.backend.draw_color_glyphs(
+                &self.font,
+                self.size,
+                &variations,
+                &mut glyphs,
+                self.color,
+                transform,
+                None,
+            );
+            let bitmap_glyphs = scene.backend.take_bitmap_glyphs(&self.font, self.size, &mut glyphs, None);
+            for (image, bitmap_transform) in bitmap_glyphs {
+                scene.backend.draw_image(&image, bitmap_transform * transform);
+            }
+            scene.backend.draw_glyphs(
+                &self.font,
+                self.size,
+                &variations,
+                &glyphs,
+                &brush,
+                transform,
+                None,
+                self.rendering,
+                None,
+            );
+        }
+    }
+}
+
+/// Decode the data of a bitmap glyph into an RGBA image.
+fn decode_bitmap_glyph(bitmap: &vello::skrifa::bitmap::BitmapGlyph) -> Option<Image> {
+    use vello::skrifa::bitmap::BitmapData;
+    let image = match bitmap.data {
+        BitmapData::Png(png) => image::load_from_memory(png).ok()?,
+        BitmapData::Bgra(bgra) => {
+            let rgba = bgra.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect();
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_raw(bitmap.width, bitmap.height, rgba)?)
+        }
+        BitmapData::Mask(_) => return None,
+    };
+    Some(Image::new(&image))
+}
+
+/// Add a glyph run to a matching batch (or start a new one). Returns false if the run can't be
+/// batched because its transform can't be inverted.
+#[allow(clippy::too_many_arguments)]
+fn add_to_glyph_batch(
+    batches: &mut Vec<GlyphBatch>,
+    font: &VelloFont,
+    font_size: f32,
+    variations: &[(&str, f32)],
+    glyphs: &[Glyph],
+    color: RGBA,
+    transform: Affine,
+    glyph_transform: Option<Affine>,
+    rendering: TextRendering,
+) -> bool {
+    if transform.determinant().abs() < 1e-12 {
+        return false;
+    }
+    let same_color = |other: &RGBA| [other.r, other.g, other.b, other.a] == [color.r, color.g, color.b, color.a];
+    let linear = |t: &Affine| [t.0[0], t.0[1], t.0[2], t.0[3]];
+    let batch = batches.iter_mut().find(|batch| {
+        batch.font.data.id() == font.font.data.id()
+            && batch.font.index == font.font.index
+            && batch.font_size == font_size
+            && batch.variations.iter().map(|(tag, value)| (tag.as_str(), *value)).eq(variations.iter().copied())
+            && same_color(&batch.color)
+            // runs can only be merged if they differ by a translation
+            && linear(&batch.transform) == linear(&transform)
+            && batch.glyph_transform.map(|t| t.0) == glyph_transform.map(|t| t.0)
+            && batch.rendering == rendering
+    });
+    let Some(batch) = batch else {
+        batches.push(GlyphBatch {
+            font: font.font.clone(),
+            font_size,
+            variations: variations.iter().map(|(tag, value)| (tag.to_string(), *value)).collect(),
+            color,
+            transform,
+            glyph_transform,
+            rendering,
+            glyphs: glyphs.to_vec(),
+        });
+        return true;
+    };
+
+    // the origin of this run in the space of the batch
+    let offset = batch.transform.inverse().transform_point(transform.transform_point(Point { x: 0.0, y: 0.0 }));
+    batch.glyphs.extend(glyphs.iter().map(|glyph| Glyph {
+        x: glyph.x + offset.x as f32,
+        y: glyph.y + offset.y as f32,
+        ..*glyph
+    }));
+    true
+}
+
+/// The colors of the first palette of a color font.
+fn color_palette(font_ref: &vello::skrifa::FontRef) -> Vec<vello::peniko::Color> {
+    use vello::skrifa::raw::TableProvider;
+    let Ok(cpal) = font_ref.cpal() else {
+        return Vec::new();
+    };
+    let (Some(Ok(records)), Some(first)) = (cpal.color_records_array(), cpal.color_record_indices().first()) else {
+        return Vec::new();
+    };
+    let first = first.get() as usize;
+    records
+        .iter()
+        .skip(first)
+        .take(cpal.num_palette_entries() as usize)
+        .map(|color| vello::peniko::Color::rgba8(color.red(), color.green(), color.blue(), color.alpha()))
+        .collect()
+}
+
+/// Encodes the layers of a COLR glyph into a vello scene.
+struct ColorGlyphPainter<'a> {
+    scene: &'a mut vello::Scene,
+    outlines: vello::skrifa::outline::OutlineGlyphCollection<'a>,
+    location: &'a vello::skrifa::instance::Location,
+    palette: &'a [vello::peniko::Color],
+    /// The color used for the palette index 0xFFFF.
+    foreground: vello::peniko::Color,
+    /// The stack of transforms from glyph space to device pixels.
+    transforms: Vec<Affine>,
+    /// The number of clips and layers that are pushed and not popped yet.
+    layers: usize,
+}
+
+impl ColorGlyphPainter<'_> {
+    /// The area filled by paints, which are only limited by the clips around them.
+    const FILL_AREA: vello::kurbo::Rect = vello::kurbo::Rect::new(-1.0e5, -1.0e5, 1.0e5, 1.0e5);
+
+    fn transform(&self) -> vello::kurbo::Affine {
+        (*self.transforms.last().expect("the glyph transform is never popped")).into()
+    }
+
+    fn color(&self, palette_index: u16, alpha: f32) -> vello::peniko::Color {
+        let color = match palette_index {
+            0xFFFF => self.foreground,
+            index => self.palette.get(index as usize).copied().unwrap_or(self.foreground),
         };
+        color.with_alpha_factor(alpha)
+    }
 
-        let transform = transform.pre_translate(vello::kurbo::Vec2::new(transform_x, transform_y));
+    fn stops(&self, stops: &[vello::skrifa::color::ColorStop]) -> Vec<vello::peniko::ColorStop> {
+        stops
+            .iter()
+            .map(|stop| vello::peniko::ColorStop {
+                offset: stop.offset,
+                color: self.color(stop.palette_index, stop.alpha),
+            })
+            .collect()
+    }
 
-        scene
-            .backend
-            .vello_scene
-            .draw_glyphs(font)
-            .font_size(self.size)
-            .transform(transform)
-            .glyph_transform(self.glyph_transform.map(|t| t.into()))
-            .normalized_coords(var_loc.coords())
-            .brush(brush_color)
-            .hint(false)
-            .draw(vello::peniko::Fill::NonZero, glyphs.into_iter());
+    fn glyph_path(&self, glyph_id: vello::skrifa::GlyphId) -> Option<vello::kurbo::BezPath> {
+        let outline = self.outlines.get(glyph_id)?;
+        let mut pen = PathPen(vello::kurbo::BezPath::new());
+        let size = vello::skrifa::instance::Size::unscaled();
+        outline.draw((size, self.location), &mut pen).ok()?;
+        Some(pen.0)
+    }
+}
+
+impl vello::skrifa::color::ColorPainter for ColorGlyphPainter<'_> {
+    fn push_transform(&mut self, transform: vello::skrifa::color::Transform) {
+        let local = Affine(
+            [transform.xx, transform.yx, transform.xy, transform.yy, transform.dx, transform.dy].map(|v| v as f64),
+        );
+        let current = *self.transforms.last().expect("the glyph transform is never popped");
+        self.transforms.push(local * current);
+    }
+
+    fn pop_transform(&mut self) {
+        if self.transforms.len() > 1 {
+            self.transforms.pop();
+        }
+    }
+
+    fn push_clip_glyph(&mut self, glyph_id: vello::skrifa::GlyphId) {
+        let path = self.glyph_path(glyph_id).unwrap_or_default();
+        self.scene.push_layer(vello::peniko::Mix::Clip, 1.0, self.transform(), &path);
+        self.layers += 1;
+    }
+
+    fn push_clip_box(&mut self, clip_box: vello::skrifa::raw::types::BoundingBox<f32>) {
+        let rect = vello::kurbo::Rect::new(
+            clip_box.x_min as f64,
+            clip_box.y_min as f64,
+            clip_box.x_max as f64,
+            clip_box.y_max as f64,
+        );
+        self.scene.push_layer(vello::peniko::Mix::Clip, 1.0, self.transform(), &rect);
+        self.layers += 1;
+    }
+
+    fn pop_clip(&mut self) {
+        self.pop_layer();
+    }
+
+    fn fill(&mut self, brush: vello::skrifa::color::Brush<'_>) {
+        use vello::skrifa::color::Brush as ColorBrush;
+        let extend = |extend: vello::skrifa::color::Extend| match extend {
+            vello::skrifa::color::Extend::Repeat => vello::peniko::Extend::Repeat,
+            vello::skrifa::color::Extend::Reflect => vello::peniko::Extend::Reflect,
+            _ => vello::peniko::Extend::Pad,
+        };
+        let point = |p: vello::skrifa::raw::types::Point<f32>| vello::kurbo::Point::new(p.x as f64, p.y as f64);
+        let brush: vello::peniko::Brush = match brush {
+            ColorBrush::Solid { palette_index, alpha } => self.color(palette_index, alpha).into(),
+            ColorBrush::LinearGradient {
+                p0,
+                p1,
+                color_stops,
+                extend: e,
+            } => vello::peniko::Gradient::new_linear(point(p0), point(p1))
+                .with_stops(self.stops(color_stops).as_slice())
+                .with_extend(extend(e))
+                .into(),
+            ColorBrush::RadialGradient {
+                c0,
+                r0,
+                c1,
+                r1,
+                color_stops,
+                extend: e,
+            } => vello::peniko::Gradient::new_two_point_radial(point(c0), r0, point(c1), r1)
+                .with_stops(self.stops(color_stops).as_slice())
+                .with_extend(extend(e))
+                .into(),
+            ColorBrush::SweepGradient {
+                c0,
+                start_angle,
+                end_angle,
+                color_stops,
+                extend: e,
+            } => vello::peniko::Gradient::new_sweep(point(c0), start_angle.to_radians(), end_angle.to_radians())
+                .with_stops(self.stops(color_stops).as_slice())
+                .with_extend(extend(e))
+                .into(),
+        };
+        self.scene.fill(
+            vello::peniko::Fill::NonZero,
+            self.transform(),
+            &brush,
+            None,
+            &Self::FILL_AREA,
+        );
+    }
+
+    fn push_layer(&mut self, composite_mode: vello::skrifa::color::CompositeMode) {
+        use vello::peniko::{Compose, Mix};
+        use vello::skrifa::color::CompositeMode as C;
+        let (mix, compose) = match composite_mode {
+            C::Clear => (Mix::Normal, Compose::Clear),
+            C::Src => (Mix::Normal, Compose::Copy),
+            C::Dest => (Mix::Normal, Compose::Dest),
+            C::DestOver => (Mix::Normal, Compose::DestOver),
+            C::SrcIn => (Mix::Normal, Compose::SrcIn),
+            C::DestIn => (Mix::Normal, Compose::DestIn),
+            C::SrcOut => (Mix::Normal, Compose::SrcOut),
+            C::DestOut => (Mix::Normal, Compose::DestOut),
+            C::SrcAtop => (Mix::Normal, Compose::SrcAtop),
+            C::DestAtop => (Mix::Normal, Compose::DestAtop),
+            C::Xor => (Mix::Normal, Compose::Xor),
+            C::Plus => (Mix::Normal, Compose::Plus),
+            C::Screen => (Mix::Screen, Compose::SrcOver),
+            C::Overlay => (Mix::Overlay, Compose::SrcOver),
+            C::Darken => (Mix::Darken, Compose::SrcOver),
+            C::Lighten => (Mix::Lighten, Compose::SrcOver),
+            C::ColorDodge => (Mix::ColorDodge, Compose::SrcOver),
+            C::ColorBurn => (Mix::ColorBurn, Compose::SrcOver),
+            C::HardLight => (Mix::HardLight, Compose::SrcOver),
+            C::SoftLight => (Mix::SoftLight, Compose::SrcOver),
+            C::Difference => (Mix::Difference, Compose::SrcOver),
+            C::Exclusion => (Mix::Exclusion, Compose::SrcOver),
+            C::Multiply => (Mix::Multiply, Compose::SrcOver),
+            C::HslHue => (Mix::Hue, Compose::SrcOver),
+            C::HslSaturation => (Mix::Saturation, Compose::SrcOver),
+            C::HslColor => (Mix::Color, Compose::SrcOver),
+            C::HslLuminosity => (Mix::Luminosity, Compose::SrcOver),
+            _ => (Mix::Normal, Compose::SrcOver),
+        };
+        self.scene
+            .push_layer(BlendMode::new(mix, compose), 1.0, self.transform(), &Self::FILL_AREA);
+        self.layers += 1;
+    }
+
+    fn pop_layer(&mut self) {
+        if self.layers > 0 {
+            self.layers -= 1;
+            self.scene.pop_layer();
+        }
+    }
+}
+
+/// Collects a glyph outline into a path.
+struct PathPen(vello::kurbo::BezPath);
+
+impl vello::skrifa::outline::OutlinePen for PathPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to((x as f64, y as f64));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to((x as f64, y as f64));
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.0.quad_to((cx0 as f64, cy0 as f64), (x as f64, y as f64));
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.0
+            .curve_to((cx0 as f64, cy0 as f64), (cx1 as f64, cy1 as f64), (x as f64, y as f64));
+    }
+
+    fn close(&mut self) {
+        self.0.close_path();
     }
 }
 
-fn vello_font_to_font_ref(font: &vello::peniko::Font) -> Option<vello::skrifa::FontRef<'_>> {
+fn vello_font_to_font_ref(font: &vello::peniko::Font) -> Result<vello::skrifa::FontRef<'_>> {
     use vello::skrifa::raw::FileRef;
-    let file_ref = FileRef::new(font.data.as_ref()).ok()?;
+    let file_ref = FileRef::new(font.data.as_ref()).map_err(|_| RendererError::Font)?;
     match file_ref {
-        FileRef::Font(font) => Some(font),
-        FileRef::Collection(collection) => collection.get(font.index).ok(),
+        FileRef::Font(font) => Ok(font),
+        FileRef::Collection(collection) => collection.get(font.index).map_err(|_| RendererError::Font),
+    }
+}
+
+impl Drawable<VelloBackend> for GpuGrating {
+    fn draw(&mut self, scene: &mut Scene<VelloBackend>) {
+        if !self.visible || self.width <= 0.0 || self.height <= 0.0 {
+            return;
+        }
+
+        // one texel per device pixel
+        let transform = self.transform * scene.view_transform();
+        let scale = transform.determinant().abs().sqrt();
+        let texels = |size: f64| (size * scale).ceil().clamp(1.0, GRATING_MAX_TEXTURE_SIZE) as u32;
+        let (width, height) = (texels(self.width), texels(self.height));
+
+        let mut waves = [[0.0; 4]; MAX_GRATING_WAVES];
+        for (params, wave) in waves.iter_mut().zip(&self.waves) {
+            *params = [
+                wave.spatial_frequency as f32,
+                wave.orientation as f32,
+                wave.phase as f32,
+                wave.contrast as f32,
+            ];
+        }
+        let mean = scene.backend.color_management.convert(self.mean);
+        let params = GratingParams {
+            mean: [mean.r, mean.g, mean.b, mean.a],
+            size: [self.width as f32, self.height as f32],
+            count: self.waves.len().min(MAX_GRATING_WAVES) as u32,
+            _padding: 0,
+            waves,
+        };
+
+        let placeholder = scene.backend.placeholder(width, height);
+        let image_transform = Affine::scale_xy(self.width / width as f64, self.height / height as f64) * transform;
+        scene
+            .backend
+            .vello_scene
+            .draw_image(&placeholder, image_transform.into());
+        scene.backend.grating_passes.push(GratingPass { params, placeholder });
     }
 }
 
 impl Drawable<VelloBackend> for &PrerenderedScene {
     fn draw(&mut self, scene: &mut Scene<VelloBackend>) {
-        let global_transform = scene.backend.global_transform;
-        let transform = self.transform * global_transform;
+        let transform = self.transform * scene.view_transform();
 
         scene.backend.vello_scene.append(&mut &self.scene, Some(transform.into()));
     }
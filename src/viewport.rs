@@ -0,0 +1,72 @@
+use super::affine::Affine;
+use super::backend::RenderBackend;
+use super::brushes::Brush;
+use super::scenes::Scene;
+use super::shapes::{Point, Rectangle};
+use super::styles::{CompositeMode, FillStyle, MixMode, Style};
+use super::Drawable;
+
+/// Shows the content of another scene inside a rectangular region (picture-in-picture).
+///
+/// To show a magnified view of the scene that is currently being drawn (e.g. a magnifier
+/// inset), draw into a clone of the scene and pass the clone as source.
+#[derive(Debug)]
+pub struct Viewport<'a, Backend> {
+    /// The scene to show.
+    pub source: &'a Scene<Backend>,
+    /// The region (in scene coordinates) the viewport is drawn into. Content is clipped to it.
+    pub rect: Rectangle,
+    /// The point of the source scene (in its scene coordinates) shown at the center of the viewport.
+    pub focus: Point,
+    /// Magnification of the source content relative to its own scale.
+    pub zoom: f64,
+    /// Whether to fill the viewport with the background color of the source scene.
+    pub draw_background: bool,
+}
+
+impl<'a, Backend> Viewport<'a, Backend> {
+    /// Create a viewport showing the source scene centered on its origin at its original scale.
+    pub fn new(source: &'a Scene<Backend>, rect: Rectangle) -> Self {
+        Self {
+            source,
+            rect,
+            focus: Point { x: 0.0, y: 0.0 },
+            zoom: 1.0,
+            draw_background: true,
+        }
+    }
+}
+
+impl<'a, Backend: RenderBackend> Drawable<Backend> for Viewport<'a, Backend> {
+    fn draw(&mut self, scene: &mut Scene<Backend>) {
+        let view = scene.view_transform();
+        let center = Point {
+            x: (self.rect.a.x + self.rect.b.x) / 2.0,
+            y: (self.rect.a.y + self.rect.b.y) / 2.0,
+        };
+
+        scene
+            .backend
+            .push_layer(MixMode::Normal, CompositeMode::SourceOver, &self.rect, view, 1.0);
+
+        if self.draw_background {
+            scene.backend.draw_shape(
+                &self.rect,
+                &Style::Fill(FillStyle::NonZero),
+                &Brush::Solid(self.source.background_color),
+                view,
+                None,
+            );
+        }
+
+        // source device space -> source scene -> viewport -> device space
+        let transform = self.source.view_transform().inverse()
+            * Affine::translate(-self.focus.x, -self.focus.y)
+            * Affine::scale(self.zoom)
+            * Affine::translate(center.x, center.y)
+            * view;
+        scene.backend.append(&self.source.backend, transform);
+
+        scene.backend.pop_layer();
+    }
+}
@@ -0,0 +1,28 @@
+//! Checks the keys image brushes are cached by.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use renderer::brushes::{ColorKey, ImageMapping};
+use renderer::colors::RGBA;
+
+fn hash(mapping: &ImageMapping) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    mapping.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn mappings_with_the_same_values_in_different_operations_hash_differently() {
+    let color = RGBA::new(0.25, 0.5, 0.75, 0.0);
+    let key = ImageMapping {
+        color_key: Some(ColorKey { color, tolerance: 0.0 }),
+        ..Default::default()
+    };
+    let tint = ImageMapping {
+        tint: Some(color),
+        ..Default::default()
+    };
+    assert_ne!(hash(&key), hash(&tint));
+    assert_ne!(hash(&ImageMapping::default()), hash(&key));
+}
@@ -0,0 +1,241 @@
+//! Renders the same scenes with the vello backend and the CPU backend and checks that the results
+//! match, so that the alternative backend stays faithful as features are added.
+//!
+//! Needs both backends (`cargo test --features cpu-backend`) and a GPU adapter. Without an
+//! adapter the tests fail, unless `RENDERER_SKIP_GPU_TESTS` is set to skip them.
+#![cfg(all(feature = "vello-backend", feature = "cpu-backend"))]
+
+use renderer::brushes::{Brush, Extend, Gradient, GradientKind, Image};
+use renderer::colors::RGBA;
+use renderer::geoms::{Geom, TiledBackground};
+use renderer::image::{Rgba, RgbaImage};
+use renderer::scenes::{MaskKind, Scene};
+use renderer::shapes::{Circle, Point, Rectangle, RoundedRectangle};
+use renderer::styles::{Antialiasing, Cap, Join, StrokeOptions, Style};
+use renderer::vello_backend::{VelloBackend, VelloRenderer};
+use renderer::{backend::RenderBackend, cpu_backend::CpuBackend, Drawable};
+
+const WIDTH: u32 = 96;
+const HEIGHT: u32 = 96;
+
+/// The largest mean difference per channel (in 8-bit steps).
+const MAX_MEAN_DIFFERENCE: f64 = 1.5;
+/// The largest fraction of pixels that may differ by more than [`PIXEL_TOLERANCE`], which
+/// leaves room for antialiasing differences along edges.
+const MAX_DIFFERING_PIXELS: f64 = 0.05;
+const PIXEL_TOLERANCE: u8 = 12;
+/// The environment variable that skips the comparisons on machines without a GPU adapter.
+const SKIP_GPU_TESTS: &str = "RENDERER_SKIP_GPU_TESTS";
+
+fn gpu() -> Option<(wgpu::Device, wgpu::Queue)> {
+    pollster::block_on(async {
+        let instance = wgpu::Instance::default();
+        let adapter = instance.request_adapter(&Default::default()).await?;
+        adapter.request_device(&Default::default(), None).await.ok()
+    })
+}
+
+fn point(x: f64, y: f64) -> Point {
+    Point { x, y }
+}
+
+fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Rectangle {
+    Rectangle {
+        a: point(x0, y0),
+        b: point(x1, y1),
+    }
+}
+
+/// Draw a scene with both backends and compare the rendered images.
+fn compare(name: &str, vello_case: fn(&mut Scene<VelloBackend>), cpu_case: fn(&mut Scene<CpuBackend>)) {
+    let Some((device, queue)) = gpu() else {
+        if std::env::var_os(SKIP_GPU_TESTS).is_some() {
+            eprintln!("{name}: skipped, no GPU adapter");
+            return;
+        }
+        panic!("{name}: no GPU adapter (set {SKIP_GPU_TESTS} to skip the comparisons)");
+    };
+    let background = RGBA::new(0.2, 0.2, 0.2, 1.0);
+
+    let mut vello_scene = Scene::<VelloBackend>::new(background, WIDTH, HEIGHT);
+    vello_case(&mut vello_scene);
+    let mut renderer = VelloRenderer::new(
+        &device,
+        wgpu::TextureFormat::Rgba8Unorm,
+        WIDTH,
+        HEIGHT,
+        Antialiasing::Area,
+    )
+    .expect("failed to create the renderer");
+    let expected = vello_scene
+        .render_to_image(&mut renderer, &device, &queue)
+        .expect("failed to render with vello");
+
+    let mut cpu_scene = Scene::<CpuBackend>::new(background, WIDTH, HEIGHT);
+    cpu_case(&mut cpu_scene);
+    let actual = cpu_scene.render_to_image().expect("failed to render on the CPU");
+
+    assert_similar(name, &expected, &actual);
+}
+
+fn assert_similar(name: &str, expected: &RgbaImage, actual: &RgbaImage) {
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{name}: sizes differ");
+    let mut total = 0u64;
+    let mut differing = 0usize;
+    for (Rgba(a), Rgba(b)) in expected.pixels().zip(actual.pixels()) {
+        let differences = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b));
+        total += differences.clone().map(u64::from).sum::<u64>();
+        if differences.max().unwrap_or(0) > PIXEL_TOLERANCE {
+            differing += 1;
+        }
+    }
+    let pixels = (expected.width() * expected.height()) as usize;
+    let mean = total as f64 / (pixels * 4) as f64;
+    let fraction = differing as f64 / pixels as f64;
+    assert!(
+        mean <= MAX_MEAN_DIFFERENCE && fraction <= MAX_DIFFERING_PIXELS,
+        "{name}: backends differ (mean difference {mean:.2}, {:.1}% of pixels differ)",
+        fraction * 100.0
+    );
+}
+
+fn solid_fills<B: RenderBackend>(scene: &mut Scene<B>) {
+    Geom::fill(rect(8.0, 8.0, 48.0, 40.0), RGBA::RED).draw(scene);
+    Geom::fill(
+        Circle {
+            center: point(60.0, 60.0),
+            radius: 24.0,
+        },
+        RGBA::new(0.1, 0.6, 0.9, 0.7),
+    )
+    .draw(scene);
+}
+
+fn strokes<B: RenderBackend>(scene: &mut Scene<B>) {
+    let rounded = RoundedRectangle {
+        a: point(12.0, 12.0),
+        b: point(84.0, 52.0),
+        radius: 10.0,
+    };
+    Geom::stroke(rounded, 4.0, RGBA::WHITE).draw(scene);
+    let options = StrokeOptions::new(6.0).join(Join::Round).caps(Cap::Round);
+    Geom::new(
+        renderer::shapes::Polyline {
+            points: vec![point(12.0, 84.0), point(48.0, 64.0), point(84.0, 84.0)],
+        },
+        Style::Stroke(options),
+        RGBA::GREEN,
+    )
+    .draw(scene);
+}
+
+fn dashes<B: RenderBackend>(scene: &mut Scene<B>) {
+    let options = StrokeOptions::new(3.0).dash(&[8.0, 4.0], 2.0);
+    Geom::new(rect(16.0, 16.0, 80.0, 80.0), Style::Stroke(options), RGBA::WHITE).draw(scene);
+}
+
+fn linear_gradient<B: RenderBackend>(scene: &mut Scene<B>) {
+    let gradient = Gradient::new_equidistant(
+        Extend::Pad,
+        GradientKind::Linear {
+            start: point(8.0, 0.0),
+            end: point(88.0, 0.0),
+        },
+        &[RGBA::BLACK, RGBA::new(1.0, 0.5, 0.0, 1.0), RGBA::WHITE],
+    );
+    Geom::fill(rect(0.0, 0.0, 96.0, 96.0), gradient).draw(scene);
+}
+
+fn radial_gradient<B: RenderBackend>(scene: &mut Scene<B>) {
+    let gradient = Gradient::new_equidistant(
+        Extend::Reflect,
+        GradientKind::Radial {
+            start_center: point(48.0, 48.0),
+            start_radius: 0.0,
+            end_center: point(48.0, 48.0),
+            end_radius: 20.0,
+        },
+        &[RGBA::WHITE, RGBA::BLUE],
+    );
+    Geom::fill(rect(0.0, 0.0, 96.0, 96.0), gradient).draw(scene);
+}
+
+/// A 4x4 checkerboard of 8x8 pixel blocks.
+fn test_image() -> Image {
+    let image = RgbaImage::from_fn(32, 32, |x, y| {
+        if (x / 8 + y / 8) % 2 == 0 {
+            Rgba([255, 200, 0, 255])
+        } else {
+            Rgba([0, 80, 160, 255])
+        }
+    });
+    Image::new(&renderer::image::DynamicImage::ImageRgba8(image))
+}
+
+fn image_brush<B: RenderBackend>(scene: &mut Scene<B>) {
+    TiledBackground::image(test_image()).draw(scene);
+    Geom::fill(
+        rect(24.0, 24.0, 72.0, 72.0),
+        Brush::Image {
+            image: test_image(),
+            x: 0.0,
+            y: 0.0,
+            fit_mode: renderer::styles::ImageFitMode::Original,
+            edge_mode: Extend::Repeat,
+            edge_mode_y: None,
+            mapping: Default::default(),
+        },
+    )
+    .draw(scene);
+}
+
+fn opacity<B: RenderBackend>(scene: &mut Scene<B>) {
+    Geom::fill(rect(8.0, 8.0, 64.0, 64.0), RGBA::RED).draw(scene);
+    Geom::fill(rect(32.0, 32.0, 88.0, 88.0), RGBA::GREEN)
+        .with_opacity(0.5)
+        .draw(scene);
+}
+
+fn luminance_mask<B: RenderBackend>(scene: &mut Scene<B>) {
+    scene.with_mask(MaskKind::Luminance, linear_gradient, solid_fills);
+}
+
+#[test]
+fn solid_fills_match() {
+    compare("solid fills", solid_fills, solid_fills);
+}
+
+#[test]
+fn strokes_match() {
+    compare("strokes", strokes, strokes);
+}
+
+#[test]
+fn dashes_match() {
+    compare("dashes", dashes, dashes);
+}
+
+#[test]
+fn linear_gradients_match() {
+    compare("linear gradient", linear_gradient, linear_gradient);
+}
+
+#[test]
+fn radial_gradients_match() {
+    compare("radial gradient", radial_gradient, radial_gradient);
+}
+
+#[test]
+fn image_brushes_match() {
+    compare("image brush", image_brush, image_brush);
+}
+
+#[test]
+fn opacity_matches() {
+    compare("opacity", opacity, opacity);
+}
+
+#[test]
+fn luminance_masks_match() {
+    compare("luminance mask", luminance_mask, luminance_mask);
+}
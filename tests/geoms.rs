@@ -0,0 +1,94 @@
+//! Checks how geoms are built and drawn.
+
+use renderer::colors::RGBA;
+use renderer::error::RendererError;
+use renderer::geoms::Geom;
+use renderer::shapes::{Line, Point, Polygon, Polyline, Shape};
+
+fn point(x: f64, y: f64) -> Point {
+    Point { x, y }
+}
+
+/// Fill any shape, like code that is generic over shapes would.
+fn try_fill<S: Shape>(shape: S) -> Result<Geom<S>, RendererError> {
+    Geom::try_fill(shape, RGBA::WHITE)
+}
+
+#[test]
+fn filling_stroke_only_shapes_is_an_error() {
+    let line = Line {
+        start: point(0.0, 0.0),
+        end: point(10.0, 0.0),
+    };
+    let polyline = Polyline {
+        points: vec![point(0.0, 0.0), point(10.0, 0.0), point(10.0, 10.0)],
+    };
+    assert!(matches!(try_fill(line), Err(RendererError::InvalidArgument(_))));
+    assert!(matches!(try_fill(polyline), Err(RendererError::InvalidArgument(_))));
+
+    let triangle = Polygon {
+        points: vec![point(0.0, 0.0), point(10.0, 0.0), point(10.0, 10.0)],
+    };
+    assert!(try_fill(triangle).is_ok());
+}
+
+#[cfg(feature = "cpu-backend")]
+#[test]
+fn snapped_one_pixel_lines_cover_one_pixel_row() {
+    use renderer::cpu_backend::CpuBackend;
+    use renderer::Drawable;
+
+    const SIZE: u32 = 16;
+    // a line between two pixel rows, which would be smeared over both without snapping
+    let line = Line {
+        start: point(-8.0, 0.0),
+        end: point(8.0, 0.0),
+    };
+    let mut scene = renderer::scenes::Scene::<CpuBackend>::new(RGBA::BLACK, SIZE, SIZE);
+    scene.pixel_snapping = true;
+    Geom::stroke(line, 1.0, RGBA::WHITE).draw(&mut scene);
+    let image = scene.render_to_image().expect("failed to render on the CPU");
+
+    let rows = (0..SIZE)
+        .filter(|&y| (0..SIZE).any(|x| image.get_pixel(x, y).0[0] > 0))
+        .collect::<Vec<_>>();
+    assert_eq!(rows.len(), 1, "the line covers the rows {rows:?}");
+    for x in 0..SIZE {
+        assert_eq!(image.get_pixel(x, rows[0]).0, [255, 255, 255, 255], "pixel ({x}, {})", rows[0]);
+    }
+}
+
+#[cfg(feature = "cpu-backend")]
+#[test]
+fn geom_transforms_are_applied_before_the_camera() {
+    use renderer::affine::Affine;
+    use renderer::camera::Camera;
+    use renderer::cpu_backend::CpuBackend;
+    use renderer::shapes::Rectangle;
+    use renderer::Drawable;
+
+    const SIZE: u32 = 32;
+    let mut scene = renderer::scenes::Scene::<CpuBackend>::new(RGBA::BLACK, SIZE, SIZE);
+    scene.camera = Camera::new(point(0.0, 0.0), 2.0, 0.0);
+    let square = Rectangle {
+        a: point(-2.0, -2.0),
+        b: point(2.0, 2.0),
+    };
+    // the square is moved 6 units in the scene, which the camera zooms to 12 pixels
+    Geom::fill(square, RGBA::WHITE)
+        .with_transform(Affine::translate(6.0, 0.0))
+        .draw(&mut scene);
+    let center = scene.world_to_screen(point(6.0, 0.0));
+    let image = scene.render_to_image().expect("failed to render on the CPU");
+
+    let covered = (0..SIZE)
+        .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+        .filter(|&(x, y)| image.get_pixel(x, y).0[0] > 127)
+        .collect::<Vec<_>>();
+    let (x0, x1) = (covered.iter().map(|p| p.0).min().unwrap(), covered.iter().map(|p| p.0).max().unwrap());
+    let (y0, y1) = (covered.iter().map(|p| p.1).min().unwrap(), covered.iter().map(|p| p.1).max().unwrap());
+    // 4 units zoomed to 8 pixels, around the zoomed position
+    assert_eq!((x1 - x0 + 1, y1 - y0 + 1), (8, 8), "the square covers ({x0}, {y0}) to ({x1}, {y1})");
+    assert_eq!((x0, y0), (center.x as u32 - 4, center.y as u32 - 4));
+    assert_eq!(center.x, SIZE as f64 / 2.0 + 12.0);
+}
@@ -0,0 +1,86 @@
+//! Checks the color ramps of gradients with contrast and phase applied against the ramp they are
+//! derived from.
+
+use renderer::brushes::{ColorStop, Extend, Gradient, GradientKind};
+use renderer::colors::RGBA;
+use renderer::shapes::Point;
+
+const TOLERANCE: f32 = 1e-5;
+
+fn gradient(stops: &[(f32, RGBA)]) -> Gradient {
+    let mut gradient = Gradient::new_equidistant(
+        Extend::Pad,
+        GradientKind::Linear {
+            start: Point { x: 0.0, y: 0.0 },
+            end: Point { x: 1.0, y: 0.0 },
+        },
+        &[RGBA::BLACK, RGBA::WHITE],
+    );
+    gradient.stops = stops.iter().map(|&(offset, color)| ColorStop { offset, color }).collect();
+    gradient
+}
+
+fn assert_close(actual: RGBA, expected: RGBA, t: f32) {
+    let (a, e) = ([actual.r, actual.g, actual.b, actual.a], [expected.r, expected.g, expected.b, expected.a]);
+    assert!(
+        a.iter().zip(e).all(|(a, e)| (a - e).abs() < TOLERANCE),
+        "at offset {t}: expected {expected:?}, got {actual:?}"
+    );
+}
+
+/// Check that the ramp with `phase` is the original ramp shifted cyclically by `phase`.
+fn assert_shifted(stops: &[(f32, RGBA)], phase: f32) {
+    let original = gradient(stops);
+    let shifted = gradient(stops).with_phase(phase);
+    for i in 0..=64 {
+        let t = i as f32 / 64.0;
+        let expected = original.color_at((t - phase).rem_euclid(1.0));
+        // the very end of the shifted ramp continues its start, not the original ramp at offset 0
+        if t < 1.0 {
+            assert_close(shifted.color_at(t), expected, t);
+        }
+    }
+}
+
+#[test]
+fn phase_shifts_two_stop_ramps() {
+    let stops = [(0.0, RGBA::BLACK), (1.0, RGBA::WHITE)];
+    assert_shifted(&stops, 0.25);
+    assert_shifted(&stops, 0.5);
+}
+
+#[test]
+fn phase_shifts_three_stop_ramps() {
+    let stops = [(0.0, RGBA::RED), (0.5, RGBA::GREEN), (1.0, RGBA::BLUE)];
+    assert_shifted(&stops, 0.25);
+    assert_shifted(&stops, 0.5);
+}
+
+#[test]
+fn phase_keeps_the_wrapped_stops_before_the_unwrapped_ones() {
+    let stops = gradient(&[(0.0, RGBA::RED), (0.5, RGBA::GREEN), (1.0, RGBA::BLUE)])
+        .with_phase(0.25)
+        .resolved_stops();
+    let offsets = stops.iter().map(|stop| stop.offset).collect::<Vec<_>>();
+    assert_eq!(offsets, [0.0, 0.25, 0.25, 0.75, 1.0]);
+    // the ramp runs from the wrap point to the last stop, steps to the first and continues
+    assert_close(stops[1].color, RGBA::BLUE, 0.25);
+    assert_close(stops[2].color, RGBA::RED, 0.25);
+    assert_close(stops[3].color, RGBA::GREEN, 0.75);
+}
+
+#[test]
+fn phase_keeps_the_padding_of_stops_inside_the_ramp() {
+    let stops = [(0.25, RGBA::BLACK), (0.75, RGBA::WHITE)];
+    assert_shifted(&stops, 0.25);
+    assert_shifted(&stops, 0.5);
+}
+
+#[test]
+fn contrast_scales_around_the_offset_weighted_mean() {
+    // black up to 0.25, then white: the mean over the ramp is 0.125 + 0.75
+    let flat = gradient(&[(0.0, RGBA::BLACK), (0.25, RGBA::WHITE)]).with_contrast(0.0);
+    for stop in flat.resolved_stops() {
+        assert_close(stop.color, RGBA::new(0.875, 0.875, 0.875, 1.0), stop.offset);
+    }
+}
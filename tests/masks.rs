@@ -0,0 +1,99 @@
+//! Renders masked content with the CPU backend and compares it with reference images, so that the
+//! compositing of alpha and luminance masks is pinned down independently of a GPU.
+#![cfg(feature = "cpu-backend")]
+
+use renderer::colors::RGBA;
+use renderer::cpu_backend::CpuBackend;
+use renderer::geoms::Geom;
+use renderer::masks::MaskSet;
+use renderer::image::{Rgba, RgbaImage};
+use renderer::scenes::{MaskKind, Scene};
+use renderer::shapes::{Point, Rectangle};
+use renderer::Drawable;
+
+const SIZE: u32 = 32;
+/// The largest difference per channel (in 8-bit steps).
+const TOLERANCE: u8 = 2;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+/// Red at half opacity over blue.
+const HALF_RED: [u8; 4] = [128, 0, 127, 255];
+
+fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Rectangle {
+    Rectangle {
+        a: Point { x: x0, y: y0 },
+        b: Point { x: x1, y: y1 },
+    }
+}
+
+/// A mask with a quadrant each of white, mid gray, black and nothing (clockwise from the top
+/// left), in scene coordinates with the origin at the center.
+fn quadrants(scene: &mut Scene<CpuBackend>) {
+    let half = SIZE as f64 / 2.0;
+    Geom::fill(rect(-half, -half, 0.0, 0.0), RGBA::WHITE).draw(scene);
+    Geom::fill(rect(0.0, -half, half, 0.0), RGBA::new(0.5, 0.5, 0.5, 1.0)).draw(scene);
+    Geom::fill(rect(-half, 0.0, 0.0, half), RGBA::BLACK).draw(scene);
+}
+
+/// The [`quadrants`] mask as a drawable.
+struct Quadrants;
+
+impl Drawable<CpuBackend> for Quadrants {
+    fn draw(&mut self, scene: &mut Scene<CpuBackend>) {
+        quadrants(scene);
+    }
+}
+
+fn red(scene: &mut Scene<CpuBackend>) {
+    let half = SIZE as f64 / 2.0;
+    Geom::fill(rect(-half, -half, half, half), RGBA::RED).draw(scene);
+}
+
+/// An image with the given colors in its top left, top right, bottom left and bottom right
+/// quadrants.
+fn reference(colors: [[u8; 4]; 4]) -> RgbaImage {
+    RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        let quadrant = (x >= SIZE / 2) as usize + 2 * (y >= SIZE / 2) as usize;
+        Rgba(colors[quadrant])
+    })
+}
+
+fn render(draw: impl FnOnce(&mut Scene<CpuBackend>)) -> RgbaImage {
+    let mut scene = Scene::<CpuBackend>::new(RGBA::BLUE, SIZE, SIZE);
+    draw(&mut scene);
+    scene.render_to_image().expect("failed to render on the CPU")
+}
+
+fn assert_matches(name: &str, expected: &RgbaImage, actual: &RgbaImage) {
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{name}: sizes differ");
+    for (x, y, Rgba(a)) in expected.enumerate_pixels() {
+        let Rgba(b) = actual.get_pixel(x, y);
+        assert!(
+            a.iter().zip(b).all(|(a, b)| a.abs_diff(*b) <= TOLERANCE),
+            "{name}: pixel ({x}, {y}) is {b:?} instead of {a:?}"
+        );
+    }
+}
+
+#[test]
+fn alpha_mask_shows_content_where_the_mask_is_opaque() {
+    let actual = render(|scene| scene.with_mask(MaskKind::Alpha, quadrants, red));
+    assert_matches("alpha mask", &reference([RED, RED, RED, BLUE]), &actual);
+}
+
+#[test]
+fn luminance_mask_shows_content_where_the_mask_is_bright() {
+    let actual = render(|scene| scene.with_mask(MaskKind::Luminance, quadrants, red));
+    assert_matches("luminance mask", &reference([RED, HALF_RED, BLUE, BLUE]), &actual);
+}
+
+#[test]
+fn mask_set_combines_luminance_and_alpha_masks() {
+    let half = SIZE as f64 / 2.0;
+    let mut set = MaskSet::new("quadrants and left half")
+        .with_mask(MaskKind::Luminance, Quadrants)
+        .with_mask(MaskKind::Alpha, Geom::fill(rect(-half, -half, 0.0, half), RGBA::WHITE));
+    let actual = render(|scene| set.apply(scene, red));
+    assert_matches("mask set", &reference([RED, BLUE, BLUE, BLUE]), &actual);
+}
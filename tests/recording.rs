@@ -0,0 +1,133 @@
+//! Records sessions to temporary files and replays them.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use renderer::brushes::{Extend, Gradient, GradientKind};
+use renderer::colors::RGBA;
+use renderer::geoms::Geom;
+use renderer::recording::{SceneCommands, SessionRecorder, SessionReplayer};
+use renderer::shapes::{Circle, Line, Point, Rectangle};
+use renderer::styles::{StrokeOptions, Style};
+
+/// A path in the temporary directory that is unique to the test.
+fn session_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("renderer-{}-{name}.session", std::process::id()))
+}
+
+/// The commands of frame `i`: a rectangle that moves, a circle and a dashed line.
+fn frame(i: u32) -> SceneCommands {
+    let x = i as f64 * 2.0;
+    let mut commands = SceneCommands::new(RGBA::new(0.5, 0.5, 0.5, 1.0));
+    let rectangle = Rectangle {
+        a: Point { x: x - 8.0, y: -4.0 },
+        b: Point { x: x + 8.0, y: 4.0 },
+    };
+    commands.push(&Geom::fill(rectangle, RGBA::RED)).unwrap();
+    let circle = Circle {
+        center: Point { x: 0.0, y: 6.0 },
+        radius: 5.0,
+    };
+    commands.push(&Geom::fill(circle, RGBA::BLUE).with_opacity(0.5)).unwrap();
+    let line = Line {
+        start: Point { x: -10.0, y: -10.0 },
+        end: Point { x: 10.0, y: 10.0 },
+    };
+    let dashed = Style::Stroke(StrokeOptions::new(2.0).dash(&[3.0, 1.0], 0.5));
+    commands.push(&Geom::new(line, dashed, RGBA::WHITE)).unwrap();
+    commands
+}
+
+fn record(path: &PathBuf, frames: u32) {
+    let mut recorder = SessionRecorder::create(path).unwrap();
+    for i in 0..frames {
+        recorder
+            .record_frame_at(Duration::from_millis(i as u64 * 16), &frame(i))
+            .unwrap();
+    }
+    recorder.finish().unwrap();
+}
+
+#[test]
+fn sessions_round_trip() {
+    let path = session_path("round-trip");
+    record(&path, 3);
+    let replayer = SessionReplayer::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(replayer.frames().len(), 3);
+    assert_eq!(replayer.duration(), Duration::from_millis(32));
+    for (i, recorded) in replayer.frames().iter().enumerate() {
+        assert_eq!(recorded.index, i as u64);
+        assert_eq!(recorded.commands.encode(), frame(i as u32).encode());
+    }
+}
+
+#[test]
+fn truncated_sessions_are_rejected() {
+    let path = session_path("truncated");
+    record(&path, 2);
+    let bytes = fs::read(&path).unwrap();
+    let first_frame_len = 12 + 24 + frame(0).encode().len();
+
+    // within the index of the second frame, and within its payload
+    for len in [first_frame_len + 4, bytes.len() - 1] {
+        fs::write(&path, &bytes[..len]).unwrap();
+        let error = SessionReplayer::open(&path).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData, "truncated to {len} bytes");
+    }
+
+    // a file that ends at a frame boundary is complete
+    fs::write(&path, &bytes[..first_frame_len]).unwrap();
+    assert_eq!(SessionReplayer::open(&path).unwrap().frames().len(), 1);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn only_solid_brushes_can_be_recorded() {
+    let gradient = Gradient::new_equidistant(
+        Extend::Pad,
+        GradientKind::Linear {
+            start: Point { x: 0.0, y: 0.0 },
+            end: Point { x: 1.0, y: 0.0 },
+        },
+        &[RGBA::BLACK, RGBA::WHITE],
+    );
+    let circle = Circle {
+        center: Point { x: 0.0, y: 0.0 },
+        radius: 1.0,
+    };
+    let mut commands = SceneCommands::new(RGBA::BLACK);
+    assert!(commands.push(&Geom::fill(circle, gradient)).is_err());
+    assert!(commands.commands.is_empty());
+}
+
+#[cfg(feature = "cpu-backend")]
+#[test]
+fn replays_render_like_the_recorded_frames() {
+    use renderer::cpu_backend::CpuBackend;
+    use renderer::scenes::Scene;
+    use renderer::Drawable;
+
+    let path = session_path("replay");
+    record(&path, 3);
+    let replayer = SessionReplayer::open(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let mut replayed = Vec::new();
+    let mut scene = Scene::<CpuBackend>::new(RGBA::BLACK, 32, 32);
+    replayer.replay(&mut scene, |scene, _| {
+        replayed.push(scene.render_to_image().expect("failed to render on the CPU"));
+    });
+
+    assert_eq!(replayed.len(), 3);
+    for (i, image) in replayed.iter().enumerate() {
+        let mut scene = Scene::<CpuBackend>::new(RGBA::new(0.5, 0.5, 0.5, 1.0), 32, 32);
+        frame(i as u32).draw(&mut scene);
+        let expected = scene.render_to_image().expect("failed to render on the CPU");
+        assert!(expected == *image, "frame {i} differs from the recorded frame");
+    }
+    assert!(replayed[0] != replayed[1], "the frames should differ");
+}
@@ -0,0 +1,44 @@
+//! Checks that scene snapshots capture everything that affects the rendered output, and nothing
+//! that depends on the platform.
+#![cfg(feature = "cpu-backend")]
+
+use renderer::brushes::Brush;
+use renderer::colors::RGBA;
+use renderer::cpu_backend::CpuBackend;
+use renderer::geoms::Geom;
+use renderer::scenes::{Clear, Scene};
+use renderer::shapes::{Circle, Point};
+use renderer::Drawable;
+
+fn scene() -> Scene<CpuBackend> {
+    let mut scene = Scene::<CpuBackend>::new(RGBA::new(0.25, 0.5, 0.75, 1.0), 16, 16);
+    let circle = Circle {
+        center: Point { x: 0.0, y: 0.0 },
+        radius: 4.0,
+    };
+    Geom::fill(circle, RGBA::RED).draw(&mut scene);
+    scene
+}
+
+#[test]
+fn snapshots_are_little_endian() {
+    let snapshot = scene().snapshot();
+    assert_eq!(snapshot[..4], 0.25f32.to_bits().to_le_bytes());
+    assert_eq!(snapshot[16..20], 16u32.to_le_bytes());
+}
+
+#[test]
+fn snapshots_include_the_clear_mode() {
+    let color = scene().snapshot();
+    let mut preserve = scene();
+    preserve.set_clear(Clear::Preserve);
+    let mut green = scene();
+    green.set_clear(Clear::Brush(Box::new(Brush::Solid(RGBA::GREEN))));
+    let mut blue = scene();
+    blue.set_clear(Clear::Brush(Box::new(Brush::Solid(RGBA::BLUE))));
+
+    assert_ne!(color, preserve.snapshot());
+    assert_ne!(color, green.snapshot());
+    assert_ne!(green.snapshot(), blue.snapshot());
+    assert_eq!(scene().snapshot(), color);
+}